@@ -1,4 +1,4 @@
-use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
 use hazard_pointers_demo::LockFreeStack;
 use std::sync::Arc;
 use std::thread;
@@ -1,72 +1,540 @@
+use std::cell::RefCell;
 use std::collections::HashSet;
 use std::fmt;
-use std::ptr;
-use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread::{self, ThreadId};
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+use sync::{AtomicPtr, AtomicUsize, Mutex, Ordering, ThreadId, fence, hint, thread, thread_local};
+
+mod list;
+mod map;
+mod queue;
+mod sync;
+
+pub use list::LockFreeList;
+pub use map::LockFreeMap;
+pub use queue::LockFreeQueue;
+
+/// Source of the tokens identifying threads in hazard records (0 means "no owner")
+///
+/// Ids only need to be unique, so the id sources stay std atomics under loom.
+static NEXT_THREAD_TOKEN: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(1);
+
+/// Owner of the records reserved by `ThreadHazards` handles
+const HANDLE_OWNER: usize = usize::MAX;
+
+/// Number of hazard pointers each thread can hold at once
+///
+/// Operations such as a queue dequeue need to protect two nodes at a time.
+pub const HAZARD_SLOTS: usize = 4;
+
+/// Source of the ids telling registries apart in the thread-local record cache
+static NEXT_REGISTRY_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// The threads holding a token, only looked up by debug reports
+static TOKEN_THREADS: std::sync::Mutex<Vec<(usize, ThreadId)>> = std::sync::Mutex::new(Vec::new());
+
+/// A thread's token, registered in `TOKEN_THREADS` until the thread exits
+struct ThreadToken(usize);
+
+impl ThreadToken {
+    /// Takes a new token for the current thread
+    fn new() -> Self {
+        let token = NEXT_THREAD_TOKEN.fetch_add(1, Ordering::Relaxed);
+        TOKEN_THREADS
+            .lock()
+            .expect("Failed to lock token threads - mutex poisoned")
+            .push((token, thread::current().id()));
+        ThreadToken(token)
+    }
+}
+
+impl Drop for ThreadToken {
+    fn drop(&mut self) {
+        if let Ok(mut threads) = TOKEN_THREADS.lock() {
+            threads.retain(|(token, _)| *token != self.0);
+        }
+    }
+}
+
+thread_local! {
+    /// Token identifying the current thread as the owner of a hazard record
+    static THREAD_TOKEN: ThreadToken = ThreadToken::new();
+}
+
+/// Gets the token of the current thread
+fn current_token() -> usize {
+    THREAD_TOKEN.with(|token| token.0)
+}
+
+// loom's thread_local! doesn't take const initializers
+#[cfg(not(loom))]
+thread_local! {
+    /// The record the current thread last used in each registry, by registry id
+    /// (ids are never reused, so entries of dropped registries are never hit)
+    static CACHED_RECORDS: RefCell<Vec<(usize, *const ())>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(loom)]
+thread_local! {
+    static CACHED_RECORDS: RefCell<Vec<(usize, *const ())>> = RefCell::new(Vec::new());
+}
+
+/// A set of hazard pointer slots, owned by at most one thread at a time
+///
+/// Records are acquired by CAS-ing `owner` from 0 to the thread's token (or
+/// `HANDLE_OWNER` for `ThreadHazards` handles) and released by setting it
+/// back to 0, so they are reused by later threads.
+/// They are never unlinked from the registry until it is dropped, which lets
+/// other threads traverse the list without any locking.
+struct HazardRecord<T> {
+    /// Token of the owning thread, `HANDLE_OWNER`, or 0 if the record is free
+    owner: AtomicUsize,
+    /// Token of the thread that last claimed the record, for debug reports
+    holder: AtomicUsize,
+    /// The pointers protected by the owning thread, null for unused slots
+    hazards: [AtomicPtr<T>; HAZARD_SLOTS],
+    /// Next record in the registry, immutable once the record is published
+    next: *mut HazardRecord<T>,
+}
+
+impl<T> HazardRecord<T> {
+    /// Claims the record for the given owner if it's free
+    fn try_claim(&self, owner: usize) -> bool {
+        let claimed = self
+            .owner
+            .compare_exchange(0, owner, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok();
+        if claimed {
+            self.holder.store(current_token(), Ordering::Relaxed);
+        }
+        claimed
+    }
+
+    /// Stores a pointer in the given slot
+    ///
+    /// Panics if `index` is not below `HAZARD_SLOTS`.
+    fn protect_at(&self, index: usize, ptr: *mut T) {
+        assert!(
+            index < HAZARD_SLOTS,
+            "hazard slot {index} out of range (threads have {HAZARD_SLOTS} slots)"
+        );
+        // The fence orders the store before the caller re-validates the
+        // pointer; it pairs with the fence before the reclamation scan, so
+        // either the scan sees the hazard or the caller sees the pointer was
+        // unlinked
+        self.hazards[index].store(ptr, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+    }
+
+    /// Checks that the given slot doesn't protect anything
+    ///
+    /// Replacing the pointer of a slot would leave its guard dereferencing a
+    /// pointer that may be reclaimed.
+    ///
+    /// Panics if `index` is not below `HAZARD_SLOTS` or the slot is in use.
+    fn assert_free(&self, index: usize) {
+        assert!(
+            index < HAZARD_SLOTS,
+            "hazard slot {index} out of range (threads have {HAZARD_SLOTS} slots)"
+        );
+        assert!(
+            self.hazards[index].load(Ordering::Relaxed).is_null(),
+            "hazard slot {index} is already in use"
+        );
+    }
+
+    /// Finds a slot that doesn't protect anything
+    ///
+    /// Panics if every slot is in use.
+    fn free_slot(&self) -> usize {
+        self.hazards
+            .iter()
+            .position(|hazard| hazard.load(Ordering::Relaxed).is_null())
+            .unwrap_or_else(|| panic!("all {HAZARD_SLOTS} hazard slots of the thread are in use"))
+    }
+
+    /// Checks whether no slot protects anything
+    fn is_clear(&self) -> bool {
+        self.hazards
+            .iter()
+            .all(|hazard| hazard.load(Ordering::Relaxed).is_null())
+    }
+
+    /// Clears every slot
+    fn clear(&self) {
+        for hazard in &self.hazards {
+            hazard.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+}
+
+/// Thresholds controlling when retired pointers are reclaimed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HazardConfig {
+    /// `retire()` attempts a reclamation once more pointers than this are retired
+    pub retire_threshold: usize,
+    /// `try_reclaim(false)` does nothing unless more pointers than this are retired
+    pub scan_threshold: usize,
+}
+
+impl Default for HazardConfig {
+    fn default() -> Self {
+        HazardConfig {
+            retire_threshold: 10,
+            scan_threshold: 5,
+        }
+    }
+}
+
+/// Hazards held by one thread, as listed in a `HazardReport`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HazardHolder {
+    /// The thread holding the hazards
+    pub thread: ThreadId,
+    /// Number of pointers the thread protects
+    pub hazards: usize,
+    /// Number of retired pointers the thread keeps from being freed
+    pub blocked: usize,
+}
+
+/// Which threads keep retired pointers alive, from `HazardPointers::debug_report()`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HazardReport {
+    /// Retired pointers waiting to be freed
+    pub pending: usize,
+    /// Threads currently protecting at least one pointer
+    pub holders: Vec<HazardHolder>,
+}
+
+impl fmt::Display for HazardReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} retired node(s) pending reclamation", self.pending)?;
+        for holder in &self.holders {
+            writeln!(
+                f,
+                "  {:?} holds {} hazard(s), blocking {} retired node(s)",
+                holder.thread, holder.hazards, holder.blocked
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A snapshot of a hazard pointer registry's counters, from `HazardPointers::stats()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HazardStats {
+    /// Pointers retired since the registry was created
+    pub retired: usize,
+    /// Retired pointers freed since the registry was created
+    pub reclaimed: usize,
+    /// Hazard slots currently protecting a pointer, across all threads
+    pub protected: usize,
+    /// Retired pointers waiting to be freed, i.e. the retire list length
+    pub pending: usize,
+}
 
 /// A thread-local hazard pointer registry
 ///
 /// This struct maintains a list of pointers that a thread is currently using,
 /// protecting them from being reclaimed by other threads.
 pub struct HazardPointers<T> {
-    /// Map from thread ID to list of hazard pointers
-    thread_hazards: Mutex<Vec<(ThreadId, *mut T)>>,
+    /// Unique id of the registry, used as its key in the thread-local record cache
+    id: usize,
+    /// Lock-free list of per-thread hazard records
+    records: AtomicPtr<HazardRecord<T>>,
     /// Global retirement list of nodes awaiting safe reclamation
     retire_list: Mutex<Vec<*mut T>>,
+    /// Reclamation thresholds
+    config: HazardConfig,
+    /// Number of pointers retired so far
+    retired_count: AtomicUsize,
+    /// Number of retired pointers freed so far
+    reclaimed_count: AtomicUsize,
 }
 
+/// The hazard pointer registry, under the name used when registering threads
+///
+/// `HazardRegistry::register_current_thread()` reserves a record for the
+/// calling thread and returns its `ThreadHazards` handle.
+pub type HazardRegistry<T> = HazardPointers<T>;
+
 // Safety: HazardPointers can be safely shared between threads because
-// all its mutations are protected by internal mutexes
+// hazard records are only changed through atomics and the retirement list
+// is protected by a mutex
 unsafe impl<T> Send for HazardPointers<T> {}
 unsafe impl<T> Sync for HazardPointers<T> {}
 
+impl<T> Default for HazardPointers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> HazardPointers<T> {
     /// Creates a new hazard pointer registry
     pub fn new() -> Self {
+        Self::with_config(HazardConfig::default())
+    }
+
+    /// Creates a new hazard pointer registry with the given reclamation thresholds
+    pub fn with_config(config: HazardConfig) -> Self {
         HazardPointers {
-            thread_hazards: Mutex::new(Vec::new()),
+            id: NEXT_REGISTRY_ID.fetch_add(1, Ordering::Relaxed),
+            records: AtomicPtr::new(ptr::null_mut()),
             retire_list: Mutex::new(Vec::new()),
+            config,
+            retired_count: AtomicUsize::new(0),
+            reclaimed_count: AtomicUsize::new(0),
         }
     }
 
-    /// Registers a hazard pointer for the current thread
+    /// Gets the reclamation thresholds
+    pub fn config(&self) -> HazardConfig {
+        self.config
+    }
+
+    /// Reports which threads still hold hazards, and how many retired
+    /// pointers each of them keeps from being freed
     ///
-    /// This protects the given pointer from being reclaimed by other threads
-    /// until explicitly cleared with clear_hazards().
-    pub fn protect(&self, ptr: *mut T) -> *mut T {
-        if !ptr.is_null() {
-            let thread_id = thread::current().id();
-            let mut hazards = self
-                .thread_hazards
-                .lock()
-                .expect("Failed to lock hazard list - mutex poisoned");
+    /// Hazards a thread holds through several records (e.g. with a
+    /// `ThreadHazards` handle) are reported together.
+    pub fn debug_report(&self) -> HazardReport {
+        let retired: HashSet<*mut T> = self
+            .retire_list
+            .lock()
+            .expect("Failed to lock retire list - mutex poisoned")
+            .iter()
+            .copied()
+            .collect();
+
+        let threads = TOKEN_THREADS
+            .lock()
+            .expect("Failed to lock token threads - mutex poisoned")
+            .clone();
+        let mut holders: Vec<HazardHolder> = Vec::new();
+        for record in self.records() {
+            let hazards: HashSet<*mut T> = record
+                .hazards
+                .iter()
+                .map(|hazard| hazard.load(Ordering::SeqCst))
+                .filter(|ptr| !ptr.is_null())
+                .collect();
+            if hazards.is_empty() {
+                continue;
+            }
 
-            // Check if we already have an entry for this thread
-            for entry in hazards.iter_mut() {
-                if entry.0 == thread_id {
-                    entry.1 = ptr;
-                    return ptr;
+            // Records are tagged with a token to keep claims lock-free; it is
+            // only mapped back to its thread here. Records kept by threads that
+            // already exited can't be attributed and are left out.
+            let holder = record.holder.load(Ordering::Relaxed);
+            let Some(&(_, thread)) = threads.iter().find(|(token, _)| *token == holder) else {
+                continue;
+            };
+            let blocked = hazards.intersection(&retired).count();
+            match holders.iter_mut().find(|holder| holder.thread == thread) {
+                Some(holder) => {
+                    holder.hazards += hazards.len();
+                    holder.blocked += blocked;
                 }
+                None => holders.push(HazardHolder {
+                    thread,
+                    hazards: hazards.len(),
+                    blocked,
+                }),
             }
+        }
 
-            // No existing entry, add a new one
-            hazards.push((thread_id, ptr));
+        HazardReport {
+            pending: retired.len(),
+            holders,
         }
-        ptr
     }
 
-    /// Clears all hazard pointers for the current thread
+    /// Gets the registry's counters, without locking the retire list
     ///
-    /// This should be called when the thread no longer needs to access
-    /// previously protected pointers.
-    pub fn clear_hazards(&self) {
-        let thread_id = thread::current().id();
-        let mut hazards = self
-            .thread_hazards
-            .lock()
-            .expect("Failed to lock hazard list - mutex poisoned");
-        hazards.retain(|entry| entry.0 != thread_id);
+    /// Counters are read one after another while other threads keep running,
+    /// so they may be slightly out of sync with each other.
+    pub fn stats(&self) -> HazardStats {
+        let reclaimed = self.reclaimed_count.load(Ordering::Relaxed);
+        let retired = self.retired_count.load(Ordering::Relaxed);
+        let protected = self
+            .records()
+            .flat_map(|record| &record.hazards)
+            .filter(|hazard| !hazard.load(Ordering::Relaxed).is_null())
+            .count();
+        HazardStats {
+            retired,
+            reclaimed,
+            protected,
+            pending: retired.saturating_sub(reclaimed),
+        }
+    }
+
+    /// Iterates over all hazard records, including free ones
+    fn records(&self) -> impl Iterator<Item = &HazardRecord<T>> {
+        let mut current = self.records.load(Ordering::Acquire);
+        std::iter::from_fn(move || {
+            // Safety: records are only freed when the registry is dropped
+            let record = unsafe { current.as_ref()? };
+            current = record.next;
+            Some(record)
+        })
+    }
+
+    /// Gets the record the current thread last used in this registry, if any
+    ///
+    /// The record may have been released since, and taken by another thread.
+    fn cached_record(&self) -> Option<&HazardRecord<T>> {
+        CACHED_RECORDS.with(|cache| {
+            let cache = cache.borrow();
+            let (_, record) = cache.iter().find(|(id, _)| *id == self.id)?;
+            // Safety: the id is only cached with records of this registry,
+            // which live as long as the registry
+            Some(unsafe { &*record.cast::<HazardRecord<T>>() })
+        })
+    }
+
+    /// Checks whether any thread currently protects a pointer
+    fn is_protected(&self, ptr: *mut T) -> bool {
+        // Pairs with the fence after storing a hazard
+        fence(Ordering::SeqCst);
+        self.records()
+            .flat_map(|record| &record.hazards)
+            .any(|hazard| hazard.load(Ordering::SeqCst) == ptr)
+    }
+
+    /// Finds the current thread's record, acquiring one if needed
+    ///
+    /// The record is looked up in the thread-local cache first, so the shared
+    /// list is only searched when the thread gave its record away.
+    fn current_record(&self) -> &HazardRecord<T> {
+        let token = current_token();
+        if let Some(record) = self.cached_record()
+            && (record.owner.load(Ordering::Relaxed) == token || record.try_claim(token))
+        {
+            return record;
+        }
+
+        let record = self.claim_record(token);
+        let cached: *const HazardRecord<T> = record;
+        CACHED_RECORDS.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            cache.retain(|(id, _)| *id != self.id);
+            cache.push((self.id, cached.cast()));
+        });
+        record
+    }
+
+    /// Claims a free record for the given owner, allocating one if needed
+    fn claim_record(&self, owner: usize) -> &HazardRecord<T> {
+        // Try to take over a record released by another thread
+        for record in self.records() {
+            if record.try_claim(owner) {
+                return record;
+            }
+        }
+
+        // All records are taken, publish a new one at the head of the list
+        let record = Box::into_raw(Box::new(HazardRecord {
+            owner: AtomicUsize::new(owner),
+            holder: AtomicUsize::new(current_token()),
+            hazards: std::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+            next: ptr::null_mut(),
+        }));
+        let mut head = self.records.load(Ordering::Acquire);
+        loop {
+            // Safety: the record isn't visible to other threads until the CAS succeeds
+            unsafe {
+                (*record).next = head;
+            }
+            match self
+                .records
+                .compare_exchange(head, record, Ordering::AcqRel, Ordering::Acquire)
+            {
+                // Safety: published records live as long as the registry
+                Ok(_) => return unsafe { &*record },
+                Err(actual_head) => head = actual_head,
+            }
+        }
+    }
+
+    /// Loads a pointer and protects it for the current thread
+    ///
+    /// The pointer is re-loaded after being registered, and the process
+    /// retried until it hasn't changed, so the returned guard can safely
+    /// dereference it. The pointer is protected from being reclaimed by other
+    /// threads until the guard is dropped. Returns `None` if `source` is null.
+    ///
+    /// The guard uses a free slot of the thread's record, so a thread can
+    /// hold up to `HAZARD_SLOTS` guards at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread already holds `HAZARD_SLOTS` hazard pointers.
+    pub fn protect(&self, source: &AtomicPtr<T>) -> Option<HazardGuard<'_, T>> {
+        self.protect_in(None, source)
+    }
+
+    /// Loads and protects a pointer in one of the current thread's slots
+    ///
+    /// Like protect(), but the caller picks the slot. A slot only protects
+    /// one pointer at a time: the guard using it has to be dropped before the
+    /// slot is reused.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `HAZARD_SLOTS`, or if a guard of the
+    /// thread still uses that slot.
+    pub fn protect_at(&self, index: usize, source: &AtomicPtr<T>) -> Option<HazardGuard<'_, T>> {
+        self.protect_in(Some(index), source)
+    }
+
+    /// Protects the pointer loaded from `source` in the given or a free slot
+    fn protect_in(
+        &self,
+        index: Option<usize>,
+        source: &AtomicPtr<T>,
+    ) -> Option<HazardGuard<'_, T>> {
+        let mut ptr = NonNull::new(source.load(Ordering::Acquire))?;
+        let record = self.current_record();
+        let index = match index {
+            Some(index) => {
+                record.assert_free(index);
+                index
+            }
+            None => record.free_slot(),
+        };
+        loop {
+            record.protect_at(index, ptr.as_ptr());
+
+            // Check if the pointer has changed since we loaded it; if not, it
+            // can't have been reclaimed, since it was protected before the check
+            let current = source.load(Ordering::SeqCst);
+            if current == ptr.as_ptr() {
+                return Some(HazardGuard { record, index, ptr });
+            }
+            match NonNull::new(current) {
+                Some(current) => ptr = current,
+                None => {
+                    record.protect_at(index, ptr::null_mut());
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Reserves a hazard record for the current thread
+    ///
+    /// The returned handle protects pointers without looking up the thread's
+    /// record on every call, and is independent of the guards of protect().
+    /// Unlike protect(), it takes pointers that the caller must re-validate.
+    /// The record is released when the handle is dropped.
+    pub fn register_current_thread(&self) -> ThreadHazards<'_, T> {
+        ThreadHazards {
+            record: self.claim_record(HANDLE_OWNER),
+        }
     }
 
     /// Adds a pointer to the retirement list for later reclamation
@@ -75,14 +543,19 @@ impl<T> HazardPointers<T> {
     /// has it marked as hazardous).
     pub fn retire(&self, ptr: *mut T) {
         if !ptr.is_null() {
-            let mut retire = self
-                .retire_list
-                .lock()
-                .expect("Failed to lock retire list - mutex poisoned");
-            retire.push(ptr);
+            let retired = {
+                let mut retire = self
+                    .retire_list
+                    .lock()
+                    .expect("Failed to lock retire list - mutex poisoned");
+                retire.push(ptr);
+                self.retired_count.fetch_add(1, Ordering::Relaxed);
+                retire.len()
+            };
 
             // Attempt to reclaim memory if retire list is getting large
-            if retire.len() > 10 {
+            // (after releasing the lock, which try_reclaim takes again)
+            if retired > self.config.retire_threshold {
                 self.try_reclaim(false);
             }
         }
@@ -96,13 +569,17 @@ impl<T> HazardPointers<T> {
     /// If `force` is true, this will attempt to reclaim memory even if the
     /// retire list is small.
     pub fn try_reclaim(&self, force: bool) -> usize {
-        // Get the current set of hazardous pointers
-        // This must happen atomically with respect to the retirement list processing
-        let hazards = self
-            .thread_hazards
-            .lock()
-            .expect("Failed to lock hazard list - mutex poisoned");
-        let hazardous: HashSet<*mut T> = hazards.iter().map(|entry| entry.1).collect();
+        // Pairs with the fence after storing a hazard, so the pointers
+        // unlinked before retiring them can't be protected unnoticed
+        fence(Ordering::SeqCst);
+
+        // Get the current set of hazardous pointers from every thread's record
+        let hazardous: HashSet<*mut T> = self
+            .records()
+            .flat_map(|record| &record.hazards)
+            .map(|hazard| hazard.load(Ordering::SeqCst))
+            .filter(|ptr| !ptr.is_null())
+            .collect();
 
         // Get the retirement list
         let mut retire = self
@@ -111,7 +588,7 @@ impl<T> HazardPointers<T> {
             .expect("Failed to lock retire list - mutex poisoned");
 
         // If the retire list is empty or too small and we're not forcing reclamation, do nothing
-        if retire.is_empty() || (!force && retire.len() <= 5) {
+        if retire.is_empty() || (!force && retire.len() <= self.config.scan_threshold) {
             return 0;
         }
 
@@ -124,6 +601,8 @@ impl<T> HazardPointers<T> {
 
         // Count how many nodes we freed
         let freed_count = to_free.len();
+        self.reclaimed_count
+            .fetch_add(freed_count, Ordering::Relaxed);
 
         // Free the safe nodes
         for ptr in to_free {
@@ -143,21 +622,137 @@ impl<T> Drop for HazardPointers<T> {
 
         // If there are still pointers in the retire list, that means they're
         // still protected by some thread, which is a bug (memory leak)
-        let retire = self
-            .retire_list
-            .lock()
-            .expect("Failed to lock retire list - mutex poisoned");
-        if !retire.is_empty() {
+        let report = self.debug_report();
+        if report.pending > 0 {
             // Just log a warning in a real application you might want to panic
-            eprintln!("Warning: HazardPointers dropped with {} items still in retire list. This is a memory leak.", retire.len());
+            eprintln!(
+                "Warning: HazardPointers dropped with {} items still in retire list. This is a memory leak.",
+                report.pending
+            );
+            eprint!("{report}");
+        }
+
+        // Free the hazard records; no other thread can reach them anymore
+        let mut current = self.records.load(Ordering::Relaxed);
+        while !current.is_null() {
+            let record = unsafe { Box::from_raw(current) };
+            current = record.next;
+        }
+    }
+}
+
+/// A pointer protected for the current thread, returned by
+/// `HazardPointers::protect()`
+///
+/// The pointee can't be reclaimed while the guard lives; dropping the guard
+/// clears its hazard slot, and releases the thread's record once no slot is
+/// in use anymore.
+pub struct HazardGuard<'d, T> {
+    /// The record holding the hazard
+    record: &'d HazardRecord<T>,
+    /// The slot of the record holding the hazard
+    index: usize,
+    /// The protected pointer
+    ptr: NonNull<T>,
+}
+
+impl<T> HazardGuard<'_, T> {
+    /// Gets the protected pointer
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+}
+
+impl<T> Deref for HazardGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: the pointer was still reachable once protected, so it can't
+        // have been reclaimed, and stays protected while the guard lives
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for HazardGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HazardGuard")
+            .field("ptr", &self.ptr)
+            .field("slot", &self.index)
+            .field("value", &**self)
+            .finish()
+    }
+}
+
+impl<T> Drop for HazardGuard<'_, T> {
+    fn drop(&mut self) {
+        // Slots aren't reused while their guard lives, so this one still
+        // holds the guard's pointer
+        self.record.hazards[self.index].store(ptr::null_mut(), Ordering::Release);
+
+        // Release the record once the thread protects nothing; it stays
+        // cached, so the thread takes it back if it's still free
+        let token = current_token();
+        if self.record.is_clear() && self.record.owner.load(Ordering::Relaxed) == token {
+            self.record.owner.store(0, Ordering::Release);
         }
     }
 }
 
+/// A hazard record reserved for one thread, created by
+/// `HazardPointers::register_current_thread()`
+///
+/// Protecting a pointer through the handle is a single store to the record.
+pub struct ThreadHazards<'a, T> {
+    /// The reserved record
+    record: &'a HazardRecord<T>,
+}
+
+impl<T> ThreadHazards<'_, T> {
+    /// Protects the given pointer from reclamation in the first slot
+    pub fn protect(&self, ptr: *mut T) -> *mut T {
+        self.protect_at(0, ptr)
+    }
+
+    /// Protects the given pointer in a slot, replacing the one it held
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `HAZARD_SLOTS`.
+    pub fn protect_at(&self, index: usize, ptr: *mut T) -> *mut T {
+        self.record.protect_at(index, ptr);
+        ptr
+    }
+
+    /// Clears one slot
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `HAZARD_SLOTS`.
+    pub fn clear_at(&self, index: usize) {
+        self.record.protect_at(index, ptr::null_mut());
+    }
+
+    /// Clears every slot, keeping the record reserved
+    pub fn clear(&self) {
+        self.record.clear();
+    }
+}
+
+impl<T> Drop for ThreadHazards<'_, T> {
+    fn drop(&mut self) {
+        self.clear();
+        self.record.owner.store(0, Ordering::Release);
+    }
+}
+
 /// A node in our lock-free stack
+///
+/// The value is moved out when the node is unlinked, and the node is only
+/// freed later, once no hazard pointer protects it; `ManuallyDrop` keeps the
+/// reclamation from dropping the value a second time.
 pub struct Node<T> {
-    /// The value stored in this node
-    pub value: T,
+    /// The value stored in this node, moved out once the node is unlinked
+    pub value: ManuallyDrop<T>,
     /// Pointer to the next node in the stack
     pub next: *mut Node<T>,
 }
@@ -165,7 +760,7 @@ pub struct Node<T> {
 impl<T: fmt::Debug> fmt::Debug for Node<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Node")
-            .field("value", &self.value)
+            .field("value", &*self.value)
             .field("next", &self.next)
             .finish()
     }
@@ -189,9 +784,14 @@ pub struct LockFreeStack<T> {
 impl<T> LockFreeStack<T> {
     /// Creates a new empty stack
     pub fn new(verbose: bool) -> Self {
+        Self::with_config(verbose, HazardConfig::default())
+    }
+
+    /// Creates a new empty stack reclaiming popped nodes with the given thresholds
+    pub fn with_config(verbose: bool, config: HazardConfig) -> Self {
         LockFreeStack {
             head: AtomicPtr::new(ptr::null_mut()),
-            hazard_pointers: Arc::new(HazardPointers::new()),
+            hazard_pointers: Arc::new(HazardPointers::with_config(config)),
             size: AtomicUsize::new(0),
             verbose,
         }
@@ -201,7 +801,7 @@ impl<T> LockFreeStack<T> {
     pub fn push(&self, value: T) -> Result<(), String> {
         // Create a new node
         let new_node = Box::into_raw(Box::new(Node {
-            value,
+            value: ManuallyDrop::new(value),
             next: ptr::null_mut(),
         }));
 
@@ -255,39 +855,93 @@ impl<T> LockFreeStack<T> {
         }
     }
 
+    /// Pushes several values onto the stack at once
+    ///
+    /// The values are linked into a private chain first, then the chain is
+    /// spliced onto the head with a single CAS, so they end up next to each
+    /// other and in the same order as if pushed one by one: the last value
+    /// is on top.
+    pub fn push_all(&self, values: impl IntoIterator<Item = T>) -> Result<(), String> {
+        // Build the chain top-down, remembering its bottom node to link it
+        // to the current head
+        let mut top: *mut Node<T> = ptr::null_mut();
+        let mut bottom: *mut Node<T> = ptr::null_mut();
+        let mut count = 0;
+        for value in values {
+            top = Box::into_raw(Box::new(Node {
+                value: ManuallyDrop::new(value),
+                next: top,
+            }));
+            if bottom.is_null() {
+                bottom = top;
+            }
+            count += 1;
+        }
+        if top.is_null() {
+            return Ok(());
+        }
+
+        let mut current_head = self.head.load(Ordering::Acquire);
+        loop {
+            // Only the bottom node needs relinking on every attempt
+            unsafe {
+                (*bottom).next = current_head;
+            }
+
+            if self.verbose {
+                println!(
+                    "Attempting to push {} nodes: {:p} to {:p} with next pointing to: {:p}",
+                    count, top, bottom, current_head
+                );
+            }
+
+            match self.head.compare_exchange(
+                current_head,
+                top,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.size.fetch_add(count, Ordering::Relaxed);
+                    if self.verbose {
+                        println!("Successfully pushed {} nodes: {:p}", count, top);
+                    }
+                    return Ok(());
+                }
+                Err(actual_head) => {
+                    if self.verbose {
+                        println!(
+                            "Push conflict detected! Expected head: {:p}, actual head: {:p}",
+                            current_head, actual_head
+                        );
+                    }
+                    current_head = actual_head;
+                }
+            }
+        }
+    }
+
     /// Pops a value from the stack
     pub fn pop(&self) -> Option<T> {
         loop {
-            // Get the current head with Acquire ordering to ensure
-            // we see all previous writes to the stack
-            let current_head = self.head.load(Ordering::Acquire);
-            if current_head.is_null() {
+            // Load and protect the head; protect() re-validates it after
+            // marking it as hazardous, so other threads can't free it while
+            // the guard lives - this is the crucial ABA prevention step
+            let Some(head) = self.hazard_pointers.protect(&self.head) else {
                 // Stack is empty
                 if self.verbose {
                     println!("Stack is empty, cannot pop");
                 }
                 return None;
-            }
+            };
+            let current_head = head.as_ptr();
 
             if self.verbose {
                 println!("Attempting to pop head: {:p}", current_head);
             }
 
-            // Mark this pointer as hazardous before accessing it
-            // This prevents other threads from freeing it while we're using it
-            let protected_head = self.hazard_pointers.protect(current_head);
-
-            // Check if the head has changed since we loaded it
-            // This is a crucial ABA prevention step - if head changed, retry
-            if self.head.load(Ordering::Acquire) != current_head {
-                if self.verbose {
-                    println!("Head changed during protection, retrying pop");
-                }
-                continue;
-            }
-
-            // Get the next node - safe because we've protected the pointer
-            let next = unsafe { (*protected_head).next };
+            // Get the next node - safe because the guard protects the head
+            let next = head.next;
 
             // Try to update the head to the next node
             // Release ensures all previous writes are visible to other threads
@@ -298,11 +952,11 @@ impl<T> LockFreeStack<T> {
                 Ordering::Relaxed, // Failure case can be Relaxed as we'll retry anyway
             ) {
                 Ok(_) => {
-                    // Successfully popped the node, extract its value
+                    // Successfully popped the node, extract its value; the
+                    // node won't drop it when reclaimed
                     let value = unsafe {
                         // Move out the value
-                        let v = std::ptr::read(&(*protected_head).value);
-                        v
+                        ManuallyDrop::into_inner(std::ptr::read(&head.value))
                     };
 
                     self.size.fetch_sub(1, Ordering::Relaxed);
@@ -310,13 +964,13 @@ impl<T> LockFreeStack<T> {
                     if self.verbose {
                         println!(
                             "Successfully popped head: {:p}, new head: {:p}",
-                            protected_head, next
+                            current_head, next
                         );
                     }
 
                     // Clear hazard pointer and schedule node for reclamation
-                    self.hazard_pointers.clear_hazards();
-                    self.hazard_pointers.retire(protected_head);
+                    drop(head);
+                    self.hazard_pointers.retire(current_head);
 
                     return Some(value);
                 }
@@ -331,6 +985,119 @@ impl<T> LockFreeStack<T> {
         }
     }
 
+    /// Pops up to `n` values from the stack at once
+    ///
+    /// The nodes to take are walked under hazard pointers, then detached with
+    /// a single CAS of the head, which is only retried if the head changed in
+    /// the meantime. Values are returned from top to bottom.
+    pub fn try_pop_n(&self, n: usize) -> Vec<T> {
+        // Hazard slots protecting the first and last nodes to take
+        const FIRST_SLOT: usize = 0;
+        const LAST_SLOT: usize = 1;
+
+        let mut values = Vec::new();
+        if n == 0 {
+            return values;
+        }
+
+        let hazards = self.hazard_pointers.register_current_thread();
+        let (first, count) = 'retry: loop {
+            let first = self.head.load(Ordering::Acquire);
+            if first.is_null() {
+                if self.verbose {
+                    println!("Stack is empty, cannot pop");
+                }
+                return values;
+            }
+            hazards.protect_at(FIRST_SLOT, first);
+            if self.head.load(Ordering::SeqCst) != first {
+                continue;
+            }
+
+            // The nodes below the head only change once the head is popped,
+            // and the protected head can't come back, so each node is safe to
+            // read if the head is still the same after protecting it
+            let mut last = first;
+            let mut count = 1;
+            while count < n {
+                let next = unsafe { (*last).next };
+                if next.is_null() {
+                    break;
+                }
+                hazards.protect_at(LAST_SLOT, next);
+                if self.head.load(Ordering::SeqCst) != first {
+                    if self.verbose {
+                        println!("Head changed during batch pop, retrying");
+                    }
+                    continue 'retry;
+                }
+                last = next;
+                count += 1;
+            }
+
+            if self.verbose {
+                println!(
+                    "Attempting to pop {} nodes: {:p} to {:p}",
+                    count, first, last
+                );
+            }
+
+            let next = unsafe { (*last).next };
+            match self.head.compare_exchange(
+                first,
+                next,
+                Ordering::Release, // Success case needs Release to make changes visible
+                Ordering::Relaxed, // Failure case can be Relaxed as we'll retry anyway
+            ) {
+                Ok(_) => break (first, count),
+                Err(_) => {
+                    if self.verbose {
+                        println!("Pop conflict detected! Head changed during CAS");
+                    }
+                }
+            }
+        };
+        drop(hazards);
+
+        // Each detached node may have been the head while we walked, so
+        // treat them all like pop() does
+        let mut current = first;
+        for _ in 0..count {
+            let node = unsafe { &*current };
+            values.push(unsafe { ManuallyDrop::into_inner(std::ptr::read(&node.value)) });
+            let next = node.next;
+            self.hazard_pointers.retire(current);
+            current = next;
+        }
+        self.size.fetch_sub(count, Ordering::Relaxed);
+
+        if self.verbose {
+            println!(
+                "Successfully popped {} nodes, new head: {:p}",
+                count, current
+            );
+        }
+
+        values
+    }
+
+    /// Runs a closure on a copy of the value at the top of the stack,
+    /// without popping it
+    ///
+    /// The head is protected by a hazard pointer while its value is copied
+    /// out, then the closure runs on the copy, so it never holds up the other
+    /// operations and may use the stack itself. Values have to be `Copy`, as
+    /// a concurrent pop() moves the value out of the node: any memory the
+    /// value owned could be freed by its new owner while being read. Returns
+    /// `None` if the stack is empty.
+    pub fn peek_with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R>
+    where
+        T: Copy,
+    {
+        let value = *self.hazard_pointers.protect(&self.head)?.value;
+        Some(f(&value))
+    }
+
     /// Returns the current size of the stack
     pub fn len(&self) -> usize {
         // Relaxed is sufficient for a simple counter read
@@ -341,6 +1108,131 @@ impl<T> LockFreeStack<T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Takes every value out of the stack at once
+    ///
+    /// The whole list is stolen with a single swap of the head, so values
+    /// pushed concurrently either end up in the iterator or stay on the
+    /// stack. Values are yielded from top to bottom.
+    pub fn drain(&self) -> Drain<T> {
+        let head = self.head.swap(ptr::null_mut(), Ordering::SeqCst);
+        if head.is_null() {
+            return Drain {
+                first: None,
+                rest: ptr::null_mut(),
+                hazard_pointers: Arc::clone(&self.hazard_pointers),
+            };
+        }
+
+        // Only the old head may be protected by other operations; treat it
+        // like pop() does
+        let node = unsafe { &*head };
+        let first = unsafe { ManuallyDrop::into_inner(std::ptr::read(&node.value)) };
+        let rest = node.next;
+        self.hazard_pointers.retire(head);
+
+        // The remaining nodes are now only reachable through the iterator
+        let mut drained = 1;
+        let mut current = rest;
+        while !current.is_null() {
+            drained += 1;
+            current = unsafe { (*current).next };
+        }
+        self.size.fetch_sub(drained, Ordering::Relaxed);
+
+        if self.verbose {
+            println!("Drained {} nodes starting at: {:p}", drained, head);
+        }
+
+        Drain {
+            first: Some(first),
+            rest,
+            hazard_pointers: Arc::clone(&self.hazard_pointers),
+        }
+    }
+}
+
+/// An owning iterator over the values taken by `LockFreeStack::drain()`
+///
+/// No other thread can reach the nodes it holds, so they are freed as soon
+/// as their value is yielded, without going through the retire list. A
+/// `try_pop_n()` call that walked down the stack before it was drained may
+/// still protect a node for a moment though, so freeing waits for that.
+pub struct Drain<T> {
+    /// The value of the old head, already moved out of its retired node
+    first: Option<T>,
+    /// The nodes after the old head
+    rest: *mut Node<T>,
+    /// Registry of the drained stack, telling which nodes are protected
+    hazard_pointers: Arc<HazardPointers<Node<T>>>,
+}
+
+// Safety: the iterator owns the values and nodes it holds
+unsafe impl<T: Send> Send for Drain<T> {}
+
+impl<T> Iterator for Drain<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
+        }
+        if self.rest.is_null() {
+            return None;
+        }
+
+        // try_pop_n() calls see the head has changed as soon as they check
+        // again, so they never protect a node for long
+        while self.hazard_pointers.is_protected(self.rest) {
+            hint::spin_loop();
+        }
+        let Node { value, next, .. } = *unsafe { Box::from_raw(self.rest) };
+        self.rest = next;
+        Some(ManuallyDrop::into_inner(value))
+    }
+}
+
+impl<T> Drop for Drain<T> {
+    fn drop(&mut self) {
+        // Drop the values that weren't yielded and free their nodes
+        self.for_each(drop);
+    }
+}
+
+impl<T> Extend<T> for LockFreeStack<T> {
+    /// Pushes the values with a single `push_all()`
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.push_all(iter).expect("Failed to push values");
+    }
+}
+
+impl<T> FromIterator<T> for LockFreeStack<T> {
+    /// Creates a quiet stack with the last value on top
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = LockFreeStack::new(false);
+        stack.extend(iter);
+        stack
+    }
+}
+
+impl<T> IntoIterator for LockFreeStack<T> {
+    type Item = T;
+    type IntoIter = Drain<T>;
+
+    /// Drains the stack, yielding values from top to bottom
+    fn into_iter(self) -> Drain<T> {
+        self.drain()
+    }
+}
+
+/// Only shows the length, as other threads may be changing the stack and
+/// values can't be read in place
+impl<T> fmt::Debug for LockFreeStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockFreeStack")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
 }
 
 /// Clean up resources when the stack is dropped
@@ -354,9 +1246,10 @@ impl<T> Drop for LockFreeStack<T> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod tests {
     use super::*;
+    use std::panic;
     use std::thread;
     use std::time::Duration;
 
@@ -435,15 +1328,14 @@ mod tests {
         // Thread 1: Start pop operation but get interrupted
         let handle1 = thread::spawn(move || {
             // Begin pop operation and protect head
-            let head = stack_clone1.head.load(Ordering::Acquire);
-            stack_clone1.hazard_pointers.protect(head);
+            let guard = stack_clone1.hazard_pointers.protect(&stack_clone1.head);
 
             // Pause to allow Thread 2 to run
             thread::sleep(Duration::from_millis(100));
 
             // Try to complete the pop operation
             let result = stack_clone1.pop();
-            stack_clone1.hazard_pointers.clear_hazards();
+            drop(guard);
             result
         });
 
@@ -467,4 +1359,727 @@ mod tests {
         // Verify operation succeeded
         assert!(thread1_result.is_some());
     }
+
+    #[test]
+    fn test_protected_pointer_is_not_reclaimed() {
+        let hazards = HazardPointers::new();
+        let source = AtomicPtr::new(Box::into_raw(Box::new(1)));
+        let guard = hazards
+            .protect(&source)
+            .expect("Pointer should be protected");
+
+        hazards.retire(guard.as_ptr());
+        for i in 0..5 {
+            hazards.retire(Box::into_raw(Box::new(i)));
+        }
+        // Only the unprotected pointers are freed
+        assert_eq!(hazards.try_reclaim(true), 5);
+        assert_eq!(*guard, 1);
+
+        drop(guard);
+        assert_eq!(hazards.try_reclaim(true), 1);
+    }
+
+    #[test]
+    fn test_protect_null_returns_none() {
+        let hazards = HazardPointers::<i32>::new();
+        assert!(hazards.protect(&AtomicPtr::new(ptr::null_mut())).is_none());
+    }
+
+    #[test]
+    fn test_hazard_records_are_reused() {
+        let hazards = Arc::new(HazardPointers::<i32>::new());
+        let mut value = 1;
+        let source = Arc::new(AtomicPtr::new(&mut value));
+
+        // Threads running one after another share the same record
+        for _ in 0..4 {
+            let hazards = Arc::clone(&hazards);
+            let source = Arc::clone(&source);
+            thread::spawn(move || {
+                let guard = hazards.protect(&source);
+                assert!(guard.is_some());
+            })
+            .join()
+            .expect("Thread panicked");
+        }
+        assert_eq!(hazards.records().count(), 1);
+
+        // A thread holding several guards keeps a single record
+        let first = hazards.protect(&source);
+        let second = hazards.protect(&source);
+        assert_eq!(hazards.records().count(), 1);
+        drop((first, second));
+    }
+
+    #[test]
+    fn test_concurrent_protect_uses_separate_records() {
+        let hazards = Arc::new(HazardPointers::<i32>::new());
+        let threads = 8;
+        let barrier = Arc::new(std::sync::Barrier::new(threads));
+
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let hazards = Arc::clone(&hazards);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    let source = AtomicPtr::new(Box::into_raw(Box::new(i as i32)));
+                    let guard = hazards
+                        .protect(&source)
+                        .expect("Pointer should be protected");
+                    // Every thread holds its hazard while the others protect theirs
+                    barrier.wait();
+                    let protected = hazards
+                        .records()
+                        .filter(|record| record.hazards[0].load(Ordering::SeqCst) == guard.as_ptr())
+                        .count();
+                    barrier.wait();
+                    drop(guard);
+                    drop(unsafe { Box::from_raw(source.into_inner()) });
+                    protected
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().expect("Thread panicked"), 1);
+        }
+        assert_eq!(hazards.records().count(), threads);
+    }
+
+    #[test]
+    fn test_cached_record_is_taken_back_after_release() {
+        let hazards = HazardPointers::<i32>::new();
+        let mut value = 1;
+        let source = AtomicPtr::new(&mut value);
+
+        let guard = hazards.protect(&source);
+        let first: *const HazardRecord<i32> = hazards.current_record();
+        // Dropping the last guard releases the record but keeps it cached
+        drop(guard);
+        let cached = hazards.cached_record().expect("Record should be cached");
+        assert_eq!(cached.owner.load(Ordering::Relaxed), 0);
+        assert!(ptr::eq(first, cached));
+
+        let guard = hazards.protect(&source);
+        assert!(ptr::eq(first, hazards.current_record()));
+        drop(guard);
+        assert_eq!(hazards.records().count(), 1);
+    }
+
+    #[test]
+    fn test_thread_hazards_handle() {
+        let hazards = HazardPointers::new();
+        let protected = Box::into_raw(Box::new(1));
+
+        let handle = hazards.register_current_thread();
+        handle.protect(protected);
+        // The handle's record is separate from the one used by protect()
+        drop(hazards.protect(&AtomicPtr::new(protected)));
+        assert_eq!(hazards.records().count(), 2);
+
+        hazards.retire(protected);
+        assert_eq!(hazards.try_reclaim(true), 0);
+        handle.protect(ptr::null_mut());
+        assert_eq!(hazards.try_reclaim(true), 1);
+
+        // Dropping the handle releases its record
+        drop(handle);
+        let handle = hazards.register_current_thread();
+        handle.clear();
+        drop(handle);
+        assert_eq!(hazards.records().count(), 2);
+        assert!(
+            hazards
+                .records()
+                .all(|record| record.owner.load(Ordering::Relaxed) == 0)
+        );
+    }
+
+    #[test]
+    fn test_registry_registers_threads() {
+        let registry = HazardRegistry::new();
+        let mut value = 1;
+        let handle: ThreadHazards<'_, i32> = registry.register_current_thread();
+        handle.protect(&mut value);
+        assert_eq!(registry.stats().protected, 1);
+        drop(handle);
+        assert_eq!(registry.stats().protected, 0);
+    }
+
+    #[test]
+    fn test_multiple_hazards_per_thread() {
+        let hazards = HazardPointers::new();
+        let first = AtomicPtr::new(Box::into_raw(Box::new(1)));
+        let second = AtomicPtr::new(Box::into_raw(Box::new(2)));
+
+        // Protect two nodes at once, like a dequeue protecting head and next
+        let first_guard = hazards
+            .protect(&first)
+            .expect("Pointer should be protected");
+        let second_guard = hazards
+            .protect(&second)
+            .expect("Pointer should be protected");
+        hazards.retire(first_guard.as_ptr());
+        hazards.retire(second_guard.as_ptr());
+        assert_eq!(hazards.try_reclaim(true), 0);
+        assert_eq!(hazards.records().count(), 1);
+
+        // Dropping a guard leaves the other pointer protected
+        drop(first_guard);
+        assert_eq!(hazards.try_reclaim(true), 1);
+        assert_eq!(*second_guard, 2);
+
+        drop(second_guard);
+        assert_eq!(hazards.try_reclaim(true), 1);
+    }
+
+    #[test]
+    fn test_protect_at_keeps_occupied_slots() {
+        let hazards = HazardPointers::new();
+        let first = AtomicPtr::new(Box::into_raw(Box::new(1)));
+        let mut second = 2;
+        let second = AtomicPtr::new(&mut second);
+
+        let guard = hazards
+            .protect_at(2, &first)
+            .expect("Pointer should be protected");
+        let replaced = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            hazards.protect_at(2, &second).map(|guard| guard.as_ptr())
+        }));
+        assert!(replaced.is_err());
+
+        // The guard's pointer is still protected, so it can't be freed under it
+        hazards.retire(guard.as_ptr());
+        assert_eq!(hazards.try_reclaim(true), 0);
+        assert_eq!(*guard, 1);
+
+        drop(guard);
+        assert!(hazards.current_record().is_clear());
+        assert_eq!(hazards.try_reclaim(true), 1);
+
+        // The slot can be used again once its guard is dropped
+        let guard = hazards
+            .protect_at(2, &second)
+            .expect("Pointer should be protected");
+        assert_eq!(*guard, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "hazard slot 4 out of range")]
+    fn test_protect_at_rejects_out_of_range_slots() {
+        let hazards = HazardPointers::<i32>::new();
+        let mut value = 1;
+        let _guard = hazards.protect_at(HAZARD_SLOTS, &AtomicPtr::new(&mut value));
+    }
+
+    #[test]
+    #[should_panic(expected = "all 4 hazard slots of the thread are in use")]
+    fn test_protect_rejects_too_many_guards() {
+        let hazards = HazardPointers::<i32>::new();
+        let mut value = 1;
+        let source = AtomicPtr::new(&mut value);
+        let _guards: Vec<_> = (0..=HAZARD_SLOTS)
+            .map(|_| hazards.protect(&source))
+            .collect();
+    }
+
+    #[test]
+    fn test_reclamation_thresholds() {
+        let config = HazardConfig {
+            retire_threshold: 3,
+            scan_threshold: 1,
+        };
+        let hazards = HazardPointers::with_config(config);
+        assert_eq!(hazards.config(), config);
+
+        // Retiring more pointers than the retire threshold reclaims them
+        for i in 0..3 {
+            hazards.retire(Box::into_raw(Box::new(i)));
+        }
+        assert_eq!(hazards.retire_list.lock().expect("Lock poisoned").len(), 3);
+        hazards.retire(Box::into_raw(Box::new(3)));
+        assert!(
+            hazards
+                .retire_list
+                .lock()
+                .expect("Lock poisoned")
+                .is_empty()
+        );
+
+        // Scans are skipped up to the scan threshold unless forced
+        hazards.retire(Box::into_raw(Box::new(4)));
+        assert_eq!(hazards.try_reclaim(false), 0);
+        hazards.retire(Box::into_raw(Box::new(5)));
+        assert_eq!(hazards.try_reclaim(false), 2);
+        hazards.retire(Box::into_raw(Box::new(6)));
+        assert_eq!(hazards.try_reclaim(true), 1);
+    }
+
+    #[test]
+    fn test_stack_with_config() {
+        let config = HazardConfig {
+            retire_threshold: 0,
+            scan_threshold: 0,
+        };
+        let stack = LockFreeStack::with_config(false, config);
+        assert_eq!(stack.hazard_pointers.config(), config);
+
+        // Every popped node is reclaimed right away
+        stack.push(1).expect("Push should succeed");
+        stack.push(2).expect("Push should succeed");
+        assert_eq!(stack.pop(), Some(2));
+        assert!(
+            stack
+                .hazard_pointers
+                .retire_list
+                .lock()
+                .expect("Lock poisoned")
+                .is_empty()
+        );
+        assert_eq!(stack.pop(), Some(1));
+    }
+
+    #[test]
+    fn test_stats() {
+        let hazards = HazardPointers::new();
+        assert_eq!(hazards.stats(), HazardStats::default());
+
+        let source = AtomicPtr::new(Box::into_raw(Box::new(0)));
+        let guard = hazards
+            .protect(&source)
+            .expect("Pointer should be protected");
+        hazards.retire(guard.as_ptr());
+        for i in 1..4 {
+            hazards.retire(Box::into_raw(Box::new(i)));
+        }
+        assert_eq!(
+            hazards.stats(),
+            HazardStats {
+                retired: 4,
+                reclaimed: 0,
+                protected: 1,
+                pending: 4,
+            }
+        );
+
+        hazards.try_reclaim(true);
+        drop(guard);
+        assert_eq!(
+            hazards.stats(),
+            HazardStats {
+                retired: 4,
+                reclaimed: 3,
+                protected: 0,
+                pending: 1,
+            }
+        );
+        assert_eq!(
+            hazards.stats().pending,
+            hazards.retire_list.lock().expect("Lock poisoned").len()
+        );
+    }
+
+    #[test]
+    fn test_debug_report() {
+        let hazards = Arc::new(HazardPointers::new());
+        let first = Arc::new(AtomicPtr::new(Box::into_raw(Box::new(1))));
+        let second = AtomicPtr::new(Box::into_raw(Box::new(2)));
+        assert_eq!(hazards.debug_report(), HazardReport::default());
+
+        // This thread protects two retired nodes, one through a handle
+        let guard = hazards
+            .protect(&first)
+            .expect("Pointer should be protected");
+        let handle = hazards.register_current_thread();
+        handle.protect(second.load(Ordering::SeqCst));
+        hazards.retire(first.load(Ordering::SeqCst));
+        hazards.retire(second.load(Ordering::SeqCst));
+        hazards.retire(Box::into_raw(Box::new(3)));
+
+        // Another thread protects one of them, without keeping anything else alive
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let other = {
+            let (hazards, first) = (Arc::clone(&hazards), Arc::clone(&first));
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                let guard = hazards.protect(&first);
+                // Signal the hazard is held, then wait for the report to be checked
+                barrier.wait();
+                barrier.wait();
+                drop(guard);
+                thread::current().id()
+            })
+        };
+        barrier.wait();
+
+        let report = hazards.debug_report();
+        assert_eq!(report.pending, 3);
+        assert_eq!(
+            report
+                .holders
+                .iter()
+                .find(|holder| holder.thread == thread::current().id()),
+            Some(&HazardHolder {
+                thread: thread::current().id(),
+                hazards: 2,
+                blocked: 2,
+            })
+        );
+        assert_eq!(report.holders.len(), 2);
+        assert!(
+            report
+                .to_string()
+                .starts_with("3 retired node(s) pending reclamation\n")
+        );
+
+        barrier.wait();
+        let other_id = other.join().expect("Thread panicked");
+        let report = hazards.debug_report();
+        assert!(
+            report
+                .holders
+                .iter()
+                .all(|holder| holder.thread != other_id)
+        );
+
+        drop((guard, handle));
+        hazards.try_reclaim(true);
+        assert_eq!(hazards.debug_report(), HazardReport::default());
+    }
+
+    #[test]
+    fn test_peek_with() {
+        let stack = LockFreeStack::new(false);
+        assert_eq!(stack.peek_with(|value: &i32| *value), None);
+
+        stack.push(1).expect("Push should succeed");
+        stack.push(2).expect("Push should succeed");
+        assert_eq!(stack.peek_with(|value| value * 10), Some(20));
+        assert_eq!(stack.len(), 2);
+
+        // Nothing stays protected once the value is copied, so the closure
+        // can use the stack
+        assert_eq!(
+            stack.peek_with(|_| stack.hazard_pointers.stats().protected),
+            Some(0)
+        );
+        assert_eq!(stack.peek_with(|_| stack.pop()), Some(Some(2)));
+        stack.push(2).expect("Push should succeed");
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.peek_with(|value| *value), Some(1));
+    }
+
+    #[test]
+    fn test_peek_with_concurrent_pops() {
+        let stack = Arc::new(LockFreeStack::new(false));
+        let values = 1000;
+        for i in 0..values {
+            stack.push([i; 4]).expect("Push should succeed");
+        }
+
+        // Peekers read the heads while they're popped
+        let peekers: Vec<_> = (0..2)
+            .map(|_| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    while let Some(value) = stack.peek_with(|value| *value) {
+                        assert!(value.iter().all(|item| *item == value[0] && *item < values));
+                    }
+                })
+            })
+            .collect();
+        let popper = {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || {
+                let mut popped = 0;
+                while stack.pop().is_some() {
+                    popped += 1;
+                }
+                popped
+            })
+        };
+
+        assert_eq!(popper.join().expect("Popper panicked"), values);
+        for peeker in peekers {
+            peeker.join().expect("Peeker panicked");
+        }
+    }
+
+    #[test]
+    fn test_drain() {
+        let stack = LockFreeStack::new(false);
+        assert_eq!(stack.drain().next(), None);
+
+        for i in 1..=4 {
+            stack.push(i).expect("Push should succeed");
+        }
+        let mut drain = stack.drain();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+
+        // The stack can be used while the drained values are consumed
+        stack.push(5).expect("Push should succeed");
+        assert_eq!(drain.next(), Some(4));
+        assert_eq!(drain.collect::<Vec<_>>(), [3, 2, 1]);
+        assert_eq!(stack.len(), 1);
+
+        // Only the old head went through the retire list
+        assert_eq!(stack.hazard_pointers.stats().retired, 1);
+    }
+
+    #[test]
+    fn test_concurrent_drain() {
+        let stack = Arc::new(LockFreeStack::new(false));
+        let threads = 4;
+        let operations_per_thread = 250;
+
+        let pushers: Vec<_> = (0..threads)
+            .map(|i| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    for j in 0..operations_per_thread {
+                        stack
+                            .push(i * operations_per_thread + j)
+                            .expect("Push should succeed");
+                    }
+                })
+            })
+            .collect();
+        let drainer = {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || {
+                let mut drained = Vec::new();
+                for _ in 0..100 {
+                    drained.extend(stack.drain());
+                    drained.extend(stack.pop());
+                }
+                drained
+            })
+        };
+
+        for pusher in pushers {
+            pusher.join().expect("Pusher panicked");
+        }
+        let mut values = drainer.join().expect("Drainer panicked");
+        values.extend(stack.drain());
+
+        // Every value was taken exactly once
+        values.sort_unstable();
+        assert_eq!(
+            values,
+            (0..threads * operations_per_thread).collect::<Vec<_>>()
+        );
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_push_all() {
+        let stack = LockFreeStack::new(false);
+        stack.push_all(Vec::new()).expect("Push should succeed");
+        assert!(stack.is_empty());
+
+        stack.push(0).expect("Push should succeed");
+        stack.push_all(1..=3).expect("Push should succeed");
+        assert_eq!(stack.len(), 4);
+        assert_eq!(stack.drain().collect::<Vec<_>>(), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_concurrent_push_all() {
+        let stack = Arc::new(LockFreeStack::new(false));
+        let threads = 4;
+        let batches_per_thread = 50;
+        let batch_size = 8;
+
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    for j in 0..batches_per_thread {
+                        let batch = (i * batches_per_thread + j) * batch_size;
+                        stack
+                            .push_all(batch..batch + batch_size)
+                            .expect("Push should succeed");
+                        stack.push(usize::MAX).expect("Push should succeed");
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().expect("Thread panicked");
+        }
+        assert_eq!(stack.len(), threads * batches_per_thread * (batch_size + 1));
+
+        // Single pushes interleave with the batches but never split them
+        let values: Vec<_> = stack.drain().filter(|&value| value != usize::MAX).collect();
+        for batch in values.chunks(batch_size) {
+            let bottom = batch[batch_size - 1];
+            assert_eq!(bottom % batch_size, 0);
+            assert!(batch.iter().rev().copied().eq(bottom..bottom + batch_size));
+        }
+    }
+
+    #[test]
+    fn test_try_pop_n() {
+        let stack = LockFreeStack::new(false);
+        assert!(stack.try_pop_n(3).is_empty());
+
+        stack.push_all(1..=5).expect("Push should succeed");
+        assert!(stack.try_pop_n(0).is_empty());
+        assert_eq!(stack.try_pop_n(2), [5, 4]);
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.try_pop_n(10), [3, 2, 1]);
+        assert!(stack.is_empty());
+        assert_eq!(stack.hazard_pointers.stats().retired, 5);
+    }
+
+    #[test]
+    fn test_concurrent_try_pop_n() {
+        let stack = Arc::new(LockFreeStack::new(false));
+        let threads = 4;
+        let operations_per_thread = 500;
+
+        // Batch pops race with pushes, single pops and drains
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for j in 0..operations_per_thread {
+                        stack
+                            .push(i * operations_per_thread + j)
+                            .expect("Push should succeed");
+                        match j % 8 {
+                            0 => popped.extend(stack.pop()),
+                            7 if i == 0 => popped.extend(stack.drain()),
+                            _ if j % 3 == 0 => popped.extend(stack.try_pop_n(4)),
+                            _ => {}
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut values: Vec<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Thread panicked"))
+            .collect();
+        values.extend(stack.try_pop_n(usize::MAX));
+
+        // Every value was taken exactly once
+        values.sort_unstable();
+        assert_eq!(
+            values,
+            (0..threads * operations_per_thread).collect::<Vec<_>>()
+        );
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_std_traits() {
+        let mut stack: LockFreeStack<_> = (1..=3).collect();
+        assert_eq!(format!("{stack:?}"), "LockFreeStack { len: 3, .. }");
+
+        stack.extend([4, 5]);
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.into_iter().collect::<Vec<_>>(), [5, 4, 3, 2, 1]);
+
+        let empty = LockFreeStack::<i32>::new(false);
+        assert_eq!(format!("{empty:?}"), "LockFreeStack { len: 0, .. }");
+    }
+
+    /// Counts how many times values are dropped
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_string_values() {
+        let config = HazardConfig {
+            retire_threshold: 0,
+            scan_threshold: 0,
+        };
+        let stack = LockFreeStack::with_config(false, config);
+        for i in 0..8 {
+            stack
+                .push(format!("value {i}"))
+                .expect("Push should succeed");
+        }
+
+        // Popped nodes are reclaimed right away, without touching the
+        // values moved out of them
+        assert_eq!(stack.pop().as_deref(), Some("value 7"));
+        assert_eq!(stack.try_pop_n(2), ["value 6", "value 5"]);
+        assert_eq!(stack.hazard_pointers.stats().pending, 0);
+        assert_eq!(stack.drain().count(), 5);
+    }
+
+    #[test]
+    fn test_values_are_dropped_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = LockFreeStack::new(false);
+        let counter = || DropCounter(Arc::clone(&drops));
+        for _ in 0..20 {
+            stack.push(counter()).expect("Push should succeed");
+        }
+
+        // Values are dropped by their owner, never with their node
+        drop(stack.pop());
+        drop(stack.try_pop_n(3));
+        assert_eq!(drops.load(Ordering::SeqCst), 4);
+        stack.hazard_pointers.try_reclaim(true);
+        assert_eq!(drops.load(Ordering::SeqCst), 4);
+
+        // Values left in a partly consumed drain are dropped with it
+        let mut drain = stack.drain();
+        drop(drain.next());
+        stack
+            .push_all([counter(), counter()])
+            .expect("Push should succeed");
+        drop(drain);
+        assert_eq!(drops.load(Ordering::SeqCst), 20);
+
+        // Dropping the stack drops the values left in it
+        drop(stack);
+        assert_eq!(drops.load(Ordering::SeqCst), 22);
+    }
+
+    #[test]
+    fn test_concurrent_values_are_dropped_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = Arc::new(LockFreeStack::new(false));
+        let threads = 4;
+        let operations_per_thread = 500;
+
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let stack = Arc::clone(&stack);
+                let drops = Arc::clone(&drops);
+                thread::spawn(move || {
+                    for _ in 0..operations_per_thread {
+                        stack
+                            .push(DropCounter(Arc::clone(&drops)))
+                            .expect("Push should succeed");
+                        drop(stack.pop());
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().expect("Thread panicked");
+        }
+        drop(stack);
+        assert_eq!(
+            drops.load(Ordering::SeqCst),
+            threads * operations_per_thread
+        );
+    }
 }
@@ -0,0 +1,478 @@
+use crate::sync::{AtomicPtr, AtomicUsize, Ordering};
+use crate::{HazardConfig, HazardPointers, ThreadHazards};
+use std::cmp::Ordering as CmpOrdering;
+use std::ptr;
+use std::sync::Arc;
+
+/// Hazard slot protecting the node after the current one
+const NEXT_SLOT: usize = 0;
+/// Hazard slot protecting the current node
+const CURR_SLOT: usize = 1;
+/// Hazard slot protecting the node whose `next` link points to the current one
+const PREV_SLOT: usize = 2;
+
+/// A node in our lock-free list
+pub(crate) struct ListNode<T> {
+    /// The value stored in this node
+    value: T,
+    /// Pointer to the next node, with the lowest bit set once this node is removed
+    next: AtomicPtr<ListNode<T>>,
+}
+
+/// Sets the mark bit of a `next` link, flagging its node as removed
+fn mark<T>(ptr: *mut ListNode<T>) -> *mut ListNode<T> {
+    ptr.map_addr(|addr| addr | 1)
+}
+
+/// Clears the mark bit of a `next` link
+fn unmark<T>(ptr: *mut ListNode<T>) -> *mut ListNode<T> {
+    ptr.map_addr(|addr| addr & !1)
+}
+
+/// Checks the mark bit of a `next` link
+fn is_marked<T>(ptr: *mut ListNode<T>) -> bool {
+    ptr.addr() & 1 == 1
+}
+
+/// Where a value belongs in the list, as found by `LockFreeList::find`
+struct Position<T> {
+    /// The link pointing to `curr`: the list head or the previous node's `next`
+    prev: *const AtomicPtr<ListNode<T>>,
+    /// The first node whose value isn't less than the searched one, or null
+    curr: *mut ListNode<T>,
+    /// Whether `curr` holds the searched value
+    found: bool,
+}
+
+/// A lock-free sorted set using hazard pointers for memory management
+///
+/// This is the Harris–Michael list: nodes are kept sorted by value, and
+/// removed in two steps, first marking the node's `next` link so no node can
+/// be inserted after it, then unlinking it. Traversals protect up to three
+/// nodes at once (the previous, current and next ones) and unlink the marked
+/// nodes they come across, which makes it a more realistic use of hazard
+/// pointers than the stack.
+pub struct LockFreeList<T: Ord> {
+    /// Atomic pointer to the node with the smallest value
+    head: AtomicPtr<ListNode<T>>,
+    /// Hazard pointer registry used to protect nodes from reclamation
+    hazard_pointers: Arc<HazardPointers<ListNode<T>>>,
+    /// Counter tracking the current number of values
+    size: AtomicUsize,
+    /// Whether to print debug information
+    verbose: bool,
+}
+
+// Safety: values are moved between threads through the list and read by
+// several threads at once, and nodes are only accessed through atomics or
+// while protected by hazard pointers
+unsafe impl<T: Ord + Send + Sync> Send for LockFreeList<T> {}
+unsafe impl<T: Ord + Send + Sync> Sync for LockFreeList<T> {}
+
+impl<T: Ord> LockFreeList<T> {
+    /// Creates a new empty list
+    pub fn new(verbose: bool) -> Self {
+        Self::with_config(verbose, HazardConfig::default())
+    }
+
+    /// Creates a new empty list reclaiming removed nodes with the given thresholds
+    pub fn with_config(verbose: bool, config: HazardConfig) -> Self {
+        Self::with_registry(verbose, Arc::new(HazardPointers::with_config(config)))
+    }
+
+    /// Creates a new empty list sharing a hazard pointer registry, e.g. with
+    /// the other buckets of a `LockFreeMap`
+    pub(crate) fn with_registry(
+        verbose: bool,
+        hazard_pointers: Arc<HazardPointers<ListNode<T>>>,
+    ) -> Self {
+        LockFreeList {
+            head: AtomicPtr::new(ptr::null_mut()),
+            hazard_pointers,
+            size: AtomicUsize::new(0),
+            verbose,
+        }
+    }
+
+    /// Finds the position of a value, unlinking the removed nodes on the way
+    ///
+    /// `cmp` compares a node's value with the searched one. On return, `curr`
+    /// and the node owning `prev` are protected by the handle.
+    fn find(
+        &self,
+        cmp: &impl Fn(&T) -> CmpOrdering,
+        hazards: &ThreadHazards<'_, ListNode<T>>,
+    ) -> Position<T> {
+        'retry: loop {
+            let mut prev: *const AtomicPtr<ListNode<T>> = &self.head;
+            let mut curr = self.head.load(Ordering::Acquire);
+
+            loop {
+                if curr.is_null() {
+                    return Position {
+                        prev,
+                        curr,
+                        found: false,
+                    };
+                }
+
+                // Protect the current node, then check it's still linked from
+                // prev (whose node is protected, or is the head)
+                hazards.protect_at(CURR_SLOT, curr);
+                let prev_link = unsafe { &*prev };
+                if prev_link.load(Ordering::SeqCst) != curr {
+                    continue 'retry;
+                }
+
+                // Protect the next node the same way
+                let curr_node = unsafe { &*curr };
+                let next = curr_node.next.load(Ordering::Acquire);
+                hazards.protect_at(NEXT_SLOT, unmark(next));
+                if curr_node.next.load(Ordering::SeqCst) != next {
+                    continue 'retry;
+                }
+
+                if is_marked(next) {
+                    // The current node was removed, help unlinking it
+                    if self.verbose {
+                        println!("Unlinking removed node: {:p}", curr);
+                    }
+                    if prev_link
+                        .compare_exchange(curr, unmark(next), Ordering::Release, Ordering::Relaxed)
+                        .is_err()
+                    {
+                        continue 'retry;
+                    }
+                    hazards.clear_at(CURR_SLOT);
+                    self.hazard_pointers.retire(curr);
+                } else {
+                    match cmp(&curr_node.value) {
+                        CmpOrdering::Less => {
+                            // Move on, keeping the current node protected as prev
+                            prev = &curr_node.next;
+                            hazards.protect_at(PREV_SLOT, curr);
+                        }
+                        found => {
+                            return Position {
+                                prev,
+                                curr,
+                                found: found == CmpOrdering::Equal,
+                            };
+                        }
+                    }
+                }
+
+                // The next node stays protected in its slot until it's
+                // protected as the current one
+                curr = unmark(next);
+            }
+        }
+    }
+
+    /// Adds a value to the list
+    ///
+    /// Returns false, dropping the value, if the list already holds it.
+    pub fn insert(&self, value: T) -> bool {
+        let hazards = self.hazard_pointers.register_current_thread();
+        let new_node = Box::into_raw(Box::new(ListNode {
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }));
+        let value = unsafe { &(*new_node).value };
+        let cmp = |node: &T| node.cmp(value);
+
+        loop {
+            let position = self.find(&cmp, &hazards);
+            if position.found {
+                if self.verbose {
+                    println!(
+                        "Value already in the list, not inserting node: {:p}",
+                        new_node
+                    );
+                }
+                drop(unsafe { Box::from_raw(new_node) });
+                return false;
+            }
+
+            if self.verbose {
+                println!(
+                    "Attempting to insert node: {:p} before: {:p}",
+                    new_node, position.curr
+                );
+            }
+
+            // Link our node before the current one; this fails if a node was
+            // inserted in between, or if the previous node was removed
+            unsafe { (*new_node).next.store(position.curr, Ordering::Relaxed) };
+            let prev_link = unsafe { &*position.prev };
+            match prev_link.compare_exchange(
+                position.curr,
+                new_node,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    self.size.fetch_add(1, Ordering::Relaxed);
+                    if self.verbose {
+                        println!("Successfully inserted node: {:p}", new_node);
+                    }
+                    return true;
+                }
+                Err(_) => {
+                    if self.verbose {
+                        println!("Insert conflict detected! Link changed during CAS");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes a value from the list
+    ///
+    /// Returns false if the list doesn't hold the value.
+    pub fn remove(&self, value: &T) -> bool {
+        self.remove_by(|node| node.cmp(value))
+    }
+
+    /// Removes the value for which `cmp` returns `Equal`
+    ///
+    /// `cmp` must order values the same way as `Ord`, e.g. by comparing the
+    /// part of a value the ordering is based on.
+    pub(crate) fn remove_by(&self, cmp: impl Fn(&T) -> CmpOrdering) -> bool {
+        let hazards = self.hazard_pointers.register_current_thread();
+
+        loop {
+            let position = self.find(&cmp, &hazards);
+            if !position.found {
+                if self.verbose {
+                    println!("Value not in the list, nothing to remove");
+                }
+                return false;
+            }
+
+            // Logically remove the node by marking its next link; this fails
+            // if a node was inserted after it, or another thread removed it
+            let curr_node = unsafe { &*position.curr };
+            let next = curr_node.next.load(Ordering::Acquire);
+            if is_marked(next)
+                || curr_node
+                    .next
+                    .compare_exchange(next, mark(next), Ordering::AcqRel, Ordering::Relaxed)
+                    .is_err()
+            {
+                if self.verbose {
+                    println!("Remove conflict detected! Node changed during CAS");
+                }
+                continue;
+            }
+            self.size.fetch_sub(1, Ordering::Relaxed);
+
+            // Physically unlink it, or let a traversal do it
+            let prev_link = unsafe { &*position.prev };
+            if prev_link
+                .compare_exchange(position.curr, next, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                if self.verbose {
+                    println!("Successfully removed node: {:p}", position.curr);
+                }
+                hazards.clear();
+                self.hazard_pointers.retire(position.curr);
+            } else {
+                if self.verbose {
+                    println!("Node {:p} marked, unlinking it later", position.curr);
+                }
+                self.find(&cmp, &hazards);
+            }
+            return true;
+        }
+    }
+
+    /// Checks whether the list holds a value
+    pub fn contains(&self, value: &T) -> bool {
+        self.get_by(|node| node.cmp(value), |_| ()).is_some()
+    }
+
+    /// Reads the value for which `cmp` returns `Equal`, while it's protected
+    ///
+    /// `cmp` must order values the same way as `Ord`.
+    pub(crate) fn get_by<R>(
+        &self,
+        cmp: impl Fn(&T) -> CmpOrdering,
+        read: impl FnOnce(&T) -> R,
+    ) -> Option<R> {
+        let hazards = self.hazard_pointers.register_current_thread();
+        let position = self.find(&cmp, &hazards);
+        // Safety: the found node stays protected until the handle is dropped
+        position
+            .found
+            .then(|| read(unsafe { &(*position.curr).value }))
+    }
+
+    /// Returns the number of values in the list
+    ///
+    /// Note: This is an approximation in a concurrent setting
+    pub fn len(&self) -> usize {
+        // Relaxed is sufficient for a simple counter read
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Ord> Drop for LockFreeList<T> {
+    fn drop(&mut self) {
+        // Free the nodes still linked, including the removed ones not unlinked yet
+        let mut current = self.head.load(Ordering::Relaxed);
+        while !current.is_null() {
+            let node = unsafe { Box::from_raw(current) };
+            current = unmark(node.next.load(Ordering::Relaxed));
+        }
+
+        // Final reclamation attempt
+        self.hazard_pointers.try_reclaim(true);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_basic_operations() {
+        let list = LockFreeList::new(false);
+        assert!(list.is_empty());
+
+        assert!(list.insert(2));
+        assert!(list.insert(3));
+        assert!(list.insert(1));
+        assert!(!list.insert(2));
+
+        assert_eq!(list.len(), 3);
+        assert!(list.contains(&1));
+        assert!(list.contains(&3));
+        assert!(!list.contains(&4));
+
+        assert!(list.remove(&2));
+        assert!(!list.remove(&2));
+        assert!(!list.contains(&2));
+        assert!(list.remove(&1));
+        assert!(list.remove(&3));
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_nodes_stay_sorted() {
+        let list = LockFreeList::new(false);
+        for value in [5, 1, 4, 2, 3] {
+            list.insert(value);
+        }
+        list.remove(&4);
+
+        let mut values = Vec::new();
+        let mut current = list.head.load(Ordering::Acquire);
+        while !current.is_null() {
+            values.push(unsafe { (*current).value });
+            current = unsafe { (*current).next.load(Ordering::Acquire) };
+        }
+        assert_eq!(values, [1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn test_values_are_dropped_once() {
+        let marker = Arc::new(());
+        let list = LockFreeList::with_config(
+            false,
+            HazardConfig {
+                retire_threshold: 0,
+                scan_threshold: 0,
+            },
+        );
+        for i in 0..5 {
+            assert!(list.insert((i, Arc::clone(&marker))));
+        }
+        // A rejected duplicate is dropped right away
+        assert!(!list.insert((0, Arc::clone(&marker))));
+        assert_eq!(Arc::strong_count(&marker), 6);
+
+        // Removed values are dropped once their node is reclaimed
+        assert!(list.remove(&(1, Arc::clone(&marker))));
+        assert!(list.remove(&(3, Arc::clone(&marker))));
+        assert_eq!(Arc::strong_count(&marker), 4);
+
+        // Dropping the list drops the values left in it
+        drop(list);
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_concurrent_operations() {
+        let list = Arc::new(LockFreeList::new(false));
+        let threads = 4;
+        let operations_per_thread = 100;
+
+        // Each thread inserts its own values, then removes the odd ones,
+        // while the others traverse and unlink nodes around them
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let list = Arc::clone(&list);
+                thread::spawn(move || {
+                    for j in 0..operations_per_thread {
+                        assert!(list.insert(j * threads + i));
+                    }
+                    for j in (1..operations_per_thread).step_by(2) {
+                        assert!(list.remove(&(j * threads + i)));
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle
+                .join()
+                .expect("Thread panicked during concurrent operations");
+        }
+
+        assert_eq!(list.len(), operations_per_thread * threads / 2);
+        for value in 0..operations_per_thread * threads {
+            assert_eq!(list.contains(&value), (value / threads) % 2 == 0);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_insert_and_remove_of_same_values() {
+        let list = Arc::new(LockFreeList::new(false));
+        let threads = 4;
+
+        // Threads race to insert and remove the same values; each value must
+        // be inserted and removed successfully the same number of times
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let list = Arc::clone(&list);
+                thread::spawn(move || {
+                    let mut balance = 0i64;
+                    for round in 0..200 {
+                        let value = round % 10;
+                        if list.insert(value) {
+                            balance += 1;
+                        }
+                        if list.remove(&value) {
+                            balance -= 1;
+                        }
+                    }
+                    balance
+                })
+            })
+            .collect();
+
+        let balance: i64 = handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Thread panicked"))
+            .sum();
+        assert_eq!(balance, list.len() as i64);
+    }
+}
@@ -123,8 +123,7 @@ fn aba_demonstration(verbose: bool) {
 
         // Load the head but don't complete the operation
         let hazard_pointers = &stack_clone1.hazard_pointers;
-        let head = stack_clone1.head.load(std::sync::atomic::Ordering::Acquire);
-        hazard_pointers.protect(head);
+        let guard = hazard_pointers.protect(&stack_clone1.head);
 
         println!("{}", "Thread 1: Protected head node (with value 3)".blue());
 
@@ -139,6 +138,7 @@ fn aba_demonstration(verbose: bool) {
         );
         let result = stack_clone1.pop();
         println!("{}", format!("Thread 1: Pop result: {:?}", result).blue());
+        drop(guard);
 
         result
     });
@@ -324,7 +324,7 @@ fn stress_test(verbose: bool) {
         // Validate that the stack size is correct
         assert_eq!(
             stack.len(),
-            total_pushes - total_pops as usize,
+            total_pushes - total_pops,
             "Stack size doesn't match expected value!"
         );
         println!("{}", "Stress test validation passed!".green().bold());
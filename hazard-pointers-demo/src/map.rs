@@ -0,0 +1,271 @@
+use crate::list::{ListNode, LockFreeList};
+use crate::{HazardConfig, HazardPointers, HazardStats};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+/// Number of buckets of a map created with `LockFreeMap::new`
+const DEFAULT_BUCKETS: usize = 64;
+
+/// A key-value pair stored in a bucket, ordered by key only
+struct Entry<K, V> {
+    /// The key of the pair
+    key: K,
+    /// The value of the pair
+    value: V,
+}
+
+impl<K: Ord, V> PartialEq for Entry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, V> Eq for Entry<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Entry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for Entry<K, V> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// A lock-free hash map using hazard pointers for memory management
+///
+/// Keys are hashed into a fixed number of buckets, each a `LockFreeList` of
+/// entries sorted by key, and all buckets share one hazard pointer registry.
+/// Entries are never updated in place: `insert` only adds missing keys, so
+/// readers can safely clone values while other threads remove entries.
+pub struct LockFreeMap<K: Ord, V> {
+    /// Lists holding the entries, indexed by key hash
+    buckets: Box<[LockFreeList<Entry<K, V>>]>,
+    /// Hazard pointer registry shared by the buckets
+    hazard_pointers: Arc<HazardPointers<ListNode<Entry<K, V>>>>,
+    /// Hashes keys to pick their bucket
+    hasher: RandomState,
+}
+
+impl<K: Hash + Ord, V> LockFreeMap<K, V> {
+    /// Creates a new empty map
+    pub fn new(verbose: bool) -> Self {
+        Self::with_config(verbose, DEFAULT_BUCKETS, HazardConfig::default())
+    }
+
+    /// Creates a new empty map with the given number of buckets (at least
+    /// one), reclaiming removed entries with the given thresholds
+    pub fn with_config(verbose: bool, buckets: usize, config: HazardConfig) -> Self {
+        let hazard_pointers = Arc::new(HazardPointers::with_config(config));
+        let buckets = (0..buckets.max(1))
+            .map(|_| LockFreeList::with_registry(verbose, Arc::clone(&hazard_pointers)))
+            .collect();
+        LockFreeMap {
+            buckets,
+            hazard_pointers,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Picks the bucket holding a key
+    fn bucket(&self, key: &K) -> &LockFreeList<Entry<K, V>> {
+        let index = self.hasher.hash_one(key) % self.buckets.len() as u64;
+        &self.buckets[index as usize]
+    }
+
+    /// Adds a key-value pair, unless the map already holds the key
+    ///
+    /// Returns false, dropping the pair, if the key was already present.
+    pub fn insert(&self, key: K, value: V) -> bool {
+        self.bucket(&key).insert(Entry { key, value })
+    }
+
+    /// Removes a key and its value
+    ///
+    /// Returns false if the map doesn't hold the key.
+    pub fn remove(&self, key: &K) -> bool {
+        self.bucket(key).remove_by(|entry| entry.key.cmp(key))
+    }
+
+    /// Gets a copy of the value of a key
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.get_with(key, V::clone)
+    }
+
+    /// Reads the value of a key while it's protected from reclamation
+    pub fn get_with<R>(&self, key: &K, read: impl FnOnce(&V) -> R) -> Option<R> {
+        self.bucket(key)
+            .get_by(|entry| entry.key.cmp(key), |entry| read(&entry.value))
+    }
+
+    /// Checks whether the map holds a key
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get_with(key, |_| ()).is_some()
+    }
+
+    /// Returns the number of entries in the map
+    ///
+    /// Note: This is an approximation in a concurrent setting
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(LockFreeList::len).sum()
+    }
+
+    /// Returns true if the map is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the counters of the hazard pointer registry shared by the buckets
+    pub fn stats(&self) -> HazardStats {
+        self.hazard_pointers.stats()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_basic_operations() {
+        let map = LockFreeMap::new(false);
+        assert!(map.is_empty());
+
+        assert!(map.insert("one", 1));
+        assert!(map.insert("two", 2));
+        assert!(!map.insert("one", 10));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&"one"), Some(1));
+        assert_eq!(map.get_with(&"two", |value| value * 10), Some(20));
+        assert_eq!(map.get(&"three"), None);
+
+        assert!(map.remove(&"one"));
+        assert!(!map.remove(&"one"));
+        assert!(!map.contains_key(&"one"));
+        assert!(map.contains_key(&"two"));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_single_bucket() {
+        // Every key collides, so the bucket's list keeps them apart by key
+        let map = LockFreeMap::with_config(false, 0, HazardConfig::default());
+        for key in 0..10 {
+            assert!(map.insert(key, key.to_string()));
+        }
+        assert_eq!(map.buckets.len(), 1);
+        assert_eq!(map.get(&7), Some("7".to_string()));
+        assert!(map.remove(&7));
+        assert_eq!(map.get(&7), None);
+        assert_eq!(map.len(), 9);
+    }
+
+    #[test]
+    fn test_values_are_dropped_once() {
+        let marker = Arc::new(());
+        let config = HazardConfig {
+            retire_threshold: 0,
+            scan_threshold: 0,
+        };
+        let map = LockFreeMap::with_config(false, 4, config);
+        for key in 0..8 {
+            assert!(map.insert(key, Arc::clone(&marker)));
+        }
+        // A rejected duplicate is dropped right away
+        assert!(!map.insert(0, Arc::clone(&marker)));
+        assert_eq!(Arc::strong_count(&marker), 9);
+
+        // Removed values are dropped once their entry is reclaimed
+        assert!(map.remove(&1));
+        assert!(map.remove(&2));
+        assert_eq!(map.stats().retired, 2);
+        assert_eq!(map.stats().pending, 0);
+        assert_eq!(Arc::strong_count(&marker), 7);
+
+        // Dropping the map drops the values left in it
+        drop(map);
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_concurrent_stress() {
+        let map = Arc::new(LockFreeMap::with_config(false, 8, HazardConfig::default()));
+        let threads = 4;
+        let keys_per_thread = 500;
+
+        // Each thread inserts its own keys and removes the odd ones, while
+        // the others read and write the same buckets
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    for j in 0..keys_per_thread {
+                        let key = j * threads + i;
+                        assert!(map.insert(key, key * 2));
+                        assert_eq!(map.get(&key), Some(key * 2));
+                    }
+                    for j in (1..keys_per_thread).step_by(2) {
+                        assert!(map.remove(&(j * threads + i)));
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle
+                .join()
+                .expect("Thread panicked during concurrent operations");
+        }
+
+        assert_eq!(map.len(), keys_per_thread * threads / 2);
+        for key in 0..keys_per_thread * threads {
+            let expected = ((key / threads) % 2 == 0).then_some(key * 2);
+            assert_eq!(map.get(&key), expected);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_insert_and_remove_of_same_keys() {
+        let map = Arc::new(LockFreeMap::with_config(false, 2, HazardConfig::default()));
+        let threads = 4;
+
+        // Threads race on the same few keys; each key must be inserted and
+        // removed successfully the same number of times
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    let mut balance = 0i64;
+                    for round in 0..500 {
+                        let key = round % 8;
+                        if map.insert(key, i) {
+                            balance += 1;
+                        }
+                        // Values read concurrently are always valid
+                        if let Some(value) = map.get(&key) {
+                            assert!(value < threads);
+                        }
+                        if map.remove(&key) {
+                            balance -= 1;
+                        }
+                    }
+                    balance
+                })
+            })
+            .collect();
+
+        let balance: i64 = handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Thread panicked"))
+            .sum();
+        assert_eq!(balance, map.len() as i64);
+    }
+}
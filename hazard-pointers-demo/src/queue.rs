@@ -0,0 +1,421 @@
+use crate::sync::{AtomicPtr, AtomicUsize, Ordering};
+use crate::{HazardConfig, HazardPointers};
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+/// A node in our lock-free queue
+///
+/// The node at the head of the queue is a dummy whose value was already
+/// dequeued (or never set), so values are kept in `MaybeUninit` and never
+/// dropped along with their node.
+struct QueueNode<T> {
+    /// The value stored in this node, uninitialized for the dummy node
+    value: MaybeUninit<T>,
+    /// Pointer to the next node in the queue
+    next: AtomicPtr<QueueNode<T>>,
+}
+
+impl<T> QueueNode<T> {
+    /// Allocates a node holding the given value, or a dummy node
+    fn alloc(value: MaybeUninit<T>) -> *mut QueueNode<T> {
+        Box::into_raw(Box::new(QueueNode {
+            value,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+/// A lock-free FIFO queue using hazard pointers for memory management
+///
+/// This is the Michael–Scott queue: values are enqueued after the tail node
+/// and dequeued from the node after the head, which always points to a dummy
+/// node. Enqueuers protect the tail, and dequeuers the head and its next
+/// node, so they can follow `next` links safely while other threads retire
+/// nodes.
+pub struct LockFreeQueue<T> {
+    /// Atomic pointer to the dummy node before the first value
+    head: AtomicPtr<QueueNode<T>>,
+    /// Atomic pointer to the last node, or a node shortly before it
+    tail: AtomicPtr<QueueNode<T>>,
+    /// Hazard pointer registry used to protect nodes from reclamation
+    hazard_pointers: Arc<HazardPointers<QueueNode<T>>>,
+    /// Counter tracking the current size of the queue
+    size: AtomicUsize,
+    /// Whether to print debug information
+    verbose: bool,
+}
+
+// Safety: values are moved between threads through the queue, and nodes are
+// only accessed through atomics or while protected by hazard pointers
+unsafe impl<T: Send> Send for LockFreeQueue<T> {}
+unsafe impl<T: Send> Sync for LockFreeQueue<T> {}
+
+impl<T> LockFreeQueue<T> {
+    /// Creates a new empty queue
+    pub fn new(verbose: bool) -> Self {
+        Self::with_config(verbose, HazardConfig::default())
+    }
+
+    /// Creates a new empty queue reclaiming dequeued nodes with the given thresholds
+    pub fn with_config(verbose: bool, config: HazardConfig) -> Self {
+        let dummy = QueueNode::alloc(MaybeUninit::uninit());
+        LockFreeQueue {
+            head: AtomicPtr::new(dummy),
+            tail: AtomicPtr::new(dummy),
+            hazard_pointers: Arc::new(HazardPointers::with_config(config)),
+            size: AtomicUsize::new(0),
+            verbose,
+        }
+    }
+
+    /// Adds a value at the back of the queue
+    pub fn enqueue(&self, value: T) -> Result<(), String> {
+        // Create a new node
+        let new_node = QueueNode::alloc(MaybeUninit::new(value));
+
+        loop {
+            // Protect the tail so its next pointer can be used safely
+            let tail = self
+                .hazard_pointers
+                .protect(&self.tail)
+                .expect("Queue always has a tail node");
+            let current_tail = tail.as_ptr();
+            let next = tail.next.load(Ordering::Acquire);
+
+            if !next.is_null() {
+                // The tail is lagging behind, help the other enqueuer advance it
+                if self.verbose {
+                    println!(
+                        "Tail {:p} is lagging behind, advancing it to {:p}",
+                        current_tail, next
+                    );
+                }
+                let _ = self.tail.compare_exchange(
+                    current_tail,
+                    next,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
+                continue;
+            }
+
+            if self.verbose {
+                println!(
+                    "Attempting to enqueue node: {:p} after tail: {:p}",
+                    new_node, current_tail
+                );
+            }
+
+            // Try to link our node after the tail
+            // Release ensures the node's value is visible to dequeuers
+            match tail.next.compare_exchange(
+                ptr::null_mut(),
+                new_node,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    // Swing the tail to our node; if this fails, another
+                    // thread already advanced it
+                    let _ = self.tail.compare_exchange(
+                        current_tail,
+                        new_node,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                    );
+                    self.size.fetch_add(1, Ordering::Relaxed);
+                    if self.verbose {
+                        println!("Successfully enqueued node: {:p}", new_node);
+                    }
+                    return Ok(());
+                }
+                Err(actual_next) => {
+                    // Another node was linked first, try again
+                    if self.verbose {
+                        println!(
+                            "Enqueue conflict detected! Tail {:p} already points to: {:p}",
+                            current_tail, actual_next
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the value at the front of the queue
+    pub fn dequeue(&self) -> Option<T> {
+        loop {
+            // Protect the head (the dummy node) and the node after it, which
+            // holds the value; the head is re-checked after protecting next,
+            // since next can only be retired once the head moved past it
+            let head = self
+                .hazard_pointers
+                .protect(&self.head)
+                .expect("Queue always has a head node");
+            let current_head = head.as_ptr();
+            let next = self.hazard_pointers.protect(&head.next);
+            if self.head.load(Ordering::SeqCst) != current_head {
+                if self.verbose {
+                    println!("Head changed during protection, retrying dequeue");
+                }
+                continue;
+            }
+
+            let Some(next) = next else {
+                // Queue is empty
+                if self.verbose {
+                    println!("Queue is empty, cannot dequeue");
+                }
+                return None;
+            };
+            let new_head = next.as_ptr();
+
+            // Don't let the head pass the tail; help the enqueuer advance it
+            let current_tail = self.tail.load(Ordering::Acquire);
+            if current_head == current_tail {
+                if self.verbose {
+                    println!(
+                        "Tail {:p} is lagging behind, advancing it to {:p}",
+                        current_tail, new_head
+                    );
+                }
+                let _ = self.tail.compare_exchange(
+                    current_tail,
+                    new_head,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
+                continue;
+            }
+
+            if self.verbose {
+                println!("Attempting to dequeue head: {:p}", current_head);
+            }
+
+            // Try to make the next node the new dummy
+            match self.head.compare_exchange(
+                current_head,
+                new_head,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    // Successfully dequeued, move out the value; the node is
+                    // now the dummy, so its value won't be read again
+                    let value = unsafe { next.value.assume_init_read() };
+
+                    self.size.fetch_sub(1, Ordering::Relaxed);
+
+                    if self.verbose {
+                        println!(
+                            "Successfully dequeued head: {:p}, new head: {:p}",
+                            current_head, new_head
+                        );
+                    }
+
+                    // Clear hazard pointers and schedule the old dummy for reclamation
+                    drop((head, next));
+                    self.hazard_pointers.retire(current_head);
+
+                    return Some(value);
+                }
+                Err(_) => {
+                    // Failed to dequeue, retry
+                    if self.verbose {
+                        println!("Dequeue conflict detected! Head changed during CAS");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the number of elements in the queue
+    ///
+    /// Note: This is an approximation in a concurrent setting
+    pub fn len(&self) -> usize {
+        // Relaxed is sufficient for a simple counter read
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for LockFreeQueue<T> {
+    fn drop(&mut self) {
+        // Dequeue all elements to ensure their values are dropped
+        while self.dequeue().is_some() {}
+
+        // Free the remaining dummy node; its value was already moved out
+        unsafe {
+            drop(Box::from_raw(self.head.load(Ordering::Relaxed)));
+        }
+
+        // Final reclamation attempt
+        self.hazard_pointers.try_reclaim(true);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_basic_operations() {
+        let queue = LockFreeQueue::new(false);
+        assert!(queue.is_empty());
+
+        queue.enqueue(1).expect("Enqueue should succeed");
+        queue.enqueue(2).expect("Enqueue should succeed");
+        queue.enqueue(3).expect("Enqueue should succeed");
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.dequeue(), Some(1));
+        assert_eq!(queue.dequeue(), Some(2));
+        queue.enqueue(4).expect("Enqueue should succeed");
+        assert_eq!(queue.dequeue(), Some(3));
+        assert_eq!(queue.dequeue(), Some(4));
+        assert_eq!(queue.dequeue(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_values_are_dropped_once() {
+        let marker = Arc::new(());
+        let queue = LockFreeQueue::with_config(
+            false,
+            HazardConfig {
+                retire_threshold: 0,
+                scan_threshold: 0,
+            },
+        );
+        for _ in 0..5 {
+            queue
+                .enqueue((Arc::clone(&marker), String::from("value")))
+                .expect("Enqueue should succeed");
+        }
+
+        // Dequeued values are moved out, reclaimed nodes don't drop them again
+        let (_, value) = queue.dequeue().expect("Queue should have values");
+        assert_eq!(value, "value");
+        assert_eq!(queue.dequeue().map(|(_, value)| value.len()), Some(5));
+        assert_eq!(Arc::strong_count(&marker), 4);
+
+        // Dropping the queue drops the values left in it
+        drop(queue);
+        assert_eq!(Arc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_concurrent_operations() {
+        let queue = Arc::new(LockFreeQueue::new(false));
+        let threads = 4;
+        let operations_per_thread = 100;
+
+        let mut handles = Vec::new();
+
+        // Enqueue threads
+        for i in 0..threads {
+            let queue = Arc::clone(&queue);
+            let handle = thread::spawn(move || {
+                for j in 0..operations_per_thread {
+                    queue
+                        .enqueue(i * operations_per_thread + j)
+                        .expect("Enqueue should succeed");
+                }
+            });
+            handles.push(handle);
+        }
+
+        // Dequeue threads, checking each producer's values come out in order
+        let mut consumers = Vec::new();
+        for _ in 0..threads / 2 {
+            let queue = Arc::clone(&queue);
+            let handle = thread::spawn(move || {
+                let mut last_seen = vec![None; threads];
+                let mut dequeued = 0;
+                while dequeued < operations_per_thread {
+                    let Some(value) = queue.dequeue() else {
+                        thread::yield_now();
+                        continue;
+                    };
+                    let producer = value / operations_per_thread;
+                    assert!(last_seen[producer] < Some(value));
+                    last_seen[producer] = Some(value);
+                    dequeued += 1;
+                }
+            });
+            consumers.push(handle);
+        }
+
+        for handle in handles.into_iter().chain(consumers) {
+            handle
+                .join()
+                .expect("Thread panicked during concurrent operations");
+        }
+
+        assert_eq!(queue.len(), operations_per_thread * threads / 2);
+
+        // Clean up remaining elements
+        while queue.dequeue().is_some() {}
+    }
+
+    #[test]
+    fn test_aba_prevention() {
+        let queue = Arc::new(LockFreeQueue::new(false));
+
+        // Initial state
+        queue.enqueue(1).expect("Enqueue should succeed");
+        queue.enqueue(2).expect("Enqueue should succeed");
+
+        let queue_clone1 = Arc::clone(&queue);
+        let queue_clone2 = Arc::clone(&queue);
+
+        // Thread 1: Start dequeue operation but get interrupted
+        let handle1 = thread::spawn(move || {
+            // Begin dequeue operation and protect head
+            let guard = queue_clone1.hazard_pointers.protect(&queue_clone1.head);
+
+            // Pause to allow Thread 2 to run
+            thread::sleep(Duration::from_millis(100));
+
+            // The protected node was dequeued past but not reclaimed
+            assert_eq!(queue_clone1.hazard_pointers.stats().protected, 1);
+
+            // Try to complete the dequeue operation
+            let result = queue_clone1.dequeue();
+            drop(guard);
+            result
+        });
+
+        // Thread 2: Perform operations while Thread 1 is paused
+        let handle2 = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+
+            // Dequeue both values
+            let val1 = queue_clone2
+                .dequeue()
+                .expect("First dequeue should succeed");
+            let val2 = queue_clone2
+                .dequeue()
+                .expect("Second dequeue should succeed");
+
+            // Enqueue them again, reusing freed memory if possible
+            queue_clone2.enqueue(val1).expect("Enqueue should succeed");
+            queue_clone2.enqueue(val2).expect("Enqueue should succeed");
+        });
+
+        // Both threads should complete successfully
+        let thread1_result = handle1.join().expect("Thread 1 panicked");
+        handle2.join().expect("Thread 2 panicked");
+
+        // Verify operation succeeded
+        assert_eq!(thread1_result, Some(1));
+        assert_eq!(queue.dequeue(), Some(2));
+    }
+}
@@ -0,0 +1,22 @@
+//! Synchronization primitives used by the data structures
+//!
+//! Building with `--cfg loom` swaps them for loom's, so the model checker
+//! can explore the interleavings of the atomic operations (see `tests/loom.rs`).
+
+#[cfg(not(loom))]
+pub(crate) use std::{
+    hint,
+    sync::Mutex,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering, fence},
+    thread::{self, ThreadId},
+    thread_local,
+};
+
+#[cfg(loom)]
+pub(crate) use loom::{
+    hint,
+    sync::Mutex,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering, fence},
+    thread::{self, ThreadId},
+    thread_local,
+};
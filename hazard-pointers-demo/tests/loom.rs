@@ -0,0 +1,191 @@
+//! Model checks of the hazard pointers and the stack under loom
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release --test loom`: loom
+//! then runs every test under all the interleavings (and weak memory
+//! behaviours) of the atomic operations, instead of the few a stress test
+//! happens to hit.
+#![cfg(loom)]
+
+use hazard_pointers_demo::{HazardConfig, HazardPointers, LockFreeStack};
+use loom::sync::Arc;
+use loom::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use loom::thread;
+
+/// Runs a test under loom, exploring up to 3 preemptions per execution
+/// unless `LOOM_MAX_PREEMPTIONS` asks for another bound
+///
+/// Exploring every interleaving of the stack operations takes hours, while
+/// 3 preemptions take seconds and already cover the races below.
+fn model(f: impl Fn() + Sync + Send + 'static) {
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound.get_or_insert(3);
+    builder.check(f);
+}
+
+/// Reclaims on every retire, so frees race with the other threads
+const EAGER: HazardConfig = HazardConfig {
+    retire_threshold: 0,
+    scan_threshold: 0,
+};
+
+/// A value recording when it's dropped
+struct Tracked {
+    /// Set once the value is dropped
+    dropped: Arc<AtomicBool>,
+    /// Counts the drops of all the values of a test
+    drops: Arc<AtomicUsize>,
+}
+
+impl Tracked {
+    fn new(drops: &Arc<AtomicUsize>) -> Self {
+        Tracked {
+            dropped: Arc::new(AtomicBool::new(false)),
+            drops: Arc::clone(drops),
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        assert!(
+            !self.dropped.swap(true, Ordering::SeqCst),
+            "Value dropped twice"
+        );
+        self.drops.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Pushes the values `0..n` onto a stack reclaiming eagerly
+fn stack_of(n: usize) -> Arc<LockFreeStack<usize>> {
+    let stack = LockFreeStack::with_config(false, EAGER);
+    stack.push_all(0..n).expect("Push should succeed");
+    Arc::new(stack)
+}
+
+#[test]
+fn protected_pointer_is_not_freed() {
+    model(|| {
+        let hazards = Arc::new(HazardPointers::with_config(EAGER));
+        let drops = Arc::new(AtomicUsize::new(0));
+        let old = Tracked::new(&drops);
+        let old_dropped = Arc::clone(&old.dropped);
+        let old = Box::into_raw(Box::new(old));
+        let source = Arc::new(AtomicPtr::new(old));
+
+        // The reader protects whichever value is current; the protection
+        // must hold off the reclamation of the replaced one
+        let reader = {
+            let hazards = Arc::clone(&hazards);
+            let source = Arc::clone(&source);
+            let old = old as usize;
+            thread::spawn(move || {
+                let guard = hazards
+                    .protect(&source)
+                    .expect("Pointer should be protected");
+                if guard.as_ptr() as usize == old {
+                    assert!(!old_dropped.load(Ordering::SeqCst), "Protected value freed");
+                }
+            })
+        };
+
+        let new = Box::into_raw(Box::new(Tracked::new(&drops)));
+        hazards.retire(source.swap(new, Ordering::SeqCst));
+        reader.join().expect("Reader panicked");
+
+        hazards.try_reclaim(true);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop(unsafe { Box::from_raw(new) });
+    });
+}
+
+#[test]
+fn concurrent_push_and_pop() {
+    model(|| {
+        let stack = stack_of(1);
+
+        let pusher = {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || {
+                stack.push(1).expect("Push should succeed");
+                stack.pop()
+            })
+        };
+        let popper = {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || stack.pop())
+        };
+
+        let mut values: Vec<_> = [pusher, popper]
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Thread panicked"))
+            .chain(stack.drain())
+            .collect();
+        values.sort_unstable();
+        assert_eq!(values, [0, 1]);
+        assert!(stack.is_empty());
+    });
+}
+
+#[test]
+fn concurrent_pops_drop_values_once() {
+    model(|| {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = Arc::new(LockFreeStack::with_config(false, EAGER));
+        stack
+            .push_all([Tracked::new(&drops), Tracked::new(&drops)])
+            .expect("Push should succeed");
+
+        let poppers: Vec<_> = (0..2)
+            .map(|_| {
+                let stack = Arc::clone(&stack);
+                thread::spawn(move || assert!(stack.pop().is_some()))
+            })
+            .collect();
+        for popper in poppers {
+            popper.join().expect("Popper panicked");
+        }
+
+        // Reclaiming the nodes leaves the popped values alone
+        stack.hazard_pointers.try_reclaim(true);
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+    });
+}
+
+#[test]
+fn peek_races_with_pop() {
+    model(|| {
+        let stack = stack_of(2);
+
+        // The peeked node may be popped and retired while its value is
+        // copied, but it can't be freed under the peek
+        let popper = {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || stack.pop())
+        };
+        let peeked = stack.peek_with(|value| *value);
+        assert!(matches!(peeked, Some(0 | 1)));
+
+        assert_eq!(popper.join().expect("Popper panicked"), Some(1));
+        assert_eq!(stack.peek_with(|value| *value), Some(0));
+    });
+}
+
+#[test]
+fn batch_pop_races_with_drain() {
+    model(|| {
+        let stack = stack_of(3);
+
+        // The batch pop walks nodes below the head, which the drain frees
+        // without retiring them
+        let batch = {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || stack.try_pop_n(2))
+        };
+        let mut values: Vec<_> = stack.drain().collect();
+        values.extend(batch.join().expect("Batch pop panicked"));
+        values.extend(stack.drain());
+
+        values.sort_unstable();
+        assert_eq!(values, [0, 1, 2]);
+    });
+}
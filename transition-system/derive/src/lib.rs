@@ -0,0 +1,148 @@
+//! `#[derive(StateMachine)]` for `transition-system`
+//!
+//! Use it through the `derive` feature of `transition-system`, which
+//! re-exports the macro next to the `StateMachine` trait it implements.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, Fields, Ident, Result, Type, parse_macro_input,
+    spanned::Spanned,
+};
+
+/// Implement `StateMachine` for a state enum from `#[transition]` attributes
+///
+/// The event type is given with `#[state_machine(event = Type)]` on the enum.
+/// Each `#[transition(from = ..., on = ..., to = ...)]` on the enum declares a
+/// transition; on a unit variant, `from` defaults to that variant. `from` can
+/// list several states, e.g. `from = [Draft, Review]`. Variant names are
+/// resolved against the state and event enums, and arguments may be given,
+/// e.g. `on = Reserve("Alice")`.
+#[proc_macro_derive(StateMachine, attributes(state_machine, transition))]
+pub fn derive_state_machine(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// A transition declared with `#[transition(...)]`
+struct Declared {
+    /// States the transition starts from
+    from: Vec<Expr>,
+    /// Event triggering the transition
+    on: Expr,
+    /// State the transition leads to
+    to: Expr,
+}
+
+/// Generate the `StateMachine` implementation
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(input.span(), "StateMachine can only be derived for enums"));
+    };
+    let event = event_type(input)?;
+
+    let mut transitions = Vec::new();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("transition")) {
+        transitions.push(parse_transition(attr, None)?);
+    }
+    for variant in &data.variants {
+        for attr in variant.attrs.iter().filter(|attr| attr.path().is_ident("transition")) {
+            let from = matches!(variant.fields, Fields::Unit).then_some(&variant.ident);
+            transitions.push(parse_transition(attr, from)?);
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let entries = transitions.iter().flat_map(|transition| {
+        let on = qualify(&transition.on, &quote!(#event));
+        let to = qualify(&transition.to, &quote!(Self));
+        transition.from.iter().map(move |from| {
+            let from = qualify(from, &quote!(Self));
+            quote!((#from, #on, #to))
+        })
+    });
+    Ok(quote! {
+        impl #impl_generics ::transition_system::generic::StateMachine for #name #ty_generics
+        #where_clause
+        {
+            type Event = #event;
+
+            fn transitions() -> ::std::vec::Vec<(Self, Self::Event, Self)> {
+                ::std::vec![#(#entries),*]
+            }
+        }
+    })
+}
+
+/// Read the event type from `#[state_machine(event = Type)]`
+fn event_type(input: &DeriveInput) -> Result<Type> {
+    let mut event = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("state_machine")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("event") {
+                event = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `event`"))
+            }
+        })?;
+    }
+    event.ok_or_else(|| {
+        syn::Error::new(input.ident.span(), "missing `#[state_machine(event = Type)]`")
+    })
+}
+
+/// Parse a `#[transition(...)]` attribute, defaulting `from` to `variant`
+fn parse_transition(attr: &Attribute, variant: Option<&Ident>) -> Result<Declared> {
+    let (mut from, mut on, mut to) = (None, None, None);
+    attr.parse_nested_meta(|meta| {
+        let slot = if meta.path.is_ident("from") {
+            &mut from
+        } else if meta.path.is_ident("on") {
+            &mut on
+        } else if meta.path.is_ident("to") {
+            &mut to
+        } else {
+            return Err(meta.error("expected `from`, `on` or `to`"));
+        };
+        *slot = Some(meta.value()?.parse::<Expr>()?);
+        Ok(())
+    })?;
+
+    let from = match (from, variant) {
+        (Some(Expr::Array(states)), _) => states.elems.into_iter().collect(),
+        (Some(state), _) => vec![state],
+        (None, Some(variant)) => vec![syn::parse_quote!(#variant)],
+        (None, None) => return Err(syn::Error::new(attr.span(), "missing `from`")),
+    };
+    let on = on.ok_or_else(|| syn::Error::new(attr.span(), "missing `on`"))?;
+    let to = to.ok_or_else(|| syn::Error::new(attr.span(), "missing `to`"))?;
+    Ok(Declared { from, on, to })
+}
+
+/// Resolve a bare variant name, with or without arguments, against `owner`
+///
+/// Arguments are converted with `Into`, so string literals can be used for
+/// `String` fields. Other expressions, e.g. full paths, are used as written.
+fn qualify(expr: &Expr, owner: &TokenStream2) -> TokenStream2 {
+    if let Some(variant) = bare_ident(expr) {
+        return quote!(<#owner>::#variant);
+    }
+    if let Expr::Call(call) = expr
+        && let Some(variant) = bare_ident(&call.func)
+    {
+        let args = call.args.iter();
+        return quote!(<#owner>::#variant(#(::core::convert::Into::into(#args)),*));
+    }
+    quote!(#expr)
+}
+
+/// Get the identifier an expression consists of, if it is a single one
+fn bare_ident(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::Path(path) if path.qself.is_none() => path.path.get_ident(),
+        _ => None,
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    definition::MachineDefinition,
+    persistence::SerializableSystemState,
+    system::{LibraryError, LibrarySystem},
+    visualization::StateVisualization,
+};
+
+/// Name of the archive entry holding the full system state
+const STATE_ENTRY: &str = "state.json";
+
+/// Write a tar archive with everything needed to share or restore a system
+///
+/// The archive contains:
+///
+/// - `state.json`: the full system state, used by [`import_archive`]
+/// - `definition.json`: the machine definition, see [`MachineDefinition`]
+/// - `history.md`: the transition history as a Markdown table
+/// - `machine.dot`: the state machine as a DOT graph, with the path taken
+///   highlighted
+///
+/// # Errors
+///
+/// Returns a `LibraryError::PersistenceError` if the archive can't be written
+pub fn export_archive(system: &LibrarySystem, path: impl AsRef<Path>) -> Result<(), LibraryError> {
+    let write_error = |e: &dyn std::fmt::Display| {
+        LibraryError::PersistenceError(format!("Failed to write archive: {e}"))
+    };
+
+    let entries = [
+        (STATE_ENTRY, serde_json::to_vec_pretty(&system.to_serializable_state())?),
+        ("definition.json", serde_json::to_vec_pretty(&MachineDefinition::from_system(system))?),
+        ("history.md", StateVisualization::history_table(system.get_history()).into_bytes()),
+        ("machine.dot", StateVisualization::generate_dot(system, true).into_bytes()),
+    ];
+
+    let mtime = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let file = File::create(path).map_err(|e| write_error(&e))?;
+    let mut builder = tar::Builder::new(file);
+    for (name, contents) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        builder.append_data(&mut header, name, contents.as_slice()).map_err(|e| write_error(&e))?;
+    }
+    builder.into_inner().and_then(|file| file.sync_all()).map_err(|e| write_error(&e))
+}
+
+/// Restore a system from an archive written by [`export_archive`]
+///
+/// The loaded system has no observers registered.
+///
+/// # Errors
+///
+/// Returns a `LibraryError::LoadError` if the archive can't be read or has no
+/// valid system state
+pub fn import_archive(path: impl AsRef<Path>) -> Result<LibrarySystem, LibraryError> {
+    let read_error =
+        |e: &dyn std::fmt::Display| LibraryError::LoadError(format!("Failed to read archive: {e}"));
+
+    let file = File::open(path).map_err(|e| read_error(&e))?;
+    let mut archive = tar::Archive::new(file);
+    for entry in archive.entries().map_err(|e| read_error(&e))? {
+        let mut entry = entry.map_err(|e| read_error(&e))?;
+        if entry.path().map_err(|e| read_error(&e))?.as_ref() != Path::new(STATE_ENTRY) {
+            continue;
+        }
+
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).map_err(|e| read_error(&e))?;
+        let state: SerializableSystemState = serde_json::from_str(&contents)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to parse {STATE_ENTRY}: {e}")))?;
+        return Ok(LibrarySystem::from_serializable_state(state));
+    }
+
+    Err(LibraryError::LoadError(format!("Archive has no {STATE_ENTRY}")))
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,41 @@
+use std::fs;
+
+use super::*;
+use crate::{book_state::BookState, events::BookEvent};
+
+#[test]
+fn test_archive_round_trip() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("archive-test-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let path = directory.join("test-book.tar");
+
+    let result = (|| {
+        let mut system = crate::state_machine! {
+            id: "test-book",
+            initial: Available,
+            transitions: {
+                Available --CheckOut("Alice")--> CheckedOut("Alice"),
+                CheckedOut("Alice") --Return--> Available,
+            },
+        }?;
+        system.process_event(BookEvent::CheckOut("Alice".into()))?;
+        system.export_archive(&path)?;
+
+        let mut names = Vec::new();
+        for entry in tar::Archive::new(File::open(&path)?).entries()? {
+            names.push(entry?.path()?.display().to_string());
+        }
+        assert_eq!(names, ["state.json", "definition.json", "history.md", "machine.dot"]);
+
+        let mut imported = LibrarySystem::import_archive(&path)?;
+        assert_eq!(*imported.current_state(), BookState::CheckedOut("Alice".into()));
+        assert_eq!(imported.get_history().len(), 1);
+        imported.process_event(BookEvent::Return)?;
+
+        assert!(LibrarySystem::import_archive(directory.join("missing.tar")).is_err());
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
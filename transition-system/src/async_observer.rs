@@ -0,0 +1,72 @@
+use std::{fmt, future::Future, pin::Pin, sync::Arc};
+
+use tokio::{
+    runtime::Handle,
+    sync::mpsc::{self, UnboundedSender},
+};
+
+use crate::{
+    book_state::BookState, events::BookEvent, logging::emit, observers::StateObserver,
+    system::StateTransition,
+};
+
+/// Future returned by an [`AsyncStateObserver`]
+pub type ObserverFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Trait for state change observation that does asynchronous work
+///
+/// Use it for notifications doing network IO, such as sending an email or
+/// calling a webhook, and register it through a [`TokioObserver`] so the
+/// state machine never waits for it. Implementations return a boxed future,
+/// usually `Box::pin(async move { ... })`.
+pub trait AsyncStateObserver: Send + Sync {
+    /// Called with the full history entry when a state transition occurs
+    fn on_state_change<'a>(&'a self, transition: &'a StateTransition) -> ObserverFuture<'a>;
+}
+
+/// Runs an [`AsyncStateObserver`] on a tokio runtime
+///
+/// Register it on a system like any other observer. Every transition is
+/// queued without blocking and handed to the asynchronous observer by a task
+/// on the runtime, one at a time and in order, so a slow webhook delays later
+/// notifications but never the state machine. The task ends once this
+/// observer is dropped and the queue is drained.
+pub struct TokioObserver {
+    /// Queue of transitions the task hands to the observer
+    queue: UnboundedSender<StateTransition>,
+}
+
+// Manual implementation of Debug for TokioObserver
+impl fmt::Debug for TokioObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokioObserver").field("closed", &self.queue.is_closed()).finish()
+    }
+}
+
+impl TokioObserver {
+    /// Start a task on the given runtime delivering transitions to the observer
+    #[must_use]
+    pub fn new(observer: Arc<dyn AsyncStateObserver>, runtime: &Handle) -> Self {
+        let (queue, mut transitions) = mpsc::unbounded_channel::<StateTransition>();
+        runtime.spawn(async move {
+            while let Some(transition) = transitions.recv().await {
+                observer.on_state_change(&transition).await;
+            }
+        });
+        Self { queue }
+    }
+}
+
+impl StateObserver for TokioObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {}
+
+    fn on_transition(&self, transition: &StateTransition) {
+        if self.queue.send(transition.clone()).is_err() {
+            emit!(warn, "ASYNC: Observer task has stopped, dropping notification");
+        }
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
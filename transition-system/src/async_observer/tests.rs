@@ -0,0 +1,70 @@
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use super::*;
+use crate::system::{LibraryError, LibrarySystem};
+
+/// Forwards the target state of every transition after yielding once
+#[derive(Debug)]
+struct Forwarder(UnboundedSender<BookState>);
+
+impl AsyncStateObserver for Forwarder {
+    fn on_state_change<'a>(&'a self, transition: &'a StateTransition) -> ObserverFuture<'a> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            self.0.send(transition.to.clone()).ok();
+        })
+    }
+}
+
+/// Receive the next `count` forwarded states
+async fn receive(received: &mut UnboundedReceiver<BookState>, count: usize) -> Vec<BookState> {
+    let mut states = Vec::new();
+    for _ in 0..count {
+        if let Some(state) = received.recv().await {
+            states.push(state);
+        }
+    }
+    states
+}
+
+#[test]
+fn test_tokio_observer_delivers_in_order() -> Result<(), LibraryError> {
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let mut system = crate::state_machine! {
+        id: "async-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Alice")--> Reserved("Alice"),
+            Reserved("Alice") --CancelReservation--> Available,
+        },
+    }?;
+    let (sender, mut received) = mpsc::unbounded_channel();
+    system.register_observer(Box::new(TokioObserver::new(
+        Arc::new(Forwarder(sender)),
+        runtime.handle(),
+    )));
+
+    // Processing doesn't wait for the observer, which only runs on the runtime
+    system.process_event(BookEvent::Reserve("Alice".into()))?;
+    system.process_event(BookEvent::CancelReservation)?;
+    assert!(received.is_empty());
+
+    let states = runtime.block_on(receive(&mut received, 2));
+    assert_eq!(states, vec![BookState::Reserved("Alice".into()), BookState::Available]);
+    Ok(())
+}
+
+#[test]
+fn test_tokio_observer_stops_with_runtime() -> Result<(), LibraryError> {
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let (sender, _received) = mpsc::unbounded_channel();
+    let observer = TokioObserver::new(Arc::new(Forwarder(sender)), runtime.handle());
+    drop(runtime);
+
+    let mut system = LibrarySystem::new(BookState::Available, "async-book");
+    system.add_state(BookState::Lost);
+    system.add_transition(0, BookEvent::ReportLost, 1);
+    system.register_observer(Box::new(observer));
+    assert_eq!(system.process_event(BookEvent::ReportLost)?, &BookState::Lost);
+    Ok(())
+}
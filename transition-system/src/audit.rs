@@ -0,0 +1,184 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book_state::BookState,
+    events::BookEvent,
+    logging::emit,
+    observers::{ObserverError, StateObserver},
+    persistence::TimeStamp,
+    system::{LibraryError, StateTransition},
+};
+
+/// A line of an audit file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuditRecord {
+    /// System the transition happened in
+    pub system_id: String,
+    /// The state before the transition
+    pub from: BookState,
+    /// The state after the transition
+    pub to: BookState,
+    /// The event that triggered the transition
+    pub event: BookEvent,
+    /// Wall-clock time the event occurred
+    pub timestamp: TimeStamp,
+    /// Who triggered the event, if known
+    pub actor: Option<String>,
+}
+
+/// Appends every transition of a system to a JSON-lines audit file
+///
+/// The audit file is append-only and independent of the state files, so it
+/// keeps a trail even if the state is rewritten or restored from a backup.
+/// With [`Self::with_rotation`] the file is rotated once it grows too large:
+/// it becomes `<file>.1`, the previous `.1` becomes `.2`, and so on.
+///
+/// Failing to write is reported through
+/// [`StateObserver::try_on_transition`], so the system's
+/// [`ObserverErrorPolicy`](crate::observers::ObserverErrorPolicy) decides
+/// whether the transition is kept without an audit entry.
+#[derive(Debug)]
+pub struct FileAuditObserver {
+    /// Path of the current audit file
+    path: PathBuf,
+    /// System whose transitions are audited
+    system_id: String,
+    /// Size in bytes after which the file is rotated, if any
+    max_bytes: Option<u64>,
+    /// Number of rotated files to keep
+    max_files: usize,
+    /// Serializes writes and rotations
+    lock: Mutex<()>,
+}
+
+impl FileAuditObserver {
+    /// Audit a system's transitions to the given file, without rotation
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, system_id: &str) -> Self {
+        Self {
+            path: path.into(),
+            system_id: system_id.to_string(),
+            max_bytes: None,
+            max_files: 0,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Rotate the file once it reaches `max_bytes`, keeping `max_files` rotated files
+    ///
+    /// With `max_files` of 0 the full file is deleted instead of kept.
+    #[must_use]
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self.max_files = max_files;
+        self
+    }
+
+    /// Get the path of the current audit file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the path of the `n`th rotated audit file, 1 being the most recent
+    #[must_use]
+    pub fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        PathBuf::from(path)
+    }
+
+    /// Read the records of an audit file
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the file can't be read or a line
+    /// can't be parsed
+    pub fn read_records(path: impl AsRef<Path>) -> Result<Vec<AuditRecord>, LibraryError> {
+        let file = File::open(path)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to open audit file: {e}")))?;
+        BufReader::new(file)
+            .lines()
+            .map(|line| {
+                let line = line.map_err(|e| {
+                    LibraryError::LoadError(format!("Failed to read audit file: {e}"))
+                })?;
+                serde_json::from_str(&line).map_err(|e| {
+                    LibraryError::LoadError(format!("Failed to parse audit record: {e}"))
+                })
+            })
+            .collect()
+    }
+
+    /// Append a record, rotating the file first if it is full
+    fn append(&self, record: &AuditRecord) -> std::io::Result<()> {
+        let line = serde_json::to_string(record)?;
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(max_bytes) = self.max_bytes
+            && fs::metadata(&self.path).is_ok_and(|metadata| metadata.len() >= max_bytes)
+        {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Shift the rotated files up by one and move the current file to `.1`
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+
+        let oldest = self.rotated_path(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for n in (1..self.max_files).rev() {
+            let rotated = self.rotated_path(n);
+            if rotated.exists() {
+                fs::rename(&rotated, self.rotated_path(n.saturating_add(1)))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+impl StateObserver for FileAuditObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {
+        // Transitions are audited in `try_on_transition`
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        if let Err(e) = self.try_on_transition(transition) {
+            emit!(error, "AUDIT: {e}");
+        }
+    }
+
+    fn try_on_transition(&self, transition: &StateTransition) -> Result<(), ObserverError> {
+        let record = AuditRecord {
+            system_id: self.system_id.clone(),
+            from: transition.from.clone(),
+            to: transition.to.clone(),
+            event: transition.event.clone(),
+            timestamp: transition
+                .occurred_at
+                .map_or_else(TimeStamp::now, TimeStamp::from_system_time),
+            actor: transition.actor.clone(),
+        };
+        self.append(&record)
+            .map_err(|e| ObserverError(format!("Failed to append to audit file: {e}")))
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,90 @@
+use super::*;
+use crate::{events::EventEnvelope, system::LibrarySystem};
+
+/// Build a system audited to the given observer
+fn build_system(observer: FileAuditObserver) -> Result<LibrarySystem, LibraryError> {
+    let mut system = crate::state_machine! {
+        id: "audit-test",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+        },
+    }?;
+    system.register_observer(Box::new(observer));
+    Ok(system)
+}
+
+/// Create an empty directory for a test
+fn test_directory(name: &str) -> Result<PathBuf, LibraryError> {
+    let directory = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+    if directory.exists() {
+        fs::remove_dir_all(&directory)?;
+    }
+    fs::create_dir_all(&directory)?;
+    Ok(directory)
+}
+
+#[test]
+fn test_audit_records() -> Result<(), LibraryError> {
+    let directory = test_directory("audit-records-test")?;
+    let path = directory.join("audit.jsonl");
+    let result = (|| {
+        let mut system = build_system(FileAuditObserver::new(&path, "audit-test"))?;
+        let occurred_at = std::time::SystemTime::UNIX_EPOCH;
+        system.process_event_with_meta(
+            EventEnvelope::new(BookEvent::Reserve("Test User".into()))
+                .actor("front-desk")
+                .occurred_at(occurred_at),
+        )?;
+        system.process_event(BookEvent::CancelReservation)?;
+
+        let records = FileAuditObserver::read_records(&path)?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records.first(),
+            Some(&AuditRecord {
+                system_id: "audit-test".to_string(),
+                from: BookState::Available,
+                to: BookState::Reserved("Test User".into()),
+                event: BookEvent::Reserve("Test User".into()),
+                timestamp: TimeStamp::from_system_time(occurred_at),
+                actor: Some("front-desk".to_string()),
+            })
+        );
+        assert!(records.get(1).is_some_and(|record| record.actor.is_none()));
+        Ok(())
+    })();
+    fs::remove_dir_all(&directory).ok();
+    result
+}
+
+#[test]
+fn test_audit_rotation() -> Result<(), LibraryError> {
+    let directory = test_directory("audit-rotation-test")?;
+    let path = directory.join("audit.jsonl");
+    let result = (|| {
+        // Every record exceeds the limit, so each append rotates the previous one
+        let observer = FileAuditObserver::new(&path, "audit-test").with_rotation(1, 2);
+        let (first, second, third) =
+            (observer.rotated_path(1), observer.rotated_path(2), observer.rotated_path(3));
+        let mut system = build_system(observer)?;
+        for _ in 0..2 {
+            system.process_event(BookEvent::Reserve("Test User".into()))?;
+            system.process_event(BookEvent::CancelReservation)?;
+        }
+
+        assert_eq!(FileAuditObserver::read_records(&path)?.len(), 1);
+        let newest = FileAuditObserver::read_records(&first)?;
+        assert!(
+            newest
+                .first()
+                .is_some_and(|record| record.event == BookEvent::Reserve("Test User".into()))
+        );
+        assert_eq!(FileAuditObserver::read_records(&second)?.len(), 1);
+        assert!(!third.exists());
+        Ok(())
+    })();
+    fs::remove_dir_all(&directory).ok();
+    result
+}
@@ -0,0 +1,103 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{logging::emit, persistence::StateStore, system::LibrarySystem};
+
+/// Background worker that persists a shared system at a fixed interval
+///
+/// The system is only locked while a snapshot of it is taken; writing the
+/// snapshot to the store happens outside the lock, so event processing isn't
+/// held up by slow storage. When the saver is stopped or dropped, the worker
+/// writes one final snapshot before exiting, so nothing processed since the
+/// last interval is lost on a clean shutdown.
+#[derive(Debug)]
+pub struct AutoSaver {
+    /// Flag telling the worker thread to exit
+    stop: Arc<AtomicBool>,
+    /// Handle of the worker thread
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AutoSaver {
+    /// Start saving a shared system to the given store every `interval`
+    #[must_use]
+    pub fn spawn(
+        system: Arc<Mutex<LibrarySystem>>,
+        store: Arc<dyn StateStore>,
+        interval: Duration,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            let mut next_save = Instant::now().checked_add(interval);
+            while !worker_stop.load(Ordering::Acquire) {
+                let now = Instant::now();
+                match next_save {
+                    Some(deadline) if now < deadline => {
+                        thread::park_timeout(deadline.duration_since(now));
+                        continue;
+                    }
+                    _ => {}
+                }
+                if !save(&system, store.as_ref()) {
+                    return;
+                }
+                next_save = now.checked_add(interval);
+            }
+
+            // Final flush on shutdown
+            save(&system, store.as_ref());
+        });
+
+        Self { stop, handle: Some(handle) }
+    }
+
+    /// Stop the worker thread after a final save and wait for it to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signal the worker to exit and join it
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            if handle.join().is_err() {
+                emit!(error, "AUTOSAVE: Worker thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for AutoSaver {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Snapshot the system and write it to the store
+///
+/// Returns `false` if the system's lock is poisoned, as such a system can't
+/// be trusted any more. Store errors are logged and retried at the next
+/// interval.
+fn save(system: &Mutex<LibrarySystem>, store: &dyn StateStore) -> bool {
+    let Ok(state) = system.lock().map(|system| system.to_serializable_state()) else {
+        return false;
+    };
+    if let Err(e) = store.save(&state) {
+        emit!(warn, "AUTOSAVE: Failed to save {}: {e}", state.system_id);
+        return true;
+    }
+    system.lock().map(|system| system.record_saved(&state)).is_ok()
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
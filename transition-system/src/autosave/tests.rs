@@ -0,0 +1,59 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    autosave::AutoSaver,
+    book_state::BookState,
+    events::BookEvent,
+    persistence::{FileStore, StateStore},
+    system::{LibraryError, LibrarySystem},
+};
+
+#[test]
+fn test_autosaver_saves_periodically_and_on_stop() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("autosaver-test-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = Arc::new(FileStore::new(&directory));
+
+    let system = Arc::new(Mutex::new(crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+        },
+    }?));
+
+    let result = (|| {
+        let saver = AutoSaver::spawn(Arc::clone(&system), store.clone(), Duration::from_millis(10));
+        system
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .process_event(BookEvent::Reserve("Test User".into()))?;
+
+        // The interval alone persists the reservation
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(store.load("test-book")?.sequence, 1);
+
+        // Stopping flushes what happened since the last interval
+        drop(saver);
+        let saver = AutoSaver::spawn(Arc::clone(&system), store.clone(), Duration::from_hours(1));
+        system
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .process_event(BookEvent::CancelReservation)?;
+        saver.stop();
+
+        let loaded = LibrarySystem::load_from(store.as_ref(), "test-book")?;
+        assert_eq!(*loaded.current_state(), BookState::Available);
+        assert_eq!(loaded.sequence(), 2);
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
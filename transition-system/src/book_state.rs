@@ -1,21 +1,37 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+use serde_json::Value;
+
+use crate::{
+    patrons::PatronId,
+    schema::{self, STATE_MIGRATIONS},
+};
 
 /// Represents the possible states of a library book
+///
+/// States renamed since older state files were written are upgraded on load,
+/// see [`schema`].
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(remote = "Self")]
 pub enum BookState {
     /// Book is available for checkout
     #[default]
     Available,
     /// Book is reserved by a patron
-    Reserved(String),
+    Reserved(PatronId),
     /// Book is checked out by a patron
-    CheckedOut(String),
+    CheckedOut(PatronId),
     /// Book is in transit between library branches
     InTransit,
     /// Book is being repaired
     UnderRepair,
     /// Book is marked as lost
     Lost,
+    /// Book is damaged, for the given reason, and can't be lent out
+    Damaged(String),
+    /// Book is waiting on the hold shelf for a patron to pick it up
+    OnHoldShelf(PatronId),
+    /// Book has been permanently removed from the collection
+    Withdrawn,
 }
 
 impl BookState {
@@ -29,6 +45,26 @@ impl BookState {
             Self::InTransit => "Book is in transit between library branches".to_string(),
             Self::UnderRepair => "Book is currently being repaired".to_string(),
             Self::Lost => "Book is marked as lost".to_string(),
+            Self::Damaged(reason) => format!("Book is damaged: {reason}"),
+            Self::OnHoldShelf(patron) => format!("Book is on the hold shelf for {patron}"),
+            Self::Withdrawn => "Book has been withdrawn from the collection".to_string(),
+        }
+    }
+}
+
+impl Serialize for BookState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BookState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Binary formats always used the derived representation
+        if !deserializer.is_human_readable() {
+            return Self::deserialize(deserializer);
         }
+        let value = schema::upgrade_state(Value::deserialize(deserializer)?, STATE_MIGRATIONS);
+        Self::deserialize(value).map_err(D::Error::custom)
     }
 }
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{book_state::BookState, events::BookEvent, system::LibraryError};
+
+/// A library branch that books can be transferred between
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Branch {
+    /// Unique identifier of the branch
+    pub id: String,
+    /// Human-readable branch name
+    pub name: String,
+}
+
+/// Registry of the branches known to a library
+#[derive(Debug, Clone, Default)]
+pub struct BranchRegistry {
+    /// Registered branches keyed by ID
+    branches: BTreeMap<String, Branch>,
+}
+
+impl BranchRegistry {
+    /// Create an empty registry
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a branch, replacing any branch with the same ID
+    pub fn register(&mut self, id: &str, name: &str) {
+        self.branches.insert(id.to_string(), Branch { id: id.to_string(), name: name.to_string() });
+    }
+
+    /// Get a registered branch
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&Branch> {
+        self.branches.get(id)
+    }
+
+    /// Check whether a branch is registered
+    #[must_use]
+    pub fn contains(&self, id: &str) -> bool {
+        self.branches.contains_key(id)
+    }
+
+    /// Iterate over all registered branches in ID order
+    pub fn iter(&self) -> impl Iterator<Item = &Branch> {
+        self.branches.values()
+    }
+
+    /// Fail with `LibraryError::UnknownBranch` if the branch isn't registered
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::UnknownBranch` if no branch has the given ID
+    pub fn ensure_registered(&self, id: &str) -> Result<(), LibraryError> {
+        if self.contains(id) { Ok(()) } else { Err(LibraryError::UnknownBranch(id.to_string())) }
+    }
+}
+
+/// A transfer that has been started but not completed yet
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PendingTransfer {
+    /// Branch the book is leaving, if known
+    pub source: Option<String>,
+    /// Branch the book is travelling to
+    pub destination: String,
+}
+
+/// Where a book is located, tracked alongside its state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BranchLocation {
+    /// Branch currently holding the book, if known
+    pub current_branch: Option<String>,
+    /// Transfer in progress, if any
+    pub transfer: Option<PendingTransfer>,
+}
+
+impl BranchLocation {
+    /// Check that a transfer to `destination` makes sense
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::InvalidTransfer` if the book is already at the
+    /// destination branch
+    pub fn validate_transfer(&self, destination: &str) -> Result<(), LibraryError> {
+        if self.current_branch.as_deref() == Some(destination) {
+            return Err(LibraryError::InvalidTransfer(format!(
+                "Book is already at branch {destination}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Update the location after a transition has been applied
+    ///
+    /// Completing a transfer moves the book to the pending transfer's
+    /// destination. Leaving transit any other way (e.g. the book getting lost)
+    /// abandons the transfer.
+    pub fn on_transition(&mut self, to: &BookState, event: &BookEvent) {
+        if *event == BookEvent::TransferComplete
+            && let Some(transfer) = self.transfer.take()
+        {
+            self.current_branch = Some(transfer.destination);
+        } else if *to != BookState::InTransit {
+            self.transfer = None;
+        }
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use crate::{
+    book_state::BookState,
+    branches::BranchRegistry,
+    events::BookEvent,
+    system::{LibraryError, LibrarySystem},
+};
+
+/// Create a book that can be transferred between branches
+fn transfer_system() -> LibrarySystem {
+    let mut registry = BranchRegistry::new();
+    registry.register("main", "Main Library");
+    registry.register("north", "North Branch");
+
+    let mut system = LibrarySystem::new(BookState::Available, "test-book");
+    let in_transit_idx = system.add_state(BookState::InTransit);
+    system.add_transition(0, BookEvent::Transfer { destination: None }, in_transit_idx);
+    system.add_transition(in_transit_idx, BookEvent::TransferComplete, 0);
+    system.set_branch_registry(Arc::new(registry));
+    system
+}
+
+#[test]
+fn test_transfer_moves_book_to_destination() {
+    let mut system = transfer_system();
+    assert!(system.set_current_branch("main").is_ok());
+
+    assert!(matches!(system.transfer_to("north"), Ok(BookState::InTransit)));
+    let transfer = system.location().transfer.clone();
+    assert_eq!(
+        transfer.map(|t| (t.source, t.destination)),
+        Some((Some("main".into()), "north".into()))
+    );
+
+    assert!(system.process_event(BookEvent::TransferComplete).is_ok());
+    assert_eq!(system.location().current_branch.as_deref(), Some("north"));
+    assert_eq!(system.location().transfer, None);
+
+    let transfer_to = system.get_history().first().and_then(|t| t.metadata.get("transfer_to"));
+    assert_eq!(transfer_to.map(String::as_str), Some("north"));
+}
+
+#[test]
+fn test_invalid_transfers_are_rejected() {
+    let mut system = transfer_system();
+    assert!(system.set_current_branch("main").is_ok());
+
+    assert!(matches!(system.transfer_to("main"), Err(LibraryError::InvalidTransfer(_))));
+    assert!(matches!(
+        system.transfer_to("south"),
+        Err(LibraryError::UnknownBranch(branch)) if branch == "south"
+    ));
+    assert_eq!(*system.current_state(), BookState::Available);
+    assert_eq!(system.location().transfer, None);
+}
+
+#[test]
+fn test_transfer_event_sets_pending_transfer() -> Result<(), LibraryError> {
+    let mut system = transfer_system();
+    system.set_current_branch("main")?;
+
+    let unknown = BookEvent::Transfer { destination: Some("south".to_string()) };
+    assert!(matches!(system.process_event(unknown), Err(LibraryError::UnknownBranch(_))));
+    assert_eq!(*system.current_state(), BookState::Available);
+
+    system.process_event(BookEvent::Transfer { destination: Some("north".to_string()) })?;
+    let transfer = system.location().transfer.clone();
+    assert_eq!(
+        transfer.map(|t| (t.source, t.destination)),
+        Some((Some("main".into()), "north".into()))
+    );
+    system.process_event(BookEvent::TransferComplete)?;
+    assert_eq!(system.location().current_branch.as_deref(), Some("north"));
+    Ok(())
+}
@@ -0,0 +1,372 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use crate::{
+    book_state::BookState,
+    clock::Clock,
+    dead_letter::DeadLetterSink,
+    events::BookEvent,
+    interceptors::TransitionInterceptor,
+    observers::{ObserverErrorPolicy, StateObserver},
+    patrons::PatronId,
+    system::{LibraryError, LibrarySystem},
+};
+
+/// Fluent builder for setting up a [`LibrarySystem`]
+///
+/// States are referenced by value and their indices resolved internally, so
+/// transitions can't accidentally point at the wrong state.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use transition_system::{BookEvent, BookState, PatronId, builder::LibrarySystemBuilder};
+///
+/// let alice = || PatronId::from("Alice");
+/// let system = LibrarySystemBuilder::new("book-1", BookState::Available)
+///     .transition(BookState::Available, BookEvent::Reserve(alice()), BookState::Reserved(alice()))
+///     .transition(BookState::Reserved(alice()), BookEvent::CancelReservation, BookState::Available)
+///     .timeout(BookState::Reserved(alice()), Duration::from_hours(72), BookEvent::CancelReservation)
+///     .build()?;
+///
+/// assert_eq!(system.get_states().len(), 2);
+/// # Ok::<(), transition_system::system::LibraryError>(())
+/// ```
+pub struct LibrarySystemBuilder {
+    /// Unique identifier for the built system
+    system_id: String,
+    /// State the system starts in
+    initial_state: BookState,
+    /// Declared states in declaration order
+    states: Vec<BookState>,
+    /// Declared transitions as (from, event, to)
+    transitions: Vec<(BookState, BookEvent, BookState)>,
+    /// Declared timing constraints as (state, max duration, timeout event)
+    timeouts: Vec<(BookState, Duration, BookEvent)>,
+    /// States expected to have no outgoing transitions
+    final_states: Vec<BookState>,
+    /// Categories to tag states with as (state, category)
+    categories: Vec<(BookState, String)>,
+    /// Observers to register on the built system
+    observers: Vec<Box<dyn StateObserver>>,
+    /// What to do when an observer fails to handle a transition
+    observer_error_policy: ObserverErrorPolicy,
+    /// Policies to consult before every transition
+    interceptors: Vec<Box<dyn TransitionInterceptor>>,
+    /// Where the built system records rejected events
+    dead_letters: Option<Box<dyn DeadLetterSink>>,
+    /// Clock to use instead of the system clock
+    clock: Option<Arc<dyn Clock>>,
+}
+
+// Manual implementation of Debug for LibrarySystemBuilder
+impl fmt::Debug for LibrarySystemBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LibrarySystemBuilder")
+            .field("system_id", &self.system_id)
+            .field("initial_state", &self.initial_state)
+            .field("states", &self.states)
+            .field("transitions", &self.transitions)
+            .field("timeouts", &self.timeouts)
+            .field("final_states", &self.final_states)
+            .field("categories", &self.categories)
+            .field("observers_count", &self.observers.len())
+            .field("observer_error_policy", &self.observer_error_policy)
+            .field("interceptors_count", &self.interceptors.len())
+            .field("dead_letters", &self.dead_letters.is_some())
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+impl LibrarySystemBuilder {
+    /// Start building a system with the given ID and initial state
+    #[must_use]
+    pub fn new(system_id: &str, initial_state: BookState) -> Self {
+        Self {
+            system_id: system_id.to_string(),
+            initial_state,
+            states: Vec::new(),
+            transitions: Vec::new(),
+            timeouts: Vec::new(),
+            final_states: Vec::new(),
+            categories: Vec::new(),
+            observers: Vec::new(),
+            observer_error_policy: ObserverErrorPolicy::default(),
+            interceptors: Vec::new(),
+            dead_letters: None,
+            clock: None,
+        }
+    }
+
+    /// Start from the lifecycle most libraries need, for the given patrons
+    ///
+    /// Books start `Available` and can be reserved, put on the hold shelf,
+    /// checked out, transferred, repaired, reported lost or damaged, and
+    /// withdrawn for good. Reservations expire after 3 days, books on the
+    /// hold shelf after 7 and loans after 14. Patron and damage states are
+    /// declared for every given patron and damage reason.
+    #[must_use]
+    pub fn standard_library_machine(
+        system_id: &str,
+        patrons: &[&str],
+        damage_reasons: &[&str],
+    ) -> Self {
+        let mut builder = Self::new(system_id, BookState::Available);
+
+        for patron in patrons.iter().copied().map(PatronId::from) {
+            let reserved = BookState::Reserved(patron.clone());
+            let on_hold_shelf = BookState::OnHoldShelf(patron.clone());
+            let checked_out = BookState::CheckedOut(patron.clone());
+
+            builder = builder
+                // Reservations, pickups and checkouts from the shelf
+                .transition(
+                    BookState::Available,
+                    BookEvent::Reserve(patron.clone()),
+                    reserved.clone(),
+                )
+                .transition(
+                    BookState::Available,
+                    BookEvent::HoldForPickup(patron.clone()),
+                    on_hold_shelf.clone(),
+                )
+                .transition(
+                    BookState::Available,
+                    BookEvent::CheckOut(patron.clone()),
+                    checked_out.clone(),
+                )
+                // Reserved books are put on the hold shelf, cancelled or lost
+                .transition(
+                    reserved.clone(),
+                    BookEvent::HoldForPickup(patron.clone()),
+                    on_hold_shelf.clone(),
+                )
+                .transition(
+                    reserved.clone(),
+                    BookEvent::CheckOut(patron.clone()),
+                    checked_out.clone(),
+                )
+                .transition(reserved.clone(), BookEvent::CancelReservation, BookState::Available)
+                .transition(reserved.clone(), BookEvent::ReportLost, BookState::Lost)
+                // Books on the hold shelf are picked up or go back after a week
+                .transition(
+                    on_hold_shelf.clone(),
+                    BookEvent::CheckOut(patron.clone()),
+                    checked_out.clone(),
+                )
+                .transition(
+                    on_hold_shelf.clone(),
+                    BookEvent::CancelReservation,
+                    BookState::Available,
+                )
+                // Checked out books are returned or lost
+                .transition(checked_out.clone(), BookEvent::Return, BookState::Available)
+                .transition(checked_out.clone(), BookEvent::ReportLost, BookState::Lost)
+                .timeout(reserved, Duration::from_hours(3 * 24), BookEvent::CancelReservation)
+                .timeout(on_hold_shelf, Duration::from_hours(7 * 24), BookEvent::CancelReservation)
+                .timeout(checked_out.clone(), Duration::from_hours(14 * 24), BookEvent::Return);
+
+            // Damage is noticed on the shelf or when a loan comes back
+            for reason in damage_reasons.iter().map(ToString::to_string) {
+                builder = builder.transition(
+                    checked_out.clone(),
+                    BookEvent::ReportDamage(reason.clone()),
+                    BookState::Damaged(reason),
+                );
+            }
+        }
+
+        for reason in damage_reasons.iter().map(ToString::to_string) {
+            let damaged = BookState::Damaged(reason.clone());
+            builder = builder
+                .transition(BookState::Available, BookEvent::ReportDamage(reason), damaged.clone())
+                .transition(
+                    damaged.clone(),
+                    BookEvent::SendToRepair { condition: None },
+                    BookState::UnderRepair,
+                )
+                .transition(damaged, BookEvent::Withdraw, BookState::Withdrawn);
+        }
+
+        builder
+            // Transfers between branches
+            .transition(
+                BookState::Available,
+                BookEvent::Transfer { destination: None },
+                BookState::InTransit,
+            )
+            .transition(BookState::InTransit, BookEvent::TransferComplete, BookState::Available)
+            .transition(BookState::InTransit, BookEvent::ReportLost, BookState::Lost)
+            // Repairs
+            .transition(
+                BookState::Available,
+                BookEvent::SendToRepair { condition: None },
+                BookState::UnderRepair,
+            )
+            .transition(BookState::UnderRepair, BookEvent::CompleteRepair, BookState::Available)
+            .transition(BookState::UnderRepair, BookEvent::ReportLost, BookState::Lost)
+            .transition(BookState::UnderRepair, BookEvent::Withdraw, BookState::Withdrawn)
+            // Lost books
+            .transition(BookState::Available, BookEvent::ReportLost, BookState::Lost)
+            .transition(BookState::Lost, BookEvent::Found, BookState::Available)
+            .transition(BookState::Lost, BookEvent::Withdraw, BookState::Withdrawn)
+            // Withdrawn books never come back
+            .transition(BookState::Available, BookEvent::Withdraw, BookState::Withdrawn)
+            .final_state(BookState::Withdrawn)
+    }
+
+    /// Declare a state
+    ///
+    /// States used in transitions are declared automatically; this is only
+    /// needed for states without transitions or to control state ordering.
+    #[must_use]
+    pub fn state(mut self, state: BookState) -> Self {
+        self.states.push(state);
+        self
+    }
+
+    /// Declare a transition from one state to another when an event occurs
+    #[must_use]
+    pub fn transition(mut self, from: BookState, event: BookEvent, to: BookState) -> Self {
+        self.transitions.push((from, event, to));
+        self
+    }
+
+    /// Declare a transition for a custom event, see [`BookEvent::Custom`]
+    #[must_use]
+    pub fn custom_transition(self, from: BookState, event: &str, to: BookState) -> Self {
+        self.transition(from, BookEvent::custom(event), to)
+    }
+
+    /// Declare a timing constraint firing `event` after `max_duration` in `state`
+    #[must_use]
+    pub fn timeout(mut self, state: BookState, max_duration: Duration, event: BookEvent) -> Self {
+        self.timeouts.push((state, max_duration, event));
+        self
+    }
+
+    /// Declare a final state, which is allowed to have no outgoing transitions
+    #[must_use]
+    pub fn final_state(mut self, state: BookState) -> Self {
+        self.final_states.push(state);
+        self
+    }
+
+    /// Tag a state with a category, declaring the state if needed
+    #[must_use]
+    pub fn category(mut self, state: BookState, category: &str) -> Self {
+        self.categories.push((state, category.to_string()));
+        self
+    }
+
+    /// Register an observer on the built system
+    #[must_use]
+    pub fn observer(mut self, observer: Box<dyn StateObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Choose what happens when an observer fails to handle a transition
+    #[must_use]
+    pub fn observer_error_policy(mut self, policy: ObserverErrorPolicy) -> Self {
+        self.observer_error_policy = policy;
+        self
+    }
+
+    /// Add a policy consulted before every transition of the built system
+    #[must_use]
+    pub fn interceptor(mut self, interceptor: Box<dyn TransitionInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Record the events the built system rejects in the given sink
+    #[must_use]
+    pub fn dead_letter_sink(mut self, sink: Box<dyn DeadLetterSink>) -> Self {
+        self.dead_letters = Some(sink);
+        self
+    }
+
+    /// Use the given clock for timing constraints
+    #[must_use]
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Validate the definition and build the system
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::InvalidDefinition` if:
+    /// - The same state and event lead to two different target states
+    /// - A timing constraint refers to a state that isn't part of the machine
+    /// - A timeout event has no transition from its state
+    pub fn build(self) -> Result<LibrarySystem, LibraryError> {
+        let mut system = match self.clock {
+            Some(clock) => LibrarySystem::with_clock(self.initial_state, &self.system_id, clock),
+            None => LibrarySystem::new(self.initial_state, &self.system_id),
+        };
+
+        for state in self.states {
+            system.add_state(state);
+        }
+
+        for (from, event, to) in self.transitions {
+            let from_idx = system.add_state(from);
+            let to_idx = system.add_state(to);
+            match system.get_all_transitions().get(&(from_idx, event.clone().transition_key())) {
+                Some(&existing) if existing != to_idx => {
+                    return Err(LibraryError::InvalidDefinition(format!(
+                        "Conflicting transitions for {event:?} from state {from_idx}"
+                    )));
+                }
+                _ => system.add_transition(from_idx, event, to_idx),
+            }
+        }
+
+        for (state, max_duration, event) in self.timeouts {
+            let Some(state_idx) = system.get_state_idx(&state) else {
+                return Err(LibraryError::InvalidDefinition(format!(
+                    "Timeout declared for unknown state {state:?}"
+                )));
+            };
+            if !system
+                .get_all_transitions()
+                .contains_key(&(state_idx, event.clone().transition_key()))
+            {
+                return Err(LibraryError::InvalidDefinition(format!(
+                    "Timeout event {event:?} has no transition from state {state:?}"
+                )));
+            }
+            system.add_timing_constraint(state_idx, max_duration, event);
+        }
+
+        for state in self.final_states {
+            let state_idx = system.add_state(state);
+            system.mark_final_state(state_idx);
+        }
+
+        for (state, category) in self.categories {
+            let state_idx = system.add_state(state);
+            system.set_state_category(state_idx, &category);
+        }
+
+        for observer in self.observers {
+            system.register_observer(observer);
+        }
+        system.set_observer_error_policy(self.observer_error_policy);
+        for interceptor in self.interceptors {
+            system.add_interceptor(interceptor);
+        }
+        if let Some(sink) = self.dead_letters {
+            system.set_dead_letter_sink(sink);
+        }
+
+        Ok(system)
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
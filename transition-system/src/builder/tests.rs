@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use crate::{
+    book_state::BookState, builder::LibrarySystemBuilder, events::BookEvent, patrons::PatronId,
+    system::LibraryError,
+};
+
+#[test]
+#[allow(clippy::panic)]
+fn test_builder_resolves_states_by_value() {
+    let patron = || PatronId::from("Test User");
+    let system = LibrarySystemBuilder::new("test-book", BookState::Available)
+        .state(BookState::Lost)
+        .transition(
+            BookState::Available,
+            BookEvent::Reserve(patron()),
+            BookState::Reserved(patron()),
+        )
+        .transition(
+            BookState::Reserved(patron()),
+            BookEvent::CancelReservation,
+            BookState::Available,
+        )
+        .timeout(
+            BookState::Reserved(patron()),
+            Duration::from_mins(1),
+            BookEvent::CancelReservation,
+        )
+        .build();
+
+    let Ok(mut system) = system else {
+        panic!("Definition should be valid");
+    };
+    assert_eq!(
+        system.get_states(),
+        &vec![BookState::Available, BookState::Lost, BookState::Reserved(patron())]
+    );
+    assert_eq!(system.get_timing_constraints().len(), 1);
+    assert!(system.process_event(BookEvent::Reserve(patron())).is_ok());
+    assert_eq!(*system.current_state(), BookState::Reserved(patron()));
+}
+
+#[test]
+fn test_builder_rejects_invalid_definitions() {
+    let conflicting = LibrarySystemBuilder::new("test-book", BookState::Available)
+        .transition(
+            BookState::Available,
+            BookEvent::SendToRepair { condition: None },
+            BookState::UnderRepair,
+        )
+        .transition(
+            BookState::Available,
+            BookEvent::SendToRepair { condition: None },
+            BookState::Lost,
+        )
+        .build();
+    assert!(matches!(conflicting, Err(LibraryError::InvalidDefinition(_))));
+
+    let unknown_state = LibrarySystemBuilder::new("test-book", BookState::Available)
+        .timeout(BookState::Lost, Duration::from_mins(1), BookEvent::Found)
+        .build();
+    assert!(matches!(unknown_state, Err(LibraryError::InvalidDefinition(_))));
+
+    let missing_timeout_transition = LibrarySystemBuilder::new("test-book", BookState::Available)
+        .transition(BookState::Available, BookEvent::ReportLost, BookState::Lost)
+        .timeout(BookState::Lost, Duration::from_mins(1), BookEvent::Found)
+        .build();
+    assert!(matches!(missing_timeout_transition, Err(LibraryError::InvalidDefinition(_))));
+}
+
+#[test]
+#[allow(clippy::panic)]
+fn test_state_machine_macro_matches_builder() {
+    let from_macro = crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+            Available --ReportLost--> Lost,
+        },
+        timeouts: {
+            Reserved("Test User") after Duration::from_mins(1) => CancelReservation,
+        },
+    };
+    let from_builder = LibrarySystemBuilder::new("test-book", BookState::Available)
+        .transition(
+            BookState::Available,
+            BookEvent::Reserve("Test User".into()),
+            BookState::Reserved("Test User".into()),
+        )
+        .transition(
+            BookState::Reserved("Test User".into()),
+            BookEvent::CancelReservation,
+            BookState::Available,
+        )
+        .transition(BookState::Available, BookEvent::ReportLost, BookState::Lost)
+        .timeout(
+            BookState::Reserved("Test User".into()),
+            Duration::from_mins(1),
+            BookEvent::CancelReservation,
+        )
+        .build();
+
+    let (Ok(from_macro), Ok(from_builder)) = (from_macro, from_builder) else {
+        panic!("Both definitions should be valid");
+    };
+    assert_eq!(from_macro.get_states(), from_builder.get_states());
+    assert_eq!(from_macro.get_all_transitions(), from_builder.get_all_transitions());
+    assert_eq!(from_macro.get_timing_constraints().len(), 1);
+}
+
+#[test]
+fn test_standard_library_machine() -> Result<(), LibraryError> {
+    let alice = || PatronId::from("Alice");
+    let mut system =
+        LibrarySystemBuilder::standard_library_machine("book-1", &["Alice"], &["Water damage"])
+            .build()?;
+    assert!(system.validate().is_empty());
+
+    system.process_event(BookEvent::Reserve(alice()))?;
+    system.process_event(BookEvent::HoldForPickup(alice()))?;
+    assert_eq!(*system.current_state(), BookState::OnHoldShelf(alice()));
+    system.process_event(BookEvent::CheckOut(alice()))?;
+    system.process_event(BookEvent::ReportDamage("Water damage".to_string()))?;
+    assert_eq!(*system.current_state(), BookState::Damaged("Water damage".to_string()));
+    system.process_event(BookEvent::Withdraw)?;
+    assert_eq!(*system.current_state(), BookState::Withdrawn);
+    assert!(system.valid_events().is_empty());
+    Ok(())
+}
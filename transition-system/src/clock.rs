@@ -0,0 +1,64 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Source of the current time for timing constraints
+///
+/// Injecting a clock lets tests control time instead of sleeping or rewinding
+/// internal timestamps.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Get the current instant
+    fn now(&self) -> Instant;
+}
+
+/// Clock backed by the operating system's monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Manually controlled clock for tests
+///
+/// Clones share the same underlying time, so a test can keep one handle and
+/// hand another to the system under test.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    /// The instant currently reported by the clock
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Create a mock clock starting at the current real time
+    #[must_use]
+    pub fn new() -> Self {
+        Self { now: Arc::new(Mutex::new(Instant::now())) }
+    }
+
+    /// Move the clock forward by the given duration
+    ///
+    /// The clock stays put if the new instant cannot be represented.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(advanced) = now.checked_add(duration) {
+            *now = advanced;
+        }
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
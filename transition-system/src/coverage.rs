@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::{
+    book_state::BookState,
+    events::BookEvent,
+    model_check::{TraceStep, outgoing_transitions},
+    system::{LibraryError, LibrarySystem},
+};
+
+/// A sequence of events to drive against a fresh system, with the expected states
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestSequence {
+    /// Steps in order, starting from the system's start state
+    pub steps: Vec<TraceStep>,
+}
+
+impl TestSequence {
+    /// Iterate over the events of the sequence
+    pub fn events(&self) -> impl Iterator<Item = &BookEvent> {
+        self.steps.iter().map(|step| &step.event)
+    }
+
+    /// Process every event of the sequence, checking each resulting state
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`LibrarySystem::process_event`], or a
+    /// `LibraryError::InvalidTransition` if a step ends in an unexpected state
+    pub fn run(&self, system: &mut LibrarySystem) -> Result<(), LibraryError> {
+        for step in &self.steps {
+            if *system.process_event(step.event.clone())? != step.to {
+                return Err(LibraryError::InvalidTransition {
+                    from_state: step.from.clone(),
+                    event: step.event.clone(),
+                    valid_events: system.valid_events(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generate event sequences that together take every reachable transition
+///
+/// Each sequence starts from the system's current state. The generator walks
+/// greedily to the nearest transition that hasn't been taken yet, so
+/// sequences stay short, and only starts a new sequence when no untaken
+/// transition is reachable from where the previous one ended.
+#[must_use]
+pub fn edge_coverage_sequences(system: &LibrarySystem) -> Vec<TestSequence> {
+    let states = system.get_states();
+    let outgoing = outgoing_transitions(system);
+    let start = system.get_current_state_idx();
+
+    let mut uncovered: BTreeSet<(usize, usize)> = reachable_edges(&outgoing, start, states.len());
+    let mut sequences = Vec::new();
+
+    while !uncovered.is_empty() {
+        let mut steps = Vec::new();
+        let mut current = start;
+        while let Some(path) = path_to_uncovered(&outgoing, &uncovered, current) {
+            for (state_idx, edge_idx) in path {
+                let Some(&(event, to_idx)) =
+                    outgoing.get(&state_idx).and_then(|edges| edges.get(edge_idx))
+                else {
+                    continue;
+                };
+                uncovered.remove(&(state_idx, edge_idx));
+                steps.push(trace_step(states, state_idx, event, to_idx));
+                current = to_idx;
+            }
+        }
+        if steps.is_empty() {
+            break;
+        }
+        sequences.push(TestSequence { steps });
+    }
+
+    sequences
+}
+
+/// Collect every edge reachable from `start` as (state index, edge position)
+fn reachable_edges(
+    outgoing: &BTreeMap<usize, Vec<(&BookEvent, usize)>>,
+    start: usize,
+    state_count: usize,
+) -> BTreeSet<(usize, usize)> {
+    let mut edges = BTreeSet::new();
+    let mut visited = BTreeSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(state_idx) = queue.pop_front() {
+        for (edge_idx, &(_, to_idx)) in outgoing.get(&state_idx).into_iter().flatten().enumerate() {
+            if to_idx >= state_count {
+                continue;
+            }
+            edges.insert((state_idx, edge_idx));
+            if visited.insert(to_idx) {
+                queue.push_back(to_idx);
+            }
+        }
+    }
+    edges
+}
+
+/// Find the shortest path from `from` ending with an uncovered edge
+fn path_to_uncovered(
+    outgoing: &BTreeMap<usize, Vec<(&BookEvent, usize)>>,
+    uncovered: &BTreeSet<(usize, usize)>,
+    from: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let mut parents: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    let mut visited = BTreeSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(state_idx) = queue.pop_front() {
+        let edges = outgoing.get(&state_idx).into_iter().flatten().enumerate();
+        if let Some(edge_idx) = edges
+            .clone()
+            .map(|(edge_idx, _)| edge_idx)
+            .find(|edge_idx| uncovered.contains(&(state_idx, *edge_idx)))
+        {
+            let mut path = vec![(state_idx, edge_idx)];
+            let mut node = state_idx;
+            while let Some(&(parent, parent_edge)) = parents.get(&node) {
+                path.push((parent, parent_edge));
+                node = parent;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for (edge_idx, &(_, to_idx)) in edges {
+            if visited.insert(to_idx) {
+                parents.insert(to_idx, (state_idx, edge_idx));
+                queue.push_back(to_idx);
+            }
+        }
+    }
+
+    None
+}
+
+/// Build a trace step from state indices
+fn trace_step(states: &[BookState], from: usize, event: &BookEvent, to: usize) -> TraceStep {
+    TraceStep {
+        from: states.get(from).cloned().unwrap_or_default(),
+        event: event.clone(),
+        to: states.get(to).cloned().unwrap_or_default(),
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
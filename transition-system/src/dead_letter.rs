@@ -0,0 +1,185 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book_state::BookState,
+    events::{BookEvent, EventEnvelope},
+    logging::emit,
+    persistence::TimeStamp,
+    system::LibraryError,
+};
+
+/// An event the system rejected because no transition matched it
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeadLetter {
+    /// System that rejected the event
+    pub system_id: String,
+    /// The state the system was in when the event arrived
+    pub state: BookState,
+    /// The rejected event
+    pub event: BookEvent,
+    /// Wall-clock time the event occurred
+    pub timestamp: TimeStamp,
+    /// Who triggered the event, if known
+    pub actor: Option<String>,
+    /// Note attached to the event, if any
+    pub note: Option<String>,
+}
+
+impl DeadLetter {
+    /// Turn the letter back into an envelope, e.g. to process it again
+    ///
+    /// The envelope keeps the original actor, note and time.
+    #[must_use]
+    pub fn into_envelope(self) -> EventEnvelope {
+        EventEnvelope {
+            event: self.event,
+            actor: self.actor,
+            note: self.note,
+            occurred_at: self.timestamp.to_system_time(),
+        }
+    }
+}
+
+/// Destination for events rejected with `LibraryError::InvalidTransition`
+///
+/// The caller still gets the error; the sink keeps a copy so rejected events
+/// can be inspected or replayed later instead of being lost in the caller's
+/// error branch. Events blocked by an interceptor or a borrowing limit are
+/// not dead letters.
+pub trait DeadLetterSink: Send {
+    /// Called with every event the system rejects
+    fn record(&self, letter: &DeadLetter);
+}
+
+impl<F> DeadLetterSink for F
+where
+    F: Fn(&DeadLetter) + Send,
+{
+    fn record(&self, letter: &DeadLetter) {
+        self(letter);
+    }
+}
+
+/// Keeps dead letters in memory
+///
+/// Clones share the same letters, so keep a clone to inspect what the system
+/// rejected after handing the sink over.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDeadLetters {
+    /// The recorded letters, oldest first
+    letters: Arc<Mutex<Vec<DeadLetter>>>,
+}
+
+impl MemoryDeadLetters {
+    /// Create an empty sink
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a copy of the recorded letters, oldest first
+    #[must_use]
+    pub fn letters(&self) -> Vec<DeadLetter> {
+        self.letters.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Remove and return the recorded letters, oldest first
+    #[must_use]
+    pub fn take(&self) -> Vec<DeadLetter> {
+        std::mem::take(&mut *self.letters.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Get the number of recorded letters
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.letters.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Check whether no letters have been recorded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl DeadLetterSink for MemoryDeadLetters {
+    fn record(&self, letter: &DeadLetter) {
+        self.letters.lock().unwrap_or_else(PoisonError::into_inner).push(letter.clone());
+    }
+}
+
+/// Appends dead letters to a JSON-lines file
+///
+/// A letter that can't be written is logged and dropped, since the rejected
+/// event has already been reported to the caller.
+#[derive(Debug)]
+pub struct FileDeadLetters {
+    /// Path of the dead-letter file
+    path: PathBuf,
+    /// Serializes writes
+    lock: Mutex<()>,
+}
+
+impl FileDeadLetters {
+    /// Append dead letters to the given file
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+
+    /// Get the path of the dead-letter file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the letters of a dead-letter file
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the file can't be read or a line
+    /// can't be parsed
+    pub fn read_letters(path: impl AsRef<Path>) -> Result<Vec<DeadLetter>, LibraryError> {
+        let file = File::open(path).map_err(|e| {
+            LibraryError::LoadError(format!("Failed to open dead-letter file: {e}"))
+        })?;
+        BufReader::new(file)
+            .lines()
+            .map(|line| {
+                let line = line.map_err(|e| {
+                    LibraryError::LoadError(format!("Failed to read dead-letter file: {e}"))
+                })?;
+                serde_json::from_str(&line).map_err(|e| {
+                    LibraryError::LoadError(format!("Failed to parse dead letter: {e}"))
+                })
+            })
+            .collect()
+    }
+
+    /// Append a letter to the file
+    fn append(&self, letter: &DeadLetter) -> std::io::Result<()> {
+        let line = serde_json::to_string(letter)?;
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{line}")
+    }
+}
+
+impl DeadLetterSink for FileDeadLetters {
+    fn record(&self, letter: &DeadLetter) {
+        if let Err(e) = self.append(letter) {
+            emit!(warn, "DEAD LETTER: Failed to write {:?}: {e}", letter.event);
+        }
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
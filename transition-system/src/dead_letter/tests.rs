@@ -0,0 +1,119 @@
+use std::{fs, time::SystemTime};
+
+use super::*;
+use crate::{builder::LibrarySystemBuilder, patrons::PatronId, system::LibrarySystem};
+
+/// Build a system with a single reservation cycle and the given sink
+fn build_system(sink: Box<dyn DeadLetterSink>) -> Result<LibrarySystem, LibraryError> {
+    let patron = PatronId::from("Test User");
+    LibrarySystemBuilder::new("dead-letter-test", BookState::Available)
+        .transition(
+            BookState::Available,
+            BookEvent::Reserve(patron.clone()),
+            BookState::Reserved(patron.clone()),
+        )
+        .transition(BookState::Reserved(patron), BookEvent::CancelReservation, BookState::Available)
+        .dead_letter_sink(sink)
+        .build()
+}
+
+#[test]
+fn test_rejected_events_are_recorded() -> Result<(), LibraryError> {
+    let letters = MemoryDeadLetters::new();
+    let mut system = build_system(Box::new(letters.clone()))?;
+
+    let occurred_at = SystemTime::UNIX_EPOCH;
+    let result = system.process_event_with_meta(
+        EventEnvelope::new(BookEvent::Return)
+            .actor("front-desk")
+            .note("Scanned at the wrong desk")
+            .occurred_at(occurred_at),
+    );
+    assert!(matches!(result, Err(LibraryError::InvalidTransition { .. })));
+
+    assert_eq!(
+        letters.letters(),
+        vec![DeadLetter {
+            system_id: "dead-letter-test".to_string(),
+            state: BookState::Available,
+            event: BookEvent::Return,
+            timestamp: TimeStamp::from_system_time(occurred_at),
+            actor: Some("front-desk".to_string()),
+            note: Some("Scanned at the wrong desk".to_string()),
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_accepted_and_vetoed_events_are_not_recorded() -> Result<(), LibraryError> {
+    let letters = MemoryDeadLetters::new();
+    let mut system = build_system(Box::new(letters.clone()))?;
+    system.add_interceptor(Box::new(|_: &BookState, _: &BookState, event: &BookEvent| {
+        if *event == BookEvent::CancelReservation {
+            Err(crate::interceptors::VetoReason("Not today".to_string()))
+        } else {
+            Ok(())
+        }
+    }));
+
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+    assert!(system.process_event(BookEvent::CancelReservation).is_err());
+    assert!(letters.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_replay_dead_letters() -> Result<(), LibraryError> {
+    let letters = MemoryDeadLetters::new();
+    let mut system = build_system(Box::new(letters.clone()))?;
+
+    // Cancelled before the reservation arrived
+    assert!(system.process_event(BookEvent::CancelReservation).is_err());
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+
+    let pending = letters.take();
+    assert_eq!(pending.len(), 1);
+    assert!(letters.is_empty());
+    for letter in pending {
+        system.process_event_with_meta(letter.into_envelope())?;
+    }
+    assert_eq!(system.current_state(), &BookState::Available);
+    Ok(())
+}
+
+#[test]
+fn test_closure_sink() -> Result<(), LibraryError> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut system = build_system(Box::new(letters_to_channel(sender)))?;
+
+    assert!(system.process_event(BookEvent::ReportLost).is_err());
+    assert_eq!(receiver.try_recv().ok().map(|letter| letter.event), Some(BookEvent::ReportLost));
+    Ok(())
+}
+
+/// Create a sink forwarding every letter to a channel
+fn letters_to_channel(sender: std::sync::mpsc::Sender<DeadLetter>) -> impl Fn(&DeadLetter) + Send {
+    move |letter: &DeadLetter| {
+        sender.send(letter.clone()).ok();
+    }
+}
+
+#[test]
+fn test_file_dead_letters() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("dead-letter-test-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let path = directory.join("dead-letters.jsonl");
+    let result = (|| {
+        let mut system = build_system(Box::new(FileDeadLetters::new(&path)))?;
+        assert!(system.process_event(BookEvent::Return).is_err());
+        assert!(system.process_event(BookEvent::CompleteRepair).is_err());
+
+        let letters = FileDeadLetters::read_letters(&path)?;
+        let events: Vec<_> = letters.into_iter().map(|letter| letter.event).collect();
+        assert_eq!(events, vec![BookEvent::Return, BookEvent::CompleteRepair]);
+        Ok(())
+    })();
+    fs::remove_dir_all(&directory).ok();
+    result
+}
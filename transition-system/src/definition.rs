@@ -0,0 +1,264 @@
+use std::{fs, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book_state::BookState,
+    builder::LibrarySystemBuilder,
+    dot_import,
+    events::BookEvent,
+    system::{LibraryError, LibrarySystem},
+};
+
+/// File formats a machine definition can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    /// A DOT graph, used for files ending in `.dot` or `.gv`
+    Dot,
+    /// TOML, used for files ending in `.toml`
+    Toml,
+    /// YAML, used for files ending in `.yaml` or `.yml`
+    Yaml,
+}
+
+impl DefinitionFormat {
+    /// Pick the format matching a file's extension
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "dot" | "gv" => Some(Self::Dot),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A transition declared in a machine definition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransitionDefinition {
+    /// State the transition starts from
+    pub from: BookState,
+    /// Event triggering the transition
+    pub event: BookEvent,
+    /// State the transition leads to
+    pub to: BookState,
+}
+
+/// A timing constraint declared in a machine definition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TimeoutDefinition {
+    /// State the constraint applies to
+    pub state: BookState,
+    /// Seconds the book may stay in the state
+    pub after_secs: u64,
+    /// Event fired when the time runs out
+    pub event: BookEvent,
+}
+
+/// A group of related states declared in a machine definition
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CategoryDefinition {
+    /// Name of the category, e.g. "circulation"
+    pub name: String,
+    /// States belonging to the category
+    pub states: Vec<BookState>,
+}
+
+/// Declarative description of a state machine, as read from a file
+///
+/// States and events use their serde representation: unit variants are plain
+/// strings and variants with a patron are single-key tables, e.g.
+///
+/// ```toml
+/// id = "book-1"
+/// initial = "Available"
+///
+/// [[transitions]]
+/// from = "Available"
+/// event = { Reserve = "Alice" }
+/// to = { Reserved = "Alice" }
+///
+/// [[transitions]]
+/// from = { Reserved = "Alice" }
+/// event = "CancelReservation"
+/// to = "Available"
+///
+/// [[timeouts]]
+/// state = { Reserved = "Alice" }
+/// after_secs = 259200
+/// event = "CancelReservation"
+///
+/// [[categories]]
+/// name = "circulation"
+/// states = ["Available", { Reserved = "Alice" }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MachineDefinition {
+    /// Unique identifier for the system
+    pub id: String,
+    /// State the system starts in
+    #[serde(default)]
+    pub initial: BookState,
+    /// States to declare up front, in addition to those used by transitions
+    #[serde(default)]
+    pub states: Vec<BookState>,
+    /// Transitions of the machine
+    #[serde(default)]
+    pub transitions: Vec<TransitionDefinition>,
+    /// Timing constraints of the machine
+    #[serde(default)]
+    pub timeouts: Vec<TimeoutDefinition>,
+    /// States that are allowed to have no outgoing transitions
+    #[serde(default)]
+    pub final_states: Vec<BookState>,
+    /// Categories the states are grouped into
+    #[serde(default)]
+    pub categories: Vec<CategoryDefinition>,
+}
+
+impl MachineDefinition {
+    /// Parse a definition from a string in the given format
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the contents can't be parsed or
+    /// support for the format was not compiled in
+    pub fn parse(contents: &str, format: DefinitionFormat) -> Result<Self, LibraryError> {
+        match format {
+            DefinitionFormat::Dot => dot_import::parse_dot(contents),
+            #[cfg(feature = "toml")]
+            DefinitionFormat::Toml => toml::from_str(contents)
+                .map_err(|e| LibraryError::LoadError(format!("Failed to parse TOML: {e}"))),
+            #[cfg(feature = "yaml")]
+            DefinitionFormat::Yaml => {
+                // serde_yaml expects `!Tag` syntax for enums; going through a
+                // JSON value lets YAML use the same single-key maps as TOML
+                serde_yaml::from_str::<serde_json::Value>(contents)
+                    .and_then(|value| {
+                        serde_json::from_value(value).map_err(serde::de::Error::custom)
+                    })
+                    .map_err(|e| LibraryError::LoadError(format!("Failed to parse YAML: {e}")))
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = contents;
+                Err(LibraryError::LoadError(format!("Support for {format:?} is not enabled")))
+            }
+        }
+    }
+
+    /// Describe an existing system's states, transitions and timeouts
+    ///
+    /// The first state of the system is used as the initial state. Transitions,
+    /// timeouts and categories are sorted so the result is deterministic.
+    #[must_use]
+    pub fn from_system(system: &LibrarySystem) -> Self {
+        let states = system.get_states();
+        let state = |idx: usize| states.get(idx).cloned().unwrap_or_default();
+
+        let mut transitions: Vec<_> = system
+            .get_all_transitions()
+            .iter()
+            .map(|((from, event), to)| TransitionDefinition {
+                from: state(*from),
+                event: event.clone(),
+                to: state(*to),
+            })
+            .collect();
+        transitions.sort_by_key(|t| format!("{:?} {:?} {:?}", t.from, t.event, t.to));
+
+        let mut timeouts: Vec<_> = system
+            .get_timing_constraints()
+            .iter()
+            .map(|(state_idx, constraint)| TimeoutDefinition {
+                state: state(*state_idx),
+                after_secs: constraint.max_duration.as_secs(),
+                event: constraint.timeout_event.clone(),
+            })
+            .collect();
+        timeouts.sort_by_key(|t| format!("{:?}", t.state));
+
+        let mut categories: Vec<CategoryDefinition> = Vec::new();
+        for (idx, state) in states.iter().enumerate() {
+            let Some(name) = system.state_category(idx) else {
+                continue;
+            };
+            match categories.iter_mut().find(|category| category.name == name) {
+                Some(category) => category.states.push(state.clone()),
+                None => categories.push(CategoryDefinition {
+                    name: name.to_string(),
+                    states: vec![state.clone()],
+                }),
+            }
+        }
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            id: system.system_id().to_string(),
+            initial: state(0),
+            states: states.clone(),
+            transitions,
+            timeouts,
+            final_states: (0..states.len())
+                .filter(|idx| system.is_final_state(*idx))
+                .map(state)
+                .collect(),
+            categories,
+        }
+    }
+
+    /// Read and parse a definition file, picking the format from its extension
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the extension is not recognised,
+    /// the file can't be read, or its contents can't be parsed
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LibraryError> {
+        let path = path.as_ref();
+        let format = DefinitionFormat::from_path(path).ok_or_else(|| {
+            LibraryError::LoadError(format!("Unknown definition format: {}", path.display()))
+        })?;
+        let contents = fs::read_to_string(path)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to read file: {e}")))?;
+        Self::parse(&contents, format)
+    }
+
+    /// Turn the definition into a builder, e.g. to attach observers or a clock
+    #[must_use]
+    pub fn into_builder(self) -> LibrarySystemBuilder {
+        let builder = self
+            .states
+            .into_iter()
+            .fold(LibrarySystemBuilder::new(&self.id, self.initial), LibrarySystemBuilder::state);
+        let builder = self
+            .transitions
+            .into_iter()
+            .fold(builder, |builder, t| builder.transition(t.from, t.event, t.to));
+        let builder = self.timeouts.into_iter().fold(builder, |builder, t| {
+            builder.timeout(t.state, Duration::from_secs(t.after_secs), t.event)
+        });
+        let builder =
+            self.final_states.into_iter().fold(builder, LibrarySystemBuilder::final_state);
+        self.categories.into_iter().fold(builder, |builder, category| {
+            category
+                .states
+                .into_iter()
+                .fold(builder, |builder, state| builder.category(state, &category.name))
+        })
+    }
+
+    /// Validate the definition and build the system
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::InvalidDefinition` under the same conditions
+    /// as [`LibrarySystemBuilder::build`]
+    pub fn build(self) -> Result<LibrarySystem, LibraryError> {
+        self.into_builder().build()
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,106 @@
+use super::*;
+
+/// TOML definition used by the tests
+#[cfg(feature = "toml")]
+const TOML_DEFINITION: &str = r#"
+id = "test-book"
+initial = "Available"
+
+[[transitions]]
+from = "Available"
+event = { Reserve = "Test User" }
+to = { Reserved = "Test User" }
+
+[[transitions]]
+from = { Reserved = "Test User" }
+event = "CancelReservation"
+to = "Available"
+
+[[timeouts]]
+state = { Reserved = "Test User" }
+after_secs = 60
+event = "CancelReservation"
+
+[[categories]]
+name = "circulation"
+states = ["Available", { Reserved = "Test User" }]
+"#;
+
+/// YAML equivalent of `TOML_DEFINITION`
+#[cfg(feature = "yaml")]
+const YAML_DEFINITION: &str = r"
+id: test-book
+initial: Available
+transitions:
+  - from: Available
+    event: { Reserve: Test User }
+    to: { Reserved: Test User }
+  - from: { Reserved: Test User }
+    event: CancelReservation
+    to: Available
+timeouts:
+  - state: { Reserved: Test User }
+    after_secs: 60
+    event: CancelReservation
+categories:
+  - name: circulation
+    states: [Available, { Reserved: Test User }]
+";
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(DefinitionFormat::from_path(Path::new("a.toml")), Some(DefinitionFormat::Toml));
+    assert_eq!(DefinitionFormat::from_path(Path::new("a.YML")), Some(DefinitionFormat::Yaml));
+    assert_eq!(DefinitionFormat::from_path(Path::new("a.json")), None);
+    assert_eq!(DefinitionFormat::from_path(Path::new("a")), None);
+}
+
+#[test]
+#[cfg(all(feature = "toml", feature = "yaml"))]
+fn test_toml_and_yaml_match() -> Result<(), LibraryError> {
+    let from_toml = MachineDefinition::parse(TOML_DEFINITION, DefinitionFormat::Toml)?;
+    let from_yaml = MachineDefinition::parse(YAML_DEFINITION, DefinitionFormat::Yaml)?;
+    assert_eq!(from_toml, from_yaml);
+    assert_eq!(from_toml.transitions.len(), 2);
+    assert_eq!(from_toml.timeouts.len(), 1);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_from_definition_file() -> Result<(), LibraryError> {
+    let path = std::env::temp_dir().join(format!("definition-test-{}.toml", std::process::id()));
+    fs::write(&path, TOML_DEFINITION).map_err(|e| LibraryError::LoadError(e.to_string()))?;
+    let result = LibrarySystem::from_definition_file(&path);
+    fs::remove_file(&path).ok();
+
+    let mut system = result?;
+    assert_eq!(system.system_id(), "test-book");
+    assert_eq!(system.get_timing_constraints().len(), 1);
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+    assert_eq!(*system.current_state(), BookState::Reserved("Test User".into()));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_parse_error() {
+    let result = MachineDefinition::parse("id = ", DefinitionFormat::Toml);
+    assert!(matches!(result, Err(LibraryError::LoadError(_))));
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_from_system_round_trip() -> Result<(), LibraryError> {
+    let definition = MachineDefinition::parse(TOML_DEFINITION, DefinitionFormat::Toml)?;
+    let described = MachineDefinition::from_system(&definition.clone().build()?);
+    assert_eq!(described.id, definition.id);
+    assert_eq!(described.initial, definition.initial);
+    assert_eq!(described.transitions.len(), definition.transitions.len());
+    assert_eq!(described.timeouts, definition.timeouts);
+    assert_eq!(described.categories, definition.categories);
+
+    // Describing the rebuilt system gives the same definition again
+    assert_eq!(MachineDefinition::from_system(&described.clone().build()?), described);
+    Ok(())
+}
@@ -0,0 +1,423 @@
+use std::{iter::Peekable, time::Duration, vec::IntoIter};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::{
+    book_state::BookState,
+    definition::{CategoryDefinition, MachineDefinition, TimeoutDefinition, TransitionDefinition},
+    system::LibraryError,
+};
+
+/// Name of the cluster holding the legend of generated graphs, which is skipped
+const LEGEND_CLUSTER: &str = "cluster_legend";
+
+/// Prefix of the clusters grouping the states of a category
+const CLUSTER_PREFIX: &str = "cluster_";
+
+/// A lexical token of a DOT file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Identifier, number or quoted string, with quotes and escapes removed
+    Id(String),
+    /// The directed edge operator `->`
+    Arrow,
+    /// One of `{ } [ ] = , ;`
+    Punct(char),
+}
+
+/// A node of the parsed graph
+#[derive(Debug)]
+struct Node {
+    /// DOT identifier of the node, e.g. `s0`
+    id: String,
+    /// Label of the node, defaulting to its identifier
+    label: String,
+    /// Whether the node is drawn as a final state (`shape=doublecircle`)
+    is_final: bool,
+    /// Category of the innermost cluster the node was listed in
+    category: Option<String>,
+}
+
+/// An edge of the parsed graph
+#[derive(Debug)]
+struct Edge {
+    /// Identifier of the source node
+    from: String,
+    /// Identifier of the target node
+    to: String,
+    /// Label of the edge, if any
+    label: Option<String>,
+    /// Timeout of a dashed edge with an `after <duration>` tail label
+    timeout: Option<Duration>,
+}
+
+/// The nodes and edges of a parsed graph
+#[derive(Debug)]
+struct Graph {
+    /// Name of the graph
+    id: String,
+    /// Nodes in order of first appearance
+    nodes: Vec<Node>,
+    /// Edges in order of appearance
+    edges: Vec<Edge>,
+}
+
+/// Parse a DOT graph as written by
+/// [`StateVisualization::generate_dot`](crate::visualization::StateVisualization::generate_dot)
+///
+/// Node labels are states and edge labels are events, written as in the
+/// generated graphs (`Reserved(Alice)`, `Reserve("Alice")`). The graph name is
+/// the system ID, the first node the initial state, nodes with
+/// `shape=doublecircle` are final states, dashed edges with a tail label like
+/// `after 3d` are timeouts, and nodes listed in a `cluster_<category>`
+/// subgraph belong to that category. The generated legend and other styling
+/// attributes are ignored, so a diagram can be edited and re-styled by hand
+/// and still be loaded.
+///
+/// # Errors
+///
+/// Returns a `LibraryError::LoadError` if the graph can't be parsed, isn't
+/// directed, has an edge without a label, or uses an unknown state or event
+pub fn parse_dot(contents: &str) -> Result<MachineDefinition, LibraryError> {
+    let Graph { id, nodes, edges } = parse_graph(contents)?;
+
+    let states = nodes
+        .iter()
+        .map(|node| parse_label(&node.label, "state"))
+        .collect::<Result<Vec<BookState>, _>>()?;
+    let state_of = |node_id: &str| {
+        nodes
+            .iter()
+            .zip(&states)
+            .find(|(node, _)| node.id == node_id)
+            .map(|(_, state)| state.clone())
+            .unwrap_or_default()
+    };
+
+    let transitions = edges
+        .iter()
+        .map(|edge| {
+            let label = edge.label.as_deref().ok_or_else(|| {
+                parse_error(&format!("edge {} -> {} has no event label", edge.from, edge.to))
+            })?;
+            Ok(TransitionDefinition {
+                from: state_of(&edge.from),
+                event: parse_label(label, "event")?,
+                to: state_of(&edge.to),
+            })
+        })
+        .collect::<Result<Vec<_>, LibraryError>>()?;
+    let timeouts = edges
+        .iter()
+        .zip(&transitions)
+        .filter_map(|(edge, transition)| {
+            Some(TimeoutDefinition {
+                state: transition.from.clone(),
+                after_secs: edge.timeout?.as_secs(),
+                event: transition.event.clone(),
+            })
+        })
+        .collect();
+
+    let mut categories: Vec<CategoryDefinition> = Vec::new();
+    for (node, state) in nodes.iter().zip(&states) {
+        let Some(name) = &node.category else {
+            continue;
+        };
+        match categories.iter_mut().find(|category| category.name == *name) {
+            Some(category) => category.states.push(state.clone()),
+            None => categories
+                .push(CategoryDefinition { name: name.clone(), states: vec![state.clone()] }),
+        }
+    }
+
+    Ok(MachineDefinition {
+        id,
+        initial: states.first().cloned().unwrap_or_default(),
+        final_states: nodes
+            .iter()
+            .zip(&states)
+            .filter(|(node, _)| node.is_final)
+            .map(|(_, state)| state.clone())
+            .collect(),
+        states,
+        transitions,
+        timeouts,
+        categories,
+    })
+}
+
+/// Parse the nodes and edges of a directed graph
+fn parse_graph(contents: &str) -> Result<Graph, LibraryError> {
+    let mut tokens = tokenize(contents)?.into_iter().peekable();
+
+    if tokens.peek() == Some(&Token::Id(String::from("strict"))) {
+        tokens.next();
+    }
+    match tokens.next() {
+        Some(Token::Id(keyword)) if keyword == "digraph" => {}
+        _ => return Err(parse_error("expected a `digraph`")),
+    }
+    let id = match tokens.next_if(|token| matches!(token, Token::Id(_))) {
+        Some(Token::Id(id)) => id,
+        _ => String::from("state_machine"),
+    };
+    expect(&mut tokens, &Token::Punct('{'))?;
+
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut edges = Vec::new();
+    // Category of each open block, the graph itself being the outermost one
+    let mut blocks: Vec<Option<String>> = vec![None];
+    while !blocks.is_empty() {
+        match tokens.next() {
+            None => return Err(parse_error("unexpected end of file")),
+            Some(Token::Punct(';')) => {}
+            Some(Token::Punct('{')) => blocks.push(None),
+            Some(Token::Punct('}')) => {
+                blocks.pop();
+            }
+            Some(Token::Id(keyword)) if keyword == "subgraph" => {
+                let name = match tokens.next_if(|token| matches!(token, Token::Id(_))) {
+                    Some(Token::Id(name)) => Some(name),
+                    _ => None,
+                };
+                expect(&mut tokens, &Token::Punct('{'))?;
+                if name.as_deref() == Some(LEGEND_CLUSTER) {
+                    skip_block(&mut tokens)?;
+                } else {
+                    let category = name
+                        .as_deref()
+                        .and_then(|name| name.strip_prefix(CLUSTER_PREFIX))
+                        .map(str::to_string);
+                    blocks.push(category);
+                }
+            }
+            Some(Token::Id(name)) => match tokens.peek() {
+                // Graph attribute such as `rankdir=LR`
+                Some(Token::Punct('=')) => {
+                    tokens.next();
+                    next_id(&mut tokens)?;
+                }
+                // Default attributes
+                Some(Token::Punct('[')) if matches!(name.as_str(), "graph" | "node" | "edge") => {
+                    attributes(&mut tokens)?;
+                }
+                Some(Token::Arrow) => {
+                    let mut chain = vec![name];
+                    while tokens.next_if_eq(&Token::Arrow).is_some() {
+                        chain.push(next_id(&mut tokens)?);
+                    }
+                    let attributes = attributes(&mut tokens)?;
+                    let label = attribute(&attributes, "label");
+                    let timeout = parse_timeout(&attributes)?;
+                    for node in &chain {
+                        add_node(&mut nodes, node);
+                    }
+                    for pair in chain.windows(2) {
+                        if let [from, to] = pair {
+                            edges.push(Edge {
+                                from: from.clone(),
+                                to: to.clone(),
+                                label: label.clone(),
+                                timeout,
+                            });
+                        }
+                    }
+                }
+                _ => {
+                    let attributes = attributes(&mut tokens)?;
+                    add_node(&mut nodes, &name);
+                    if let Some(node) = nodes.iter_mut().find(|node| node.id == name) {
+                        if let Some(label) = attribute(&attributes, "label") {
+                            node.label = label;
+                        }
+                        if let Some(shape) = attribute(&attributes, "shape") {
+                            node.is_final = shape == "doublecircle";
+                        }
+                        if let Some(category) = blocks.iter().rev().flatten().next() {
+                            node.category = Some(category.clone());
+                        }
+                    }
+                }
+            },
+            Some(token) => return Err(parse_error(&format!("unexpected {token:?}"))),
+        }
+    }
+
+    Ok(Graph { id, nodes, edges })
+}
+
+/// Split DOT source into tokens, dropping comments
+fn tokenize(contents: &str) -> Result<Vec<Token>, LibraryError> {
+    let mut tokens = Vec::new();
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '#' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                let mut previous = ' ';
+                chars.by_ref().find(|c| {
+                    let end = previous == '*' && *c == '/';
+                    previous = *c;
+                    end
+                });
+            }
+            '-' if chars.next_if_eq(&'>').is_some() => tokens.push(Token::Arrow),
+            '-' if chars.peek() == Some(&'-') => {
+                return Err(parse_error("undirected edges are not supported"));
+            }
+            '{' | '}' | '[' | ']' | '=' | ',' | ';' => tokens.push(Token::Punct(c)),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(parse_error("unterminated string")),
+                        Some('"') => break,
+                        // `\"` is the only escape; others are kept for Graphviz
+                        Some('\\') if chars.next_if_eq(&'"').is_some() => text.push('"'),
+                        Some(c) => text.push(c),
+                    }
+                }
+                tokens.push(Token::Id(text));
+            }
+            c if c.is_alphanumeric() || matches!(c, '_' | '.' | '-') => {
+                let mut text = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || matches!(c, '_' | '.'))
+                {
+                    text.push(c);
+                }
+                tokens.push(Token::Id(text));
+            }
+            c => return Err(parse_error(&format!("unexpected character {c:?}"))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Consume the given token or fail
+fn expect(tokens: &mut Peekable<IntoIter<Token>>, expected: &Token) -> Result<(), LibraryError> {
+    match tokens.next() {
+        Some(token) if token == *expected => Ok(()),
+        token => Err(parse_error(&format!("expected {expected:?}, found {token:?}"))),
+    }
+}
+
+/// Consume an identifier or fail
+fn next_id(tokens: &mut Peekable<IntoIter<Token>>) -> Result<String, LibraryError> {
+    match tokens.next() {
+        Some(Token::Id(id)) => Ok(id),
+        token => Err(parse_error(&format!("expected an identifier, found {token:?}"))),
+    }
+}
+
+/// Consume any number of `[name=value, ...]` attribute lists
+fn attributes(
+    tokens: &mut Peekable<IntoIter<Token>>,
+) -> Result<Vec<(String, String)>, LibraryError> {
+    let mut attributes = Vec::new();
+    while tokens.next_if_eq(&Token::Punct('[')).is_some() {
+        while tokens.next_if_eq(&Token::Punct(']')).is_none() {
+            if tokens.next_if(|t| matches!(t, Token::Punct(',' | ';'))).is_some() {
+                continue;
+            }
+            let name = next_id(tokens)?;
+            expect(tokens, &Token::Punct('='))?;
+            attributes.push((name, next_id(tokens)?));
+        }
+    }
+    Ok(attributes)
+}
+
+/// Get the last value of an attribute
+fn attribute(attributes: &[(String, String)], name: &str) -> Option<String> {
+    attributes.iter().rev().find(|(key, _)| key == name).map(|(_, value)| value.clone())
+}
+
+/// Add a node with its identifier as label, unless it is already known
+fn add_node(nodes: &mut Vec<Node>, id: &str) {
+    if !nodes.iter().any(|node| node.id == id) {
+        nodes.push(Node {
+            id: id.to_string(),
+            label: id.to_string(),
+            is_final: false,
+            category: None,
+        });
+    }
+}
+
+/// Consume tokens up to and including the brace closing the current block
+fn skip_block(tokens: &mut Peekable<IntoIter<Token>>) -> Result<(), LibraryError> {
+    let mut depth = 1_usize;
+    while depth > 0 {
+        match tokens.next() {
+            None => return Err(parse_error("unexpected end of file")),
+            Some(Token::Punct('{')) => depth = depth.saturating_add(1),
+            Some(Token::Punct('}')) => depth = depth.saturating_sub(1),
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Read the timeout of a dashed edge labelled `after 3d 12h` at its tail
+fn parse_timeout(attributes: &[(String, String)]) -> Result<Option<Duration>, LibraryError> {
+    let Some(duration) = attribute(attributes, "taillabel")
+        .filter(|_| attribute(attributes, "style").as_deref() == Some("dashed"))
+        .and_then(|label| label.strip_prefix("after ").map(str::to_string))
+    else {
+        return Ok(None);
+    };
+
+    let mut secs = 0_u64;
+    for part in duration.split_whitespace() {
+        let unit = part.trim_start_matches(|c: char| c.is_ascii_digit());
+        let value: u64 = part
+            .strip_suffix(unit)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| parse_error(&format!("invalid timeout {duration:?}")))?;
+        let unit_secs = match unit {
+            "d" => 86_400,
+            "h" => 3_600,
+            "m" => 60,
+            "s" => 1,
+            _ => return Err(parse_error(&format!("invalid timeout {duration:?}"))),
+        };
+        secs = value.saturating_mul(unit_secs).saturating_add(secs);
+    }
+    Ok(Some(Duration::from_secs(secs)))
+}
+
+/// Turn a label like `Reserved(Alice)` or `Reserve("Alice")` into a state or event
+fn parse_label<T: DeserializeOwned>(label: &str, kind: &str) -> Result<T, LibraryError> {
+    let label = label.trim();
+    let value = match label.strip_suffix(')').and_then(|rest| rest.split_once('(')) {
+        Some((name, argument)) => {
+            let argument = argument.trim();
+            // Debug output quotes and escapes the argument like JSON does
+            let argument =
+                serde_json::from_str::<String>(argument).unwrap_or_else(|_| argument.to_string());
+            json!({ name.trim(): argument })
+        }
+        None => Value::String(label.to_string()),
+    };
+    serde_json::from_value(value)
+        .map_err(|_| parse_error(&format!("unknown {kind} label {label:?}")))
+}
+
+/// Build the error returned for malformed DOT files
+fn parse_error(message: &str) -> LibraryError {
+    LibraryError::LoadError(format!("Failed to parse DOT: {message}"))
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
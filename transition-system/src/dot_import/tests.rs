@@ -0,0 +1,116 @@
+use std::{fs, time::Duration};
+
+use super::*;
+use crate::{
+    BookEvent, BookState, LibrarySystemBuilder, PatronId, definition::DefinitionFormat,
+    visualization::StateVisualization,
+};
+
+#[test]
+fn test_round_trip_generated_dot() -> Result<(), LibraryError> {
+    let alice = || PatronId::from("Alice \"Al\" Smith");
+    let mut system = LibrarySystemBuilder::new("book-1", BookState::Available)
+        .transition(BookState::Available, BookEvent::Reserve(alice()), BookState::Reserved(alice()))
+        .transition(
+            BookState::Reserved(alice()),
+            BookEvent::CancelReservation,
+            BookState::Available,
+        )
+        .transition(BookState::Available, BookEvent::ReportLost, BookState::Lost)
+        .custom_transition(BookState::Available, "inventory miss", BookState::Lost)
+        .timeout(
+            BookState::Reserved(alice()),
+            Duration::from_hours(72),
+            BookEvent::CancelReservation,
+        )
+        .final_state(BookState::Lost)
+        .category(BookState::Available, "circulation")
+        .category(BookState::Reserved(alice()), "circulation")
+        .category(BookState::Lost, "missing")
+        .build()?;
+    system.process_event(BookEvent::Reserve(alice()))?;
+
+    let definition = parse_dot(&StateVisualization::generate_dot(&system, true))?;
+    assert_eq!(definition.id, "book-1");
+    assert_eq!(definition.initial, BookState::Available);
+    assert_eq!(definition.final_states, vec![BookState::Lost]);
+    assert_eq!(definition.categories.len(), 2);
+
+    assert_eq!(
+        MachineDefinition::from_system(&definition.build()?),
+        MachineDefinition::from_system(&system)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_hand_written_dot() -> Result<(), LibraryError> {
+    let definition = MachineDefinition::parse(
+        r#"
+        // Edited by hand
+        digraph "book-2" {
+            node [shape=box];
+            subgraph cluster_shelf { s0 [label="Available"]; }
+            s0 -> s1 -> s0 [label="Transfer"]; /* same event both ways */
+            s1 [label = InTransit, color=red]
+            s0 -> s2 [label="CheckOut(Bob)"];
+            s2 [label="CheckedOut(\"Bob\")"];
+        }
+        "#,
+        DefinitionFormat::Dot,
+    )?;
+
+    assert_eq!(definition.id, "book-2");
+    assert_eq!(
+        definition.states,
+        vec![BookState::Available, BookState::InTransit, BookState::CheckedOut("Bob".into())]
+    );
+    assert_eq!(definition.transitions.len(), 3);
+    assert!(definition.transitions.contains(&TransitionDefinition {
+        from: BookState::Available,
+        event: BookEvent::CheckOut("Bob".into()),
+        to: BookState::CheckedOut("Bob".into()),
+    }));
+    assert_eq!(
+        definition.categories,
+        vec![CategoryDefinition { name: "shelf".into(), states: vec![BookState::Available] }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rejects_invalid_dot() {
+    for contents in [
+        "graph g { a -- b }",
+        "digraph { s0 -> s1 }",
+        r#"digraph { s0 [label="Misplaced"]; }"#,
+        r#"digraph { s0 -> s1 [label="Fly"]; }"#,
+        r#"digraph { s0 [label="Available"]"#,
+    ] {
+        assert!(
+            matches!(parse_dot(contents), Err(LibraryError::LoadError(_))),
+            "{contents} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_definition_file_from_dot() -> Result<(), LibraryError> {
+    let dir = std::env::temp_dir().join(format!("dot-import-test-{}", std::process::id()));
+    let result = (|| {
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("machine.gv");
+        fs::write(
+            &path,
+            "digraph \"book-3\" { s0 [label=Lost]; s1 [label=Available]; s0 -> s1 [label=Found]; }",
+        )?;
+
+        let system = crate::LibrarySystem::from_definition_file(&path)?;
+        assert_eq!(system.system_id(), "book-3");
+        assert_eq!(*system.current_state(), BookState::Lost);
+        assert_eq!(system.valid_events(), vec![BookEvent::Found]);
+        Ok(())
+    })();
+    fs::remove_dir_all(&dir).ok();
+    result
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use chacha20poly1305::{
+    AeadCore, ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, Payload},
+};
+
+use crate::{
+    persistence::{FileStore, SerializableSystemState, StateStore},
+    system::LibraryError,
+};
+
+/// First bytes of an encrypted state file
+const MAGIC: &[u8] = b"LSENC1";
+
+/// Length of a ChaCha20-Poly1305 nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// 256-bit key used to encrypt saved state
+///
+/// The key is never printed, not even in debug output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    /// Use the given bytes as key
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generate a new random key
+    #[must_use]
+    pub fn generate() -> Self {
+        Self(ChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+}
+
+// Manual implementation of Debug for EncryptionKey
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// Encrypts system state at rest with ChaCha20-Poly1305
+///
+/// Wraps a [`FileStore`], which still handles the format, compression,
+/// locking and atomic replacement of the files. The encoded state is
+/// encrypted with a fresh random nonce on every save, and the system ID is
+/// authenticated alongside it, so a file renamed to another system's name is
+/// rejected on load just like a file encrypted with a different key.
+#[derive(Debug, Clone)]
+pub struct EncryptedStore {
+    /// Store the encrypted files are written through
+    inner: FileStore,
+    /// Key used to encrypt and decrypt the files
+    key: EncryptionKey,
+}
+
+impl EncryptedStore {
+    /// Encrypt the files of the given store with the given key
+    #[must_use]
+    pub fn new(inner: FileStore, key: EncryptionKey) -> Self {
+        Self { inner, key }
+    }
+
+    /// Load the `n`th previous version of a system's state, see [`FileStore::load_backup`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the backup doesn't exist or
+    /// can't be read, or a `LibraryError::CorruptState` if it can't be
+    /// decrypted
+    pub fn load_backup(
+        &self,
+        system_id: &str,
+        n: usize,
+    ) -> Result<SerializableSystemState, LibraryError> {
+        let contents = self.inner.read_path(system_id, &self.inner.backup_path(system_id, n))?;
+        self.decrypt(system_id, &contents)
+    }
+
+    /// Create the cipher for the store's key
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.key.0))
+    }
+
+    /// Decrypt and decode the contents of one of a system's files
+    fn decrypt(
+        &self,
+        system_id: &str,
+        contents: &[u8],
+    ) -> Result<SerializableSystemState, LibraryError> {
+        let (nonce, ciphertext) = contents
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| LibraryError::LoadError("File is not encrypted".to_string()))?
+            .split_at_checked(NONCE_LEN)
+            .ok_or_else(|| LibraryError::CorruptState("Encrypted file is truncated".to_string()))?;
+
+        let payload = Payload { msg: ciphertext, aad: system_id.as_bytes() };
+        let plaintext = self.cipher().decrypt(Nonce::from_slice(nonce), payload).map_err(|_| {
+            LibraryError::CorruptState(
+                "Failed to decrypt state: wrong key or corrupted file".to_string(),
+            )
+        })?;
+        self.inner.decode(plaintext)
+    }
+}
+
+impl StateStore for EncryptedStore {
+    fn save(&self, state: &SerializableSystemState) -> Result<(), LibraryError> {
+        let plaintext = self.inner.encode(state)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload { msg: &plaintext, aad: state.system_id.as_bytes() };
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, payload)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to encrypt state: {e}")))?;
+
+        let mut contents = Vec::with_capacity(
+            MAGIC.len().saturating_add(NONCE_LEN).saturating_add(ciphertext.len()),
+        );
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(&nonce);
+        contents.extend_from_slice(&ciphertext);
+        self.inner.write_file(&state.system_id, &contents)
+    }
+
+    fn load(&self, system_id: &str) -> Result<SerializableSystemState, LibraryError> {
+        self.decrypt(system_id, &self.inner.read_file(system_id)?)
+    }
+
+    fn list_systems(&self) -> Result<Vec<String>, LibraryError> {
+        self.inner.list_systems()
+    }
+
+    fn delete(&self, system_id: &str) -> Result<(), LibraryError> {
+        self.inner.delete(system_id)
+    }
+
+    fn exists(&self, system_id: &str) -> Result<bool, LibraryError> {
+        self.inner.exists(system_id)
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
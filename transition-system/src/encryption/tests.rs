@@ -0,0 +1,66 @@
+use std::fs;
+
+use super::*;
+use crate::{book_state::BookState, events::BookEvent, system::LibrarySystem};
+
+#[test]
+fn test_encrypted_round_trip() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("encrypted-store-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = EncryptedStore::new(FileStore::new(&directory), EncryptionKey::generate());
+
+    let result = (|| {
+        let mut system = crate::state_machine! {
+            id: "test-book",
+            initial: Available,
+            transitions: {
+                Available --CheckOut("Alice Smith")--> CheckedOut("Alice Smith"),
+            },
+        }?;
+        system.process_event(BookEvent::CheckOut("Alice Smith".into()))?;
+        system.save_to(&store)?;
+
+        // Patron names don't appear in the file
+        let contents = fs::read(FileStore::new(&directory).path("test-book"))?;
+        assert!(!contents.windows(11).any(|window| window == b"Alice Smith"));
+        assert!(LibrarySystem::load_from(&FileStore::new(&directory), "test-book").is_err());
+
+        let loaded = LibrarySystem::load_from(&store, "test-book")?;
+        assert_eq!(*loaded.current_state(), BookState::CheckedOut("Alice Smith".into()));
+
+        // A different key, or a file moved to another system's name, is rejected
+        let other = EncryptedStore::new(FileStore::new(&directory), EncryptionKey::generate());
+        assert!(matches!(
+            LibrarySystem::load_from(&other, "test-book"),
+            Err(LibraryError::CorruptState(_))
+        ));
+        fs::copy(
+            FileStore::new(&directory).path("test-book"),
+            FileStore::new(&directory).path("other-book"),
+        )?;
+        assert!(matches!(
+            LibrarySystem::load_from(&store, "other-book"),
+            Err(LibraryError::CorruptState(_))
+        ));
+
+        // So is a file cut short after its header
+        let contents = fs::read(FileStore::new(&directory).path("test-book"))?;
+        fs::write(
+            FileStore::new(&directory).path("test-book"),
+            contents.get(..8).unwrap_or_default(),
+        )?;
+        assert!(matches!(
+            LibrarySystem::load_from(&store, "test-book"),
+            Err(LibraryError::CorruptState(_))
+        ));
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_key_not_printed() {
+    assert_eq!(format!("{:?}", EncryptionKey::from_bytes([7; 32])), "EncryptionKey(..)");
+}
@@ -0,0 +1,237 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book_state::BookState,
+    events::BookEvent,
+    logging::emit,
+    observers::{ObserverError, StateObserver},
+    persistence::FileNaming,
+    system::{LibraryError, LibrarySystem, StateTransition},
+};
+
+/// A line of an event log
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum LogRecord {
+    /// The system starts in this state instead of its initial state
+    Start(BookState),
+    /// A transition that was applied to the system
+    Transition(StateTransition),
+}
+
+/// Append-only, event-sourced persistence
+///
+/// Each transition is appended as a JSON line to `<system_id>.events.jsonl`,
+/// with the ID sanitized like [`FileNaming::Sanitized`] does, instead of
+/// rewriting the whole system state. Loading builds the system
+/// from its definition and replays the logged transitions on top.
+///
+/// Holds, attached registries and due dates are not part of the log.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    /// Directory the log files are kept in; empty for the working directory
+    directory: PathBuf,
+}
+
+impl EventLog {
+    /// Create an event log keeping its files in the given directory
+    #[must_use]
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: directory.into() }
+    }
+
+    /// Get the path of the given system's log file
+    #[must_use]
+    pub fn path(&self, system_id: &str) -> PathBuf {
+        self.directory.join(format!("{}.events.jsonl", FileNaming::Sanitized.file_stem(system_id)))
+    }
+
+    /// Append a record to a system's log
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the record can't be
+    /// serialized or written
+    pub fn append(&self, system_id: &str, record: &LogRecord) -> Result<(), LibraryError> {
+        let line = serde_json::to_string(record)
+            .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(system_id))
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to open log: {e}")))?;
+        writeln!(file, "{line}")
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to append to log: {e}")))
+    }
+
+    /// Remove a record from the end of a system's log
+    ///
+    /// Nothing is removed if the log doesn't end with the record, e.g. because
+    /// it was compacted since.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the record can't be
+    /// serialized or the log can't be read or shortened
+    pub fn retract(&self, system_id: &str, record: &LogRecord) -> Result<(), LibraryError> {
+        let line = serde_json::to_string(record)
+            .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+        let path = self.path(system_id);
+        let contents = fs::read(&path)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to read log: {e}")))?;
+        let Some(kept) = contents
+            .strip_suffix(b"\n")
+            .and_then(|contents| contents.strip_suffix(line.as_bytes()))
+        else {
+            return Ok(());
+        };
+
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_len(kept.len() as u64))
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to shorten log: {e}")))
+    }
+
+    /// Read every record of a system's log; a missing log has no records
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the log can't be read or a line
+    /// can't be parsed
+    pub fn records(&self, system_id: &str) -> Result<Vec<LogRecord>, LibraryError> {
+        let path = self.path(system_id);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = File::open(&path)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to open log: {e}")))?;
+
+        let mut records = Vec::new();
+        for (line_idx, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.map_err(|e| LibraryError::LoadError(format!("Failed to read log: {e}")))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|e| {
+                LibraryError::LoadError(format!(
+                    "Failed to parse log line {}: {e}",
+                    line_idx.saturating_add(1)
+                ))
+            })?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Create an observer appending every transition of a system to its log
+    #[must_use]
+    pub fn observer(&self, system_id: &str) -> EventLogObserver {
+        EventLogObserver { log: self.clone(), system_id: system_id.to_string() }
+    }
+
+    /// Replay a system's log over a freshly built system
+    ///
+    /// `system` must be built from the same definition the log was recorded
+    /// with. An observer appending further transitions to the log is
+    /// registered on the returned system.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the log can't be read or doesn't
+    /// match the definition
+    pub fn load(&self, mut system: LibrarySystem) -> Result<LibrarySystem, LibraryError> {
+        let system_id = system.system_id().to_string();
+        emit!(info, "PERSISTENCE: Replaying event log: {}", self.path(&system_id).display());
+
+        for record in self.records(&system_id)? {
+            match record {
+                LogRecord::Start(state) => system.restore_current_state(state),
+                LogRecord::Transition(transition) => system.replay_transition(transition)?,
+            }
+        }
+        system.register_observer(Box::new(self.observer(&system_id)));
+        Ok(system)
+    }
+
+    /// Rewrite a system's log so it only holds the system's retained history
+    ///
+    /// The compacted log starts with the state the oldest retained transition
+    /// left from, so replaying it yields the same current state and history.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the log can't be rewritten
+    pub fn compact(&self, system: &LibrarySystem) -> Result<(), LibraryError> {
+        let history = system.get_history();
+        let start = history.first().map_or(system.current_state(), |transition| &transition.from);
+
+        let mut contents = String::new();
+        let records = std::iter::once(LogRecord::Start(start.clone()))
+            .chain(history.iter().cloned().map(LogRecord::Transition));
+        for record in records {
+            let line = serde_json::to_string(&record)
+                .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+
+        // Write to a temporary file first so a crash can't lose the log
+        let path = self.path(system.system_id());
+        let temp_path = path.with_extension("jsonl.tmp");
+        fs::write(&temp_path, contents)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to write log: {e}")))?;
+        fs::rename(&temp_path, &path)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to replace log: {e}")))
+    }
+}
+
+/// Appends every transition of a system to its event log
+#[derive(Debug, Clone)]
+pub struct EventLogObserver {
+    /// Log the transitions are appended to
+    log: EventLog,
+    /// System whose log is appended to
+    system_id: String,
+}
+
+impl StateObserver for EventLogObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {
+        // Transitions are appended in `on_transition`
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        if let Err(e) = self.try_on_transition(transition) {
+            emit!(error, "PERSISTENCE: {e}");
+        }
+    }
+
+    /// Append the transition, failing if it can't be written so the
+    /// [`ObserverErrorPolicy`](crate::observers::ObserverErrorPolicy) decides
+    /// whether the transition is kept
+    fn try_on_transition(&self, transition: &StateTransition) -> Result<(), ObserverError> {
+        let record = LogRecord::Transition(transition.clone());
+        self.log
+            .append(&self.system_id, &record)
+            .map_err(|e| ObserverError(format!("Failed to append to event log: {e}")))
+    }
+
+    /// Remove the undone transition from the log again
+    fn on_rollback(&self, transition: &StateTransition) {
+        let record = LogRecord::Transition(transition.clone());
+        if let Err(e) = self.log.retract(&self.system_id, &record) {
+            emit!(error, "PERSISTENCE: Failed to remove undone transition from log: {e}");
+        }
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,167 @@
+use super::*;
+use crate::observers::ObserverErrorPolicy;
+
+/// Rejects every reservation
+#[derive(Debug)]
+struct RejectingObserver;
+
+impl StateObserver for RejectingObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {}
+
+    fn try_on_transition(&self, transition: &StateTransition) -> Result<(), ObserverError> {
+        match transition.event {
+            BookEvent::Reserve(_) => Err(ObserverError("reservations are closed".to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Build the test definition
+fn build_system() -> Result<LibrarySystem, LibraryError> {
+    crate::state_machine! {
+        id: "event-log-test",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+            Reserved("Test User") --CheckOut("Test User")--> CheckedOut("Test User"),
+            CheckedOut("Test User") --Return--> Available,
+        },
+    }
+}
+
+/// Create an empty directory for a test
+fn test_directory(name: &str) -> Result<PathBuf, LibraryError> {
+    let directory = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+    if directory.exists() {
+        fs::remove_dir_all(&directory)?;
+    }
+    fs::create_dir_all(&directory)?;
+    Ok(directory)
+}
+
+/// Drive the system through a reservation cycle and a checkout
+fn run_events(system: &mut LibrarySystem) -> Result<(), LibraryError> {
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+    system.process_event(BookEvent::CancelReservation)?;
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+    system.process_event(BookEvent::CheckOut("Test User".into()))?;
+    Ok(())
+}
+
+#[test]
+fn test_replay_and_compaction() -> Result<(), LibraryError> {
+    let directory = test_directory("event-log-test")?;
+    let log = EventLog::new(&directory);
+
+    let result = (|| {
+        let mut system = log.load(build_system()?)?;
+        run_events(&mut system)?;
+        assert_eq!(log.records("event-log-test")?.len(), 4);
+
+        let replayed = log.load(build_system()?)?;
+        assert_eq!(replayed.current_state(), system.current_state());
+        assert_eq!(replayed.get_history().len(), 4);
+
+        // Compacting keeps only the retained history
+        system.set_max_history_size(1);
+        log.compact(&system)?;
+        let records = log.records("event-log-test")?;
+        assert!(matches!(records.as_slice(), [LogRecord::Start(_), LogRecord::Transition(_)]));
+
+        let mut compacted = log.load(build_system()?)?;
+        assert_eq!(compacted.current_state(), system.current_state());
+        compacted.process_event(BookEvent::Return)?;
+        assert_eq!(log.records("event-log-test")?.len(), 3);
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_mismatched_log_is_rejected() -> Result<(), LibraryError> {
+    let directory = test_directory("event-log-mismatch-test")?;
+    let log = EventLog::new(&directory);
+
+    let result = (|| {
+        let mut system = log.load(build_system()?)?;
+        run_events(&mut system)?;
+
+        // A system that doesn't start where the log does can't replay it
+        let other = LibrarySystem::new(BookState::Lost, "event-log-test");
+        assert!(matches!(log.load(other), Err(LibraryError::LoadError(_))));
+
+        // Nor can a definition missing the logged transitions
+        let mut other = LibrarySystem::new(BookState::Available, "event-log-test");
+        let reserved = other.add_state(BookState::Reserved("Test User".into()));
+        other.add_transition(0, BookEvent::Reserve("Test User".into()), reserved);
+        let error = log.load(other).err();
+        assert!(
+            matches!(&error, Some(LibraryError::LoadError(e)) if e.contains("not in the definition"))
+        );
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_failed_append_rolls_back() -> Result<(), LibraryError> {
+    let directory = test_directory("event-log-rollback-test")?;
+    let log = EventLog::new(directory.join("missing"));
+
+    let result = (|| {
+        let mut system = build_system()?;
+        system.register_observer(Box::new(log.observer("event-log-test")));
+        system.set_observer_error_policy(ObserverErrorPolicy::Rollback);
+        assert!(matches!(
+            system.process_event(BookEvent::Reserve("Test User".into())),
+            Err(LibraryError::ObserverFailed(_))
+        ));
+        assert_eq!(*system.current_state(), BookState::Available);
+        assert!(system.get_history().is_empty());
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_rolled_back_transition_leaves_log() -> Result<(), LibraryError> {
+    let directory = test_directory("event-log-undo-test")?;
+    let log = EventLog::new(&directory);
+
+    let result = (|| {
+        let mut system = log.load(build_system()?)?;
+        system.register_observer(Box::new(RejectingObserver));
+        system.set_observer_error_policy(ObserverErrorPolicy::Rollback);
+        assert!(matches!(
+            system.process_event(BookEvent::Reserve("Test User".into())),
+            Err(LibraryError::ObserverFailed(_))
+        ));
+        assert_eq!(*system.current_state(), BookState::Available);
+        assert!(log.records("event-log-test")?.is_empty());
+
+        // The log still replays after the undone transition
+        system.clear_observers();
+        system.register_observer(Box::new(log.observer("event-log-test")));
+        run_events(&mut system)?;
+        let replayed = log.load(build_system()?)?;
+        assert_eq!(replayed.current_state(), system.current_state());
+        assert_eq!(replayed.get_history().len(), 4);
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_log_names_are_sanitized() {
+    let log = EventLog::new("logs");
+    assert_eq!(log.path("../book 1"), PathBuf::from("logs/..%2Fbook%201.events.jsonl"));
+}
@@ -1,22 +1,46 @@
-use serde::{Deserialize, Serialize};
+use std::{fmt, time::SystemTime};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+use serde_json::Value;
+
+use crate::{
+    patrons::PatronId,
+    schema::{self, EVENT_MIGRATIONS},
+};
 
 /// Events that can cause a book state transition
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+///
+/// Some events carry details, like the destination of a transfer, that are
+/// recorded in the history but don't change where the event leads: they are
+/// ignored when looking up transitions, see [`Self::transition_key`]. Events
+/// without details are written the same way as before they could carry any,
+/// and older state files and definitions are upgraded on load, see
+/// [`schema`].
+#[derive(Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(remote = "Self")]
 pub enum BookEvent {
     /// Reserve a book for a patron
-    Reserve(String),
+    Reserve(PatronId),
     /// Cancel a reservation
     CancelReservation,
     /// Check out a book to a patron
-    CheckOut(String),
+    CheckOut(PatronId),
     /// Return a book to the library
     Return,
     /// Send a book for repair
-    SendToRepair,
+    SendToRepair {
+        /// Notes on the book's condition, e.g. "spine detached"
+        #[serde(default)]
+        condition: Option<String>,
+    },
     /// Mark a book as repaired
     CompleteRepair,
     /// Transfer a book to another branch
-    Transfer,
+    Transfer {
+        /// Branch the book is sent to
+        #[serde(default)]
+        destination: Option<String>,
+    },
     /// Mark a transfer as complete
     TransferComplete,
     /// Report a book as lost
@@ -24,4 +48,192 @@ pub enum BookEvent {
     /// Book has been found
     #[default]
     Found,
+    /// Domain-specific event defined outside this crate, with a name and payload
+    ///
+    /// Only the name selects the transition; declare transitions with
+    /// [`Self::custom`] and process events with [`Self::custom_with`].
+    Custom(String, #[serde(with = "payload")] Box<Value>),
+    /// Report a book as damaged, for the given reason
+    ReportDamage(String),
+    /// Put a book on the hold shelf for a patron to pick up
+    HoldForPickup(PatronId),
+    /// Permanently remove a book from the collection
+    Withdraw,
+}
+
+impl BookEvent {
+    /// Get the event without the details that don't affect the transition taken
+    ///
+    /// Transitions are declared and looked up with this key, so e.g. one
+    /// `Transfer` transition serves transfers to every destination.
+    #[must_use]
+    pub fn transition_key(self) -> Self {
+        match self {
+            Self::SendToRepair { .. } => Self::SendToRepair { condition: None },
+            Self::Transfer { .. } => Self::Transfer { destination: None },
+            Self::Custom(name, _) => Self::custom(&name),
+            event => event,
+        }
+    }
+
+    /// Create a custom event without payload, e.g. to declare its transitions
+    #[must_use]
+    pub fn custom(name: &str) -> Self {
+        Self::Custom(name.to_string(), Box::new(Value::Null))
+    }
+
+    /// Create a custom event carrying a payload
+    #[must_use]
+    pub fn custom_with(name: &str, payload: Value) -> Self {
+        Self::Custom(name.to_string(), Box::new(payload))
+    }
+}
+
+// Events without details print as plain names, like unit variants
+impl fmt::Debug for BookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reserve(patron) => f.debug_tuple("Reserve").field(patron).finish(),
+            Self::CancelReservation => f.write_str("CancelReservation"),
+            Self::CheckOut(patron) => f.debug_tuple("CheckOut").field(patron).finish(),
+            Self::Return => f.write_str("Return"),
+            Self::SendToRepair { condition: None } => f.write_str("SendToRepair"),
+            Self::SendToRepair { condition: Some(condition) } => {
+                f.debug_struct("SendToRepair").field("condition", condition).finish()
+            }
+            Self::CompleteRepair => f.write_str("CompleteRepair"),
+            Self::Transfer { destination: None } => f.write_str("Transfer"),
+            Self::Transfer { destination: Some(destination) } => {
+                f.debug_struct("Transfer").field("destination", destination).finish()
+            }
+            Self::TransferComplete => f.write_str("TransferComplete"),
+            Self::ReportLost => f.write_str("ReportLost"),
+            Self::Found => f.write_str("Found"),
+            Self::Custom(name, payload) if payload.is_null() => {
+                f.debug_tuple("Custom").field(name).finish()
+            }
+            Self::Custom(name, payload) => {
+                f.debug_tuple("Custom").field(name).field(payload).finish()
+            }
+            Self::ReportDamage(reason) => f.debug_tuple("ReportDamage").field(reason).finish(),
+            Self::HoldForPickup(patron) => f.debug_tuple("HoldForPickup").field(patron).finish(),
+            Self::Withdraw => f.write_str("Withdraw"),
+        }
+    }
+}
+
+impl Serialize for BookEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Keep the plain name older versions wrote for events without details
+        if serializer.is_human_readable() {
+            match self {
+                Self::SendToRepair { condition: None } => {
+                    return serializer.serialize_unit_variant("BookEvent", 4, "SendToRepair");
+                }
+                Self::Transfer { destination: None } => {
+                    return serializer.serialize_unit_variant("BookEvent", 6, "Transfer");
+                }
+                Self::Custom(name, payload) if payload.is_null() => {
+                    return serializer.serialize_newtype_variant("BookEvent", 10, "Custom", name);
+                }
+                _ => {}
+            }
+        }
+        Self::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BookEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Binary formats always used the derived representation
+        if !deserializer.is_human_readable() {
+            return Self::deserialize(deserializer);
+        }
+        let value = schema::upgrade_event(Value::deserialize(deserializer)?, EVENT_MIGRATIONS);
+        Self::deserialize(value).map_err(D::Error::custom)
+    }
+}
+
+/// Serialization of custom event payloads
+///
+/// Binary formats can't hold arbitrary JSON values, so they store the payload
+/// as JSON text.
+mod payload {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+    use serde_json::Value;
+
+    /// Write a payload as is, or as JSON text in binary formats
+    pub(super) fn serialize<S: Serializer>(
+        payload: &Value,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            payload.serialize(serializer)
+        } else {
+            payload.to_string().serialize(serializer)
+        }
+    }
+
+    /// Read a payload written by [`serialize`]
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<Value>, D::Error> {
+        if deserializer.is_human_readable() {
+            Box::<Value>::deserialize(deserializer)
+        } else {
+            serde_json::from_str(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+        }
+    }
 }
+
+/// An event together with who triggered it, why, and when
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventEnvelope {
+    /// The event to process
+    pub event: BookEvent,
+    /// Who triggered the event, e.g. a staff member or service account
+    pub actor: Option<String>,
+    /// Free-form note explaining the event
+    pub note: Option<String>,
+    /// Wall-clock time the event occurred
+    pub occurred_at: SystemTime,
+}
+
+impl EventEnvelope {
+    /// Wrap an event that occurred now, without an actor or note
+    #[must_use]
+    pub fn new(event: BookEvent) -> Self {
+        Self { event, actor: None, note: None, occurred_at: SystemTime::now() }
+    }
+
+    /// Record who triggered the event
+    #[must_use]
+    pub fn actor(mut self, actor: &str) -> Self {
+        self.actor = Some(actor.to_string());
+        self
+    }
+
+    /// Attach a note explaining the event
+    #[must_use]
+    pub fn note(mut self, note: &str) -> Self {
+        self.note = Some(note.to_string());
+        self
+    }
+
+    /// Record when the event occurred, e.g. when importing past events
+    #[must_use]
+    pub fn occurred_at(mut self, occurred_at: SystemTime) -> Self {
+        self.occurred_at = occurred_at;
+        self
+    }
+}
+
+impl From<BookEvent> for EventEnvelope {
+    fn from(event: BookEvent) -> Self {
+        Self::new(event)
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,72 @@
+use super::*;
+
+#[test]
+fn test_events_without_details_keep_plain_names() -> Result<(), serde_json::Error> {
+    let transfer = BookEvent::Transfer { destination: None };
+    assert_eq!(serde_json::to_string(&transfer)?, r#""Transfer""#);
+    assert_eq!(serde_json::from_str::<BookEvent>(r#""Transfer""#)?, transfer);
+    assert_eq!(
+        serde_json::from_str::<BookEvent>(r#""SendToRepair""#)?,
+        BookEvent::SendToRepair { condition: None }
+    );
+    assert_eq!(serde_json::from_str::<BookEvent>(r#""Return""#)?, BookEvent::Return);
+    assert_eq!(format!("{transfer:?}"), "Transfer");
+    Ok(())
+}
+
+#[test]
+fn test_event_details_round_trip() -> Result<(), serde_json::Error> {
+    let events = [
+        BookEvent::Transfer { destination: Some("Main".to_string()) },
+        BookEvent::SendToRepair { condition: Some("Spine detached".to_string()) },
+        BookEvent::Reserve("patron-42".into()),
+    ];
+    for event in events {
+        let json = serde_json::to_string(&event)?;
+        assert_eq!(serde_json::from_str::<BookEvent>(&json)?, event);
+    }
+    assert_eq!(
+        serde_json::from_str::<BookEvent>(r#"{"Transfer":{"destination":"Main"}}"#)?,
+        BookEvent::Transfer { destination: Some("Main".to_string()) }
+    );
+    assert!(serde_json::from_str::<BookEvent>(r#""Teleport""#).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_custom_event_serialization() -> Result<(), serde_json::Error> {
+    let declared = BookEvent::custom("inventory");
+    assert_eq!(serde_json::to_string(&declared)?, r#"{"Custom":"inventory"}"#);
+    assert_eq!(serde_json::from_str::<BookEvent>(r#"{"Custom":"inventory"}"#)?, declared);
+
+    let event = BookEvent::custom_with("inventory", serde_json::json!({ "week": 42 }));
+    let json = serde_json::to_string(&event)?;
+    assert_eq!(json, r#"{"Custom":["inventory",{"week":42}]}"#);
+    assert_eq!(serde_json::from_str::<BookEvent>(&json)?, event);
+    assert_eq!(event.transition_key(), declared);
+    Ok(())
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_event_details_round_trip_binary() -> Result<(), bincode::Error> {
+    for event in [
+        BookEvent::Transfer { destination: None },
+        BookEvent::Transfer { destination: Some("Main".to_string()) },
+        BookEvent::Found,
+        BookEvent::custom_with("inventory", serde_json::json!({ "week": 42 })),
+    ] {
+        assert_eq!(bincode::deserialize::<BookEvent>(&bincode::serialize(&event)?)?, event);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_transition_key_drops_details() {
+    let transfer = BookEvent::Transfer { destination: Some("Main".to_string()) };
+    assert_eq!(format!("{transfer:?}"), r#"Transfer { destination: "Main" }"#);
+    assert_eq!(transfer.transition_key(), BookEvent::Transfer { destination: None });
+
+    let reserve = BookEvent::Reserve("patron-42".into());
+    assert_eq!(reserve.clone().transition_key(), reserve);
+}
@@ -0,0 +1,157 @@
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListState, Paragraph},
+};
+
+use crate::system::{LibraryError, LibrarySystem};
+
+/// Interactive terminal explorer driving a live system
+///
+/// Shows the current state, the events valid from it and the transition
+/// history. Events are picked with the arrow keys (or `j`/`k`) and fired with
+/// Enter; `q` or Esc quits.
+#[derive(Debug)]
+pub struct Explorer {
+    /// System the fired events are processed by
+    system: LibrarySystem,
+    /// Position of the highlighted event among the valid events
+    selected: usize,
+    /// Outcome of the last fired event
+    status: String,
+}
+
+impl Explorer {
+    /// Create an explorer for the given system
+    #[must_use]
+    pub fn new(system: LibrarySystem) -> Self {
+        Self { system, selected: 0, status: String::from("Select an event and press Enter") }
+    }
+
+    /// Get the explored system
+    #[must_use]
+    pub fn system(&self) -> &LibrarySystem {
+        &self.system
+    }
+
+    /// Give the explored system back, e.g. to save it after the session
+    #[must_use]
+    pub fn into_system(self) -> LibrarySystem {
+        self.system
+    }
+
+    /// Get the outcome of the last fired event
+    #[must_use]
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Take over the terminal until the user quits
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::Io` if the terminal can't be drawn to or read
+    /// from
+    pub fn run(&mut self) -> Result<(), LibraryError> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    /// Draw and handle key presses until the user quits
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), LibraryError> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// React to a key press, returning `false` once the user wants to quit
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        let event_count = self.system.valid_events().len();
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = self.selected.saturating_add(1).min(event_count.saturating_sub(1));
+            }
+            KeyCode::Enter => self.fire_selected(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Process the highlighted event and record the outcome
+    fn fire_selected(&mut self) {
+        let Some(event) = self.system.valid_events().get(self.selected).cloned() else {
+            self.status = String::from("No event can be fired from this state");
+            return;
+        };
+        self.status = match self.system.process_event(event.clone()) {
+            Ok(state) => format!("{event:?} -> {state:?}"),
+            Err(e) => format!("{event:?} failed: {e}"),
+        };
+        self.selected = 0;
+    }
+
+    /// Render the current state, valid events, history and status line
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(3)])
+                .areas(frame.area());
+        let [events_area, history_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(body);
+
+        frame.render_widget(
+            Paragraph::new(format!("{:?}", self.system.current_state())).block(
+                Block::bordered().title(format!("Current state of {}", self.system.system_id())),
+            ),
+            header,
+        );
+
+        let events: Vec<_> =
+            self.system.valid_events().iter().map(|event| format!("{event:?}")).collect();
+        let mut events_state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(
+            List::new(events)
+                .block(Block::bordered().title("Valid events"))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                .highlight_symbol("> "),
+            events_area,
+            &mut events_state,
+        );
+
+        // Show the most recent transitions that fit, newest at the bottom
+        let visible = usize::from(history_area.height.saturating_sub(2));
+        let history = self.system.get_history();
+        let history: Vec<_> = history
+            .iter()
+            .skip(history.len().saturating_sub(visible))
+            .map(|t| format!("#{} {:?} --{:?}--> {:?}", t.sequence, t.from, t.event, t.to))
+            .collect();
+        frame.render_widget(
+            List::new(history).block(Block::bordered().title("History")),
+            history_area,
+        );
+
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(self.status.as_str())])
+                .block(Block::bordered().title("↑/↓ select · Enter fire · q quit")),
+            footer,
+        );
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+use ratatui::{Terminal, backend::TestBackend};
+
+use super::*;
+use crate::{BookEvent, BookState};
+
+/// Create a small system the explorer can drive
+fn setup_test_system() -> Result<LibrarySystem, LibraryError> {
+    crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --CheckOut("Alice")--> CheckedOut("Alice"),
+            Available --ReportLost--> Lost,
+            CheckedOut("Alice") --Return--> Available,
+        },
+    }
+}
+
+#[test]
+fn test_keys_fire_selected_event() -> Result<(), LibraryError> {
+    let mut explorer = Explorer::new(setup_test_system()?);
+    let events = explorer.system().valid_events();
+
+    // Moving past the last event stays on it
+    for _ in 0..=events.len() {
+        assert!(explorer.handle_key(KeyCode::Down));
+    }
+    assert!(explorer.handle_key(KeyCode::Up));
+    assert!(explorer.handle_key(KeyCode::Enter));
+
+    assert_eq!(explorer.system().get_history().len(), 1);
+    assert_eq!(explorer.system().get_history().first().map(|t| &t.event), events.first());
+    assert!(explorer.status().contains("->"));
+
+    assert!(!explorer.handle_key(KeyCode::Char('q')));
+    Ok(())
+}
+
+#[test]
+fn test_draw_shows_state_events_and_history() -> Result<(), LibraryError> {
+    let mut system = setup_test_system()?;
+    system.process_event(BookEvent::CheckOut("Alice".into()))?;
+    let explorer = Explorer::new(system);
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 12))?;
+    terminal.draw(|frame| explorer.draw(frame))?;
+    let screen: String =
+        terminal.backend().buffer().content().iter().map(ratatui::buffer::Cell::symbol).collect();
+
+    assert!(screen.contains(&format!("{:?}", BookState::CheckedOut("Alice".into()))));
+    assert!(screen.contains("> Return"));
+    assert!(screen.contains("#1 Available --CheckOut(\"Alice\")--> CheckedOut(\"Alice\")"));
+    Ok(())
+}
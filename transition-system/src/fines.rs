@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{book_state::BookState, events::BookEvent};
+
+/// Number of seconds in a fine-accruing day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Rules for charging fines on overdue books
+///
+/// All amounts are in cents to avoid floating point money.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FinePolicy {
+    /// How long a patron may keep a checked out book
+    pub loan_period: Duration,
+    /// Extra time after the due date before fines start accruing
+    pub grace_period: Duration,
+    /// Fine charged for each started day past the grace period
+    pub daily_rate_cents: u64,
+    /// Upper bound for a single fine, if any
+    pub max_fine_cents: Option<u64>,
+}
+
+impl Default for FinePolicy {
+    fn default() -> Self {
+        Self {
+            loan_period: Duration::from_hours(14 * 24),
+            grace_period: Duration::from_hours(24),
+            daily_rate_cents: 25,
+            max_fine_cents: Some(1000),
+        }
+    }
+}
+
+impl FinePolicy {
+    /// Compute the fine for a book that is `overdue_by` past its due date
+    #[must_use]
+    pub fn fine_for(&self, overdue_by: Duration) -> u64 {
+        let chargeable = overdue_by.saturating_sub(self.grace_period);
+        if chargeable.is_zero() {
+            return 0;
+        }
+
+        // Every started day counts as a full day
+        let days = chargeable.as_secs().max(1).div_ceil(SECONDS_PER_DAY);
+        let fine = days.saturating_mul(self.daily_rate_cents);
+        self.max_fine_cents.map_or(fine, |cap| fine.min(cap))
+    }
+
+    /// Compute the fine accrued at `now` for a book due at `due_date`
+    #[must_use]
+    pub fn fine_at(&self, due_date: Instant, now: Instant) -> u64 {
+        self.fine_for(now.saturating_duration_since(due_date))
+    }
+}
+
+/// Tracks the due date of a checked out book and assesses fines on return
+#[derive(Debug, Clone)]
+pub struct FineTracker {
+    /// The rules used to compute fines
+    policy: FinePolicy,
+    /// When the current loan is due, if the book is checked out
+    due_date: Option<Instant>,
+}
+
+impl FineTracker {
+    /// Create a tracker with no active loan
+    #[must_use]
+    pub fn new(policy: FinePolicy) -> Self {
+        Self { policy, due_date: None }
+    }
+
+    /// Get the policy used by this tracker
+    #[must_use]
+    pub fn policy(&self) -> &FinePolicy {
+        &self.policy
+    }
+
+    /// Get the due date of the active loan
+    #[must_use]
+    pub fn due_date(&self) -> Option<Instant> {
+        self.due_date
+    }
+
+    /// Start a new loan at `now`
+    pub fn start_loan(&mut self, now: Instant) {
+        self.due_date = now.checked_add(self.policy.loan_period);
+    }
+
+    /// Resume a loan that is due at the given instant, e.g. after a restart
+    pub fn resume_loan(&mut self, due_date: Instant) {
+        self.due_date = Some(due_date);
+    }
+
+    /// Get the fine accrued so far by the active loan
+    #[must_use]
+    pub fn current_fine(&self, now: Instant) -> u64 {
+        self.due_date.map_or(0, |due| self.policy.fine_at(due, now))
+    }
+
+    /// Update the loan for a transition and return the fine assessed by it
+    ///
+    /// Entering a `CheckedOut` state starts a loan; a `Return` ends it and
+    /// assesses the accrued fine. Any other way of leaving `CheckedOut` (e.g.
+    /// the book being reported lost) ends the loan without a fine.
+    pub fn on_transition(
+        &mut self,
+        to: &BookState,
+        event: &BookEvent,
+        now: Instant,
+    ) -> Option<u64> {
+        let fine = match event {
+            BookEvent::Return => self.due_date.map(|due| self.policy.fine_at(due, now)),
+            _ => None,
+        };
+
+        if matches!(to, BookState::CheckedOut(_)) {
+            self.start_loan(now);
+        } else {
+            self.due_date = None;
+        }
+
+        fine
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
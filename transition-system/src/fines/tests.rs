@@ -0,0 +1,54 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    book_state::BookState, clock::MockClock, events::BookEvent, fines::FinePolicy,
+    system::LibrarySystem,
+};
+
+/// Policy with a one day loan, one hour grace period and a 50 cent cap
+fn test_policy() -> FinePolicy {
+    FinePolicy {
+        loan_period: Duration::from_hours(24),
+        grace_period: Duration::from_hours(1),
+        daily_rate_cents: 20,
+        max_fine_cents: Some(50),
+    }
+}
+
+#[test]
+fn test_fine_for_respects_grace_period_and_cap() {
+    let policy = test_policy();
+
+    assert_eq!(policy.fine_for(Duration::ZERO), 0);
+    assert_eq!(policy.fine_for(Duration::from_mins(30)), 0);
+    // Any started day past the grace period is charged in full
+    assert_eq!(policy.fine_for(Duration::from_mins(61)), 20);
+    assert_eq!(policy.fine_for(Duration::from_hours(26)), 40);
+    assert_eq!(policy.fine_for(Duration::from_hours(24 * 30)), 50);
+}
+
+#[test]
+fn test_fine_recorded_on_return() {
+    let clock = MockClock::new();
+    let mut system =
+        LibrarySystem::with_clock(BookState::Available, "test-book", Arc::new(clock.clone()));
+    let checked_out_idx = system.add_state(BookState::CheckedOut("Test User".into()));
+    system.add_transition(0, BookEvent::CheckOut("Test User".into()), checked_out_idx);
+    system.add_transition(checked_out_idx, BookEvent::Return, 0);
+    system.set_fine_policy(test_policy());
+
+    assert!(system.process_event(BookEvent::CheckOut("Test User".into())).is_ok());
+    assert!(system.due_date().is_some());
+    assert_eq!(system.current_fine(), 0);
+
+    // Two days late, one hour of which is forgiven
+    clock.advance(Duration::from_hours(24 * 3));
+    assert_eq!(system.current_fine(), 40);
+
+    assert!(system.process_event(BookEvent::Return).is_ok());
+    assert_eq!(system.due_date(), None);
+    assert_eq!(system.current_fine(), 0);
+
+    let fine = system.get_history().last().and_then(|t| t.metadata.get("fine_cents").cloned());
+    assert_eq!(fine.as_deref(), Some("40"));
+}
@@ -0,0 +1,2545 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Write},
+    fs,
+    future::{Future, Ready},
+    marker::PhantomData,
+    mem::{self, Discriminant},
+    ops::{Deref, Not},
+    path::Path,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    logging::emit,
+    observers::{ObserverId, TransitionLogger},
+    persistence::SerializableInstant,
+    system::LibraryError,
+    visualization::{HistoryStep, Visualizable, VisualizationTheme},
+};
+
+/// Trait for types that can be used as states in a [`TransitionSystem`]
+pub trait State: Clone + fmt::Debug + PartialEq {}
+
+// Any type with the required bounds can be used as a state
+impl<T: Clone + fmt::Debug + PartialEq> State for T {}
+
+/// Possible errors during state transitions
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TransitionError {
+    /// The transition is not allowed from the current state
+    #[error("No valid transition for this event from the current state")]
+    InvalidTransition,
+    /// A guard condition prevented the transition
+    #[error("Guard failed: {0}")]
+    GuardFailed(String),
+    /// A custom error occurred during the transition
+    #[error("{0}")]
+    Custom(String),
+    /// A transition was built without one of its required parts
+    #[error("Transition is missing its {0}")]
+    Incomplete(&'static str),
+    /// A transition with async guards or actions was applied synchronously
+    #[error("Transition has async guards or actions, apply it with apply_event_async")]
+    AsyncRequired,
+    /// A transition would never be applied because an earlier one always wins
+    #[error("Conflicting transition: {0}")]
+    Conflict(String),
+}
+
+/// Future returned by async guards, actions and transitions
+///
+/// Implementations usually return `Box::pin(async move { ... })`. The futures
+/// of a [`Shared`] system must be `Send`, see [`Sharing::Future`].
+pub type TransitionFuture<'a, T, K = Local> = <K as Sharing>::Future<'a, T>;
+
+/// A transition between states of a [`TransitionSystem`] in sharing mode `K`
+pub trait Transition<S: State, K: Sharing = Local> {
+    /// The event type that triggers this transition
+    type Event;
+
+    /// The error type returned if the transition fails
+    type Error;
+
+    /// Apply the transition to the current state on an event
+    ///
+    /// The event is borrowed, so trying a transition never clones it; a
+    /// [`TransitionSystem`] only takes ownership of it to record the applied
+    /// transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transition is not allowed for the state and event
+    fn apply(&self, state: &S, event: &Self::Event) -> Result<S, Self::Error>;
+
+    /// Check whether the transition would be allowed, without applying it
+    fn is_valid(&self, state: &S, event: &Self::Event) -> bool;
+
+    /// Get the state the transition would lead to, without applying it
+    ///
+    /// Returns `None` if the transition is not valid for the state and event,
+    /// or if its target can't be known without applying it, the default.
+    /// Actions are not run.
+    fn target_state(&self, state: &S, event: &Self::Event) -> Option<S> {
+        let _ = (state, event);
+        None
+    }
+
+    /// Get which events trigger the transition
+    ///
+    /// A [`TransitionSystem`] only considers a transition matching a single
+    /// variant for events of that variant. The default, `None`, considers it
+    /// for every event.
+    fn event_matcher(&self) -> Option<&EventMatcher<Self::Event>> {
+        None
+    }
+
+    /// Get the states the transition starts from
+    ///
+    /// A [`TransitionSystem`] only considers the transition in states of the
+    /// same variants. The default, no states, considers it in every state.
+    fn source_states(&self) -> &[S] {
+        &[]
+    }
+
+    /// Get the state the transition always leads to, if it is a fixed one
+    ///
+    /// Used to draw the transition; the default, `None`, draws it to an
+    /// unknown state.
+    fn fixed_target(&self) -> Option<&S> {
+        None
+    }
+
+    /// Describe the transition's guards, in the order they are checked
+    ///
+    /// Used to label the transition when drawing it. The default is no guards.
+    fn guard_labels(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Apply the transition, awaiting any asynchronous checks and side effects
+    ///
+    /// The default applies the transition synchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transition is not allowed for the state and event
+    fn apply_async<'a>(
+        &'a self,
+        state: &'a S,
+        event: &'a Self::Event,
+    ) -> TransitionFuture<'a, Result<S, Self::Error>, K>
+    where
+        Self::Error: 'a,
+        K: Boxes<Ready<Result<S, Self::Error>>>,
+    {
+        K::boxed(std::future::ready(self.apply(state, event)))
+    }
+
+    /// Describe the transition so it can be saved, if possible
+    ///
+    /// Transitions defined by arbitrary code can't be described and return
+    /// `None`, the default.
+    fn descriptor(&self) -> Option<TransitionDescriptor<S, Self::Event>> {
+        None
+    }
+}
+
+/// Predicate deciding which events trigger a transition
+///
+/// Predicates only look at the event, so they are `Send + Sync` whatever the
+/// [`Sharing`] mode, and a matcher can be used by transitions of any system.
+type EventPredicate<E> = Arc<dyn Fn(&E) -> bool + Send + Sync>;
+
+/// Which events trigger a [`GuardedTransition`]
+#[derive(Default)]
+pub enum EventMatcher<E> {
+    /// Any event
+    #[default]
+    Any,
+    /// Events equal to the given one
+    Exact(E),
+    /// Events of the same variant as the given one, whatever their data
+    Variant(E),
+    /// Events the predicate accepts, with a description for diagrams
+    Predicate(String, EventPredicate<E>),
+}
+
+// Manual implementation of Debug for EventMatcher
+impl<E: fmt::Debug> fmt::Debug for EventMatcher<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("Any"),
+            Self::Exact(event) => f.debug_tuple("Exact").field(event).finish(),
+            Self::Variant(event) => f.debug_tuple("Variant").field(event).finish(),
+            Self::Predicate(description, _) => {
+                f.debug_tuple("Predicate").field(description).finish_non_exhaustive()
+            }
+        }
+    }
+}
+
+impl<E: Clone> Clone for EventMatcher<E> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Any => Self::Any,
+            Self::Exact(event) => Self::Exact(event.clone()),
+            Self::Variant(event) => Self::Variant(event.clone()),
+            Self::Predicate(description, predicate) => {
+                Self::Predicate(description.clone(), Arc::clone(predicate))
+            }
+        }
+    }
+}
+
+impl<E> EventMatcher<E> {
+    /// Check whether an event triggers the transition
+    pub fn matches(&self, event: &E) -> bool
+    where
+        E: PartialEq,
+    {
+        match self {
+            Self::Any => true,
+            Self::Exact(expected) => expected == event,
+            Self::Variant(sample) => mem::discriminant(sample) == mem::discriminant(event),
+            Self::Predicate(_, predicate) => predicate(event),
+        }
+    }
+
+    /// Get the variant every matching event has, if there is a single one
+    #[must_use]
+    pub fn variant(&self) -> Option<Discriminant<E>> {
+        match self {
+            Self::Exact(event) | Self::Variant(event) => Some(mem::discriminant(event)),
+            Self::Any | Self::Predicate(..) => None,
+        }
+    }
+
+    /// Describe the matched events, e.g. `Reserve(..)` for a variant
+    #[must_use]
+    pub fn label(&self) -> String
+    where
+        E: fmt::Debug,
+    {
+        match self {
+            Self::Any => "any event".to_string(),
+            Self::Exact(event) => format!("{event:?}"),
+            Self::Variant(sample) => {
+                let debug = format!("{sample:?}");
+                let name = debug.split(['(', ' ', '{']).next().unwrap_or_default();
+                if name.len() == debug.len() { debug } else { format!("{name}(..)") }
+            }
+            Self::Predicate(description, _) => description.clone(),
+        }
+    }
+
+    /// Check whether every event `other` matches is also matched by `self`
+    ///
+    /// Predicates can't be compared, so they only cover each other when they
+    /// are the same closure.
+    fn covers(&self, other: &Self) -> bool
+    where
+        E: PartialEq,
+    {
+        match (self, other) {
+            (Self::Any, _) => true,
+            (Self::Exact(a), Self::Exact(b)) => a == b,
+            (Self::Variant(a), Self::Exact(b) | Self::Variant(b)) => {
+                mem::discriminant(a) == mem::discriminant(b)
+            }
+            (Self::Predicate(_, a), Self::Predicate(_, b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Serializable description of a [`GuardedTransition`]
+///
+/// Guards and actions are closures and can't be serialized, so they are
+/// saved by name and looked up in a [`Behaviors`] registry on load.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransitionDescriptor<S, E> {
+    /// States the transition starts from, empty for any state
+    pub from: Vec<S>,
+    /// Event triggering the transition, `None` for any event
+    pub event: Option<E>,
+    /// Whether any event of the same variant as `event` triggers the transition
+    #[serde(default)]
+    pub match_variant: bool,
+    /// State the transition leads to
+    pub to: S,
+    /// Names of the transition's guards, in the order they are checked
+    #[serde(default)]
+    pub guards: Vec<String>,
+    /// Names of the transition's actions, in the order they run
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+/// A transition defined by a single function computing the next state
+///
+/// `Src` and `Tgt` are marker types documenting which states the transition
+/// connects. The function borrows the event. Checking validity calls it, so
+/// it should not have side effects; use a [`TransitionBuilder`] for
+/// transitions with actions.
+pub struct TypedTransition<S, E, Src, Tgt, F>
+where
+    S: State,
+    Src: 'static,
+    Tgt: 'static,
+    F: Fn(&S, &E) -> Result<S, TransitionError>,
+{
+    /// Computes the next state, or why there is none
+    transition_fn: F,
+    /// Marker for the source state
+    _source_state: PhantomData<Src>,
+    /// Marker for the target state
+    _target_state: PhantomData<Tgt>,
+    /// Marker for the state type
+    _state: PhantomData<S>,
+    /// Marker for the event type
+    _event: PhantomData<E>,
+}
+
+// Manual implementation of Debug for TypedTransition
+impl<S, E, Src, Tgt, F> fmt::Debug for TypedTransition<S, E, Src, Tgt, F>
+where
+    S: State,
+    Src: 'static,
+    Tgt: 'static,
+    F: Fn(&S, &E) -> Result<S, TransitionError>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedTransition").finish_non_exhaustive()
+    }
+}
+
+impl<S, E, Src, Tgt, F> TypedTransition<S, E, Src, Tgt, F>
+where
+    S: State,
+    Src: 'static,
+    Tgt: 'static,
+    F: Fn(&S, &E) -> Result<S, TransitionError>,
+{
+    /// Create a transition from the function computing the next state
+    #[must_use]
+    pub fn new(transition_fn: F) -> Self {
+        Self {
+            transition_fn,
+            _source_state: PhantomData,
+            _target_state: PhantomData,
+            _state: PhantomData,
+            _event: PhantomData,
+        }
+    }
+}
+
+impl<S, E, Src, Tgt, F, K> Transition<S, K> for TypedTransition<S, E, Src, Tgt, F>
+where
+    S: State,
+    K: Sharing,
+    Src: 'static,
+    Tgt: 'static,
+    F: Fn(&S, &E) -> Result<S, TransitionError>,
+{
+    type Event = E;
+    type Error = TransitionError;
+
+    fn apply(&self, state: &S, event: &Self::Event) -> Result<S, Self::Error> {
+        (self.transition_fn)(state, event)
+    }
+
+    fn is_valid(&self, state: &S, event: &Self::Event) -> bool {
+        (self.transition_fn)(state, event).is_ok()
+    }
+
+    fn target_state(&self, state: &S, event: &Self::Event) -> Option<S> {
+        (self.transition_fn)(state, event).ok()
+    }
+}
+
+/// Marker type of a state of a machine generated by [`typestate!`](crate::typestate)
+///
+/// Ties the compile-time marker to the runtime state it stands for, so a
+/// typed machine can be turned into a [`TransitionSystem`].
+pub trait StateMarker {
+    /// The runtime state type
+    type State;
+
+    /// The runtime state the marker stands for
+    const STATE: Self::State;
+}
+
+/// State type that declares its own transitions
+///
+/// Usually implemented with `#[derive(StateMachine)]` (`derive` feature),
+/// which reads the transitions from `#[transition(from = ..., on = ..., to =
+/// ...)]` attributes on the enum, so no transition has to be registered by
+/// hand:
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use transition_system::generic::StateMachine;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum DocEvent {
+///     Submit,
+///     Approve,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq, StateMachine)]
+/// #[state_machine(event = DocEvent)]
+/// #[transition(from = Review, on = Approve, to = Published)]
+/// enum DocState {
+///     #[transition(on = Submit, to = Review)]
+///     Draft,
+///     Review,
+///     Published,
+/// }
+///
+/// let mut system = DocState::transition_system(DocState::Draft)?;
+/// system.apply_event(DocEvent::Submit)?;
+/// assert_eq!(*system.current_state(), DocState::Review);
+/// # }
+/// # Ok::<(), transition_system::generic::TransitionError>(())
+/// ```
+pub trait StateMachine: State + 'static {
+    /// The event type driving the machine
+    type Event: Clone + PartialEq + 'static;
+
+    /// Get the declared transitions as `(from, event, to)`, in declaration order
+    fn transitions() -> Vec<(Self, Self::Event, Self)>;
+
+    /// Build a system in the given state with the declared transitions
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a transition can't be built
+    fn transition_system(
+        initial: Self,
+    ) -> Result<TransitionSystem<Self, Self::Event>, TransitionError> {
+        let mut system = TransitionSystem::new(initial);
+        for (from, event, to) in Self::transitions() {
+            system.register_transition(
+                TransitionBuilder::new().from(from).on_event(event).to(to).build()?,
+            );
+        }
+        Ok(system)
+    }
+}
+
+/// Check whether a `(state, event)` row of a [`transitions!`](crate::transitions) table repeats
+///
+/// Used by the macro to reject duplicate rows at compile time.
+#[doc(hidden)]
+#[must_use]
+pub const fn has_duplicate_row(rows: &[(isize, isize)]) -> bool {
+    let mut rest = rows;
+    while let [row, tail @ ..] = rest {
+        let mut others = tail;
+        while let [other, others_tail @ ..] = others {
+            if row.0 == other.0 && row.1 == other.1 {
+                return true;
+            }
+            others = others_tail;
+        }
+        rest = tail;
+    }
+    false
+}
+
+/// Derive [`StateMachine`] from `#[transition]` attributes
+#[cfg(feature = "derive")]
+pub use transition_system_derive::StateMachine;
+
+/// A transition applied by a [`TransitionSystem`], as kept in its history
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransitionRecord<S, E> {
+    /// The state before the transition
+    pub from: S,
+    /// The state after the transition
+    pub to: S,
+    /// The event that triggered the transition
+    pub event: E,
+    /// When the transition occurred
+    pub timestamp: SerializableInstant,
+    /// Position of the transition among all transitions of the system, from 1
+    pub sequence: u64,
+}
+
+/// How long a [`TransitionSystem`] may stay in a state, and what happens then
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TimingConstraint<E> {
+    /// Maximum time allowed in the state
+    pub max_duration: Duration,
+    /// Event to apply when the time runs out
+    pub timeout_event: E,
+}
+
+/// A transition of a [`TransitionSystem`] that is never applied from a state
+///
+/// Found by [`TransitionSystem::validate`]: an earlier transition without
+/// guards accepts every event the later one does from the same state, so the
+/// earlier one always wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionConflict<S> {
+    /// State both transitions start from, `None` if both start from any state
+    pub state: Option<S>,
+    /// Events the shadowed transition accepts, as in [`EventMatcher::label`]
+    pub event: String,
+    /// Registration position of the transition that wins, from 0
+    pub winner: usize,
+    /// Registration position of the transition that is never applied
+    pub shadowed: usize,
+}
+
+impl<S: fmt::Debug> fmt::Display for TransitionConflict<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { event, winner, shadowed, .. } = self;
+        match &self.state {
+            Some(state) => write!(f, "Transition {shadowed} on {event} from {state:?}")?,
+            None => write!(f, "Transition {shadowed} on {event} from any state")?,
+        }
+        write!(f, " is shadowed by transition {winner}")
+    }
+}
+
+/// Observer of the transitions of a [`TransitionSystem`]
+///
+/// The generic counterpart of [`StateObserver`](crate::observers::StateObserver),
+/// for cross-cutting concerns like logging or metrics.
+pub trait Observer<S, E> {
+    /// Called when a state transition occurs
+    fn on_state_change(&self, from: &S, to: &S, event: &E);
+
+    /// Called with the full history entry when a state transition occurs
+    ///
+    /// Override this to access the transition's timestamp and sequence number;
+    /// the default forwards to [`Self::on_state_change`].
+    fn on_transition(&self, record: &TransitionRecord<S, E>) {
+        self.on_state_change(&record.from, &record.to, &record.event);
+    }
+}
+
+impl<S, E> Observer<S, E> for TransitionLogger
+where
+    S: fmt::Debug,
+    E: fmt::Debug,
+{
+    fn on_state_change(&self, from: &S, to: &S, event: &E) {
+        emit!(info, "LOGGER: Transition occurred: {from:?} --({event:?})--> {to:?}");
+    }
+}
+
+/// Bucket of a [`TransitionSystem`]'s dispatch index: the variants of state
+/// and event a transition accepts, `None` standing for any
+type DispatchKey<S, E> = (Option<Discriminant<S>>, Option<Discriminant<E>>);
+
+/// How a [`TransitionSystem`] and its transitions keep their closures
+///
+/// [`Local`], the default, shares guards and actions with `Rc` and accepts
+/// any closure. [`Shared`] shares them with `Arc` and `Mutex` and only accepts
+/// `Send + Sync` closures, transitions and observers, so the system itself is
+/// `Send + Sync`, e.g. to keep it behind an async web handler. Everything
+/// else, from the dispatch index to persistence, works the same in both.
+pub trait Sharing: Sized + 'static {
+    /// Future of an async guard, action or transition, `Send` for [`Shared`]
+    type Future<'a, T>: Future<Output = T>;
+
+    /// Condition checked by a [`Guard`]
+    type GuardFn<S, E>: Clone + Deref<Target: Fn(&S, &E) -> Result<(), String>>;
+
+    /// Side effect run when a transition is applied, shared by the
+    /// transitions a [`Behaviors`] registry rebuilds with it
+    type ActionFn<S, E>: Clone;
+
+    /// Computes the target state of a transition from the state and event
+    type TargetFn<S, E>: Deref<Target: Fn(&S, &E) -> S>;
+
+    /// Condition checked asynchronously, e.g. by asking a permission service
+    type AsyncGuardFn<S, E>: Deref<
+        Target: for<'a> Fn(&'a S, &'a E) -> Self::Future<'a, Result<(), String>>,
+    >;
+
+    /// Side effect awaited when a transition is applied
+    type AsyncActionFn<S, E>: Deref<Target: for<'a> Fn(&'a S, &'a E) -> Self::Future<'a, ()>>;
+
+    /// Transition as stored by a [`TransitionSystem`]
+    type BoxedTransition<S: State, E>: Deref<
+        Target: Transition<S, Self, Event = E, Error = TransitionError>,
+    >;
+
+    /// Observer as stored by a [`TransitionSystem`]
+    type BoxedObserver<S, E>: Deref<Target: Observer<S, E>>;
+
+    /// Run an action
+    fn run_action<S, E>(action: &Self::ActionFn<S, E>, state: &S, event: &E);
+}
+
+/// A [`Sharing`] mode able to keep an `F` as a `T`
+///
+/// Implemented for every closure and transition the mode accepts, so
+/// [`Shared`] only takes `Send + Sync` ones.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` systems can't keep this closure or transition",
+    note = "`Shared` systems only accept `Send + Sync` closures and transitions (actions only `Send`)"
+)]
+pub trait Stores<F, T>: Sharing {
+    /// Wrap the value the way the mode keeps it
+    fn store(value: F) -> T;
+}
+
+/// A [`Sharing`] mode able to return an `F` as its [`Sharing::Future`]
+///
+/// [`Shared`] only returns `Send` futures.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` systems can't return this future",
+    note = "`Shared` systems only return `Send` futures"
+)]
+pub trait Boxes<F: Future>: Sharing {
+    /// Box the future the way the mode returns it
+    fn boxed<'a>(future: F) -> Self::Future<'a, F::Output>
+    where
+        F: 'a;
+}
+
+/// A [`Sharing`] mode able to await the async guards and actions of its
+/// [`GuardedTransition`]s over `S` and `E`
+///
+/// [`Shared`] futures are `Send`, so it needs `Send + Sync` states and events.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` systems can't await transitions over `{S}` and `{E}`",
+    note = "`Shared` systems need `Send + Sync` states and events"
+)]
+pub trait AwaitsGuards<S: State, E>: Sharing {
+    /// Apply a transition, awaiting its async guards and actions
+    fn apply_guarded<'a>(
+        transition: &'a GuardedTransition<S, E, Self>,
+        state: &'a S,
+        event: &'a E,
+    ) -> Self::Future<'a, Result<S, TransitionError>>;
+}
+
+/// [`Sharing`] mode for systems used from a single thread, the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Local;
+
+impl Sharing for Local {
+    type Future<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+    type GuardFn<S, E> = Rc<dyn Fn(&S, &E) -> Result<(), String>>;
+    type ActionFn<S, E> = Rc<RefCell<dyn FnMut(&S, &E)>>;
+    type TargetFn<S, E> = Box<dyn Fn(&S, &E) -> S>;
+    type AsyncGuardFn<S, E> =
+        Rc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>>>;
+    type AsyncActionFn<S, E> = Rc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, ()>>;
+    type BoxedTransition<S: State, E> = Box<dyn Transition<S, Event = E, Error = TransitionError>>;
+    type BoxedObserver<S, E> = Box<dyn Observer<S, E>>;
+
+    /// Skip an action that is already running, so an action applying an
+    /// event to its own system doesn't run again
+    fn run_action<S, E>(action: &Self::ActionFn<S, E>, state: &S, event: &E) {
+        if let Ok(mut action) = action.try_borrow_mut() {
+            (*action)(state, event);
+        }
+    }
+}
+
+impl<S, E, F> Stores<F, Rc<dyn Fn(&S, &E) -> Result<(), String>>> for Local
+where
+    F: Fn(&S, &E) -> Result<(), String> + 'static,
+{
+    fn store(value: F) -> Rc<dyn Fn(&S, &E) -> Result<(), String>> {
+        Rc::new(value)
+    }
+}
+
+impl<S, E, F> Stores<F, Rc<RefCell<dyn FnMut(&S, &E)>>> for Local
+where
+    F: FnMut(&S, &E) + 'static,
+{
+    fn store(value: F) -> Rc<RefCell<dyn FnMut(&S, &E)>> {
+        Rc::new(RefCell::new(value))
+    }
+}
+
+impl<S, E, F> Stores<F, Box<dyn Fn(&S, &E) -> S>> for Local
+where
+    F: Fn(&S, &E) -> S + 'static,
+{
+    fn store(value: F) -> Box<dyn Fn(&S, &E) -> S> {
+        Box::new(value)
+    }
+}
+
+impl<S, E, F>
+    Stores<F, Rc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>>>>
+    for Local
+where
+    F: for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>> + 'static,
+{
+    fn store(
+        value: F,
+    ) -> Rc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>>> {
+        Rc::new(value)
+    }
+}
+
+impl<S, E, F> Stores<F, Rc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, ()>>> for Local
+where
+    F: for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, ()> + 'static,
+{
+    fn store(value: F) -> Rc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, ()>> {
+        Rc::new(value)
+    }
+}
+
+impl<S, E, T> Stores<T, Box<dyn Transition<S, Event = E, Error = TransitionError>>> for Local
+where
+    S: State,
+    T: Transition<S, Event = E, Error = TransitionError> + 'static,
+{
+    fn store(value: T) -> Box<dyn Transition<S, Event = E, Error = TransitionError>> {
+        Box::new(value)
+    }
+}
+
+impl<F: Future> Boxes<F> for Local {
+    fn boxed<'a>(future: F) -> Self::Future<'a, F::Output>
+    where
+        F: 'a,
+    {
+        Box::pin(future)
+    }
+}
+
+impl<S, E> AwaitsGuards<S, E> for Local
+where
+    S: State,
+    E: Clone + PartialEq,
+{
+    fn apply_guarded<'a>(
+        transition: &'a GuardedTransition<S, E, Self>,
+        state: &'a S,
+        event: &'a E,
+    ) -> Self::Future<'a, Result<S, TransitionError>> {
+        Box::pin(transition.await_transition(state, event))
+    }
+}
+
+/// [`Sharing`] mode for systems shared between threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Shared;
+
+impl Sharing for Shared {
+    type Future<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+    type GuardFn<S, E> = Arc<dyn Fn(&S, &E) -> Result<(), String> + Send + Sync>;
+    type ActionFn<S, E> = Arc<Mutex<dyn FnMut(&S, &E) + Send>>;
+    type TargetFn<S, E> = Box<dyn Fn(&S, &E) -> S + Send + Sync>;
+    type AsyncGuardFn<S, E> = Arc<
+        dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>, Self>
+            + Send
+            + Sync,
+    >;
+    type AsyncActionFn<S, E> =
+        Arc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, (), Self> + Send + Sync>;
+    type BoxedTransition<S: State, E> =
+        Box<dyn Transition<S, Self, Event = E, Error = TransitionError> + Send + Sync>;
+    type BoxedObserver<S, E> = Box<dyn Observer<S, E> + Send + Sync>;
+
+    /// Wait for the action if another thread is running it
+    fn run_action<S, E>(action: &Self::ActionFn<S, E>, state: &S, event: &E) {
+        (*action.lock().unwrap_or_else(PoisonError::into_inner))(state, event);
+    }
+}
+
+impl<S, E, F> Stores<F, Arc<dyn Fn(&S, &E) -> Result<(), String> + Send + Sync>> for Shared
+where
+    F: Fn(&S, &E) -> Result<(), String> + Send + Sync + 'static,
+{
+    fn store(value: F) -> Arc<dyn Fn(&S, &E) -> Result<(), String> + Send + Sync> {
+        Arc::new(value)
+    }
+}
+
+impl<S, E, F> Stores<F, Arc<Mutex<dyn FnMut(&S, &E) + Send>>> for Shared
+where
+    F: FnMut(&S, &E) + Send + 'static,
+{
+    fn store(value: F) -> Arc<Mutex<dyn FnMut(&S, &E) + Send>> {
+        Arc::new(Mutex::new(value))
+    }
+}
+
+impl<S, E, F> Stores<F, Box<dyn Fn(&S, &E) -> S + Send + Sync>> for Shared
+where
+    F: Fn(&S, &E) -> S + Send + Sync + 'static,
+{
+    fn store(value: F) -> Box<dyn Fn(&S, &E) -> S + Send + Sync> {
+        Box::new(value)
+    }
+}
+
+impl<S, E, F>
+    Stores<
+        F,
+        Arc<
+            dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>, Self>
+                + Send
+                + Sync,
+        >,
+    > for Shared
+where
+    F: for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>, Self>
+        + Send
+        + Sync
+        + 'static,
+{
+    fn store(
+        value: F,
+    ) -> Arc<
+        dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>, Self>
+            + Send
+            + Sync,
+    > {
+        Arc::new(value)
+    }
+}
+
+impl<S, E, F>
+    Stores<F, Arc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, (), Self> + Send + Sync>>
+    for Shared
+where
+    F: for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, (), Self> + Send + Sync + 'static,
+{
+    fn store(
+        value: F,
+    ) -> Arc<dyn for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, (), Self> + Send + Sync> {
+        Arc::new(value)
+    }
+}
+
+impl<S, E, T>
+    Stores<T, Box<dyn Transition<S, Self, Event = E, Error = TransitionError> + Send + Sync>>
+    for Shared
+where
+    S: State,
+    T: Transition<S, Self, Event = E, Error = TransitionError> + Send + Sync + 'static,
+{
+    fn store(
+        value: T,
+    ) -> Box<dyn Transition<S, Self, Event = E, Error = TransitionError> + Send + Sync> {
+        Box::new(value)
+    }
+}
+
+impl<F: Future + Send> Boxes<F> for Shared {
+    fn boxed<'a>(future: F) -> Self::Future<'a, F::Output>
+    where
+        F: 'a,
+    {
+        Box::pin(future)
+    }
+}
+
+impl<S, E> AwaitsGuards<S, E> for Shared
+where
+    S: State + Send + Sync,
+    E: Clone + PartialEq + Send + Sync,
+{
+    fn apply_guarded<'a>(
+        transition: &'a GuardedTransition<S, E, Self>,
+        state: &'a S,
+        event: &'a E,
+    ) -> Self::Future<'a, Result<S, TransitionError>> {
+        Box::pin(transition.await_transition(state, event))
+    }
+}
+
+/// A state machine over any state and event types
+///
+/// Unlike [`LibrarySystem`](crate::system::LibrarySystem), which is tied to
+/// [`BookState`](crate::book_state::BookState) and
+/// [`BookEvent`](crate::events::BookEvent), this works with any state type,
+/// e.g. a document workflow or a traffic light. Transitions are tried in the
+/// order they were registered and the first valid one is applied. They are
+/// indexed by the variants of their [`Transition::source_states`] and
+/// [`Transition::event_matcher`], so only those that can match are checked and
+/// the guards of the others are never run. Like `LibrarySystem`, the most
+/// recent 100 applied transitions are kept in a history, and states can have
+/// timing constraints firing an event once the system stayed in them too long.
+/// Systems created with [`Self::shared`] are `Send + Sync`, see [`Sharing`].
+pub struct TransitionSystem<S, E, K = Local>
+where
+    S: State,
+    K: Sharing,
+{
+    /// The state the system is in
+    current_state: S,
+    /// Registered transitions, in registration order
+    transitions: Vec<K::BoxedTransition<S, E>>,
+    /// Positions of the transitions by the states and events they accept
+    index: HashMap<DispatchKey<S, E>, Vec<usize>>,
+    /// Super-state of each sub-state, as `(state, parent)`
+    parents: Vec<(S, S)>,
+    /// Timing constraints by state
+    timing_constraints: Vec<(S, TimingConstraint<E>)>,
+    /// When the current state was entered
+    state_entered_at: Instant,
+    /// Most recent applied transitions, oldest first
+    history: Vec<TransitionRecord<S, E>>,
+    /// Maximum number of history entries to keep
+    max_history_size: usize,
+    /// Number of transitions applied since the system was created
+    sequence: u64,
+    /// Source of the current time for history timestamps
+    clock: Arc<dyn Clock>,
+    /// Registered transition observers
+    observers: Vec<(ObserverId, K::BoxedObserver<S, E>)>,
+    /// Identifier handed to the next registered observer
+    next_observer_id: u64,
+}
+
+// Manual implementation of Debug for TransitionSystem
+impl<S, E, K> fmt::Debug for TransitionSystem<S, E, K>
+where
+    S: State,
+    E: fmt::Debug,
+    K: Sharing,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransitionSystem")
+            .field("current_state", &self.current_state)
+            .field("transitions_count", &self.transitions.len())
+            .field("index_buckets", &self.index.len())
+            .field("parents", &self.parents)
+            .field("timing_constraints", &self.timing_constraints)
+            .field("state_entered_at", &self.state_entered_at)
+            .field("history", &self.history)
+            .field("max_history_size", &self.max_history_size)
+            .field("sequence", &self.sequence)
+            .field("clock", &self.clock)
+            .field("observers_count", &self.observers.len())
+            .field("next_observer_id", &self.next_observer_id)
+            .finish()
+    }
+}
+
+impl<S, E> TransitionSystem<S, E>
+where
+    S: State,
+    E: Clone,
+{
+    /// Create a new transition system with the given initial state
+    #[must_use]
+    pub fn new(initial_state: S) -> Self {
+        Self::with_clock(initial_state, Arc::new(SystemClock))
+    }
+
+    /// Create a new transition system that reads the time from the given clock
+    #[must_use]
+    pub fn with_clock(initial_state: S, clock: Arc<dyn Clock>) -> Self {
+        Self::create(initial_state, clock)
+    }
+}
+
+impl<S, E> TransitionSystem<S, E, Shared>
+where
+    S: State,
+    E: Clone,
+{
+    /// Create a new transition system that can be shared between threads
+    ///
+    /// Only `Send + Sync` transitions and observers can be registered, e.g.
+    /// those built with [`TransitionBuilder::shared`].
+    #[must_use]
+    pub fn shared(initial_state: S) -> Self {
+        Self::shared_with_clock(initial_state, Arc::new(SystemClock))
+    }
+
+    /// Create a new shared transition system that reads the time from the given clock
+    #[must_use]
+    pub fn shared_with_clock(initial_state: S, clock: Arc<dyn Clock>) -> Self {
+        Self::create(initial_state, clock)
+    }
+}
+
+impl<S, E, K> TransitionSystem<S, E, K>
+where
+    S: State,
+    E: Clone,
+    K: Sharing,
+{
+    /// Create a new transition system in the given state and clock
+    fn create(initial_state: S, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            current_state: initial_state,
+            transitions: Vec::new(),
+            index: HashMap::new(),
+            parents: Vec::new(),
+            timing_constraints: Vec::new(),
+            state_entered_at: clock.now(),
+            history: Vec::new(),
+            max_history_size: 100,
+            sequence: 0,
+            clock,
+            observers: Vec::new(),
+            next_observer_id: 0,
+        }
+    }
+
+    /// Register a transition in the system
+    pub fn register_transition<T>(&mut self, transition: T)
+    where
+        T: Transition<S, K, Event = E, Error = TransitionError> + 'static,
+        K: Stores<T, <K as Sharing>::BoxedTransition<S, E>>,
+    {
+        let position = self.transitions.len();
+        let event = transition.event_matcher().and_then(EventMatcher::variant);
+        let mut states = Vec::new();
+        for state in transition.source_states() {
+            let state = Some(mem::discriminant(state));
+            if !states.contains(&state) {
+                states.push(state);
+            }
+        }
+        if states.is_empty() {
+            states.push(None);
+        }
+        for state in states {
+            self.index.entry((state, event)).or_default().push(position);
+        }
+        self.transitions.push(K::store(transition));
+    }
+
+    /// Declare `parent` as the super-state of `state`
+    ///
+    /// Events no transition accepts in a state bubble up to its parent, then
+    /// to the parent's parent, so a transition shared by several states can
+    /// be declared once with the super-state as its source. Transitions of
+    /// the state itself take precedence. The guards, actions and computed
+    /// target of a transition inherited this way see the super-state it was
+    /// declared on. Replaces any parent declared before; a declaration that
+    /// would make a state its own ancestor is ignored when resolving events.
+    pub fn set_parent(&mut self, state: S, parent: S) {
+        self.parents.retain(|(child, _)| *child != state);
+        self.parents.push((state, parent));
+    }
+
+    /// Get the super-state of a state, if one was declared
+    #[must_use]
+    pub fn parent_of(&self, state: &S) -> Option<&S> {
+        self.parents.iter().find(|(child, _)| child == state).map(|(_, parent)| parent)
+    }
+
+    /// Check whether the system is in a state or one of its sub-states
+    #[must_use]
+    pub fn is_in(&self, state: &S) -> bool {
+        self.lineage().contains(&state)
+    }
+
+    /// Get the current state followed by its super-states, innermost first
+    fn lineage(&self) -> Vec<&S> {
+        let mut lineage = vec![&self.current_state];
+        while let Some(parent) = lineage.last().and_then(|state| self.parent_of(state)) {
+            if lineage.contains(&parent) {
+                break;
+            }
+            lineage.push(parent);
+        }
+        lineage
+    }
+
+    /// Get the registered transitions valid for an event, with the state they
+    /// are applied from: those of the current state, then those inherited from
+    /// its super-states, each in registration order
+    fn valid_transitions<'a>(
+        &'a self,
+        event: &E,
+    ) -> impl Iterator<Item = (&'a K::BoxedTransition<S, E>, &'a S)> {
+        self.lineage().into_iter().flat_map(move |state| {
+            self.transitions_from(state, event).map(move |transition| (transition, state))
+        })
+    }
+
+    /// Get the registered transitions valid for a state and event, in
+    /// registration order
+    fn transitions_from<'a>(
+        &'a self,
+        state: &'a S,
+        event: &E,
+    ) -> impl Iterator<Item = &'a K::BoxedTransition<S, E>> {
+        let variant = Some(mem::discriminant(state));
+        let kind = Some(mem::discriminant(event));
+        let mut positions: Vec<usize> =
+            [(variant, kind), (variant, None), (None, kind), (None, None)]
+                .iter()
+                .filter_map(|key| self.index.get(key))
+                .flatten()
+                .copied()
+                .collect();
+        positions.sort_unstable();
+        positions
+            .into_iter()
+            .filter_map(|position| self.transitions.get(position))
+            .filter(|t| t.is_valid(state, event))
+    }
+
+    /// Find the transition to apply for an event, with the state it is applied from
+    fn find_transition(&self, event: &E) -> Option<(&K::BoxedTransition<S, E>, &S)> {
+        self.valid_transitions(event).next()
+    }
+
+    /// Apply an event to the current state, recording the transition in the history
+    ///
+    /// If the current state has timed out, its timeout event is applied first
+    /// and the event is then applied from the resulting state. The registered
+    /// observers are notified once the transition is applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransitionError::InvalidTransition` if no registered
+    /// transition accepts the event (or a due timeout event) from the current
+    /// state, or the error of the transition that failed to apply
+    pub fn apply_event(&mut self, event: E) -> Result<&S, TransitionError> {
+        self.fire_timeout_if_due()?;
+        self.apply_now(event)
+    }
+
+    /// Apply an event without checking for timeouts
+    fn apply_now(&mut self, event: E) -> Result<&S, TransitionError> {
+        let Some((transition, from)) = self.find_transition(&event) else {
+            return Err(TransitionError::InvalidTransition);
+        };
+        let to = transition.apply(from, &event)?;
+        Ok(self.enter(to, event))
+    }
+
+    /// Apply an event to the current state, awaiting async guards and actions
+    ///
+    /// Behaves like [`Self::apply_event`], but transitions built with
+    /// [`TransitionBuilder::async_guard`] or [`TransitionBuilder::async_action`]
+    /// can only be applied this way. Their async guards are checked after the
+    /// transition was picked, so a failing one returns a
+    /// `TransitionError::GuardFailed` instead of trying the next transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransitionError::InvalidTransition` if no registered
+    /// transition accepts the event from the current state, or the error of
+    /// the transition that failed to apply
+    pub async fn apply_event_async(&mut self, event: E) -> Result<&S, TransitionError>
+    where
+        K: Boxes<Ready<Result<S, TransitionError>>>,
+    {
+        if let Some(timeout_event) = self.due_timeout_event() {
+            self.apply_now_async(timeout_event).await?;
+        }
+        self.apply_now_async(event).await
+    }
+
+    /// Apply an event asynchronously without checking for timeouts
+    async fn apply_now_async(&mut self, event: E) -> Result<&S, TransitionError>
+    where
+        K: Boxes<Ready<Result<S, TransitionError>>>,
+    {
+        let Some((transition, from)) = self.find_transition(&event) else {
+            return Err(TransitionError::InvalidTransition);
+        };
+        let to = transition.apply_async(from, &event).await?;
+        Ok(self.enter(to, event))
+    }
+
+    /// Add a timing constraint to a state, replacing any previous one
+    ///
+    /// Once the system has been in the state for longer than `max_duration`,
+    /// `timeout_event` is applied by [`Self::fire_timeout_if_due`] or before
+    /// the next event. Time is read from the system's clock, so a
+    /// [`MockClock`](crate::clock::MockClock) makes timeouts testable.
+    pub fn add_timing_constraint(&mut self, state: S, max_duration: Duration, timeout_event: E) {
+        self.timing_constraints.retain(|(constrained, _)| *constrained != state);
+        self.timing_constraints.push((state, TimingConstraint { max_duration, timeout_event }));
+    }
+
+    /// Get all timing constraints, as `(state, constraint)`
+    #[must_use]
+    pub fn get_timing_constraints(&self) -> &[(S, TimingConstraint<E>)] {
+        &self.timing_constraints
+    }
+
+    /// Get the current state's timing constraint, if it has one
+    fn current_constraint(&self) -> Option<&TimingConstraint<E>> {
+        self.timing_constraints
+            .iter()
+            .find(|(state, _)| *state == self.current_state)
+            .map(|(_, constraint)| constraint)
+    }
+
+    /// Get how long the system has been in its current state
+    #[must_use]
+    pub fn time_in_current_state(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.state_entered_at)
+    }
+
+    /// Get how long until the current state's timing constraint expires
+    ///
+    /// Returns `None` if the current state has no timing constraint, and
+    /// `Some(Duration::ZERO)` if the constraint has already expired.
+    #[must_use]
+    pub fn time_until_timeout(&self) -> Option<Duration> {
+        self.current_constraint()
+            .map(|constraint| constraint.max_duration.saturating_sub(self.time_in_current_state()))
+    }
+
+    /// Get the current state's timeout event if its deadline has passed
+    fn due_timeout_event(&self) -> Option<E> {
+        self.current_constraint()
+            .filter(|constraint| self.time_in_current_state() > constraint.max_duration)
+            .map(|constraint| constraint.timeout_event.clone())
+    }
+
+    /// Apply the current state's timeout event if its deadline has passed
+    ///
+    /// Returns `Ok(None)` if no timeout is due, otherwise the state reached by
+    /// applying the timeout event. Observers are notified as for any other
+    /// transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransitionError::InvalidTransition` if no registered
+    /// transition accepts the timeout event, or the error of the transition
+    /// that failed to apply
+    pub fn fire_timeout_if_due(&mut self) -> Result<Option<&S>, TransitionError> {
+        let Some(timeout_event) = self.due_timeout_event() else {
+            return Ok(None);
+        };
+        self.apply_now(timeout_event).map(Some)
+    }
+
+    /// Move to a new state, recording the transition and notifying the observers
+    fn enter(&mut self, to: S, event: E) -> &S {
+        let from = std::mem::replace(&mut self.current_state, to.clone());
+        self.state_entered_at = self.clock.now();
+
+        self.sequence = self.sequence.saturating_add(1);
+        let record = TransitionRecord {
+            from,
+            to,
+            event,
+            timestamp: SerializableInstant::from_instant(self.clock.now()),
+            sequence: self.sequence,
+        };
+        for (_, observer) in &self.observers {
+            observer.on_transition(&record);
+        }
+
+        self.history.push(record);
+        if self.history.len() > self.max_history_size {
+            self.history.remove(0);
+        }
+        &self.current_state
+    }
+
+    /// Register an observer notified of every applied transition
+    ///
+    /// Returns a handle that can be passed to [`Self::unregister_observer`].
+    pub fn register_observer(&mut self, observer: K::BoxedObserver<S, E>) -> ObserverId {
+        let id = ObserverId(self.next_observer_id);
+        self.next_observer_id = self.next_observer_id.saturating_add(1);
+        self.observers.push((id, observer));
+        id
+    }
+
+    /// Remove a registered observer, handing it back
+    ///
+    /// Returns `None` if no observer with this handle is registered, e.g.
+    /// because it has already been removed.
+    pub fn unregister_observer(&mut self, id: ObserverId) -> Option<K::BoxedObserver<S, E>> {
+        let position = self.observers.iter().position(|(observer_id, _)| *observer_id == id)?;
+        Some(self.observers.remove(position).1)
+    }
+
+    /// Remove all registered observers
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Get the transition history, oldest first
+    #[must_use]
+    pub fn get_history(&self) -> &Vec<TransitionRecord<S, E>> {
+        &self.history
+    }
+
+    /// Change how many history entries are kept, dropping the oldest extra ones
+    pub fn set_max_history_size(&mut self, max_history_size: usize) {
+        self.max_history_size = max_history_size;
+        let excess = self.history.len().saturating_sub(max_history_size);
+        self.history.drain(..excess);
+    }
+
+    /// Get the number of transitions applied since the system was created
+    #[must_use]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Get the current state of the system
+    #[must_use]
+    pub fn current_state(&self) -> &S {
+        &self.current_state
+    }
+
+    /// Check whether an event can be applied to the current state
+    #[must_use]
+    pub fn can_transition(&self, event: &E) -> bool {
+        self.find_transition(event).is_some()
+    }
+
+    /// Get the state an event would lead to, without applying it
+    ///
+    /// Returns `None` if the event can't be applied to the current state, or
+    /// if the transition [`Self::apply_event`] would pick can't tell its
+    /// target without being applied (see [`Transition::target_state`]). No
+    /// action is run and async guards are not checked.
+    #[must_use]
+    pub fn can_reach_with(&self, event: &E) -> Option<S> {
+        let (transition, from) = self.find_transition(event)?;
+        transition.target_state(from, event)
+    }
+
+    /// Get the states every transition valid for an event would lead to
+    ///
+    /// Targets are listed in registration order, so the first one is where
+    /// [`Self::apply_event`] would go. Like [`Self::can_reach_with`], transitions
+    /// that can't tell their target are skipped.
+    #[must_use]
+    pub fn targets_of(&self, event: &E) -> Vec<S> {
+        self.valid_transitions(event).filter_map(|(t, from)| t.target_state(from, event)).collect()
+    }
+
+    /// Get the events among `events` that can be applied to the current state
+    #[must_use]
+    pub fn possible_transitions(&self, events: &[E]) -> Vec<E> {
+        events.iter().filter(|e| self.can_transition(e)).cloned().collect()
+    }
+}
+
+/// Condition a transition must meet, failing with a message
+///
+/// Guards combine with [`Self::and`], [`Self::or`] and `!` ([`Not`]). A guard
+/// given a name with [`Self::named`] or [`Self::with_name`] prefixes its
+/// failure messages with it, so a failing condition deep inside a combination
+/// can still be told apart. Combining named guards names the result after
+/// them, e.g. `is_owner or (is_editor and not locked)`, which is also the name
+/// a saved transition refers to it by. Clones share the same conditions.
+pub struct Guard<S, E, K = Local>
+where
+    K: Sharing,
+{
+    /// Name used in failure messages and saved descriptions
+    name: Option<String>,
+    /// The condition itself
+    condition: Condition<S, E, K>,
+}
+
+/// How a [`Guard`] decides
+enum Condition<S, E, K>
+where
+    K: Sharing,
+{
+    /// A condition given as a function
+    Check(K::GuardFn<S, E>),
+    /// A guard whose failure messages are prefixed with the name
+    Named(String, Box<Guard<S, E, K>>),
+    /// Both guards must pass
+    And(Box<Guard<S, E, K>>, Box<Guard<S, E, K>>),
+    /// Either guard must pass
+    Or(Box<Guard<S, E, K>>, Box<Guard<S, E, K>>),
+    /// The guard must fail, failing with the message otherwise
+    Not(Box<Guard<S, E, K>>, String),
+}
+
+// Manual implementation of Debug for Guard
+impl<S, E, K: Sharing> fmt::Debug for Guard<S, E, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Guard").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+impl<S, E, K: Sharing> Clone for Guard<S, E, K> {
+    fn clone(&self) -> Self {
+        Self { name: self.name.clone(), condition: self.condition.clone() }
+    }
+}
+
+impl<S, E, K: Sharing> Clone for Condition<S, E, K> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Check(check) => Self::Check(check.clone()),
+            Self::Named(name, guard) => Self::Named(name.clone(), guard.clone()),
+            Self::And(left, right) => Self::And(left.clone(), right.clone()),
+            Self::Or(left, right) => Self::Or(left.clone(), right.clone()),
+            Self::Not(guard, message) => Self::Not(guard.clone(), message.clone()),
+        }
+    }
+}
+
+impl<S, E> Guard<S, E> {
+    /// Create an unnamed guard from a condition
+    #[must_use]
+    pub fn new<F>(check: F) -> Self
+    where
+        F: Fn(&S, &E) -> Result<(), String> + 'static,
+    {
+        Self::from_fn(check)
+    }
+
+    /// Create a named guard from a condition
+    #[must_use]
+    pub fn named<F>(name: &str, check: F) -> Self
+    where
+        F: Fn(&S, &E) -> Result<(), String> + 'static,
+    {
+        Self::new(check).with_name(name)
+    }
+}
+
+impl<S, E> Guard<S, E, Shared> {
+    /// Create an unnamed guard for a [`Shared`] system
+    #[must_use]
+    pub fn shared<F>(check: F) -> Self
+    where
+        F: Fn(&S, &E) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Self::from_fn(check)
+    }
+}
+
+impl<S, E, K: Sharing> Guard<S, E, K> {
+    /// Create an unnamed guard from a condition the mode can keep
+    fn from_fn<F>(check: F) -> Self
+    where
+        K: Stores<F, <K as Sharing>::GuardFn<S, E>>,
+    {
+        Self { name: None, condition: Condition::Check(K::store(check)) }
+    }
+
+    /// Get the guard's name, if it has one
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Check the condition for a state and event
+    ///
+    /// # Errors
+    ///
+    /// Returns the failure message if the condition is not met
+    pub fn check(&self, state: &S, event: &E) -> Result<(), String> {
+        match &self.condition {
+            Condition::Check(check) => check(state, event),
+            Condition::Named(name, guard) => {
+                guard.check(state, event).map_err(|message| format!("{name}: {message}"))
+            }
+            Condition::And(left, right) => {
+                left.check(state, event)?;
+                right.check(state, event)
+            }
+            Condition::Or(left, right) => {
+                let Err(first) = left.check(state, event) else {
+                    return Ok(());
+                };
+                right.check(state, event).map_err(|second| format!("{first}; {second}"))
+            }
+            Condition::Not(guard, message) => match guard.check(state, event) {
+                Ok(()) => Err(message.clone()),
+                Err(_) => Ok(()),
+            },
+        }
+    }
+
+    /// Give the guard a name, prefixing its failure messages with it
+    #[must_use]
+    pub fn with_name(self, name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            condition: Condition::Named(name.to_string(), Box::new(self)),
+        }
+    }
+
+    /// Require both guards to pass, failing with the first failure
+    #[must_use]
+    pub fn and(self, other: Self) -> Self {
+        let name = Self::combined_name(&self, "and", &other);
+        Self { name, condition: Condition::And(Box::new(self), Box::new(other)) }
+    }
+
+    /// Require either guard to pass, failing with both failures
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        let name = Self::combined_name(&self, "or", &other);
+        Self { name, condition: Condition::Or(Box::new(self), Box::new(other)) }
+    }
+
+    /// Name a combination after its operands, if both are named
+    fn combined_name(left: &Self, operator: &str, right: &Self) -> Option<String> {
+        let (left, right) = (left.name.as_deref()?, right.name.as_deref()?);
+        Some(format!("{} {operator} {}", Self::operand(left), Self::operand(right)))
+    }
+
+    /// Parenthesize a combined name used as an operand
+    ///
+    /// Negations bind tighter than `and` and `or`, so they are left as is.
+    fn operand(name: &str) -> String {
+        if name.contains(' ') && !name.starts_with("not ") {
+            format!("({name})")
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+impl<S, E, K: Sharing> Not for Guard<S, E, K> {
+    type Output = Self;
+
+    /// Require the guard to fail
+    fn not(self) -> Self::Output {
+        let name = self.name.as_deref().map(|name| format!("not {}", Self::operand(name)));
+        let message = self
+            .name
+            .as_deref()
+            .map_or_else(|| "Negated guard passed".to_string(), |name| format!("{name} passed"));
+        Self { name, condition: Condition::Not(Box::new(self), message) }
+    }
+}
+
+/// Action together with the name it is saved under, if any
+type NamedAction<S, E, K> = (Option<String>, <K as Sharing>::ActionFn<S, E>);
+
+/// Where a [`GuardedTransition`] leads
+enum Target<S, E, K>
+where
+    K: Sharing,
+{
+    /// Always the same state
+    Fixed(S),
+    /// A state derived from the state and event when the transition is applied
+    Computed(K::TargetFn<S, E>),
+}
+
+// Manual implementation of Debug for Target
+impl<S: fmt::Debug, E, K: Sharing> fmt::Debug for Target<S, E, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(state) => f.debug_tuple("Fixed").field(state).finish(),
+            Self::Computed(_) => f.debug_tuple("Computed").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// A transition built by a [`TransitionBuilder`]
+///
+/// It is valid for its event (any event if none was given), from its source
+/// states (any state if none were given), when all its guards pass. Its
+/// actions only run when it is applied, not when its validity is checked.
+pub struct GuardedTransition<S, E, K = Local>
+where
+    S: State,
+    K: Sharing,
+{
+    /// States the transition starts from, empty for any state
+    source_states: Vec<S>,
+    /// State the transition leads to
+    target: Target<S, E, K>,
+    /// Events triggering the transition
+    event: EventMatcher<E>,
+    /// Conditions checked before the transition, with their names if given
+    guards: Vec<Guard<S, E, K>>,
+    /// Side effects run when the transition is applied, with their names if given
+    actions: Vec<NamedAction<S, E, K>>,
+    /// Conditions awaited before the transition
+    async_guards: Vec<K::AsyncGuardFn<S, E>>,
+    /// Side effects awaited when the transition is applied
+    async_actions: Vec<K::AsyncActionFn<S, E>>,
+}
+
+// Manual implementation of Debug for GuardedTransition
+impl<S, E, K> fmt::Debug for GuardedTransition<S, E, K>
+where
+    S: State,
+    E: fmt::Debug,
+    K: Sharing,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardedTransition")
+            .field("source_states", &self.source_states)
+            .field("target", &self.target)
+            .field("event", &self.event)
+            .field("guards_count", &self.guards.len())
+            .field("actions_count", &self.actions.len())
+            .field("async_guards_count", &self.async_guards.len())
+            .field("async_actions_count", &self.async_actions.len())
+            .finish()
+    }
+}
+
+impl<S, E, K> GuardedTransition<S, E, K>
+where
+    S: State,
+    E: PartialEq,
+    K: Sharing,
+{
+    /// Check the event, source state and guards
+    fn check(&self, state: &S, event: &E) -> Result<(), TransitionError> {
+        if !self.event.matches(event)
+            || (!self.source_states.is_empty() && !self.source_states.contains(state))
+        {
+            return Err(TransitionError::InvalidTransition);
+        }
+        self.guards
+            .iter()
+            .try_for_each(|guard| guard.check(state, event))
+            .map_err(TransitionError::GuardFailed)
+    }
+
+    /// Check whether the transition can only be applied asynchronously
+    fn is_async(&self) -> bool {
+        !self.async_guards.is_empty() || !self.async_actions.is_empty()
+    }
+
+    /// Get the state the transition leads to
+    fn target(&self, state: &S, event: &E) -> S {
+        match &self.target {
+            Target::Fixed(target) => target.clone(),
+            Target::Computed(target_fn) => target_fn(state, event),
+        }
+    }
+
+    /// Run the synchronous actions
+    fn run_actions(&self, state: &S, event: &E) {
+        for (_, action) in &self.actions {
+            K::run_action(action, state, event);
+        }
+    }
+
+    /// Apply the transition, awaiting its async guards and actions
+    async fn await_transition(&self, state: &S, event: &E) -> Result<S, TransitionError> {
+        self.check(state, event)?;
+        for guard in &self.async_guards {
+            guard(state, event).await.map_err(TransitionError::GuardFailed)?;
+        }
+        let target = self.target(state, event);
+        self.run_actions(state, event);
+        for action in &self.async_actions {
+            action(state, event).await;
+        }
+        Ok(target)
+    }
+}
+
+impl<S, E, K> Transition<S, K> for GuardedTransition<S, E, K>
+where
+    S: State,
+    E: Clone + PartialEq,
+    K: AwaitsGuards<S, E>,
+{
+    type Event = E;
+    type Error = TransitionError;
+
+    fn apply(&self, state: &S, event: &Self::Event) -> Result<S, Self::Error> {
+        self.check(state, event)?;
+        if self.is_async() {
+            return Err(TransitionError::AsyncRequired);
+        }
+        let target = self.target(state, event);
+        self.run_actions(state, event);
+        Ok(target)
+    }
+
+    /// Check the synchronous conditions only; async guards are awaited by
+    /// [`Self::apply_async`]
+    fn is_valid(&self, state: &S, event: &Self::Event) -> bool {
+        self.check(state, event).is_ok()
+    }
+
+    /// Check the synchronous conditions only, like [`Self::is_valid`]
+    fn target_state(&self, state: &S, event: &Self::Event) -> Option<S> {
+        self.check(state, event).ok().map(|()| self.target(state, event))
+    }
+
+    fn event_matcher(&self) -> Option<&EventMatcher<Self::Event>> {
+        Some(&self.event)
+    }
+
+    fn source_states(&self) -> &[S] {
+        &self.source_states
+    }
+
+    fn fixed_target(&self) -> Option<&S> {
+        match &self.target {
+            Target::Fixed(target) => Some(target),
+            Target::Computed(_) => None,
+        }
+    }
+
+    /// Name every guard, calling unnamed ones `guard` and async ones `async guard`
+    fn guard_labels(&self) -> Vec<String> {
+        let sync = self.guards.iter().map(|guard| guard.name().unwrap_or("guard").to_string());
+        sync.chain(self.async_guards.iter().map(|_| "async guard".to_string())).collect()
+    }
+
+    /// Await the async guards after the synchronous ones, and the async
+    /// actions after the synchronous ones
+    fn apply_async<'a>(
+        &'a self,
+        state: &'a S,
+        event: &'a Self::Event,
+    ) -> TransitionFuture<'a, Result<S, Self::Error>, K>
+    where
+        Self::Error: 'a,
+    {
+        K::apply_guarded(self, state, event)
+    }
+
+    /// Describe the transition, unless it has a computed target, an event
+    /// predicate, async guards or actions, or a guard or action without a name
+    fn descriptor(&self) -> Option<TransitionDescriptor<S, E>> {
+        let Target::Fixed(to) = &self.target else {
+            return None;
+        };
+        let (event, match_variant) = match &self.event {
+            EventMatcher::Any => (None, false),
+            EventMatcher::Exact(event) => (Some(event.clone()), false),
+            EventMatcher::Variant(event) => (Some(event.clone()), true),
+            EventMatcher::Predicate(..) => return None,
+        };
+        if self.is_async() {
+            return None;
+        }
+        let guards = self.guards.iter().map(|guard| guard.name.clone()).collect::<Option<_>>()?;
+        let actions = self.actions.iter().map(|(name, _)| name.clone()).collect::<Option<_>>()?;
+        Some(TransitionDescriptor {
+            from: self.source_states.clone(),
+            event,
+            match_variant,
+            to: to.clone(),
+            guards,
+            actions,
+        })
+    }
+}
+
+/// Fluent builder for transitions with guards and actions
+///
+/// [`Self::new`] builds transitions for [`TransitionSystem::new`] and
+/// [`Self::shared`] `Send + Sync` ones for [`TransitionSystem::shared`].
+pub struct TransitionBuilder<S, E, K = Local>
+where
+    S: State,
+    K: Sharing,
+{
+    /// States the transition starts from
+    source_states: Vec<S>,
+    /// State the transition leads to
+    target: Option<Target<S, E, K>>,
+    /// Events triggering the transition
+    event: EventMatcher<E>,
+    /// Conditions checked before the transition, with their names if given
+    guards: Vec<Guard<S, E, K>>,
+    /// Side effects run when the transition is applied, with their names if given
+    actions: Vec<NamedAction<S, E, K>>,
+    /// Conditions awaited before the transition
+    async_guards: Vec<K::AsyncGuardFn<S, E>>,
+    /// Side effects awaited when the transition is applied
+    async_actions: Vec<K::AsyncActionFn<S, E>>,
+}
+
+// Manual implementation of Debug for TransitionBuilder
+impl<S, E, K> fmt::Debug for TransitionBuilder<S, E, K>
+where
+    S: State,
+    E: fmt::Debug,
+    K: Sharing,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransitionBuilder")
+            .field("source_states", &self.source_states)
+            .field("target", &self.target)
+            .field("event", &self.event)
+            .field("guards_count", &self.guards.len())
+            .field("actions_count", &self.actions.len())
+            .field("async_guards_count", &self.async_guards.len())
+            .field("async_actions_count", &self.async_actions.len())
+            .finish()
+    }
+}
+
+impl<S, E, K> Default for TransitionBuilder<S, E, K>
+where
+    S: State,
+    K: Sharing,
+{
+    fn default() -> Self {
+        Self {
+            source_states: Vec::new(),
+            target: None,
+            event: EventMatcher::Any,
+            guards: Vec::new(),
+            actions: Vec::new(),
+            async_guards: Vec::new(),
+            async_actions: Vec::new(),
+        }
+    }
+}
+
+impl<S, E> TransitionBuilder<S, E>
+where
+    S: State,
+{
+    /// Start building a transition
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, E> TransitionBuilder<S, E, Shared>
+where
+    S: State,
+{
+    /// Start building a transition for a [`Shared`] system
+    ///
+    /// Its guards, actions and target function must be `Send + Sync`
+    /// (actions only `Send`).
+    #[must_use]
+    pub fn shared() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, E, K> TransitionBuilder<S, E, K>
+where
+    S: State,
+    K: Sharing,
+{
+    /// Add a state the transition starts from
+    ///
+    /// Call it several times for a transition shared by several states. A
+    /// transition without source states applies from any state.
+    #[must_use]
+    pub fn from(mut self, state: S) -> Self {
+        self.source_states.push(state);
+        self
+    }
+
+    /// Set the state the transition leads to
+    #[must_use]
+    pub fn to(mut self, state: S) -> Self {
+        self.target = Some(Target::Fixed(state));
+        self
+    }
+
+    /// Derive the state the transition leads to from the state and event
+    ///
+    /// The function is called when the transition is applied, after the
+    /// guards passed, e.g. to go from `Reserve(name)` to `Reserved(name)`.
+    /// A transition with a computed target can't be saved.
+    #[must_use]
+    pub fn to_fn<F>(mut self, target_fn: F) -> Self
+    where
+        F: Fn(&S, &E) -> S + 'static,
+        K: Stores<F, <K as Sharing>::TargetFn<S, E>>,
+    {
+        self.target = Some(Target::Computed(K::store(target_fn)));
+        self
+    }
+
+    /// Set the event that triggers the transition
+    ///
+    /// Without an event, the transition is triggered by any event its guards
+    /// accept.
+    #[must_use]
+    pub fn on_event(self, event: E) -> Self {
+        self.on(EventMatcher::Exact(event))
+    }
+
+    /// Trigger the transition by any event of the same variant as `event`
+    ///
+    /// E.g. `on_variant(Reserve(String::new()))` matches reservations by anyone.
+    #[must_use]
+    pub fn on_variant(self, event: E) -> Self {
+        self.on(EventMatcher::Variant(event))
+    }
+
+    /// Trigger the transition by the events a predicate accepts
+    ///
+    /// The description labels the transition in diagrams. A transition
+    /// triggered by a predicate can't be saved.
+    #[must_use]
+    pub fn on_event_matching<F>(self, description: &str, predicate: F) -> Self
+    where
+        F: Fn(&E) -> bool + Send + Sync + 'static,
+    {
+        self.on(EventMatcher::Predicate(description.to_string(), Arc::new(predicate)))
+    }
+
+    /// Set which events trigger the transition
+    #[must_use]
+    pub fn on(mut self, matcher: EventMatcher<E>) -> Self {
+        self.event = matcher;
+        self
+    }
+
+    /// Add a condition checked before the transition, failing with a message
+    ///
+    /// A transition with an unnamed guard can't be saved; use
+    /// [`Self::named_guard`] for systems that are persisted.
+    #[must_use]
+    pub fn guard<F>(mut self, guard_fn: F) -> Self
+    where
+        F: Fn(&S, &E) -> Result<(), String> + 'static,
+        K: Stores<F, <K as Sharing>::GuardFn<S, E>>,
+    {
+        self.guards.push(Guard::from_fn(guard_fn));
+        self
+    }
+
+    /// Add a condition saved under the given name
+    ///
+    /// Register the same name in the [`Behaviors`] used to load the system.
+    #[must_use]
+    pub fn named_guard<F>(mut self, name: &str, guard_fn: F) -> Self
+    where
+        F: Fn(&S, &E) -> Result<(), String> + 'static,
+        K: Stores<F, <K as Sharing>::GuardFn<S, E>>,
+    {
+        self.guards.push(Guard::from_fn(guard_fn).with_name(name));
+        self
+    }
+
+    /// Add a [`Guard`], e.g. one combined from others
+    ///
+    /// A transition with a guard without a name can't be saved; combining
+    /// named guards gives a named guard.
+    #[must_use]
+    pub fn when(mut self, guard: Guard<S, E, K>) -> Self {
+        self.guards.push(guard);
+        self
+    }
+
+    /// Add a side effect run when the transition is applied
+    ///
+    /// A transition with an unnamed action can't be saved; use
+    /// [`Self::named_action`] for systems that are persisted.
+    #[must_use]
+    pub fn action<F>(mut self, action_fn: F) -> Self
+    where
+        F: FnMut(&S, &E) + 'static,
+        K: Stores<F, <K as Sharing>::ActionFn<S, E>>,
+    {
+        self.actions.push((None, K::store(action_fn)));
+        self
+    }
+
+    /// Add a side effect saved under the given name
+    ///
+    /// Register the same name in the [`Behaviors`] used to load the system.
+    #[must_use]
+    pub fn named_action<F>(mut self, name: &str, action_fn: F) -> Self
+    where
+        F: FnMut(&S, &E) + 'static,
+        K: Stores<F, <K as Sharing>::ActionFn<S, E>>,
+    {
+        self.actions.push((Some(name.to_string()), K::store(action_fn)));
+        self
+    }
+
+    /// Add a condition awaited before the transition, failing with a message
+    ///
+    /// Async guards are checked after the synchronous ones, and only by
+    /// [`TransitionSystem::apply_event_async`]; applying the transition
+    /// synchronously returns a `TransitionError::AsyncRequired`. A transition
+    /// with async guards can't be saved. The futures of a [`Self::shared`]
+    /// builder must be `Send`, like its closures.
+    #[must_use]
+    pub fn async_guard<F>(mut self, guard_fn: F) -> Self
+    where
+        F: for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, Result<(), String>, K> + 'static,
+        K: Stores<F, <K as Sharing>::AsyncGuardFn<S, E>>,
+    {
+        self.async_guards.push(K::store(guard_fn));
+        self
+    }
+
+    /// Add a side effect awaited when the transition is applied
+    ///
+    /// Async actions run after the synchronous ones, and like async guards
+    /// require [`TransitionSystem::apply_event_async`].
+    #[must_use]
+    pub fn async_action<F>(mut self, action_fn: F) -> Self
+    where
+        F: for<'a> Fn(&'a S, &'a E) -> TransitionFuture<'a, (), K> + 'static,
+        K: Stores<F, <K as Sharing>::AsyncActionFn<S, E>>,
+    {
+        self.async_actions.push(K::store(action_fn));
+        self
+    }
+
+    /// Build the transition
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransitionError::Incomplete` if the target state has not
+    /// been set
+    pub fn build(self) -> Result<GuardedTransition<S, E, K>, TransitionError> {
+        Ok(GuardedTransition {
+            source_states: self.source_states,
+            target: self.target.ok_or(TransitionError::Incomplete("target state"))?,
+            event: self.event,
+            guards: self.guards,
+            actions: self.actions,
+            async_guards: self.async_guards,
+            async_actions: self.async_actions,
+        })
+    }
+}
+
+/// Guards and actions by name, for re-attaching them to loaded transitions
+///
+/// Register every name used with [`TransitionBuilder::named_guard`] and
+/// [`TransitionBuilder::named_action`] before loading a saved system, and
+/// every combined guard passed to [`TransitionBuilder::when`]. An action
+/// shared by several transitions keeps a single state.
+pub struct Behaviors<S, E, K = Local>
+where
+    K: Sharing,
+{
+    /// Guards by name
+    guards: HashMap<String, Guard<S, E, K>>,
+    /// Actions by name, shared by the transitions using them
+    actions: HashMap<String, K::ActionFn<S, E>>,
+}
+
+// Manual implementation of Debug for Behaviors
+impl<S, E, K: Sharing> fmt::Debug for Behaviors<S, E, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut guards: Vec<_> = self.guards.keys().collect();
+        guards.sort();
+        let mut actions: Vec<_> = self.actions.keys().collect();
+        actions.sort();
+        f.debug_struct("Behaviors").field("guards", &guards).field("actions", &actions).finish()
+    }
+}
+
+impl<S, E, K: Sharing> Default for Behaviors<S, E, K> {
+    fn default() -> Self {
+        Self { guards: HashMap::new(), actions: HashMap::new() }
+    }
+}
+
+impl<S, E> Behaviors<S, E> {
+    /// Create an empty registry
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, E> Behaviors<S, E, Shared> {
+    /// Create an empty registry for loading [`Shared`] systems
+    #[must_use]
+    pub fn shared() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, E, K> Behaviors<S, E, K>
+where
+    S: State,
+    E: Clone + PartialEq,
+    K: Sharing,
+{
+    /// Register a guard under the given name
+    #[must_use]
+    pub fn guard<F>(mut self, name: &str, guard_fn: F) -> Self
+    where
+        F: Fn(&S, &E) -> Result<(), String> + 'static,
+        K: Stores<F, <K as Sharing>::GuardFn<S, E>>,
+    {
+        self.guards.insert(name.to_string(), Guard::from_fn(guard_fn).with_name(name));
+        self
+    }
+
+    /// Register a [`Guard`] under its own name, e.g. one combined from others
+    ///
+    /// A guard without a name can't be referred to by a saved transition and
+    /// is not registered.
+    #[must_use]
+    pub fn register_guard(mut self, guard: Guard<S, E, K>) -> Self {
+        if let Some(name) = guard.name.clone() {
+            self.guards.insert(name, guard);
+        }
+        self
+    }
+
+    /// Register an action under the given name
+    #[must_use]
+    pub fn action<F>(mut self, name: &str, action_fn: F) -> Self
+    where
+        F: FnMut(&S, &E) + 'static,
+        K: Stores<F, <K as Sharing>::ActionFn<S, E>>,
+    {
+        self.actions.insert(name.to_string(), K::store(action_fn));
+        self
+    }
+
+    /// Rebuild a transition from its description
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if one of its guards or actions is
+    /// not registered
+    pub fn rebuild(
+        &self,
+        descriptor: TransitionDescriptor<S, E>,
+    ) -> Result<GuardedTransition<S, E, K>, LibraryError> {
+        let mut builder = descriptor
+            .from
+            .into_iter()
+            .fold(TransitionBuilder::default(), TransitionBuilder::from)
+            .to(descriptor.to);
+        builder.event = match (descriptor.event, descriptor.match_variant) {
+            (None, _) => EventMatcher::Any,
+            (Some(event), false) => EventMatcher::Exact(event),
+            (Some(event), true) => EventMatcher::Variant(event),
+        };
+        let mut builder = descriptor.guards.iter().try_fold(builder, |builder, name| {
+            let guard = self.guards.get(name).ok_or_else(|| {
+                LibraryError::LoadError(format!("Guard {name:?} is not registered"))
+            })?;
+            Ok::<_, LibraryError>(builder.when(guard.clone()))
+        })?;
+        for name in &descriptor.actions {
+            let action = self.actions.get(name).ok_or_else(|| {
+                LibraryError::LoadError(format!("Action {name:?} is not registered"))
+            })?;
+            builder.actions.push((Some(name.clone()), action.clone()));
+        }
+        builder.build().map_err(|e| LibraryError::LoadError(e.to_string()))
+    }
+}
+
+/// Everything needed to restore a [`TransitionSystem`] later
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SerializableTransitionSystem<S, E> {
+    /// The state the system is in
+    pub current_state: S,
+    /// Registered transitions, in registration order
+    pub transitions: Vec<TransitionDescriptor<S, E>>,
+    /// Most recent applied transitions, oldest first
+    pub history: Vec<TransitionRecord<S, E>>,
+    /// Maximum number of history entries to keep
+    pub max_history_size: usize,
+    /// Number of transitions applied since the system was created
+    pub sequence: u64,
+    /// Super-state of each sub-state, as `(state, parent)`
+    #[serde(default = "Vec::new")]
+    pub parents: Vec<(S, S)>,
+    /// Timing constraints by state
+    #[serde(default = "Vec::new")]
+    pub timing_constraints: Vec<(S, TimingConstraint<E>)>,
+    /// When the current state was entered, as wall-clock time
+    pub state_entered_at: Option<SerializableInstant>,
+}
+
+impl<S, E, K> TransitionSystem<S, E, K>
+where
+    S: State,
+    E: Clone + fmt::Debug,
+    K: Sharing,
+{
+    /// Generate a DOT graph of the registered transitions
+    ///
+    /// States and events are labelled with their `Debug` output and guards
+    /// with their names, e.g. `Submit [has_title]`. The current state and the
+    /// transitions taken so far are highlighted like in
+    /// [`StateVisualization::generate_dot`](crate::visualization::StateVisualization::generate_dot).
+    /// Transitions from any state start at a `*` node and those whose target
+    /// is computed end at a `?` node.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with_theme(&VisualizationTheme::default())
+    }
+
+    /// Generate a DOT graph using the colors and shapes of a theme
+    #[must_use]
+    pub fn to_dot_with_theme(&self, theme: &VisualizationTheme) -> String {
+        let mut states = vec![self.current_state.clone()];
+        let mut node = |state: &S| {
+            let idx = states.iter().position(|known| known == state).unwrap_or_else(|| {
+                states.push(state.clone());
+                states.len().saturating_sub(1)
+            });
+            format!("s{idx}")
+        };
+        let mut edges = Vec::new();
+        for transition in &self.transitions {
+            let event = transition
+                .event_matcher()
+                .map_or_else(|| "any event".to_string(), EventMatcher::label);
+            let guards = transition.guard_labels();
+            let label =
+                if guards.is_empty() { event } else { format!("{event} [{}]", guards.join(", ")) };
+            let to = transition.fixed_target().map_or_else(|| "computed".to_string(), &mut node);
+            let sources = transition.source_states();
+            if sources.is_empty() {
+                edges.push(("any".to_string(), to, label));
+            } else {
+                for from in sources {
+                    edges.push((node(from), to.clone(), label.clone()));
+                }
+            }
+        }
+        let taken: Vec<_> =
+            self.history.iter().map(|record| (node(&record.from), node(&record.to))).collect();
+        Self::dot_graph(&states, &edges, &taken, theme)
+    }
+
+    /// Render the states and labelled edges collected by [`Self::to_dot_with_theme`]
+    fn dot_graph(
+        states: &[S],
+        edges: &[(String, String, String)],
+        taken: &[(String, String)],
+        theme: &VisualizationTheme,
+    ) -> String {
+        let mut dot = String::from("digraph \"TransitionSystem\" {\n  rankdir=LR;\n");
+        let _ = writeln!(
+            dot,
+            "  node [shape={}, style=filled, fillcolor=\"{}\"];",
+            theme.node_shape, theme.state_color
+        );
+        for (idx, state) in states.iter().enumerate() {
+            let label = format!("{state:?}").replace('"', "\\\"");
+            // The current state is always the first one
+            if idx == 0 {
+                let _ = writeln!(
+                    dot,
+                    "  s{idx} [label=\"{label}\", fillcolor=\"{}\", peripheries=2];",
+                    theme.current_color
+                );
+            } else {
+                let _ = writeln!(dot, "  s{idx} [label=\"{label}\"];");
+            }
+        }
+        if edges.iter().any(|(from, _, _)| from == "any") {
+            dot.push_str("  any [label=\"*\", shape=plaintext, style=\"\"];\n");
+        }
+        if edges.iter().any(|(_, to, _)| to == "computed") {
+            dot.push_str("  computed [label=\"?\", shape=plaintext, style=\"\"];\n");
+        }
+        for (from, to, label) in edges {
+            let color = if taken.iter().any(|(f, t)| f == from && t == to) {
+                format!("color=\"{}\", penwidth=2.0", theme.highlight_color)
+            } else {
+                format!("color=\"{}\"", theme.edge_color)
+            };
+            let label = label.replace('"', "\\\"");
+            let _ = writeln!(dot, "  {from} -> {to} [label=\"{label}\", {color}];");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Draws the system with [`StateVisualization`](crate::visualization::StateVisualization)
+///
+/// States are numbered in order of appearance: those of the history, oldest
+/// first, then the current state and the states of the registered transitions,
+/// super-states and timing constraints. The first state of the history is thus
+/// the first one drawn, as long as the history hasn't been trimmed. Only
+/// transitions from declared states on an exact event to a fixed target have
+/// a single edge to draw; the others (any state or event, a variant, a
+/// predicate or a computed target) are left out, see [`Self::to_dot`] for them.
+impl<S, E, K> Visualizable for TransitionSystem<S, E, K>
+where
+    S: State,
+    E: Clone + PartialEq + fmt::Debug,
+    K: Sharing,
+{
+    type State = S;
+    type Event = E;
+
+    fn system_id(&self) -> &'static str {
+        "TransitionSystem"
+    }
+
+    fn states(&self) -> Cow<'_, [S]> {
+        let mut states: Vec<S> = Vec::new();
+        let history = self.history.iter().flat_map(|record| [&record.from, &record.to]);
+        let transitions = self.transitions.iter().flat_map(|transition| {
+            transition.source_states().iter().chain(transition.fixed_target())
+        });
+        let parents = self.parents.iter().flat_map(|(state, parent)| [state, parent]);
+        let constrained = self.timing_constraints.iter().map(|(state, _)| state);
+        for state in history
+            .chain([&self.current_state])
+            .chain(transitions)
+            .chain(parents)
+            .chain(constrained)
+        {
+            if !states.contains(state) {
+                states.push(state.clone());
+            }
+        }
+        Cow::Owned(states)
+    }
+
+    fn transitions(&self) -> Vec<(usize, &E, usize)> {
+        let states = self.states();
+        let idx = |state: &S| states.iter().position(|known| known == state);
+        let mut edges = Vec::new();
+        for transition in &self.transitions {
+            let (Some(EventMatcher::Exact(event)), Some(to)) =
+                (transition.event_matcher(), transition.fixed_target())
+            else {
+                continue;
+            };
+            for from in transition.source_states() {
+                if let (Some(from), Some(to)) = (idx(from), idx(to)) {
+                    edges.push((from, event, to));
+                }
+            }
+        }
+        edges
+    }
+
+    fn current_state(&self) -> &S {
+        &self.current_state
+    }
+
+    fn history(&self) -> Vec<HistoryStep<'_, S, E>> {
+        self.history
+            .iter()
+            .map(|record| HistoryStep {
+                from: &record.from,
+                to: &record.to,
+                event: &record.event,
+                timestamp: &record.timestamp,
+            })
+            .collect()
+    }
+
+    fn timeout(&self, state_idx: usize) -> Option<(Duration, &E)> {
+        let states = self.states();
+        let state = states.get(state_idx)?;
+        self.timing_constraints
+            .iter()
+            .find(|(constrained, _)| constrained == state)
+            .map(|(_, constraint)| (constraint.max_duration, &constraint.timeout_event))
+    }
+
+    fn time_in_current_state(&self) -> Duration {
+        self.time_in_current_state()
+    }
+}
+
+impl<S, E, K> TransitionSystem<S, E, K>
+where
+    S: State,
+    E: Clone + PartialEq + fmt::Debug,
+    K: Sharing,
+{
+    /// Find the transitions that are never applied because an earlier one wins
+    ///
+    /// Transitions are tried in registration order, so a transition without
+    /// guards accepting an event from a state hides every later transition
+    /// accepting the same event from that state. Transitions with guards are
+    /// not reported as winners, since their guards decide, and transitions
+    /// without an [`EventMatcher`] can't be compared. An empty list means no
+    /// transition is shadowed.
+    #[must_use]
+    pub fn validate(&self) -> Vec<TransitionConflict<S>> {
+        self.transitions
+            .iter()
+            .enumerate()
+            .flat_map(|(position, transition)| self.conflicts_with(&**transition, position))
+            .collect()
+    }
+
+    /// Register a transition, rejecting it if it would never be applied
+    ///
+    /// The strict counterpart of [`Self::register_transition`], checking the
+    /// transition against the registered ones as [`Self::validate`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransitionError::Conflict` describing the first registered
+    /// transition that shadows the new one, which is then not registered
+    pub fn try_register_transition<T>(&mut self, transition: T) -> Result<(), TransitionError>
+    where
+        T: Transition<S, K, Event = E, Error = TransitionError> + 'static,
+        K: Stores<T, <K as Sharing>::BoxedTransition<S, E>>,
+    {
+        if let Some(conflict) = self.conflicts_with(&transition, self.transitions.len()).first() {
+            return Err(TransitionError::Conflict(conflict.to_string()));
+        }
+        self.register_transition(transition);
+        Ok(())
+    }
+
+    /// Find the states from which the transitions before `position` shadow `later`
+    fn conflicts_with<T>(&self, later: &T, position: usize) -> Vec<TransitionConflict<S>>
+    where
+        T: Transition<S, K, Event = E, Error = TransitionError> + ?Sized,
+    {
+        let mut conflicts: Vec<TransitionConflict<S>> = Vec::new();
+        let Some(matcher) = later.event_matcher() else {
+            return conflicts;
+        };
+        for (winner, earlier) in self.transitions.iter().enumerate().take(position) {
+            let covers = earlier.event_matcher().is_some_and(|m| m.covers(matcher));
+            if !covers || !earlier.guard_labels().is_empty() {
+                continue;
+            }
+            let states: Vec<Option<&S>> = match (earlier.source_states(), later.source_states()) {
+                ([], []) => vec![None],
+                ([], states) | (states, []) => states.iter().map(Some).collect(),
+                (earlier, later) => {
+                    later.iter().filter(|s| earlier.contains(s)).map(Some).collect()
+                }
+            };
+            for state in states {
+                if !conflicts.iter().any(|conflict| conflict.state.as_ref() == state) {
+                    conflicts.push(TransitionConflict {
+                        state: state.cloned(),
+                        event: matcher.label(),
+                        winner,
+                        shadowed: position,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+impl<S, E, K> TransitionSystem<S, E, K>
+where
+    S: State + Serialize + DeserializeOwned + 'static,
+    E: Clone + PartialEq + Serialize + DeserializeOwned + 'static,
+    K: Sharing,
+{
+    /// Capture everything needed to restore the system later
+    ///
+    /// Observers and the clock are not part of the snapshot and must be
+    /// re-attached after restoring.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if a registered transition
+    /// can't be described, e.g. because it has an unnamed guard or action
+    pub fn to_serializable_state(
+        &self,
+    ) -> Result<SerializableTransitionSystem<S, E>, LibraryError> {
+        let transitions = self
+            .transitions
+            .iter()
+            .enumerate()
+            .map(|(idx, transition)| {
+                transition.descriptor().ok_or_else(|| {
+                    LibraryError::PersistenceError(format!(
+                        "Transition {idx} can't be saved, name its guards and actions"
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(SerializableTransitionSystem {
+            current_state: self.current_state.clone(),
+            transitions,
+            history: self.history.clone(),
+            max_history_size: self.max_history_size,
+            sequence: self.sequence,
+            parents: self.parents.clone(),
+            timing_constraints: self.timing_constraints.clone(),
+            state_entered_at: Some(SerializableInstant::from_instant(self.state_entered_at)),
+        })
+    }
+
+    /// Restore a system from a snapshot, re-attaching guards and actions by name
+    ///
+    /// The restored system uses the system clock and has no observers.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if a guard or action of a saved
+    /// transition is not registered in `behaviors`
+    pub fn from_serializable_state(
+        state: SerializableTransitionSystem<S, E>,
+        behaviors: &Behaviors<S, E, K>,
+    ) -> Result<Self, LibraryError>
+    where
+        K: AwaitsGuards<S, E>
+            + Stores<GuardedTransition<S, E, K>, <K as Sharing>::BoxedTransition<S, E>>,
+    {
+        let mut system = Self::create(state.current_state, Arc::new(SystemClock));
+        for descriptor in state.transitions {
+            system.register_transition(behaviors.rebuild(descriptor)?);
+        }
+        system.history = state.history;
+        system.max_history_size = state.max_history_size;
+        system.sequence = state.sequence;
+        system.parents = state.parents;
+        system.timing_constraints = state.timing_constraints;
+        if let Some(entered_at) = state.state_entered_at {
+            system.state_entered_at = *entered_at.inner();
+        }
+        Ok(system)
+    }
+
+    /// Save the system to a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if a transition can't be
+    /// described, or the state can't be encoded or written
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), LibraryError> {
+        let json = serde_json::to_vec_pretty(&self.to_serializable_state()?).map_err(|e| {
+            LibraryError::PersistenceError(format!("Failed to serialize state: {e}"))
+        })?;
+        fs::write(path, json)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to write file: {e}")))
+    }
+
+    /// Load a system saved with [`Self::save_to_file`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the file can't be read or
+    /// parsed, or a guard or action is not registered in `behaviors`
+    pub fn load_from_file(
+        path: impl AsRef<Path>,
+        behaviors: &Behaviors<S, E, K>,
+    ) -> Result<Self, LibraryError>
+    where
+        K: AwaitsGuards<S, E>
+            + Stores<GuardedTransition<S, E, K>, <K as Sharing>::BoxedTransition<S, E>>,
+    {
+        let json = fs::read(path)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to read file: {e}")))?;
+        let state = serde_json::from_slice(&json)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to parse state: {e}")))?;
+        Self::from_serializable_state(state, behaviors)
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1525 @@
+use std::{
+    cell::{Cell, RefCell},
+    pin::Pin,
+    rc::Rc,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+use super::*;
+use crate::{clock::MockClock, visualization::StateVisualization};
+
+/// Simple traffic light states for testing
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum TrafficLight {
+    /// Stop
+    Red,
+    /// Prepare to stop
+    Yellow,
+    /// Go
+    Green,
+}
+
+/// Events driving the traffic light
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum TrafficEvent {
+    /// The light's timer ran out
+    Timer,
+    /// An emergency vehicle is approaching
+    Emergency,
+    /// The light is reset by maintenance
+    Reset,
+}
+
+/// Build a transition between two traffic light states
+fn light(
+    from: TrafficLight,
+    event: TrafficEvent,
+    to: TrafficLight,
+) -> Result<GuardedTransition<TrafficLight, TrafficEvent>, TransitionError> {
+    TransitionBuilder::new().from(from).to(to).on_event(event).build()
+}
+
+#[test]
+fn test_basic_transitions() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(
+        TrafficLight::Green,
+        TrafficEvent::Timer,
+        TrafficLight::Yellow,
+    )?);
+    system.register_transition(light(
+        TrafficLight::Yellow,
+        TrafficEvent::Timer,
+        TrafficLight::Red,
+    )?);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Red)
+            .from(TrafficLight::Yellow)
+            .from(TrafficLight::Green)
+            .to(TrafficLight::Red)
+            .on_event(TrafficEvent::Emergency)
+            .build()?,
+    );
+
+    // Normal cycle
+    assert_eq!(*system.current_state(), TrafficLight::Red);
+    assert_eq!(*system.apply_event(TrafficEvent::Timer)?, TrafficLight::Green);
+    assert_eq!(*system.apply_event(TrafficEvent::Timer)?, TrafficLight::Yellow);
+    assert_eq!(*system.apply_event(TrafficEvent::Timer)?, TrafficLight::Red);
+
+    // Emergency override
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(*system.apply_event(TrafficEvent::Emergency)?, TrafficLight::Red);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_transition() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(system.apply_event(TrafficEvent::Reset), Err(TransitionError::InvalidTransition));
+    assert_eq!(system.apply_event(TrafficEvent::Timer), Err(TransitionError::InvalidTransition));
+    assert_eq!(*system.current_state(), TrafficLight::Green);
+    Ok(())
+}
+
+#[test]
+fn test_guard_conditions() -> Result<(), TransitionError> {
+    let allowed = Rc::new(Cell::new(false));
+    let guard_allowed = Rc::clone(&allowed);
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Red)
+            .to(TrafficLight::Green)
+            .on_event(TrafficEvent::Timer)
+            .guard(
+                move |_, _| {
+                    if guard_allowed.get() { Ok(()) } else { Err("Crossing is busy".to_string()) }
+                },
+            )
+            .build()?,
+    );
+
+    assert!(!system.can_transition(&TrafficEvent::Timer));
+    assert!(system.apply_event(TrafficEvent::Timer).is_err());
+    assert_eq!(*system.current_state(), TrafficLight::Red);
+
+    allowed.set(true);
+    assert_eq!(*system.apply_event(TrafficEvent::Timer)?, TrafficLight::Green);
+    Ok(())
+}
+
+#[test]
+fn test_guard_failure_is_reported_by_apply() -> Result<(), TransitionError> {
+    let transition = TransitionBuilder::new()
+        .from(TrafficLight::Red)
+        .to(TrafficLight::Green)
+        .on_event(TrafficEvent::Timer)
+        .guard(|_, _| Err("Crossing is busy".to_string()))
+        .build()?;
+
+    assert_eq!(
+        transition.apply(&TrafficLight::Red, &TrafficEvent::Timer),
+        Err(TransitionError::GuardFailed("Crossing is busy".to_string()))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dispatch_skips_guards_of_other_states_and_events() -> Result<(), TransitionError> {
+    let checks = Rc::new(Cell::new(0_u32));
+    let counting = |from: TrafficLight, event: TrafficEvent, to: TrafficLight| {
+        let checks = Rc::clone(&checks);
+        TransitionBuilder::new()
+            .from(from)
+            .to(to)
+            .on_event(event)
+            .guard(move |_, _| {
+                checks.set(checks.get().saturating_add(1));
+                Ok(())
+            })
+            .build()
+    };
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(counting(
+        TrafficLight::Green,
+        TrafficEvent::Timer,
+        TrafficLight::Yellow,
+    )?);
+    system.register_transition(counting(
+        TrafficLight::Red,
+        TrafficEvent::Reset,
+        TrafficLight::Red,
+    )?);
+    system.register_transition(counting(
+        TrafficLight::Red,
+        TrafficEvent::Timer,
+        TrafficLight::Green,
+    )?);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(*system.current_state(), TrafficLight::Green);
+    // Only the matching transition's guard ran, once to pick it, once to apply it
+    assert_eq!(checks.get(), 2);
+    assert!(!system.can_transition(&TrafficEvent::Emergency));
+    assert_eq!(checks.get(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_dispatch_keeps_registration_order() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    // Accepts any event in any state, and was registered first
+    system.register_transition(
+        TransitionBuilder::new()
+            .to(TrafficLight::Yellow)
+            .guard(|_, event| match event {
+                TrafficEvent::Emergency => Ok(()),
+                _ => Err("Only for emergencies".to_string()),
+            })
+            .build()?,
+    );
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(
+        TrafficLight::Red,
+        TrafficEvent::Emergency,
+        TrafficLight::Red,
+    )?);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(*system.current_state(), TrafficLight::Green);
+    system.apply_event(TrafficEvent::Emergency)?;
+    assert_eq!(*system.current_state(), TrafficLight::Yellow);
+    Ok(())
+}
+
+#[test]
+fn test_validate_reports_shadowed_transitions() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    // Guarded alternatives are fine, the guard decides
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Green)
+            .on_event(TrafficEvent::Timer)
+            .to(TrafficLight::Yellow)
+            .guard(|_, _| Ok(()))
+            .build()?,
+    );
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Timer, TrafficLight::Red)?);
+    assert_eq!(system.validate(), []);
+
+    // Same state and event as the first transition, which always wins
+    system.register_transition(light(
+        TrafficLight::Red,
+        TrafficEvent::Timer,
+        TrafficLight::Yellow,
+    )?);
+    // Accepts any event from any state, hiding all later transitions
+    system.register_transition(TransitionBuilder::new().to(TrafficLight::Red).build()?);
+    system.register_transition(light(
+        TrafficLight::Yellow,
+        TrafficEvent::Reset,
+        TrafficLight::Red,
+    )?);
+
+    let conflicts = system.validate();
+    assert_eq!(
+        conflicts,
+        [
+            TransitionConflict {
+                state: Some(TrafficLight::Red),
+                event: "Timer".to_string(),
+                winner: 0,
+                shadowed: 3,
+            },
+            TransitionConflict {
+                state: Some(TrafficLight::Yellow),
+                event: "Reset".to_string(),
+                winner: 4,
+                shadowed: 5,
+            },
+        ]
+    );
+    assert_eq!(
+        conflicts.first().map(ToString::to_string).as_deref(),
+        Some("Transition 3 on Timer from Red is shadowed by transition 0")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_try_register_transition_rejects_conflicts() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(Book::Available);
+    system.try_register_transition(
+        TransitionBuilder::new()
+            .on_variant(BookAction::Reserve(String::new()))
+            .to(Book::Available)
+            .build()?,
+    )?;
+    let exact = TransitionBuilder::new()
+        .from(Book::Available)
+        .on_event(BookAction::Reserve("Alice".into()))
+        .to(Book::Reserved("Alice".into()))
+        .build()?;
+    assert_eq!(
+        system.try_register_transition(exact),
+        Err(TransitionError::Conflict(
+            "Transition 1 on Reserve(\"Alice\") from Available is shadowed by transition 0"
+                .to_string()
+        ))
+    );
+    system.try_register_transition(
+        TransitionBuilder::new().on_event(BookAction::Cancel).to(Book::Available).build()?,
+    )?;
+    system.apply_event(BookAction::Reserve("Bob".into()))?;
+    assert_eq!(system.get_history().len(), 1);
+    assert_eq!(system.validate(), []);
+    Ok(())
+}
+
+#[test]
+fn test_transitions_macro() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    crate::transitions!(system: TrafficLight, TrafficEvent {
+        Red + Timer => Green,
+        Green + Timer => Yellow,
+        Yellow + Timer => Red,
+        Green + Emergency => Red,
+    })?;
+    assert_eq!(system.validate(), []);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    system.apply_event(TrafficEvent::Emergency)?;
+    assert_eq!(*system.current_state(), TrafficLight::Red);
+    assert!(!system.can_transition(&TrafficEvent::Reset));
+
+    assert!(!has_duplicate_row(&[(0, 0), (0, 1), (1, 0)]));
+    assert!(has_duplicate_row(&[(0, 0), (0, 1), (0, 0)]));
+    Ok(())
+}
+
+/// Event counting how often it is cloned
+#[derive(Debug)]
+struct Counted {
+    /// Which event it is
+    kind: u8,
+    /// Number of clones made of the event and its clones
+    clones: Rc<Cell<u32>>,
+}
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        self.clones.set(self.clones.get().saturating_add(1));
+        Self { kind: self.kind, clones: Rc::clone(&self.clones) }
+    }
+}
+
+impl PartialEq for Counted {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+#[test]
+fn test_dispatch_does_not_clone_events() -> Result<(), TransitionError> {
+    let clones = Rc::new(Cell::new(0));
+    let event = |kind| Counted { kind, clones: Rc::clone(&clones) };
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(
+        TransitionBuilder::new()
+            .to(TrafficLight::Yellow)
+            .guard(|_, event: &Counted| if event.kind == 0 { Ok(()) } else { Err(String::new()) })
+            .build()?,
+    );
+    system.register_transition(TypedTransition::<_, _, (), (), _>::new(
+        |state: &TrafficLight, event: &Counted| match (state, event.kind) {
+            (TrafficLight::Green, 1) => Ok(TrafficLight::Red),
+            _ => Err(TransitionError::InvalidTransition),
+        },
+    ));
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Red)
+            .on_event(event(1))
+            .to(TrafficLight::Green)
+            .action(|_, _| {})
+            .build()?,
+    );
+    clones.set(0);
+
+    assert!(system.can_transition(&event(1)));
+    system.apply_event(event(1))?;
+    system.apply_event(event(1))?;
+    assert!(system.apply_event(event(2)).is_err());
+    assert_eq!(*system.current_state(), TrafficLight::Red);
+    assert_eq!(clones.get(), 0);
+    assert_eq!(system.get_history().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_actions_run_once_per_applied_transition() -> Result<(), TransitionError> {
+    let counter = Rc::new(Cell::new(0_u32));
+    let action_counter = Rc::clone(&counter);
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Red)
+            .to(TrafficLight::Green)
+            .on_event(TrafficEvent::Timer)
+            .action(move |_, _| action_counter.set(action_counter.get().saturating_add(1)))
+            .build()?,
+    );
+
+    // Checking validity must not run the action
+    assert!(system.can_transition(&TrafficEvent::Timer));
+    assert_eq!(counter.get(), 0);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(counter.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_possible_transitions() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(
+        TrafficLight::Red,
+        TrafficEvent::Emergency,
+        TrafficLight::Red,
+    )?);
+
+    let all_events = [TrafficEvent::Timer, TrafficEvent::Emergency, TrafficEvent::Reset];
+    assert_eq!(
+        system.possible_transitions(&all_events),
+        vec![TrafficEvent::Timer, TrafficEvent::Emergency]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_target_queries_do_not_apply() -> Result<(), TransitionError> {
+    let actions = Rc::new(Cell::new(0_u32));
+    let counter = Rc::clone(&actions);
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Red)
+            .to(TrafficLight::Green)
+            .on_event(TrafficEvent::Timer)
+            .action(move |_, _| counter.set(counter.get().saturating_add(1)))
+            .build()?,
+    );
+    system.register_transition(TransitionBuilder::new().to(TrafficLight::Yellow).build()?);
+    system.register_transition(TypedTransition::<_, _, (), (), _>::new(
+        |state: &TrafficLight, event: &TrafficEvent| match (state, event) {
+            (TrafficLight::Red, TrafficEvent::Timer) => Ok(TrafficLight::Red),
+            _ => Err(TransitionError::InvalidTransition),
+        },
+    ));
+
+    assert_eq!(system.can_reach_with(&TrafficEvent::Timer), Some(TrafficLight::Green));
+    assert_eq!(
+        system.targets_of(&TrafficEvent::Timer),
+        vec![TrafficLight::Green, TrafficLight::Yellow, TrafficLight::Red]
+    );
+    assert_eq!(system.can_reach_with(&TrafficEvent::Reset), Some(TrafficLight::Yellow));
+    assert_eq!(*system.current_state(), TrafficLight::Red);
+    assert_eq!((system.sequence(), actions.get()), (0, 0));
+
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(system.targets_of(&TrafficEvent::Timer), vec![TrafficLight::Yellow]);
+    Ok(())
+}
+
+#[test]
+fn test_to_dot() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Green)
+            .to(TrafficLight::Yellow)
+            .on_event(TrafficEvent::Timer)
+            .named_guard("crossing_clear", |_, _| Ok(()))
+            .guard(|_, _| Ok(()))
+            .build()?,
+    );
+    system.register_transition(
+        TransitionBuilder::new().to_fn(|state: &TrafficLight, _| state.clone()).build()?,
+    );
+    system.apply_event(TrafficEvent::Timer)?;
+
+    let dot = system.to_dot();
+    assert!(dot.starts_with("digraph \"TransitionSystem\" {"));
+    assert!(dot.contains("s0 [label=\"Green\", fillcolor=\"palegreen\", peripheries=2];"));
+    assert!(dot.contains("s1 [label=\"Red\"];"));
+    assert!(dot.contains("s1 -> s0 [label=\"Timer\", color=\"red\", penwidth=2.0];"));
+    assert!(dot.contains("s0 -> s2 [label=\"Timer [crossing_clear, guard]\", color=\"black\"];"));
+    assert!(dot.contains("any -> computed [label=\"any event\", color=\"black\"];"));
+    Ok(())
+}
+
+#[test]
+fn test_state_visualization() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(
+        TrafficLight::Green,
+        TrafficEvent::Timer,
+        TrafficLight::Yellow,
+    )?);
+    // Computed targets have no edge to draw
+    system.register_transition(
+        TransitionBuilder::new().to_fn(|state: &TrafficLight, _| state.clone()).build()?,
+    );
+    system.add_timing_constraint(TrafficLight::Yellow, Duration::from_secs(5), TrafficEvent::Reset);
+    system.apply_event(TrafficEvent::Timer)?;
+
+    assert_eq!(
+        system.states().as_ref(),
+        [TrafficLight::Red, TrafficLight::Green, TrafficLight::Yellow]
+    );
+    assert_eq!(system.current_state_idx(), 1);
+
+    let mermaid = StateVisualization::generate_mermaid(&system, true);
+    assert!(mermaid.contains("    s0: Red\n"));
+    assert!(mermaid.contains("    s0 --> s1: Timer ✔\n"));
+    assert!(mermaid.contains("    s1 --> s2: Timer\n"));
+    assert!(mermaid.contains("    class s1 current\n"));
+
+    let dot = StateVisualization::generate_dot(&system, false);
+    assert!(dot.starts_with("digraph \"TransitionSystem\" {"));
+    assert!(dot.contains(r#"s1 [label="Green", fillcolor="palegreen", peripheries=2];"#));
+    assert_eq!(StateVisualization::generate_dot_frames(&system).len(), 2);
+
+    let timeline = StateVisualization::timeline(&system);
+    assert_eq!(timeline.len(), 1);
+    assert_eq!(timeline.first().map(|segment| &segment.state), Some(&TrafficLight::Green));
+
+    let stats = StateVisualization::stats(&system);
+    assert_eq!(stats.state_count, 3);
+    assert_eq!(stats.transition_count, 2);
+    assert_eq!(stats.current_state, TrafficLight::Green);
+    Ok(())
+}
+
+#[test]
+fn test_incomplete_transition() {
+    let missing_target = TransitionBuilder::<TrafficLight, TrafficEvent>::new()
+        .on_event(TrafficEvent::Timer)
+        .build();
+    assert_eq!(missing_target.err(), Some(TransitionError::Incomplete("target state")));
+}
+
+#[test]
+fn test_transition_without_event() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Green);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Green)
+            .to(TrafficLight::Red)
+            .guard(|_, event| match event {
+                TrafficEvent::Timer => Err("Timers don't stop a green light".to_string()),
+                _ => Ok(()),
+            })
+            .build()?,
+    );
+
+    assert!(!system.can_transition(&TrafficEvent::Timer));
+    assert_eq!(*system.apply_event(TrafficEvent::Reset)?, TrafficLight::Red);
+    Ok(())
+}
+
+#[test]
+fn test_typed_transition() {
+    /// Marker for the red light
+    struct Red;
+    /// Marker for the green light
+    struct Green;
+
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(TypedTransition::<_, _, Red, Green, _>::new(
+        |state: &TrafficLight, event: &TrafficEvent| match (state, event) {
+            (TrafficLight::Red, TrafficEvent::Timer) => Ok(TrafficLight::Green),
+            _ => Err(TransitionError::InvalidTransition),
+        },
+    ));
+
+    assert!(system.can_transition(&TrafficEvent::Timer));
+    assert!(!system.can_transition(&TrafficEvent::Reset));
+    assert_eq!(system.apply_event(TrafficEvent::Timer).cloned(), Ok(TrafficLight::Green));
+}
+
+/// States of a document in a publishing workflow
+#[derive(Debug, Clone, PartialEq)]
+enum DocumentState {
+    /// Being written
+    Draft,
+    /// Waiting for a reviewer
+    Review,
+    /// Accepted by the reviewer
+    Approved,
+    /// Visible to readers
+    Published,
+    /// Sent back to the author
+    Rejected,
+}
+
+/// Events of the publishing workflow
+#[derive(Debug, Clone, PartialEq)]
+enum DocumentEvent {
+    /// The author submits the draft
+    Submit,
+    /// The reviewer accepts the document
+    Approve,
+    /// The reviewer sends the document back
+    Reject,
+    /// The document goes live
+    Publish,
+    /// The author starts a new draft
+    Revise,
+}
+
+#[test]
+fn test_document_workflow() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(DocumentState::Draft);
+    for (from, event, to) in [
+        (DocumentState::Draft, DocumentEvent::Submit, DocumentState::Review),
+        (DocumentState::Review, DocumentEvent::Approve, DocumentState::Approved),
+        (DocumentState::Review, DocumentEvent::Reject, DocumentState::Rejected),
+        (DocumentState::Approved, DocumentEvent::Publish, DocumentState::Published),
+        (DocumentState::Rejected, DocumentEvent::Revise, DocumentState::Draft),
+    ] {
+        system.register_transition(
+            TransitionBuilder::new().from(from).to(to).on_event(event).build()?,
+        );
+    }
+    let all_events = [
+        DocumentEvent::Submit,
+        DocumentEvent::Approve,
+        DocumentEvent::Reject,
+        DocumentEvent::Publish,
+        DocumentEvent::Revise,
+    ];
+
+    system.apply_event(DocumentEvent::Submit)?;
+    assert_eq!(
+        system.possible_transitions(&all_events),
+        vec![DocumentEvent::Approve, DocumentEvent::Reject]
+    );
+    system.apply_event(DocumentEvent::Approve)?;
+    assert_eq!(*system.apply_event(DocumentEvent::Publish)?, DocumentState::Published);
+
+    // A published document can't be revised
+    assert!(system.possible_transitions(&all_events).is_empty());
+    assert_eq!(system.apply_event(DocumentEvent::Revise), Err(TransitionError::InvalidTransition));
+    Ok(())
+}
+
+#[test]
+fn test_history() -> Result<(), TransitionError> {
+    let clock = MockClock::new();
+    let start = clock.now();
+    let mut system = TransitionSystem::with_clock(TrafficLight::Red, Arc::new(clock.clone()));
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Timer, TrafficLight::Red)?);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    clock.advance(Duration::from_secs(30));
+    system.apply_event(TrafficEvent::Timer)?;
+    // Rejected events are not recorded
+    assert!(system.apply_event(TrafficEvent::Reset).is_err());
+
+    let history = system.get_history();
+    assert_eq!(history.len(), 2);
+    let first = history.first().ok_or(TransitionError::InvalidTransition)?;
+    assert_eq!(
+        (&first.from, &first.event, &first.to, first.sequence),
+        (&TrafficLight::Red, &TrafficEvent::Timer, &TrafficLight::Green, 1)
+    );
+    assert_eq!(*first.timestamp.inner(), start);
+    let second = history.get(1).ok_or(TransitionError::InvalidTransition)?;
+    assert_eq!(second.timestamp.inner().duration_since(start), Duration::from_secs(30));
+    assert_eq!(system.sequence(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_history_is_bounded() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Timer, TrafficLight::Red)?);
+    system.set_max_history_size(3);
+
+    for _ in 0..5 {
+        system.apply_event(TrafficEvent::Timer)?;
+    }
+    let sequences: Vec<_> = system.get_history().iter().map(|record| record.sequence).collect();
+    assert_eq!(sequences, vec![3, 4, 5]);
+
+    system.set_max_history_size(1);
+    assert_eq!(system.get_history().len(), 1);
+    assert_eq!(system.sequence(), 5);
+    Ok(())
+}
+
+/// Observer describing the transitions it is notified of
+#[derive(Debug, Default)]
+struct RecordingObserver(Rc<RefCell<Vec<String>>>);
+
+impl Observer<TrafficLight, TrafficEvent> for RecordingObserver {
+    fn on_state_change(&self, _from: &TrafficLight, _to: &TrafficLight, _event: &TrafficEvent) {}
+
+    fn on_transition(&self, record: &TransitionRecord<TrafficLight, TrafficEvent>) {
+        self.0.borrow_mut().push(format!(
+            "#{} {:?} --{:?}--> {:?}",
+            record.sequence, record.from, record.event, record.to
+        ));
+    }
+}
+
+#[test]
+fn test_timing_constraints() -> Result<(), Box<dyn std::error::Error>> {
+    let clock = MockClock::new();
+    let mut system = TransitionSystem::with_clock(TrafficLight::Red, Arc::new(clock.clone()));
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Timer, TrafficLight::Red)?);
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Reset, TrafficLight::Red)?);
+    system.register_transition(light(
+        TrafficLight::Red,
+        TrafficEvent::Emergency,
+        TrafficLight::Red,
+    )?);
+    system.add_timing_constraint(TrafficLight::Green, Duration::from_mins(1), TrafficEvent::Reset);
+    assert_eq!(system.get_timing_constraints().len(), 1);
+    assert_eq!(system.time_until_timeout(), None);
+
+    system.apply_event(TrafficEvent::Timer)?;
+    clock.advance(Duration::from_secs(40));
+    assert_eq!(system.time_in_current_state(), Duration::from_secs(40));
+    assert_eq!(system.time_until_timeout(), Some(Duration::from_secs(20)));
+    assert_eq!(system.fire_timeout_if_due()?, None);
+
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(system.time_until_timeout(), Some(Duration::ZERO));
+    assert_eq!(system.fire_timeout_if_due()?, Some(&TrafficLight::Red));
+    assert_eq!(system.time_in_current_state(), Duration::ZERO);
+    let last = system.get_history().last().map(|record| record.event.clone());
+    assert_eq!(last, Some(TrafficEvent::Reset));
+
+    // A due timeout fires before the next event is applied
+    system.apply_event(TrafficEvent::Timer)?;
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(system.apply_event(TrafficEvent::Emergency)?, &TrafficLight::Red);
+    let events: Vec<_> = system.get_history().iter().map(|record| record.event.clone()).collect();
+    assert_eq!(
+        events,
+        [
+            TrafficEvent::Timer,
+            TrafficEvent::Reset,
+            TrafficEvent::Timer,
+            TrafficEvent::Reset,
+            TrafficEvent::Emergency,
+        ]
+    );
+
+    // Adding a constraint for the same state replaces it
+    system.add_timing_constraint(TrafficLight::Green, Duration::from_secs(5), TrafficEvent::Timer);
+    let constraints = system.get_timing_constraints();
+    assert_eq!(constraints.len(), 1);
+    assert_eq!(constraints.first().map(|(_, c)| c.max_duration), Some(Duration::from_secs(5)));
+
+    // Constraints survive saving and loading
+    let loaded = TransitionSystem::from_serializable_state(
+        system.to_serializable_state()?,
+        &Behaviors::new(),
+    )?;
+    assert_eq!(loaded.get_timing_constraints(), system.get_timing_constraints());
+    Ok(())
+}
+
+#[test]
+fn test_async_apply_fires_due_timeout() -> Result<(), TransitionError> {
+    let clock = MockClock::new();
+    let mut system = TransitionSystem::with_clock(TrafficLight::Green, Arc::new(clock.clone()));
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Reset, TrafficLight::Red)?);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.add_timing_constraint(TrafficLight::Green, Duration::from_mins(1), TrafficEvent::Reset);
+
+    // Without the timeout, Timer is not accepted in Green
+    assert!(block_on(system.apply_event_async(TrafficEvent::Timer)).is_err());
+    clock.advance(Duration::from_secs(90));
+    assert_eq!(block_on(system.apply_event_async(TrafficEvent::Timer))?, &TrafficLight::Green);
+    assert_eq!(system.get_history().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_observers() -> Result<(), TransitionError> {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+    system.register_transition(light(TrafficLight::Green, TrafficEvent::Timer, TrafficLight::Red)?);
+    let id = system.register_observer(Box::new(RecordingObserver(Rc::clone(&seen))));
+    system.register_observer(Box::new(TransitionLogger));
+
+    system.apply_event(TrafficEvent::Timer)?;
+    assert!(system.apply_event(TrafficEvent::Reset).is_err());
+    assert_eq!(*seen.borrow(), vec!["#1 Red --Timer--> Green".to_string()]);
+
+    assert!(system.unregister_observer(id).is_some());
+    assert!(system.unregister_observer(id).is_none());
+    system.apply_event(TrafficEvent::Timer)?;
+    assert_eq!(seen.borrow().len(), 1);
+    Ok(())
+}
+
+/// Library book states, with patron names
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum Book {
+    /// On the shelf
+    Available,
+    /// Held for a patron
+    Reserved(String),
+}
+
+/// Events of a library book, with patron names
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum BookAction {
+    /// A patron reserves the book
+    Reserve(String),
+    /// The reservation is cancelled
+    Cancel,
+}
+
+#[test]
+fn test_computed_target() -> Result<(), Box<dyn std::error::Error>> {
+    let mut system = TransitionSystem::new(Book::Available);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(Book::Available)
+            .guard(|_, event| match event {
+                BookAction::Reserve(_) => Ok(()),
+                BookAction::Cancel => Err("Only reservations".to_string()),
+            })
+            .to_fn(|state, event| match event {
+                BookAction::Reserve(name) => Book::Reserved(name.clone()),
+                BookAction::Cancel => state.clone(),
+            })
+            .build()?,
+    );
+    system.register_transition(
+        TransitionBuilder::new()
+            .to(Book::Available)
+            .on_event(BookAction::Cancel)
+            .guard(|state, _| match state {
+                Book::Reserved(_) => Ok(()),
+                Book::Available => Err("Nothing to cancel".to_string()),
+            })
+            .build()?,
+    );
+
+    assert_eq!(
+        *system.apply_event(BookAction::Reserve("Alice".into()))?,
+        Book::Reserved("Alice".into())
+    );
+    system.apply_event(BookAction::Cancel)?;
+    assert_eq!(
+        *system.apply_event(BookAction::Reserve("Bob".into()))?,
+        Book::Reserved("Bob".into())
+    );
+
+    // Computed targets can't be described
+    assert!(system.to_serializable_state().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_event_matchers() -> Result<(), Box<dyn std::error::Error>> {
+    let mut system = TransitionSystem::new(Book::Available);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(Book::Available)
+            .to(Book::Reserved("someone".into()))
+            .on_variant(BookAction::Reserve(String::new()))
+            .build()?,
+    );
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(Book::Reserved("someone".into()))
+            .to(Book::Available)
+            .on_event(BookAction::Cancel)
+            .build()?,
+    );
+
+    // Any reservation matches the variant, whoever makes it
+    assert!(system.can_transition(&BookAction::Reserve("Alice".into())));
+    assert!(!system.can_transition(&BookAction::Cancel));
+    system.apply_event(BookAction::Reserve("Bob".into()))?;
+    system.apply_event(BookAction::Cancel)?;
+
+    // Variant matchers survive saving and loading
+    let state = system.to_serializable_state()?;
+    let descriptor = state.transitions.first().cloned();
+    assert_eq!(descriptor.map(|d| d.match_variant), Some(true));
+    let mut loaded = TransitionSystem::from_serializable_state(state, &Behaviors::new())?;
+    assert!(loaded.can_transition(&BookAction::Reserve("Carol".into())));
+    assert!(loaded.to_dot().contains("[label=\"Reserve(..)\""));
+
+    // Predicates can't be saved
+    loaded.register_transition(
+        TransitionBuilder::new()
+            .to(Book::Available)
+            .on_event_matching(
+                "reservation by staff",
+                |event| matches!(event, BookAction::Reserve(name) if name.starts_with("staff-")),
+            )
+            .build()?,
+    );
+    assert!(loaded.to_dot().contains("[label=\"reservation by staff\""));
+    assert!(loaded.to_serializable_state().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_event_matcher_matches() {
+    let reserve = |name: &str| BookAction::Reserve(name.to_string());
+    let exact = EventMatcher::Exact(reserve("Alice"));
+    assert!(exact.matches(&reserve("Alice")) && !exact.matches(&reserve("Bob")));
+    let variant = EventMatcher::Variant(reserve("Alice"));
+    assert!(variant.matches(&reserve("Bob")) && !variant.matches(&BookAction::Cancel));
+    assert!(EventMatcher::Any.matches(&BookAction::Cancel));
+    assert_eq!(variant.variant(), exact.variant());
+    assert_eq!(EventMatcher::<BookAction>::Any.variant(), None);
+    assert_eq!(EventMatcher::Variant(BookAction::Cancel).label(), "Cancel");
+}
+
+/// Build a transition between two named states
+fn step(
+    from: &str,
+    event: &str,
+    to: &str,
+) -> Result<GuardedTransition<String, String>, TransitionError> {
+    TransitionBuilder::new()
+        .from(from.to_string())
+        .to(to.to_string())
+        .on_event(event.to_string())
+        .build()
+}
+
+#[test]
+fn test_hierarchical_states() -> Result<(), Box<dyn std::error::Error>> {
+    let mut system = TransitionSystem::new("Editing".to_string());
+    system.set_parent("Editing".to_string(), "InProgress".to_string());
+    system.set_parent("Reviewing".to_string(), "InProgress".to_string());
+    system.register_transition(step("Editing", "Submit", "Reviewing")?);
+    // Declared once on the super-state, shared by both sub-states
+    system.register_transition(step("InProgress", "Cancel", "Cancelled")?);
+    // Overrides the shared transition in one sub-state
+    system.register_transition(step("Reviewing", "Cancel", "Editing")?);
+
+    assert!(system.is_in(&"InProgress".to_string()));
+    assert_eq!(system.parent_of(&"Editing".to_string()), Some(&"InProgress".to_string()));
+    assert_eq!(system.can_reach_with(&"Cancel".to_string()), Some("Cancelled".to_string()));
+
+    system.apply_event("Submit".to_string())?;
+    assert_eq!(
+        system.targets_of(&"Cancel".to_string()),
+        vec!["Editing".to_string(), "Cancelled".to_string()]
+    );
+    system.apply_event("Cancel".to_string())?;
+    assert_eq!(system.current_state(), "Editing");
+
+    // The history records the actual state left, and the parents are saved
+    system.apply_event("Cancel".to_string())?;
+    let last = system.get_history().last().map(|record| (record.from.clone(), record.to.clone()));
+    assert_eq!(last, Some(("Editing".to_string(), "Cancelled".to_string())));
+    assert!(!system.is_in(&"InProgress".to_string()));
+    let loaded = TransitionSystem::from_serializable_state(
+        system.to_serializable_state()?,
+        &Behaviors::new(),
+    )?;
+    assert_eq!(loaded.parent_of(&"Reviewing".to_string()), Some(&"InProgress".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_parent_cycles_are_ignored() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new("A".to_string());
+    system.set_parent("A".to_string(), "B".to_string());
+    system.set_parent("B".to_string(), "A".to_string());
+    assert!(!system.can_transition(&"Go".to_string()));
+    system.register_transition(step("B", "Go", "C")?);
+    system.apply_event("Go".to_string())?;
+    assert_eq!(system.current_state(), "C");
+    Ok(())
+}
+
+/// Build a document workflow whose review step is guarded and counted by name
+fn saveable_workflow(
+    reviews: &Rc<Cell<u32>>,
+) -> Result<TransitionSystem<String, String>, TransitionError> {
+    let reviews = Rc::clone(reviews);
+    let mut system = TransitionSystem::new("Draft".to_string());
+    system.register_transition(
+        TransitionBuilder::new()
+            .from("Draft".to_string())
+            .to("Review".to_string())
+            .on_event("Submit".to_string())
+            .named_guard("has_title", |_, _| Ok(()))
+            .named_action("count_reviews", move |_, _| reviews.set(reviews.get().saturating_add(1)))
+            .build()?,
+    );
+    system.register_transition(
+        TransitionBuilder::new()
+            .from("Review".to_string())
+            .to("Draft".to_string())
+            .on_event("Reject".to_string())
+            .build()?,
+    );
+    Ok(system)
+}
+
+#[test]
+fn test_save_and_load() -> Result<(), Box<dyn std::error::Error>> {
+    let reviews = Rc::new(Cell::new(0_u32));
+    let mut system = saveable_workflow(&reviews)?;
+    system.apply_event("Submit".to_string())?;
+    system.apply_event("Reject".to_string())?;
+
+    let path = std::env::temp_dir().join(format!("generic-save-test-{}.json", std::process::id()));
+    system.save_to_file(&path)?;
+    let loaded_reviews = Rc::new(Cell::new(0_u32));
+    let counter = Rc::clone(&loaded_reviews);
+    let behaviors = Behaviors::new().guard("has_title", |_: &String, _: &String| Ok(())).action(
+        "count_reviews",
+        move |_: &String, _: &String| {
+            counter.set(counter.get().saturating_add(1));
+        },
+    );
+    let loaded = TransitionSystem::load_from_file(&path, &behaviors);
+    std::fs::remove_file(&path).ok();
+    let mut loaded = loaded?;
+
+    assert_eq!(loaded.current_state(), "Draft");
+    assert_eq!(loaded.sequence(), 2);
+    let events: Vec<_> = loaded.get_history().iter().map(|record| record.event.clone()).collect();
+    assert_eq!(events, vec!["Submit".to_string(), "Reject".to_string()]);
+
+    // The re-attached action runs on the loaded system
+    loaded.apply_event("Submit".to_string())?;
+    assert_eq!(loaded.sequence(), 3);
+    assert_eq!((reviews.get(), loaded_reviews.get()), (1, 1));
+    Ok(())
+}
+
+#[test]
+fn test_save_requires_named_behaviors() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(TrafficLight::Red)
+            .to(TrafficLight::Green)
+            .on_event(TrafficEvent::Timer)
+            .guard(|_, _| Ok(()))
+            .build()?,
+    );
+    assert!(matches!(system.to_serializable_state(), Err(LibraryError::PersistenceError(_))));
+    Ok(())
+}
+
+#[test]
+fn test_load_requires_registered_behaviors() -> Result<(), Box<dyn std::error::Error>> {
+    let state = saveable_workflow(&Rc::new(Cell::new(0)))?.to_serializable_state()?;
+    let descriptor = state.transitions.first().cloned();
+    assert_eq!(
+        descriptor.map(|d| (d.guards, d.actions)),
+        Some((vec!["has_title".to_string()], vec!["count_reviews".to_string()]))
+    );
+
+    let behaviors = Behaviors::new().guard("has_title", |_: &String, _: &String| Ok(()));
+    let loaded = TransitionSystem::from_serializable_state(state, &behaviors);
+    assert!(
+        matches!(loaded, Err(LibraryError::LoadError(message)) if message.contains("count_reviews"))
+    );
+    Ok(())
+}
+
+/// Guard passing when the state equals `expected`
+fn state_is(name: &str, expected: &'static str) -> Guard<String, String> {
+    Guard::named(
+        name,
+        move |state: &String, _: &String| {
+            if state == expected { Ok(()) } else { Err(format!("state is {state}")) }
+        },
+    )
+}
+
+#[test]
+fn test_guard_combinators() {
+    let draft = || state_is("is_draft", "Draft");
+    let review = || state_is("is_review", "Review");
+    let (draft_state, review_state, event) =
+        ("Draft".to_string(), "Review".to_string(), String::new());
+
+    let both = draft().and(review());
+    assert_eq!(both.name(), Some("is_draft and is_review"));
+    assert_eq!(both.check(&draft_state, &event), Err("is_review: state is Draft".to_string()));
+
+    let either = draft().or(review());
+    assert_eq!(either.name(), Some("is_draft or is_review"));
+    assert_eq!(either.check(&review_state, &event), Ok(()));
+    assert_eq!(
+        either.check(&"Published".to_string(), &event),
+        Err("is_draft: state is Published; is_review: state is Published".to_string())
+    );
+
+    let neither = !either;
+    assert_eq!(neither.name(), Some("not (is_draft or is_review)"));
+    assert_eq!(neither.check(&"Published".to_string(), &event), Ok(()));
+    assert_eq!(
+        neither.check(&draft_state, &event),
+        Err("is_draft or is_review passed".to_string())
+    );
+
+    // A name is only derived from named operands
+    let unnamed = Guard::new(|_: &String, _: &String| Ok(()));
+    assert_eq!(draft().and(unnamed.clone()).name(), None);
+    assert_eq!((!unnamed.clone()).check(&draft_state, &event), Err("Negated guard passed".into()));
+    assert_eq!(unnamed.with_name("always").name(), Some("always"));
+}
+
+#[test]
+fn test_combined_guard_reports_failure() -> Result<(), Box<dyn std::error::Error>> {
+    let transition = TransitionBuilder::new()
+        .to("Published".to_string())
+        .on_event("Publish".to_string())
+        .when(state_is("is_review", "Review").and(!state_is("is_locked", "Locked")))
+        .build()?;
+    assert_eq!(
+        transition.apply(&"Draft".to_string(), &"Publish".to_string()),
+        Err(TransitionError::GuardFailed("is_review: state is Draft".to_string()))
+    );
+
+    // The combined guard is saved under its name and loaded from the registry
+    let mut system = TransitionSystem::new("Draft".to_string());
+    system.register_transition(transition);
+    let state = system.to_serializable_state()?;
+    let descriptor = state.transitions.first().cloned();
+    assert_eq!(descriptor.map(|d| d.guards), Some(vec!["is_review and not is_locked".to_string()]));
+    let behaviors = Behaviors::new()
+        .register_guard(state_is("is_review", "Review").and(!state_is("is_locked", "Locked")));
+    let mut loaded = TransitionSystem::from_serializable_state(state, &behaviors)?;
+    assert!(!loaded.can_transition(&"Publish".to_string()));
+    assert!(loaded.apply_event("Publish".to_string()).is_err());
+    Ok(())
+}
+
+crate::typestate! {
+    machine Ticket(Vec<String>) {
+        states: TicketState { Open, InProgress, Closed },
+        events: TicketEvent { Start, Resolve, Reopen },
+        initial: Open,
+        transitions: {
+            Open --Start--> InProgress => start,
+            InProgress --Resolve--> Closed => resolve,
+            Closed --Reopen--> Open => reopen,
+        },
+    }
+}
+
+#[test]
+fn test_typestate_machine() {
+    let ticket = Ticket::new(vec!["Crash on save".to_string()]);
+    assert_eq!(ticket.state(), TicketState::Open);
+    let ticket = ticket.start().resolve();
+    assert_eq!(ticket.state(), TicketState::Closed);
+    let ticket = ticket.reopen();
+    assert_eq!(ticket.state(), TicketState::Open);
+    assert_eq!(ticket.into_data(), vec!["Crash on save".to_string()]);
+}
+
+#[test]
+fn test_typestate_runtime_counterpart() -> Result<(), TransitionError> {
+    let ticket = Ticket::new(Vec::new()).start();
+    let mut system = ticket.system()?;
+    assert_eq!(*system.current_state(), TicketState::InProgress);
+    assert_eq!(system.apply_event(TicketEvent::Start), Err(TransitionError::InvalidTransition));
+    system.apply_event(TicketEvent::Resolve)?;
+    system.apply_event(TicketEvent::Reopen)?;
+    assert_eq!(*system.current_state(), TicketState::Open);
+    assert_eq!(
+        system.possible_transitions(&[TicketEvent::Start, TicketEvent::Resolve]),
+        vec![TicketEvent::Start]
+    );
+    Ok(())
+}
+
+/// Events driving a [`Loan`]
+#[cfg(feature = "derive")]
+#[derive(Debug, Clone, PartialEq)]
+enum LoanEvent {
+    /// Lend the item to someone
+    Lend(String),
+    /// Give the item back
+    Return,
+    /// The item is lost
+    Lose,
+}
+
+/// An item that can be lent, with its transitions declared on the type
+#[cfg(feature = "derive")]
+#[derive(Debug, Clone, PartialEq, StateMachine)]
+#[state_machine(event = LoanEvent)]
+#[transition(from = Lent("Alice"), on = Return, to = Available)]
+#[transition(from = [Available, Lent("Alice")], on = Lose, to = Lost)]
+enum Loan {
+    /// On the shelf
+    #[transition(on = Lend("Alice"), to = Lent("Alice"))]
+    Available,
+    /// Lent to someone
+    Lent(String),
+    /// Gone for good
+    Lost,
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn test_derived_state_machine() -> Result<(), TransitionError> {
+    let alice = || "Alice".to_string();
+    assert_eq!(
+        Loan::transitions(),
+        vec![
+            (Loan::Lent(alice()), LoanEvent::Return, Loan::Available),
+            (Loan::Available, LoanEvent::Lose, Loan::Lost),
+            (Loan::Lent(alice()), LoanEvent::Lose, Loan::Lost),
+            (Loan::Available, LoanEvent::Lend(alice()), Loan::Lent(alice())),
+        ]
+    );
+
+    let mut system = Loan::transition_system(Loan::Available)?;
+    system.apply_event(LoanEvent::Lend(alice()))?;
+    assert_eq!(*system.current_state(), Loan::Lent(alice()));
+    assert!(!system.can_transition(&LoanEvent::Lend(alice())));
+    system.apply_event(LoanEvent::Return)?;
+    system.apply_event(LoanEvent::Lose)?;
+    assert_eq!(*system.current_state(), Loan::Lost);
+    Ok(())
+}
+
+/// Run a future to completion on the current thread
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Future that is pending once before completing, like a remote call
+#[derive(Debug, Default)]
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _context: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+fn test_async_guards_and_actions() -> Result<(), TransitionError> {
+    let permitted = Rc::new(Cell::new(false));
+    let service = Rc::clone(&permitted);
+    let notified = Rc::new(RefCell::new(Vec::new()));
+    let outbox = Rc::clone(&notified);
+
+    let mut system = TransitionSystem::new(DocumentState::Review);
+    system.register_transition(
+        TransitionBuilder::new()
+            .from(DocumentState::Review)
+            .to(DocumentState::Approved)
+            .on_event(DocumentEvent::Approve)
+            .async_guard(move |_, _| {
+                let service = Rc::clone(&service);
+                Box::pin(async move {
+                    YieldOnce::default().await;
+                    if service.get() {
+                        Ok(())
+                    } else {
+                        Err("Reviewer lacks permission".to_string())
+                    }
+                })
+            })
+            .async_action(move |from, event| {
+                let outbox = Rc::clone(&outbox);
+                Box::pin(async move {
+                    YieldOnce::default().await;
+                    outbox.borrow_mut().push(format!("{event:?} from {from:?}"));
+                })
+            })
+            .build()?,
+    );
+
+    // Async guards can't be checked synchronously
+    assert_eq!(system.apply_event(DocumentEvent::Approve), Err(TransitionError::AsyncRequired));
+    assert_eq!(
+        block_on(system.apply_event_async(DocumentEvent::Approve)),
+        Err(TransitionError::GuardFailed("Reviewer lacks permission".to_string()))
+    );
+    assert!(notified.borrow().is_empty());
+
+    permitted.set(true);
+    assert_eq!(
+        block_on(system.apply_event_async(DocumentEvent::Approve)).cloned(),
+        Ok(DocumentState::Approved)
+    );
+    assert_eq!(*notified.borrow(), vec!["Approve from Review".to_string()]);
+    assert_eq!(system.get_history().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_apply_event_async_with_sync_transitions() -> Result<(), TransitionError> {
+    let mut system = TransitionSystem::new(TrafficLight::Red);
+    system.register_transition(light(TrafficLight::Red, TrafficEvent::Timer, TrafficLight::Green)?);
+
+    assert_eq!(
+        block_on(system.apply_event_async(TrafficEvent::Timer)).cloned(),
+        Ok(TrafficLight::Green)
+    );
+    assert_eq!(
+        block_on(system.apply_event_async(TrafficEvent::Timer)),
+        Err(TransitionError::InvalidTransition)
+    );
+    Ok(())
+}
+
+/// States of an order handled by a web service
+#[derive(Debug, Clone, PartialEq)]
+enum Order {
+    /// Waiting for payment
+    Pending,
+    /// Paid for the given amount
+    Paid(u32),
+    /// Sent to the customer
+    Shipped,
+}
+
+/// Events of an order
+#[derive(Debug, Clone, PartialEq)]
+enum OrderEvent {
+    /// The customer paid the given amount
+    Pay(u32),
+    /// The order left the warehouse
+    Ship,
+}
+
+/// Build a system taking payments of at least 10 and shipping paid orders
+fn order_system(
+    payments: &Arc<AtomicU32>,
+) -> Result<TransitionSystem<Order, OrderEvent, Shared>, TransitionError> {
+    let payments = Arc::clone(payments);
+    let mut system = TransitionSystem::shared(Order::Pending);
+    system.register_transition(
+        TransitionBuilder::shared()
+            .from(Order::Pending)
+            .on_variant(OrderEvent::Pay(0))
+            .to_fn(|_, event| match event {
+                OrderEvent::Pay(amount) => Order::Paid(*amount),
+                OrderEvent::Ship => Order::Pending,
+            })
+            .guard(|_, event| match event {
+                OrderEvent::Pay(amount) if *amount >= 10 => Ok(()),
+                _ => Err("Minimum payment is 10".to_string()),
+            })
+            .action(move |_, _| {
+                payments.fetch_add(1, Ordering::SeqCst);
+            })
+            .build()?,
+    );
+    system.register_transition(TypedTransition::<_, _, (), (), _>::new(
+        |state: &Order, event: &OrderEvent| match (state, event) {
+            (Order::Paid(_), OrderEvent::Ship) => Ok(Order::Shipped),
+            _ => Err(TransitionError::InvalidTransition),
+        },
+    ));
+    Ok(system)
+}
+
+/// Fail to compile unless the type can be shared between threads
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_shared_system_is_send_and_sync() {
+    assert_send_sync::<TransitionSystem<Order, OrderEvent, Shared>>();
+    assert_send_sync::<GuardedTransition<Order, OrderEvent, Shared>>();
+    assert_send_sync::<TransitionBuilder<Order, OrderEvent, Shared>>();
+    assert_send_sync::<Guard<Order, OrderEvent, Shared>>();
+    assert_send_sync::<Behaviors<Order, OrderEvent, Shared>>();
+}
+
+#[test]
+fn test_shared_transitions() -> Result<(), TransitionError> {
+    let payments = Arc::new(AtomicU32::new(0));
+    let mut system = order_system(&payments)?;
+
+    assert_eq!(system.can_reach_with(&OrderEvent::Pay(25)), Some(Order::Paid(25)));
+    assert!(!system.can_transition(&OrderEvent::Pay(5)));
+    assert!(!system.can_transition(&OrderEvent::Ship));
+    // Checking validity doesn't run actions
+    assert_eq!(payments.load(Ordering::SeqCst), 0);
+
+    system.apply_event(OrderEvent::Pay(25))?;
+    assert_eq!(system.apply_event(OrderEvent::Ship)?, &Order::Shipped);
+    assert_eq!(payments.load(Ordering::SeqCst), 1);
+    assert_eq!(system.sequence(), 2);
+    let events: Vec<_> = system.get_history().iter().map(|record| record.event.clone()).collect();
+    assert_eq!(events, [OrderEvent::Pay(25), OrderEvent::Ship]);
+    Ok(())
+}
+
+/// Fail to compile unless the value can be sent to another thread
+fn assert_send<T: Send>(_: &T) {}
+
+#[test]
+fn test_shared_async_transitions_are_send() -> Result<(), TransitionError> {
+    let shipped = Arc::new(AtomicU32::new(0));
+    let counter = Arc::clone(&shipped);
+    let payments = Arc::new(AtomicU32::new(0));
+    let mut system = order_system(&payments)?;
+    system.register_transition(
+        TransitionBuilder::shared()
+            .from(Order::Shipped)
+            .to(Order::Pending)
+            .on_event(OrderEvent::Ship)
+            .async_guard(|_, _| {
+                Box::pin(async {
+                    YieldOnce::default().await;
+                    Ok(())
+                })
+            })
+            .async_action(move |_, _| {
+                let counter = Arc::clone(&counter);
+                Box::pin(async move {
+                    YieldOnce::default().await;
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+            })
+            .build()?,
+    );
+    system.apply_event(OrderEvent::Pay(25))?;
+    system.apply_event(OrderEvent::Ship)?;
+
+    let future = system.apply_event_async(OrderEvent::Ship);
+    assert_send(&future);
+    assert_eq!(block_on(future).cloned(), Ok(Order::Pending));
+    assert_eq!(shipped.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn test_shared_system_across_threads() -> Result<(), TransitionError> {
+    let payments = Arc::new(AtomicU32::new(0));
+    let system = Arc::new(Mutex::new(order_system(&payments)?));
+
+    let handles: Vec<_> = [5, 20, 30]
+        .into_iter()
+        .map(|amount| {
+            let system = Arc::clone(&system);
+            thread::spawn(move || {
+                let mut system = system.lock().unwrap_or_else(PoisonError::into_inner);
+                system.apply_event(OrderEvent::Pay(amount)).is_ok()
+            })
+        })
+        .collect();
+    let applied = handles.into_iter().filter_map(|handle| handle.join().ok()).filter(|ok| *ok);
+    // Only the first valid payment is applied, whatever the thread order
+    assert_eq!(applied.count(), 1);
+    let mut system = system.lock().unwrap_or_else(PoisonError::into_inner);
+    assert!(matches!(system.current_state(), Order::Paid(20 | 30)));
+    assert_eq!(payments.load(Ordering::SeqCst), 1);
+    system.apply_event(OrderEvent::Ship)?;
+    assert_eq!(system.current_state(), &Order::Shipped);
+    Ok(())
+}
+
+#[test]
+fn test_shared_observers() -> Result<(), TransitionError> {
+    /// Observer counting the transitions it sees
+    #[derive(Debug)]
+    struct Counter(Arc<AtomicU32>);
+
+    impl Observer<Order, OrderEvent> for Counter {
+        fn on_state_change(&self, _from: &Order, _to: &Order, _event: &OrderEvent) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let seen = Arc::new(AtomicU32::new(0));
+    let mut system = order_system(&Arc::new(AtomicU32::new(0)))?;
+    let id = system.register_observer(Box::new(Counter(Arc::clone(&seen))));
+    system.apply_event(OrderEvent::Pay(10))?;
+    assert!(system.unregister_observer(id).is_some());
+    system.apply_event(OrderEvent::Ship)?;
+    assert_eq!(seen.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn test_shared_builder_requires_target() {
+    let result =
+        TransitionBuilder::<Order, OrderEvent, Shared>::shared().on_event(OrderEvent::Ship).build();
+    assert!(matches!(result, Err(TransitionError::Incomplete("target state"))));
+}
+
+#[test]
+fn test_shared_system_round_trip() -> Result<(), LibraryError> {
+    let cycles = Arc::new(AtomicU32::new(0));
+    let counter = Arc::clone(&cycles);
+    let behaviors = Behaviors::shared()
+        .register_guard(!Guard::shared(|_, _| Err("never".to_string())).with_name("stuck"))
+        .action("count", move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+    let transition = TransitionBuilder::shared()
+        .from(TrafficLight::Red)
+        .on_event(TrafficEvent::Timer)
+        .to(TrafficLight::Green)
+        .when(!Guard::shared(|_, _| Err("never".to_string())).with_name("stuck"))
+        .named_action("count", |_, _| {})
+        .build()
+        .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+    let mut system = TransitionSystem::shared(TrafficLight::Red);
+    system.register_transition(transition);
+
+    let mut restored =
+        TransitionSystem::from_serializable_state(system.to_serializable_state()?, &behaviors)?;
+    assert_send_sync::<TransitionSystem<TrafficLight, TrafficEvent, Shared>>();
+    restored
+        .apply_event(TrafficEvent::Timer)
+        .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+    assert_eq!(restored.current_state(), &TrafficLight::Green);
+    // The registered action replaces the one the transition was built with
+    assert_eq!(cycles.load(Ordering::SeqCst), 1);
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{
+    persistence::SerializableInstant,
+    system::{LibraryError, StateTransition},
+};
+
+/// One row of an exported history
+///
+/// Times are seconds since the Unix epoch with nanosecond decimals. States
+/// and events without data are plain names like `Available`; those with data
+/// use their JSON form, e.g. `{"Reserved":"Alice"}`. Metadata is a JSON object.
+#[derive(Debug, Deserialize, Serialize)]
+struct CsvRecord {
+    /// Position of the transition among all transitions of the system
+    sequence: u64,
+    /// When the transition was recorded
+    timestamp: String,
+    /// When the event occurred, if recorded
+    occurred_at: Option<String>,
+    /// State before the transition
+    from: String,
+    /// Event that triggered the transition
+    event: String,
+    /// State after the transition
+    to: String,
+    /// Who triggered the event, if known
+    actor: Option<String>,
+    /// Note attached to the event, if any
+    note: Option<String>,
+    /// Additional details as a JSON object, empty if there are none
+    metadata: String,
+}
+
+/// Write a transition history as CSV with a header row
+///
+/// # Errors
+///
+/// Returns a `LibraryError::PersistenceError` if the CSV can't be written
+pub fn export_csv(history: &[StateTransition], writer: impl Write) -> Result<(), LibraryError> {
+    let write_error = |e: &dyn std::fmt::Display| {
+        LibraryError::PersistenceError(format!("Failed to write CSV: {e}"))
+    };
+
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for transition in history {
+        let record = to_record(transition).map_err(|e| write_error(&e))?;
+        csv_writer.serialize(record).map_err(|e| write_error(&e))?;
+    }
+    csv_writer.flush().map_err(|e| write_error(&e))
+}
+
+/// Read a transition history written by [`export_csv`]
+///
+/// # Errors
+///
+/// Returns a `LibraryError::LoadError` naming the first row that can't be
+/// read or parsed
+pub fn import_csv(reader: impl Read) -> Result<Vec<StateTransition>, LibraryError> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    csv_reader
+        .deserialize()
+        .enumerate()
+        .map(|(row, record)| {
+            record.map_err(|e| e.to_string()).and_then(from_record).map_err(|e| {
+                LibraryError::LoadError(format!(
+                    "Failed to parse CSV row {}: {e}",
+                    row.saturating_add(1)
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Flatten a transition into a CSV row
+fn to_record(transition: &StateTransition) -> Result<CsvRecord, serde_json::Error> {
+    Ok(CsvRecord {
+        sequence: transition.sequence,
+        timestamp: format_time(transition.timestamp.wall_time()),
+        occurred_at: transition.occurred_at.map(format_time),
+        from: to_cell(&transition.from)?,
+        event: to_cell(&transition.event)?,
+        to: to_cell(&transition.to)?,
+        actor: transition.actor.clone(),
+        note: transition.note.clone(),
+        metadata: if transition.metadata.is_empty() {
+            String::new()
+        } else {
+            serde_json::to_string(&transition.metadata)?
+        },
+    })
+}
+
+/// Rebuild a transition from a CSV row
+fn from_record(record: CsvRecord) -> Result<StateTransition, String> {
+    let metadata = if record.metadata.is_empty() {
+        BTreeMap::new()
+    } else {
+        serde_json::from_str(&record.metadata).map_err(|e| format!("invalid metadata: {e}"))?
+    };
+    Ok(StateTransition {
+        from: from_cell(&record.from).map_err(|e| format!("invalid from state: {e}"))?,
+        to: from_cell(&record.to).map_err(|e| format!("invalid to state: {e}"))?,
+        event: from_cell(&record.event).map_err(|e| format!("invalid event: {e}"))?,
+        timestamp: SerializableInstant::from_system_time(parse_time(&record.timestamp)?),
+        metadata,
+        actor: record.actor,
+        note: record.note,
+        occurred_at: record.occurred_at.as_deref().map(parse_time).transpose()?,
+        sequence: record.sequence,
+    })
+}
+
+/// Write a state or event as a plain name, or as JSON if it carries data
+fn to_cell<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    Ok(match serde_json::to_value(value)? {
+        Value::String(name) => name,
+        other => other.to_string(),
+    })
+}
+
+/// Read a state or event written by [`to_cell`]
+fn from_cell<T: DeserializeOwned>(cell: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_str(cell).or_else(|_| serde_json::from_value(Value::String(cell.to_string())))
+}
+
+/// Format a time as seconds since the Unix epoch with nanosecond decimals
+fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:09}", since_epoch.as_secs(), since_epoch.subsec_nanos())
+}
+
+/// Parse a time written by [`format_time`]; the decimals are optional
+fn parse_time(cell: &str) -> Result<SystemTime, String> {
+    let invalid = || format!("invalid time: {cell}");
+    let (seconds, decimals) = cell.split_once('.').unwrap_or((cell, ""));
+    if decimals.len() > 9 || !decimals.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+    let nanos: u32 = format!("{decimals:0<9}").parse().map_err(|_| invalid())?;
+    UNIX_EPOCH.checked_add(Duration::new(seconds, nanos)).ok_or_else(invalid)
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,64 @@
+use super::*;
+use crate::{
+    book_state::BookState,
+    events::{BookEvent, EventEnvelope},
+    system::LibrarySystem,
+};
+
+#[test]
+fn test_csv_round_trip() -> Result<(), LibraryError> {
+    let mut system = crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Smith, Alice")--> Reserved("Smith, Alice"),
+            Reserved("Smith, Alice") --CancelReservation--> Available,
+        },
+    }?;
+    system.process_event_with_meta(
+        EventEnvelope::new(BookEvent::Reserve("Smith, Alice".into()))
+            .actor("front-desk")
+            .note("Asked \"by phone\""),
+    )?;
+    system.process_event(BookEvent::CancelReservation)?;
+
+    let mut exported = Vec::new();
+    system.export_history_csv(&mut exported)?;
+    let text = String::from_utf8_lossy(&exported);
+    assert!(text.starts_with("sequence,timestamp,occurred_at,from,event,to,actor,note,metadata\n"));
+    assert!(text.contains(",CancelReservation,Available,"));
+
+    let imported = LibrarySystem::import_history_csv(exported.as_slice())?;
+    assert_eq!(imported.len(), 2);
+    let [reserve, cancel] = imported.as_slice() else {
+        return Err(LibraryError::LoadError("expected two rows".to_string()));
+    };
+    assert_eq!(reserve.to, BookState::Reserved("Smith, Alice".into()));
+    assert_eq!(reserve.actor.as_deref(), Some("front-desk"));
+    assert_eq!(reserve.note.as_deref(), Some("Asked \"by phone\""));
+    assert_eq!(cancel.event, BookEvent::CancelReservation);
+    assert_eq!(cancel.sequence, 2);
+    for (original, imported) in system.get_history().iter().zip(&imported) {
+        assert_eq!(original.timestamp.wall_time(), imported.timestamp.wall_time());
+        assert_eq!(original.occurred_at, imported.occurred_at);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_csv_metadata_and_errors() {
+    let csv = "sequence,timestamp,occurred_at,from,event,to,actor,note,metadata\n\
+               1,1700000000.5,,{\"CheckedOut\":\"Bob\"},Return,Available,,,\"{\"\"fine_cents\"\":\"\"150\"\"}\"\n";
+    let imported = import_csv(csv.as_bytes()).unwrap_or_default();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(
+        imported.first().and_then(|t| t.metadata.get("fine_cents")).map(String::as_str),
+        Some("150")
+    );
+
+    let bad = "sequence,timestamp,occurred_at,from,event,to,actor,note,metadata\n\
+               1,yesterday,,Available,Return,Available,,,\n";
+    assert!(
+        matches!(import_csv(bad.as_bytes()), Err(LibraryError::LoadError(e)) if e.contains("row 1"))
+    );
+}
@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::patrons::PatronId;
+
+/// First-come, first-served waitlist of patrons waiting for a book
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HoldQueue {
+    /// Patrons in the order they placed their holds
+    patrons: VecDeque<PatronId>,
+}
+
+impl HoldQueue {
+    /// Create an empty hold queue
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a patron to the end of the queue and return their 1-based position
+    ///
+    /// A patron who is already waiting keeps their current position.
+    pub fn place_hold(&mut self, patron: &str) -> usize {
+        if let Some(position) = self.position(patron) {
+            return position;
+        }
+        self.patrons.push_back(patron.into());
+        self.patrons.len()
+    }
+
+    /// Remove a patron from the queue, returning whether they were waiting
+    pub fn cancel_hold(&mut self, patron: &str) -> bool {
+        let before = self.patrons.len();
+        self.patrons.retain(|p| p != patron);
+        self.patrons.len() != before
+    }
+
+    /// Remove and return the patron at the front of the queue
+    pub fn next_patron(&mut self) -> Option<PatronId> {
+        self.patrons.pop_front()
+    }
+
+    /// Get the patron at the front of the queue without removing them
+    #[must_use]
+    pub fn peek(&self) -> Option<&str> {
+        self.patrons.front().map(PatronId::as_str)
+    }
+
+    /// Get the 1-based position of a patron in the queue
+    #[must_use]
+    pub fn position(&self, patron: &str) -> Option<usize> {
+        self.patrons.iter().position(|p| p == patron).map(|idx| idx.saturating_add(1))
+    }
+
+    /// Iterate over waiting patrons in queue order
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.patrons.iter().map(PatronId::as_str)
+    }
+
+    /// Get the number of waiting patrons
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.patrons.len()
+    }
+
+    /// Check whether nobody is waiting
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.patrons.is_empty()
+    }
+}
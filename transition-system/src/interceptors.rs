@@ -0,0 +1,40 @@
+use crate::{book_state::BookState, events::BookEvent};
+
+/// Why an interceptor blocked a transition
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{0}")]
+pub struct VetoReason(pub String);
+
+/// Policy consulted before every transition, able to block it
+///
+/// Use it for rules that cut across the whole machine, such as "no checkouts
+/// during inventory week", instead of encoding them on every transition. A
+/// vetoed transition is not applied and `process_event` returns a
+/// `LibraryError::Vetoed`.
+pub trait TransitionInterceptor: Send {
+    /// Called before the system moves from `from` to `to` on `event`
+    ///
+    /// # Errors
+    ///
+    /// Returns a `VetoReason` to block the transition
+    fn before_transition(
+        &self,
+        from: &BookState,
+        to: &BookState,
+        event: &BookEvent,
+    ) -> Result<(), VetoReason>;
+}
+
+impl<F> TransitionInterceptor for F
+where
+    F: Fn(&BookState, &BookState, &BookEvent) -> Result<(), VetoReason> + Send,
+{
+    fn before_transition(
+        &self,
+        from: &BookState,
+        to: &BookState,
+        event: &BookEvent,
+    ) -> Result<(), VetoReason> {
+        self(from, to, event)
+    }
+}
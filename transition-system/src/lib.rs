@@ -3,14 +3,65 @@
 //! This crate provides a state machine implementation for managing
 //! library book states and transitions between them.
 
+// Lets `#[derive(StateMachine)]` refer to this crate by name from within it
+extern crate self as transition_system;
+
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "tokio")]
+pub mod async_observer;
+pub mod audit;
+pub mod autosave;
 pub mod book_state;
+pub mod branches;
+pub mod builder;
+pub mod clock;
+pub mod coverage;
+pub mod dead_letter;
+pub mod definition;
+pub mod dot_import;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod event_log;
 pub mod events;
+#[cfg(feature = "tui")]
+pub mod explorer;
+pub mod fines;
+pub mod generic;
+#[cfg(feature = "csv")]
+pub mod history_csv;
+pub mod holds;
+pub mod interceptors;
+/// Internal `emit!` macro routing output through `tracing`
+mod logging;
+/// Declarative `state_machine!`, `typestate!` and `transitions!` macros, exported at the crate
+/// root
+mod macros;
+pub mod manager;
+pub mod metrics;
+pub mod model_check;
 pub mod observers;
+pub mod patrons;
 pub mod persistence;
+#[cfg(feature = "postgres")]
+pub mod postgres_store;
+pub mod recurrence;
+pub mod scheduler;
+pub mod schema;
+pub mod snapshot;
 pub mod system;
+pub mod testing;
+pub mod validation;
 pub mod visualization;
+#[cfg(feature = "web")]
+pub mod web;
 
 pub use book_state::BookState;
+pub use builder::LibrarySystemBuilder;
 pub use events::BookEvent;
+pub use generic::{TransitionBuilder, TransitionSystem};
+pub use manager::LibraryManager;
+pub use patrons::PatronId;
+pub use scheduler::TimeoutScheduler;
 pub use system::LibrarySystem;
 pub use visualization::StateVisualization;
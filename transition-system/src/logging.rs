@@ -0,0 +1,14 @@
+/// Emit a `tracing` event, mirroring it to stdout when the `stdout` feature is on
+///
+/// The level is the name of a `tracing` macro (`info`, `warn`, ...) and the
+/// rest is a format string with arguments, e.g.
+/// `emit!(info, "HOLDS: Fulfilling hold for {patron}")`.
+macro_rules! emit {
+    ($level:ident, $($arg:tt)+) => {{
+        ::tracing::$level!($($arg)+);
+        #[cfg(feature = "stdout")]
+        println!($($arg)+);
+    }};
+}
+
+pub(crate) use emit;
@@ -0,0 +1,378 @@
+/// Define a library state machine declaratively
+///
+/// Expands to [`LibrarySystemBuilder`](crate::builder::LibrarySystemBuilder)
+/// calls and evaluates to `Result<LibrarySystem, LibraryError>`. States and
+/// events are written as `BookState`/`BookEvent` variant names, so typos are
+/// caught at compile time. Variant arguments are converted with `Into`, so
+/// string literals can be used for patron names. Events carrying details,
+/// like `Transfer`, are written without them, and custom events by name, e.g.
+/// `Custom("inventory")`.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use transition_system::{BookEvent, BookState, state_machine};
+///
+/// let mut system = state_machine! {
+///     id: "book-1",
+///     initial: Available,
+///     transitions: {
+///         Available --Reserve("Alice")--> Reserved("Alice"),
+///         Reserved("Alice") --CheckOut("Alice")--> CheckedOut("Alice"),
+///         Reserved("Alice") --CancelReservation--> Available,
+///         CheckedOut("Alice") --Return--> Available,
+///     },
+///     timeouts: {
+///         Reserved("Alice") after Duration::from_hours(72) => CancelReservation,
+///     },
+/// }?;
+///
+/// system.process_event(BookEvent::Reserve("Alice".into()))?;
+/// assert_eq!(*system.current_state(), BookState::Reserved("Alice".into()));
+/// # Ok::<(), transition_system::system::LibraryError>(())
+/// ```
+#[macro_export]
+macro_rules! state_machine {
+    // Events carrying details are declared without them
+    (@event SendToRepair) => {
+        $crate::events::BookEvent::SendToRepair { condition: ::core::option::Option::None }
+    };
+    (@event Transfer) => {
+        $crate::events::BookEvent::Transfer { destination: ::core::option::Option::None }
+    };
+    (@event Custom($name:expr)) => {
+        $crate::events::BookEvent::Custom(
+            ::core::convert::Into::into($name),
+            ::core::default::Default::default(),
+        )
+    };
+    (@event $event:ident $( ( $( $event_arg:expr ),* ) )?) => {
+        $crate::events::BookEvent::$event $( ( $( ::core::convert::Into::into($event_arg) ),* ) )?
+    };
+    (
+        id: $id:expr,
+        initial: $initial:ident $( ( $( $initial_arg:expr ),* ) )?,
+        transitions: {
+            $(
+                $from:ident $( ( $( $from_arg:expr ),* ) )?
+                -- $event:ident $( ( $( $event_arg:expr ),* ) )? -->
+                $to:ident $( ( $( $to_arg:expr ),* ) )?
+            ),* $(,)?
+        }
+        $(
+            , timeouts: {
+                $(
+                    $timeout_state:ident $( ( $( $timeout_state_arg:expr ),* ) )?
+                    after $duration:expr =>
+                    $timeout_event:ident $( ( $( $timeout_event_arg:expr ),* ) )?
+                ),* $(,)?
+            }
+        )?
+        $(,)?
+    ) => {{
+        let builder = $crate::builder::LibrarySystemBuilder::new(
+            $id,
+            $crate::book_state::BookState::$initial
+                $( ( $( ::core::convert::Into::into($initial_arg) ),* ) )?,
+        );
+        $(
+            let builder = builder.transition(
+                $crate::book_state::BookState::$from
+                    $( ( $( ::core::convert::Into::into($from_arg) ),* ) )?,
+                $crate::state_machine!(@event $event $( ( $( $event_arg ),* ) )?),
+                $crate::book_state::BookState::$to
+                    $( ( $( ::core::convert::Into::into($to_arg) ),* ) )?,
+            );
+        )*
+        $($(
+            let builder = builder.timeout(
+                $crate::book_state::BookState::$timeout_state
+                    $( ( $( ::core::convert::Into::into($timeout_state_arg) ),* ) )?,
+                $duration,
+                $crate::state_machine!(
+                    @event $timeout_event $( ( $( $timeout_event_arg ),* ) )?
+                ),
+            );
+        )*)?
+        builder.build()
+    }};
+}
+
+/// Define a typestate machine and its runtime counterpart from one definition
+///
+/// Generates, in the calling module:
+///
+/// - a unit marker type for every state, implementing
+///   [`StateMarker`](crate::generic::StateMarker);
+/// - a wrapper `Machine<S>` around the given data type, created in the initial
+///   state with `new`, whose transitions are methods consuming it and
+///   returning the wrapper in the target state, so calling one in the wrong
+///   state doesn't compile;
+/// - enums of the states and events, and a `transition_system` function on
+///   the state enum building a generic
+///   [`TransitionSystem`](crate::generic::TransitionSystem) with the same
+///   transitions, for when the state is only known at runtime.
+///
+/// Each transition is written `From --Event--> To => method`. The data type
+/// must implement `Debug`.
+///
+/// ```
+/// use transition_system::typestate;
+///
+/// typestate! {
+///     pub machine Post(String) {
+///         states: PostState { Draft, Review, Published },
+///         events: PostEvent { Submit, Approve, Reject },
+///         initial: Draft,
+///         transitions: {
+///             Draft --Submit--> Review => submit,
+///             Review --Approve--> Published => approve,
+///             Review --Reject--> Draft => reject,
+///         },
+///     }
+/// }
+///
+/// let post = Post::new("Hello".to_string()).submit().approve();
+/// assert_eq!(post.state(), PostState::Published);
+///
+/// let mut system = post.system()?;
+/// assert!(!system.can_transition(&PostEvent::Submit));
+/// # Ok::<(), transition_system::generic::TransitionError>(())
+/// ```
+///
+/// Skipping the review is a compile error:
+///
+/// ```compile_fail
+/// # use transition_system::typestate;
+/// # typestate! {
+/// #     pub machine Post(String) {
+/// #         states: PostState { Draft, Review, Published },
+/// #         events: PostEvent { Submit, Approve },
+/// #         initial: Draft,
+/// #         transitions: {
+/// #             Draft --Submit--> Review => submit,
+/// #             Review --Approve--> Published => approve,
+/// #         },
+/// #     }
+/// # }
+/// let post = Post::new("Hello".to_string()).approve();
+/// ```
+#[macro_export]
+macro_rules! typestate {
+    (
+        $vis:vis machine $machine:ident ( $data:ty ) {
+            states: $state_enum:ident { $( $state:ident ),+ $(,)? },
+            events: $event_enum:ident { $( $event:ident ),+ $(,)? },
+            initial: $initial:ident,
+            transitions: {
+                $( $from:ident -- $via:ident --> $to:ident => $method:ident ),* $(,)?
+            } $(,)?
+        }
+    ) => {
+        #[doc = concat!("States of [`", stringify!($machine), "`]")]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $state_enum {
+            $(
+                #[doc = concat!("The `", stringify!($state), "` state")]
+                $state,
+            )+
+        }
+
+        #[doc = concat!("Events driving [`", stringify!($machine), "`]")]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $event_enum {
+            $(
+                #[doc = concat!("The `", stringify!($event), "` event")]
+                $event,
+            )+
+        }
+
+        $(
+            #[doc = concat!("Marker of the `", stringify!($state), "` state of [`",
+                stringify!($machine), "`]")]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+            $vis struct $state;
+
+            impl $crate::generic::StateMarker for $state {
+                type State = $state_enum;
+                const STATE: $state_enum = $state_enum::$state;
+            }
+        )+
+
+        #[doc = concat!("Data whose state, a [`", stringify!($state_enum),
+            "`], is tracked by its type")]
+        #[derive(Debug)]
+        $vis struct $machine<S> {
+            /// The wrapped data
+            data: $data,
+            /// Marker of the current state
+            state: ::core::marker::PhantomData<S>,
+        }
+
+        impl $machine<$initial> {
+            #[doc = concat!("Wrap data in the `", stringify!($initial), "` state")]
+            #[must_use]
+            $vis fn new(data: $data) -> Self {
+                Self { data, state: ::core::marker::PhantomData }
+            }
+        }
+
+        // Not every generated helper is used by every machine
+        #[allow(dead_code)]
+        impl<S> $machine<S>
+        where
+            S: $crate::generic::StateMarker<State = $state_enum>,
+        {
+            /// Get the current state as a runtime value
+            #[must_use]
+            $vis fn state(&self) -> $state_enum {
+                S::STATE
+            }
+
+            /// Get the wrapped data
+            #[must_use]
+            $vis fn data(&self) -> &$data {
+                &self.data
+            }
+
+            /// Unwrap the data
+            #[must_use]
+            $vis fn into_data(self) -> $data {
+                self.data
+            }
+
+            /// Build the runtime machine, starting in the current state
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if a transition can't be built
+            $vis fn system(
+                &self,
+            ) -> ::core::result::Result<
+                $crate::generic::TransitionSystem<$state_enum, $event_enum>,
+                $crate::generic::TransitionError,
+            > {
+                $state_enum::transition_system(S::STATE)
+            }
+        }
+
+        $(
+            impl $machine<$from> {
+                #[doc = concat!("Take the `", stringify!($via), "` transition to `",
+                    stringify!($to), "`")]
+                #[must_use]
+                $vis fn $method(self) -> $machine<$to> {
+                    $machine { data: self.data, state: ::core::marker::PhantomData }
+                }
+            }
+        )*
+
+        impl $state_enum {
+            /// Build the runtime machine with the same transitions
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if a transition can't be built
+            $vis fn transition_system(
+                initial: Self,
+            ) -> ::core::result::Result<
+                $crate::generic::TransitionSystem<Self, $event_enum>,
+                $crate::generic::TransitionError,
+            > {
+                #[allow(unused_mut)]
+                let mut system = $crate::generic::TransitionSystem::new(initial);
+                $(
+                    system.register_transition(
+                        $crate::generic::TransitionBuilder::new()
+                            .from(Self::$from)
+                            .to(Self::$to)
+                            .on_event($event_enum::$via)
+                            .build()?,
+                    );
+                )*
+                ::core::result::Result::Ok(system)
+            }
+        }
+    };
+}
+
+/// Register a table of transitions in a generic
+/// [`TransitionSystem`](crate::generic::TransitionSystem)
+///
+/// Written `system: StateType, EventType { From + Event => To, ... }`, where
+/// the state and event types are enums without fields. Every row becomes a
+/// transition from `From` to `To` on exactly `Event`, registered in order.
+/// Evaluates to `Result<(), TransitionError>`.
+///
+/// ```
+/// use transition_system::{generic::TransitionSystem, transitions};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Light {
+///     Red,
+///     Yellow,
+///     Green,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Signal {
+///     Timer,
+///     Emergency,
+/// }
+///
+/// let mut system = TransitionSystem::new(Light::Red);
+/// transitions!(system: Light, Signal {
+///     Red + Timer => Green,
+///     Green + Timer => Yellow,
+///     Yellow + Timer => Red,
+///     Green + Emergency => Red,
+/// })?;
+///
+/// system.apply_event(Signal::Timer)?;
+/// assert_eq!(*system.current_state(), Light::Green);
+/// # Ok::<(), transition_system::generic::TransitionError>(())
+/// ```
+///
+/// The same state and event appearing twice is a compile error, since the
+/// second row could never be applied:
+///
+/// ```compile_fail
+/// # use transition_system::{generic::TransitionSystem, transitions};
+/// # #[derive(Debug, Clone, PartialEq)]
+/// # enum Light { Red, Green }
+/// # #[derive(Debug, Clone, PartialEq)]
+/// # enum Signal { Timer }
+/// let mut system = TransitionSystem::new(Light::Red);
+/// transitions!(system: Light, Signal {
+///     Red + Timer => Green,
+///     Red + Timer => Red,
+/// })?;
+/// # Ok::<(), transition_system::generic::TransitionError>(())
+/// ```
+#[macro_export]
+macro_rules! transitions {
+    (
+        $system:ident : $state:ident , $event:ident {
+            $( $from:ident + $via:ident => $to:ident ),+ $(,)?
+        }
+    ) => {{
+        const _: () = ::core::assert!(
+            !$crate::generic::has_duplicate_row(&[$( ($state::$from as isize, $event::$via as isize) ),+]),
+            "transitions! lists the same state and event twice"
+        );
+        [
+            $(
+                $crate::generic::TransitionBuilder::new()
+                    .from($state::$from)
+                    .on_event($event::$via)
+                    .to($state::$to)
+                    .build(),
+            )+
+        ]
+        .into_iter()
+        .try_for_each(|transition| {
+            $system.register_transition(transition?);
+            ::core::result::Result::Ok::<(), $crate::generic::TransitionError>(())
+        })
+    }};
+}
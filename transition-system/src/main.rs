@@ -3,124 +3,55 @@
 //! This is an example application demonstrating a state transition system
 //! for tracking library books through various states.
 
-use std::time::Duration;
-
 use transition_system::{
-    StateVisualization,
-    book_state::BookState,
-    events::BookEvent,
+    LibrarySystemBuilder, StateVisualization,
+    events::{BookEvent, EventEnvelope},
     observers::{NotificationService, TransitionLogger},
-    system::LibrarySystem,
+    system::{LibraryError, LibrarySystem},
 };
 
-/// Set up the library state machine with all states, transitions and timing constraints
-fn setup_library_system(system: &mut LibrarySystem) {
-    // Set up all states
-    let available_idx = 0; // Already added as initial
-    let reserved_alice_idx = system.add_state(BookState::Reserved("Alice".to_string()));
-    let checked_out_alice_idx = system.add_state(BookState::CheckedOut("Alice".to_string()));
-    let reserved_bob_idx = system.add_state(BookState::Reserved("Bob".to_string()));
-    let checked_out_bob_idx = system.add_state(BookState::CheckedOut("Bob".to_string()));
-    let in_transit_idx = system.add_state(BookState::InTransit);
-    let under_repair_idx = system.add_state(BookState::UnderRepair);
-    let lost_idx = system.add_state(BookState::Lost);
-
-    // Add transitions from Available state
-    system.add_transition(
-        available_idx,
-        BookEvent::Reserve("Alice".to_string()),
-        reserved_alice_idx,
-    );
-    system.add_transition(available_idx, BookEvent::Reserve("Bob".to_string()), reserved_bob_idx);
-    system.add_transition(
-        available_idx,
-        BookEvent::CheckOut("Alice".to_string()),
-        checked_out_alice_idx,
-    );
-    system.add_transition(
-        available_idx,
-        BookEvent::CheckOut("Bob".to_string()),
-        checked_out_bob_idx,
-    );
-    system.add_transition(available_idx, BookEvent::Transfer, in_transit_idx);
-    system.add_transition(available_idx, BookEvent::SendToRepair, under_repair_idx);
-    system.add_transition(available_idx, BookEvent::ReportLost, lost_idx);
-
-    // Add transitions from Reserved states
-    system.add_transition(reserved_alice_idx, BookEvent::CancelReservation, available_idx);
-    system.add_transition(
-        reserved_alice_idx,
-        BookEvent::CheckOut("Alice".to_string()),
-        checked_out_alice_idx,
-    );
-    system.add_transition(reserved_alice_idx, BookEvent::ReportLost, lost_idx);
-
-    system.add_transition(reserved_bob_idx, BookEvent::CancelReservation, available_idx);
-    system.add_transition(
-        reserved_bob_idx,
-        BookEvent::CheckOut("Bob".to_string()),
-        checked_out_bob_idx,
-    );
-    system.add_transition(reserved_bob_idx, BookEvent::ReportLost, lost_idx);
-
-    // Add transitions from CheckedOut states
-    system.add_transition(checked_out_alice_idx, BookEvent::Return, available_idx);
-    system.add_transition(checked_out_alice_idx, BookEvent::ReportLost, lost_idx);
-
-    system.add_transition(checked_out_bob_idx, BookEvent::Return, available_idx);
-    system.add_transition(checked_out_bob_idx, BookEvent::ReportLost, lost_idx);
-
-    // Add transitions from InTransit state
-    system.add_transition(in_transit_idx, BookEvent::TransferComplete, available_idx);
-    system.add_transition(in_transit_idx, BookEvent::ReportLost, lost_idx);
-
-    // Add transitions from UnderRepair state
-    system.add_transition(under_repair_idx, BookEvent::CompleteRepair, available_idx);
-    system.add_transition(under_repair_idx, BookEvent::ReportLost, lost_idx);
-
-    // Add transitions from Lost state
-    system.add_transition(lost_idx, BookEvent::Found, available_idx);
-
-    // Add timing constraints
-    // Books can only be reserved for 3 days
-    system.add_timing_constraint(
-        reserved_alice_idx,
-        Duration::from_secs(3 * 24 * 60 * 60), // 3 days
-        BookEvent::CancelReservation,
-    );
-    system.add_timing_constraint(
-        reserved_bob_idx,
-        Duration::from_secs(3 * 24 * 60 * 60), // 3 days
-        BookEvent::CancelReservation,
-    );
-
-    // Books can be checked out for 14 days
-    system.add_timing_constraint(
-        checked_out_alice_idx,
-        Duration::from_secs(14 * 24 * 60 * 60), // 14 days
-        BookEvent::Return,
-    );
-    system.add_timing_constraint(
-        checked_out_bob_idx,
-        Duration::from_secs(14 * 24 * 60 * 60), // 14 days
-        BookEvent::Return,
-    );
+/// Build the library state machine with all states, transitions and timing constraints
+fn build_library_system() -> Result<LibrarySystem, LibraryError> {
+    LibrarySystemBuilder::standard_library_machine(
+        "book-1234",
+        &["Alice", "Bob"],
+        &["Water damage"],
+    )
+    .observer(Box::new(TransitionLogger))
+    .observer(Box::new(NotificationService))
+    .build()
 }
 
 fn main() {
-    // Create a new library system with a book that's initially available
-    let mut book_system = LibrarySystem::new(BookState::Available, "book-1234");
-
-    // Register observers
-    book_system.register_observer(Box::new(TransitionLogger));
-    book_system.register_observer(Box::new(NotificationService));
+    // Create a library system with a book that's initially available
+    let mut book_system = match build_library_system() {
+        Ok(system) => system,
+        Err(e) => {
+            println!("Failed to build library system: {e}");
+            return;
+        }
+    };
+
+    // Let the user drive the system from a terminal UI instead of the scripted demo
+    #[cfg(feature = "tui")]
+    if std::env::args().any(|arg| arg == "--explore") {
+        let mut explorer = transition_system::explorer::Explorer::new(book_system);
+        if let Err(e) = explorer.run() {
+            println!("Explorer failed: {e}");
+        }
+        return;
+    }
 
-    // Set up all the states and transitions
-    setup_library_system(&mut book_system);
+    // Report structural problems in the definition
+    for issue in book_system.validate() {
+        println!("VALIDATION: {issue}");
+    }
 
     // Visualize the initial state machine structure
     println!("\n==== Initial State Machine Visualization ====\n");
     StateVisualization::print_state_machine(&book_system);
+    println!();
+    StateVisualization::print_ascii(&book_system);
 
     // Generate and save DOT graph of the state machine
     let dot = StateVisualization::generate_dot(&book_system, false);
@@ -134,13 +65,16 @@ fn main() {
     println!("Initial state: {book_system}");
 
     // Alice reserves the book
-    match book_system.process_event(BookEvent::Reserve("Alice".to_string())) {
+    match book_system.process_event(BookEvent::Reserve("Alice".into())) {
         Ok(_) => println!("New state: {book_system}"),
         Err(e) => println!("Error: {e}"),
     }
 
     // Alice checks out the book
-    match book_system.process_event(BookEvent::CheckOut("Alice".to_string())) {
+    let checkout = EventEnvelope::new(BookEvent::CheckOut("Alice".into()))
+        .actor("circulation-desk")
+        .note("Picked up reservation");
+    match book_system.process_event_with_meta(checkout) {
         Ok(_) => println!("New state: {book_system}"),
         Err(e) => println!("Error: {e}"),
     }
@@ -179,6 +113,10 @@ fn main() {
         Err(e) => println!("\nFailed to save transition history table: {e}"),
     }
 
+    // Print a Mermaid diagram that renders directly in GitHub markdown
+    println!("\n==== Mermaid State Diagram ====\n");
+    println!("{}", StateVisualization::generate_mermaid(&book_system, true));
+
     // Save the state to a file before simulating a restart
     if let Err(e) = book_system.save_state_to_file() {
         println!("Error saving state: {e}");
@@ -193,7 +131,8 @@ fn main() {
             println!("Successfully loaded system from file: {loaded_system}");
 
             // Continue working with the loaded system
-            match loaded_system.process_event(BookEvent::SendToRepair) {
+            let condition = Some("Spine detached".to_string());
+            match loaded_system.process_event(BookEvent::SendToRepair { condition }) {
                 Ok(_) => println!("New state after loading: {loaded_system}"),
                 Err(e) => println!("Error after loading: {e}"),
             }
@@ -0,0 +1,203 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    book_state::BookState,
+    events::BookEvent,
+    observers::{SharedObserver, StateObserver},
+    patrons::PatronRegistry,
+    persistence::StateStore,
+    system::{LibraryError, LibrarySystem},
+};
+
+/// Owns the state machines of many books, keyed by system ID
+pub struct LibraryManager {
+    /// Managed systems keyed by their system ID
+    systems: BTreeMap<String, LibrarySystem>,
+    /// Observers attached to every managed system
+    shared_observers: Vec<Arc<dyn StateObserver + Sync>>,
+    /// Patron registry attached to every managed system
+    patrons: Option<Arc<Mutex<PatronRegistry>>>,
+}
+
+// Manual implementation of Debug for LibraryManager
+impl fmt::Debug for LibraryManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LibraryManager")
+            .field("systems", &self.systems)
+            .field("shared_observers_count", &self.shared_observers.len())
+            .field("patrons", &self.patrons)
+            .finish()
+    }
+}
+
+impl Default for LibraryManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LibraryManager {
+    /// Create an empty manager
+    #[must_use]
+    pub fn new() -> Self {
+        Self { systems: BTreeMap::new(), shared_observers: Vec::new(), patrons: None }
+    }
+
+    /// Add a system, returning the one previously stored under the same ID
+    ///
+    /// Shared observers and the patron registry are attached to the added system.
+    pub fn add_system(&mut self, mut system: LibrarySystem) -> Option<LibrarySystem> {
+        for observer in &self.shared_observers {
+            system.register_observer(Box::new(SharedObserver::new(Arc::clone(observer))));
+        }
+        if let Some(registry) = &self.patrons {
+            system.set_patron_registry(Arc::clone(registry));
+        }
+        self.systems.insert(system.system_id().to_string(), system)
+    }
+
+    /// Remove a system from the manager
+    pub fn remove_system(&mut self, system_id: &str) -> Option<LibrarySystem> {
+        self.systems.remove(system_id)
+    }
+
+    /// Get a managed system
+    #[must_use]
+    pub fn get(&self, system_id: &str) -> Option<&LibrarySystem> {
+        self.systems.get(system_id)
+    }
+
+    /// Get a managed system for modification
+    pub fn get_mut(&mut self, system_id: &str) -> Option<&mut LibrarySystem> {
+        self.systems.get_mut(system_id)
+    }
+
+    /// Iterate over the IDs of all managed systems in sorted order
+    pub fn system_ids(&self) -> impl Iterator<Item = &str> {
+        self.systems.keys().map(String::as_str)
+    }
+
+    /// Iterate over all managed systems in system ID order
+    pub fn iter(&self) -> impl Iterator<Item = &LibrarySystem> {
+        self.systems.values()
+    }
+
+    /// Get the number of managed systems
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    /// Check whether the manager has no systems
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Register an observer on every current and future managed system
+    pub fn register_shared_observer(&mut self, observer: Arc<dyn StateObserver + Sync>) {
+        for system in self.systems.values_mut() {
+            system.register_observer(Box::new(SharedObserver::new(Arc::clone(&observer))));
+        }
+        self.shared_observers.push(observer);
+    }
+
+    /// Attach a patron registry to every current and future managed system
+    ///
+    /// Borrowing limits are then enforced across all books of the manager.
+    pub fn set_patron_registry(&mut self, registry: Arc<Mutex<PatronRegistry>>) {
+        for system in self.systems.values_mut() {
+            system.set_patron_registry(Arc::clone(&registry));
+        }
+        self.patrons = Some(registry);
+    }
+
+    /// Process an event for the book with the given system ID
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::UnknownSystem` if no system has the given ID,
+    /// or any error returned by [`LibrarySystem::process_event`]
+    pub fn process_event(
+        &mut self,
+        system_id: &str,
+        event: BookEvent,
+    ) -> Result<&BookState, LibraryError> {
+        self.systems
+            .get_mut(system_id)
+            .ok_or_else(|| LibraryError::UnknownSystem(system_id.to_string()))?
+            .process_event(event)
+    }
+
+    /// Save every managed system to the given store
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reported by the store; systems after the
+    /// failing one are not saved
+    pub fn save_all(&self, store: &dyn StateStore) -> Result<(), LibraryError> {
+        self.systems.values().try_for_each(|system| system.save_to(store))
+    }
+
+    /// Load the given systems from a store and add them to the manager
+    ///
+    /// Returns the number of systems loaded. Shared observers and the patron
+    /// registry are attached to the loaded systems, see [`Self::add_system`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reported by the store; systems loaded before
+    /// the failing one stay in the manager
+    pub fn load_all<I, S>(
+        &mut self,
+        store: &dyn StateStore,
+        system_ids: I,
+    ) -> Result<usize, LibraryError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut loaded = 0_usize;
+        for system_id in system_ids {
+            self.add_system(LibrarySystem::load_from(store, system_id.as_ref())?);
+            loaded = loaded.saturating_add(1);
+        }
+        Ok(loaded)
+    }
+
+    /// Load every system persisted in a store and add them to the manager
+    ///
+    /// Returns the number of systems loaded. Shared observers and the patron
+    /// registry are attached to the loaded systems, see [`Self::add_system`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered while listing or loading; no
+    /// systems are added in that case
+    pub fn load_all_from(&mut self, store: &dyn StateStore) -> Result<usize, LibraryError> {
+        let states = store.load_all()?;
+        let loaded = states.len();
+        for state in states {
+            self.add_system(LibrarySystem::from_serializable_state(state));
+        }
+        Ok(loaded)
+    }
+
+    /// Get the IDs of all checked out books that are past their due date
+    #[must_use]
+    pub fn overdue_books(&self) -> Vec<&str> {
+        self.systems
+            .iter()
+            .filter(|(_, system)| system.is_overdue())
+            .map(|(system_id, _)| system_id.as_str())
+            .collect()
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
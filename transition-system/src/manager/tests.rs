@@ -0,0 +1,104 @@
+use std::{
+    fs,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    book_state::BookState,
+    clock::MockClock,
+    events::BookEvent,
+    manager::LibraryManager,
+    observers::StateObserver,
+    persistence::FileStore,
+    system::{LibraryError, LibrarySystem},
+};
+
+/// Counts the transitions it is notified about
+#[derive(Debug, Default)]
+struct CountingObserver(AtomicUsize);
+
+impl StateObserver for CountingObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Create a book that can be checked out by "Test User" for one day
+fn checkout_system(system_id: &str, clock: &MockClock) -> LibrarySystem {
+    let mut system =
+        LibrarySystem::with_clock(BookState::Available, system_id, Arc::new(clock.clone()));
+    let checked_out_idx = system.add_state(BookState::CheckedOut("Test User".into()));
+    system.add_transition(0, BookEvent::CheckOut("Test User".into()), checked_out_idx);
+    system.add_transition(checked_out_idx, BookEvent::Return, 0);
+    system.add_timing_constraint(checked_out_idx, Duration::from_hours(24), BookEvent::Return);
+    system
+}
+
+#[test]
+fn test_manager_routes_events_and_finds_overdue_books() {
+    let clock = MockClock::new();
+    let counter = Arc::new(CountingObserver::default());
+
+    let mut manager = LibraryManager::new();
+    manager.add_system(checkout_system("book-1", &clock));
+    manager.register_shared_observer(counter.clone());
+    manager.add_system(checkout_system("book-2", &clock));
+
+    assert!(manager.process_event("book-1", BookEvent::CheckOut("Test User".into())).is_ok());
+    assert!(manager.process_event("book-2", BookEvent::CheckOut("Test User".into())).is_ok());
+    assert!(manager.process_event("book-2", BookEvent::Return).is_ok());
+    assert!(matches!(
+        manager.process_event("book-3", BookEvent::Return),
+        Err(LibraryError::UnknownSystem(id)) if id == "book-3"
+    ));
+
+    // The shared observer saw transitions from both books
+    assert_eq!(counter.0.load(Ordering::Relaxed), 3);
+
+    assert!(manager.overdue_books().is_empty());
+    clock.advance(Duration::from_hours(25));
+    assert_eq!(manager.overdue_books(), vec!["book-1"]);
+}
+
+#[test]
+fn test_manager_round_trips_through_store() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("manager-store-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory);
+
+    let result = (|| {
+        let clock = MockClock::new();
+        let mut manager = LibraryManager::new();
+        manager.add_system(checkout_system("book-1", &clock));
+        manager.add_system(checkout_system("book-2", &clock));
+        manager.process_event("book-1", BookEvent::CheckOut("Test User".into()))?;
+        manager.save_all(&store)?;
+
+        // Loaded systems get the shared observers
+        let counter = Arc::new(CountingObserver::default());
+        let mut loaded = LibraryManager::new();
+        loaded.register_shared_observer(counter.clone());
+        assert_eq!(loaded.load_all_from(&store)?, 2);
+        assert_eq!(
+            *loaded
+                .get("book-1")
+                .map(LibrarySystem::current_state)
+                .ok_or_else(|| { LibraryError::UnknownSystem("book-1".to_string()) })?,
+            BookState::CheckedOut("Test User".into())
+        );
+        loaded.process_event("book-1", BookEvent::Return)?;
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+
+        let mut some = LibraryManager::new();
+        assert_eq!(some.load_all(&store, ["book-2"])?, 1);
+        assert_eq!(some.system_ids().collect::<Vec<_>>(), ["book-2"]);
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
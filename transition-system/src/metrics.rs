@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Write as _},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+use crate::{
+    book_state::BookState, events::BookEvent, observers::StateObserver, system::StateTransition,
+};
+
+/// Current state of one observed system
+#[derive(Debug, Clone)]
+struct CurrentState {
+    /// Variant name of the state
+    state: String,
+    /// When the observer saw the system enter the state
+    entered_at: Instant,
+}
+
+/// Values collected from all observed systems
+#[derive(Debug, Default)]
+struct Collected {
+    /// Transition counts keyed by (system ID, event, from state, to state)
+    transitions: BTreeMap<(String, String, String, String), u64>,
+    /// Current state keyed by system ID
+    current: BTreeMap<String, CurrentState>,
+}
+
+/// Transition metrics of any number of systems, in Prometheus format
+///
+/// Register [`Self::observer`] on each system, then serve [`Self::render`]
+/// from a scrape endpoint. States and events are labelled by variant name
+/// only (`Reserved`, not `Reserved(Alice)`), so patron names don't end up in
+/// label values. Clones share the collected values.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    /// Values shared with the observers
+    collected: Arc<Mutex<Collected>>,
+}
+
+impl MetricsRegistry {
+    /// Create an empty registry
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an observer recording the transitions of a system
+    #[must_use]
+    pub fn observer(&self, system_id: &str) -> MetricsObserver {
+        MetricsObserver { registry: self.clone(), system_id: system_id.to_string() }
+    }
+
+    /// Get how often a system went from one state to another on an event
+    #[must_use]
+    pub fn transition_count(
+        &self,
+        system_id: &str,
+        event: &BookEvent,
+        from: &BookState,
+        to: &BookState,
+    ) -> u64 {
+        let key =
+            (system_id.to_string(), variant_name(event), variant_name(from), variant_name(to));
+        self.lock().transitions.get(&key).copied().unwrap_or(0)
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    ///
+    /// - `library_transitions_total{system_id, event, from, to}`: counter of
+    ///   transitions
+    /// - `library_current_state{system_id, state}`: gauge set to 1 for the
+    ///   state each system is in
+    /// - `library_time_in_state_seconds{system_id, state}`: gauge of how long
+    ///   each system has been in its current state
+    ///
+    /// Systems only appear in the gauges after their first observed transition.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let collected = self.lock();
+        let mut out = String::new();
+
+        out.push_str("# HELP library_transitions_total State transitions by event and states.\n");
+        out.push_str("# TYPE library_transitions_total counter\n");
+        for ((system_id, event, from, to), count) in &collected.transitions {
+            let _ = writeln!(
+                out,
+                "library_transitions_total{{system_id=\"{}\",event=\"{}\",from=\"{}\",to=\"{}\"}} {count}",
+                escape(system_id),
+                escape(event),
+                escape(from),
+                escape(to)
+            );
+        }
+
+        out.push_str("# HELP library_current_state State each book is currently in.\n");
+        out.push_str("# TYPE library_current_state gauge\n");
+        for (system_id, current) in &collected.current {
+            let _ = writeln!(
+                out,
+                "library_current_state{{system_id=\"{}\",state=\"{}\"}} 1",
+                escape(system_id),
+                escape(&current.state)
+            );
+        }
+
+        out.push_str("# HELP library_time_in_state_seconds Time spent in the current state.\n");
+        out.push_str("# TYPE library_time_in_state_seconds gauge\n");
+        for (system_id, current) in &collected.current {
+            let _ = writeln!(
+                out,
+                "library_time_in_state_seconds{{system_id=\"{}\",state=\"{}\"}} {:.3}",
+                escape(system_id),
+                escape(&current.state),
+                current.entered_at.elapsed().as_secs_f64()
+            );
+        }
+        out
+    }
+
+    /// Lock the collected values, even if an observer panicked while holding them
+    fn lock(&self) -> MutexGuard<'_, Collected> {
+        self.collected.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Records the transitions of one system into a [`MetricsRegistry`]
+#[derive(Debug, Clone)]
+pub struct MetricsObserver {
+    /// Registry the transitions are recorded in
+    registry: MetricsRegistry,
+    /// System whose transitions are recorded
+    system_id: String,
+}
+
+impl MetricsObserver {
+    /// Render the metrics of the registry this observer records into
+    #[must_use]
+    pub fn render(&self) -> String {
+        self.registry.render()
+    }
+}
+
+impl StateObserver for MetricsObserver {
+    fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent) {
+        let mut collected = self.registry.lock();
+        let key =
+            (self.system_id.clone(), variant_name(event), variant_name(from), variant_name(to));
+        let count = collected.transitions.entry(key).or_insert(0);
+        *count = count.saturating_add(1);
+        collected.current.insert(
+            self.system_id.clone(),
+            CurrentState { state: variant_name(to), entered_at: Instant::now() },
+        );
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        self.on_state_change(&transition.from, &transition.to, &transition.event);
+    }
+}
+
+/// Get the name of an enum variant without its fields, e.g. `Reserved`
+fn variant_name(value: &impl Debug) -> String {
+    let debug = format!("{value:?}");
+    debug.split(['(', ' ', '{']).next().unwrap_or_default().to_string()
+}
+
+/// Escape a Prometheus label value
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
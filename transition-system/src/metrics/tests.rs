@@ -0,0 +1,64 @@
+use super::*;
+use crate::{
+    patrons::PatronId,
+    system::{LibraryError, LibrarySystem},
+};
+
+/// Create a system reporting to the given registry
+fn setup_test_system(registry: &MetricsRegistry) -> Result<LibrarySystem, LibraryError> {
+    let mut system = crate::state_machine! {
+        id: "book-1",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Alice")--> Reserved("Alice"),
+            Available --Reserve("Bob")--> Reserved("Bob"),
+            Reserved("Alice") --CancelReservation--> Available,
+            Reserved("Bob") --CancelReservation--> Available,
+        },
+    }?;
+    system.register_observer(Box::new(registry.observer("book-1")));
+    Ok(system)
+}
+
+#[test]
+fn test_counts_transitions_by_variant() -> Result<(), LibraryError> {
+    let registry = MetricsRegistry::new();
+    let mut system = setup_test_system(&registry)?;
+    system.process_event(BookEvent::Reserve("Alice".into()))?;
+    system.process_event(BookEvent::CancelReservation)?;
+    system.process_event(BookEvent::Reserve("Bob".into()))?;
+
+    // Both patrons' reservations share one counter
+    let reserve = BookEvent::Reserve(PatronId::default());
+    let reserved = BookState::Reserved(PatronId::default());
+    assert_eq!(registry.transition_count("book-1", &reserve, &BookState::Available, &reserved), 2);
+    assert_eq!(
+        registry.transition_count(
+            "book-1",
+            &BookEvent::CancelReservation,
+            &reserved,
+            &BookState::Available
+        ),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn test_render_exposition_format() -> Result<(), LibraryError> {
+    let registry = MetricsRegistry::new();
+    let mut system = setup_test_system(&registry)?;
+    system.process_event(BookEvent::Reserve("Alice".into()))?;
+
+    let metrics = registry.observer("book-1").render();
+    assert!(metrics.contains("# TYPE library_transitions_total counter\n"));
+    assert!(metrics.contains(
+        "library_transitions_total{system_id=\"book-1\",event=\"Reserve\",from=\"Available\",to=\"Reserved\"} 1\n"
+    ));
+    assert!(metrics.contains("library_current_state{system_id=\"book-1\",state=\"Reserved\"} 1\n"));
+    assert!(
+        metrics
+            .contains("library_time_in_state_seconds{system_id=\"book-1\",state=\"Reserved\"} 0.")
+    );
+    Ok(())
+}
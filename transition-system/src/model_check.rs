@@ -0,0 +1,235 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+};
+
+use crate::{book_state::BookState, events::BookEvent, system::LibrarySystem};
+
+/// Predicate that must hold in every reachable state
+type StateInvariant<'a> = Box<dyn Fn(&BookState) -> bool + 'a>;
+
+/// Predicate that must hold for every reachable transition (from, event, to)
+type TransitionInvariant<'a> = Box<dyn Fn(&BookState, &BookEvent, &BookState) -> bool + 'a>;
+
+/// One step of a counterexample trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// State before the event
+    pub from: BookState,
+    /// Event that was processed
+    pub event: BookEvent,
+    /// State after the event
+    pub to: BookState,
+}
+
+/// An invariant violation together with the events leading to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    /// Name of the violated invariant
+    pub invariant: String,
+    /// Shortest sequence of steps from the start state to the violation
+    pub trace: Vec<TraceStep>,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invariant violated: {}", self.invariant)?;
+        for step in &self.trace {
+            writeln!(f, "  {:?} --{:?}--> {:?}", step.from, step.event, step.to)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a model checking run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelCheckReport {
+    /// Number of distinct states visited
+    pub states_explored: usize,
+    /// Number of transitions checked
+    pub transitions_explored: usize,
+    /// Whether the depth bound cut off some of the exploration
+    pub bound_reached: bool,
+    /// At most one counterexample per violated invariant
+    pub counterexamples: Vec<Counterexample>,
+}
+
+impl ModelCheckReport {
+    /// Check whether every invariant held
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.counterexamples.is_empty()
+    }
+}
+
+/// Exhaustively explores every event sequence of a system up to a depth bound
+///
+/// The exploration is breadth-first, so every counterexample is a shortest
+/// trace from the system's current state.
+///
+/// ```
+/// use transition_system::{BookEvent, BookState, model_check::ModelChecker, state_machine};
+///
+/// let system = state_machine! {
+///     id: "book-1",
+///     initial: Available,
+///     transitions: {
+///         Available --CheckOut("Alice")--> CheckedOut("Alice"),
+///         CheckedOut("Alice") --ReportLost--> Lost,
+///         Lost --Found--> Available,
+///     },
+/// }?;
+///
+/// let report = ModelChecker::new(&system)
+///     .transition_invariant("a lost book is never checked out", |from, _, to| {
+///         !(*from == BookState::Lost && matches!(to, BookState::CheckedOut(_)))
+///     })
+///     .run();
+/// assert!(report.is_ok());
+/// # Ok::<(), transition_system::system::LibraryError>(())
+/// ```
+pub struct ModelChecker<'a> {
+    /// System whose transition graph is explored
+    system: &'a LibrarySystem,
+    /// Maximum number of events in an explored sequence
+    max_depth: usize,
+    /// Named state invariants
+    state_invariants: Vec<(String, StateInvariant<'a>)>,
+    /// Named transition invariants
+    transition_invariants: Vec<(String, TransitionInvariant<'a>)>,
+}
+
+// Manual implementation of Debug for ModelChecker
+impl fmt::Debug for ModelChecker<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModelChecker")
+            .field("system_id", &self.system.system_id())
+            .field("max_depth", &self.max_depth)
+            .field(
+                "state_invariants",
+                &self.state_invariants.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field(
+                "transition_invariants",
+                &self.transition_invariants.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<'a> ModelChecker<'a> {
+    /// Create a checker for the given system with a default depth bound of 32
+    #[must_use]
+    pub fn new(system: &'a LibrarySystem) -> Self {
+        Self {
+            system,
+            max_depth: 32,
+            state_invariants: Vec::new(),
+            transition_invariants: Vec::new(),
+        }
+    }
+
+    /// Limit the length of explored event sequences
+    #[must_use]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Require a predicate to hold in every reachable state
+    #[must_use]
+    pub fn invariant(mut self, name: &str, check: impl Fn(&BookState) -> bool + 'a) -> Self {
+        self.state_invariants.push((name.to_string(), Box::new(check)));
+        self
+    }
+
+    /// Require a predicate to hold for every reachable transition
+    #[must_use]
+    pub fn transition_invariant(
+        mut self,
+        name: &str,
+        check: impl Fn(&BookState, &BookEvent, &BookState) -> bool + 'a,
+    ) -> Self {
+        self.transition_invariants.push((name.to_string(), Box::new(check)));
+        self
+    }
+
+    /// Explore the state space and check every invariant
+    #[must_use]
+    pub fn run(&self) -> ModelCheckReport {
+        let states = self.system.get_states();
+        let outgoing = outgoing_transitions(self.system);
+
+        let mut report = ModelCheckReport::default();
+        let mut violated = BTreeSet::new();
+        let start = self.system.get_current_state_idx();
+        let mut visited = BTreeSet::from([start]);
+        let mut queue = VecDeque::from([(start, Vec::new())]);
+
+        while let Some((state_idx, trace)) = queue.pop_front() {
+            let Some(state) = states.get(state_idx) else {
+                continue;
+            };
+            report.states_explored = report.states_explored.saturating_add(1);
+            for (name, check) in &self.state_invariants {
+                if !check(state) && violated.insert(name.clone()) {
+                    report
+                        .counterexamples
+                        .push(Counterexample { invariant: name.clone(), trace: trace.clone() });
+                }
+            }
+
+            let edges = outgoing.get(&state_idx).map_or(&[][..], Vec::as_slice);
+            if trace.len() >= self.max_depth {
+                report.bound_reached |= !edges.is_empty();
+                continue;
+            }
+
+            for &(event, to_idx) in edges {
+                let Some(to) = states.get(to_idx) else {
+                    continue;
+                };
+                report.transitions_explored = report.transitions_explored.saturating_add(1);
+                let mut next_trace = trace.clone();
+                next_trace.push(TraceStep {
+                    from: state.clone(),
+                    event: event.clone(),
+                    to: to.clone(),
+                });
+
+                for (name, check) in &self.transition_invariants {
+                    if !check(state, event, to) && violated.insert(name.clone()) {
+                        report.counterexamples.push(Counterexample {
+                            invariant: name.clone(),
+                            trace: next_trace.clone(),
+                        });
+                    }
+                }
+
+                if visited.insert(to_idx) {
+                    queue.push_back((to_idx, next_trace));
+                }
+            }
+        }
+
+        report
+    }
+}
+
+/// Group a system's transitions by source state, in a deterministic order
+pub(crate) fn outgoing_transitions(
+    system: &LibrarySystem,
+) -> BTreeMap<usize, Vec<(&BookEvent, usize)>> {
+    let mut outgoing: BTreeMap<usize, Vec<(&BookEvent, usize)>> = BTreeMap::new();
+    for ((from, event), to) in system.get_all_transitions() {
+        outgoing.entry(*from).or_default().push((event, *to));
+    }
+    for edges in outgoing.values_mut() {
+        edges.sort_by_key(|(event, to)| (format!("{event:?}"), *to));
+    }
+    outgoing
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,70 @@
+use super::*;
+use crate::system::LibraryError;
+
+/// Create a system where a lost book can wrongly be checked out
+fn setup_test_system() -> Result<LibrarySystem, LibraryError> {
+    crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+            Available --ReportLost--> Lost,
+            Lost --CheckOut("Test User")--> CheckedOut("Test User"),
+            CheckedOut("Test User") --Return--> Available,
+        },
+    }
+}
+
+#[test]
+fn test_all_invariants_hold() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let report = ModelChecker::new(&system)
+        .invariant("never in repair", |state| *state != BookState::UnderRepair)
+        .run();
+
+    assert!(report.is_ok());
+    assert_eq!(report.states_explored, 4);
+    assert_eq!(report.transitions_explored, 5);
+    assert!(!report.bound_reached);
+    Ok(())
+}
+
+#[test]
+fn test_shortest_counterexample() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let report = ModelChecker::new(&system)
+        .transition_invariant("a lost book is never checked out", |from, _, to| {
+            !(*from == BookState::Lost && matches!(to, BookState::CheckedOut(_)))
+        })
+        .invariant("never checked out", |state| !matches!(state, BookState::CheckedOut(_)))
+        .run();
+
+    assert_eq!(report.counterexamples.len(), 2);
+    let expected_trace = vec![
+        TraceStep { from: BookState::Available, event: BookEvent::ReportLost, to: BookState::Lost },
+        TraceStep {
+            from: BookState::Lost,
+            event: BookEvent::CheckOut("Test User".into()),
+            to: BookState::CheckedOut("Test User".into()),
+        },
+    ];
+    for counterexample in &report.counterexamples {
+        assert_eq!(counterexample.trace, expected_trace);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_depth_bound() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let report = ModelChecker::new(&system)
+        .max_depth(1)
+        .invariant("never checked out", |state| !matches!(state, BookState::CheckedOut(_)))
+        .run();
+
+    assert!(report.is_ok());
+    assert!(report.bound_reached);
+    assert_eq!(report.states_explored, 3);
+    Ok(())
+}
@@ -1,10 +1,318 @@
+use std::{
+    mem,
+    sync::{Arc, Mutex, PoisonError, mpsc::Sender},
+    time::SystemTime,
+};
+
+use tracing::Span;
+
 use crate::book_state::BookState;
 use crate::events::BookEvent;
+use crate::logging::emit;
+use crate::system::StateTransition;
 
 /// Trait for state change observation
-pub trait StateObserver {
+///
+/// Observers must be `Send` so a system can be shared with background workers
+/// such as the [`TimeoutScheduler`](crate::scheduler::TimeoutScheduler).
+pub trait StateObserver: Send {
     /// Called when a state transition occurs
     fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent);
+
+    /// Called with the full history entry when a state transition occurs
+    ///
+    /// Override this to access the transition's actor, note and metadata; the
+    /// default forwards to [`Self::on_state_change`].
+    fn on_transition(&self, transition: &StateTransition) {
+        self.on_state_change(&transition.from, &transition.to, &transition.event);
+    }
+
+    /// Fallible variant of [`Self::on_transition`], for side effects that can fail
+    ///
+    /// This is what the system calls; what happens when it fails is decided by
+    /// the system's [`ObserverErrorPolicy`]. The default forwards to
+    /// [`Self::on_transition`] and always succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ObserverError` if the notification couldn't be handled
+    fn try_on_transition(&self, transition: &StateTransition) -> Result<(), ObserverError> {
+        self.on_transition(transition);
+        Ok(())
+    }
+
+    /// Called when a transition this observer accepted is undone
+    ///
+    /// Under [`ObserverErrorPolicy::Rollback`], the observers notified before
+    /// the failing one are told, so they can revert their side effects. The
+    /// default does nothing.
+    fn on_rollback(&self, _transition: &StateTransition) {}
+
+    /// Called when a book is automatically reserved for the next patron on the waitlist
+    fn on_hold_fulfilled(&self, _patron: &str) {}
+}
+
+/// Error reported by an observer that couldn't handle a transition
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{0}")]
+pub struct ObserverError(pub String);
+
+/// What a system does when an observer fails to handle a transition
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObserverErrorPolicy {
+    /// Keep the transition and say nothing
+    Ignore,
+    /// Keep the transition and log the error as a warning
+    #[default]
+    Log,
+    /// Undo the transition and return a `LibraryError::ObserverFailed`
+    ///
+    /// Observers notified before the failing one have already seen the
+    /// transition; their [`StateObserver::on_rollback`] is called.
+    Rollback,
+}
+
+/// Handle identifying an observer registered on a system
+///
+/// Returned by [`LibrarySystem::register_observer`](crate::system::LibrarySystem::register_observer)
+/// and used to unregister the observer again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObserverId(pub(crate) u64);
+
+/// Forwards notifications to an observer shared between several systems
+#[derive(Clone)]
+pub struct SharedObserver(Arc<dyn StateObserver + Sync>);
+
+impl SharedObserver {
+    /// Wrap a shared observer so it can be registered on a system
+    #[must_use]
+    pub fn new(observer: Arc<dyn StateObserver + Sync>) -> Self {
+        Self(observer)
+    }
+}
+
+impl std::fmt::Debug for SharedObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedObserver").field("strong_count", &Arc::strong_count(&self.0)).finish()
+    }
+}
+
+impl StateObserver for SharedObserver {
+    fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent) {
+        self.0.on_state_change(from, to, event);
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        self.0.on_transition(transition);
+    }
+
+    fn try_on_transition(&self, transition: &StateTransition) -> Result<(), ObserverError> {
+        self.0.try_on_transition(transition)
+    }
+
+    fn on_rollback(&self, transition: &StateTransition) {
+        self.0.on_rollback(transition);
+    }
+
+    fn on_hold_fulfilled(&self, patron: &str) {
+        self.0.on_hold_fulfilled(patron);
+    }
+}
+
+/// The transitions a filtered observer is interested in
+///
+/// Events and states are compared by variant, so `BookEvent::Reserve` with
+/// any patron matches every reservation. An empty list of events or target
+/// states accepts all of them.
+///
+/// ```
+/// use transition_system::{BookEvent, BookState, observers::Subscription};
+///
+/// // Only lost books
+/// let lost = Subscription::new().event(BookEvent::ReportLost);
+/// // Only books coming back on the shelf, whatever the event
+/// let shelved = Subscription::new().to_state(BookState::Available);
+/// # let _ = (lost, shelved);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subscription {
+    /// Events to be notified of
+    events: Vec<BookEvent>,
+    /// Target states to be notified of
+    to_states: Vec<BookState>,
+}
+
+impl Subscription {
+    /// Create a subscription accepting every transition
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also accept transitions triggered by this kind of event
+    #[must_use]
+    pub fn event(mut self, event: BookEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Also accept transitions into this kind of state
+    #[must_use]
+    pub fn to_state(mut self, state: BookState) -> Self {
+        self.to_states.push(state);
+        self
+    }
+
+    /// Check whether a transition triggered by `event` into `to` is accepted
+    #[must_use]
+    pub fn matches(&self, event: &BookEvent, to: &BookState) -> bool {
+        let event_matches = self.events.is_empty()
+            || self.events.iter().any(|e| mem::discriminant(e) == mem::discriminant(event));
+        let state_matches = self.to_states.is_empty()
+            || self.to_states.iter().any(|s| mem::discriminant(s) == mem::discriminant(to));
+        event_matches && state_matches
+    }
+}
+
+/// Forwards only the transitions matching a [`Subscription`] to an observer
+#[derive(Debug)]
+pub struct FilteredObserver<O> {
+    /// Observer notified of matching transitions
+    observer: O,
+    /// Transitions to forward
+    subscription: Subscription,
+}
+
+impl<O: StateObserver> FilteredObserver<O> {
+    /// Wrap an observer so it is only notified of matching transitions
+    #[must_use]
+    pub fn new(observer: O, subscription: Subscription) -> Self {
+        Self { observer, subscription }
+    }
+}
+
+impl<O: StateObserver> StateObserver for FilteredObserver<O> {
+    fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent) {
+        if self.subscription.matches(event, to) {
+            self.observer.on_state_change(from, to, event);
+        }
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        if self.subscription.matches(&transition.event, &transition.to) {
+            self.observer.on_transition(transition);
+        }
+    }
+
+    fn try_on_transition(&self, transition: &StateTransition) -> Result<(), ObserverError> {
+        if self.subscription.matches(&transition.event, &transition.to) {
+            self.observer.try_on_transition(transition)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn on_rollback(&self, transition: &StateTransition) {
+        if self.subscription.matches(&transition.event, &transition.to) {
+            self.observer.on_rollback(transition);
+        }
+    }
+
+    fn on_hold_fulfilled(&self, patron: &str) {
+        // A fulfilled hold reserves the book for the patron
+        let (event, to) = (BookEvent::Reserve(patron.into()), BookState::Reserved(patron.into()));
+        if self.subscription.matches(&event, &to) {
+            self.observer.on_hold_fulfilled(patron);
+        }
+    }
+}
+
+/// A transition as streamed by a [`ChannelObserver`]: (from, to, event, time)
+///
+/// The time is when the event occurred, as recorded in the history.
+pub type TransitionMessage = (BookState, BookState, BookEvent, SystemTime);
+
+/// Streams every transition into a channel
+///
+/// Lets external components consume transitions from another thread; see
+/// [`LibrarySystem::subscribe`](crate::system::LibrarySystem::subscribe).
+/// Transitions are dropped silently once the receiver is gone.
+#[derive(Debug, Clone)]
+pub struct ChannelObserver(Sender<TransitionMessage>);
+
+impl ChannelObserver {
+    /// Forward transitions to the given sender
+    #[must_use]
+    pub fn new(sender: Sender<TransitionMessage>) -> Self {
+        Self(sender)
+    }
+}
+
+impl StateObserver for ChannelObserver {
+    fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent) {
+        self.0.send((from.clone(), to.clone(), event.clone(), SystemTime::now())).ok();
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        let message = (
+            transition.from.clone(),
+            transition.to.clone(),
+            transition.event.clone(),
+            transition.occurred_at.unwrap_or_else(SystemTime::now),
+        );
+        self.0.send(message).ok();
+    }
+}
+
+/// Keeps a `tracing` span open for as long as a book is in a state
+///
+/// A `book_state` span is opened when the book enters a state and closed when
+/// it leaves it, so tracing backends show how long each book sat in each
+/// state. The span carries the `system_id`, the `state`, and the `event`,
+/// `actor` and `sequence` of the transition that entered it. Spans are never
+/// entered, only opened and closed, so they don't become the parent of
+/// unrelated work on the thread that processed the event.
+#[derive(Debug)]
+pub struct StateSpanObserver {
+    /// System whose states are traced
+    system_id: String,
+    /// Span of the state the book is currently in
+    span: Mutex<Span>,
+}
+
+impl StateSpanObserver {
+    /// Start tracing a system, opening a span for the state it is in now
+    #[must_use]
+    pub fn new(system_id: &str, current_state: &BookState) -> Self {
+        let span = tracing::info_span!(
+            "book_state",
+            system_id,
+            state = ?current_state,
+            event = tracing::field::Empty,
+            actor = tracing::field::Empty,
+            sequence = tracing::field::Empty,
+        );
+        Self { system_id: system_id.to_string(), span: Mutex::new(span) }
+    }
+}
+
+impl StateObserver for StateSpanObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {
+        // Spans are opened in `on_transition`
+    }
+
+    fn on_transition(&self, transition: &StateTransition) {
+        let span = tracing::info_span!(
+            "book_state",
+            system_id = self.system_id,
+            state = ?transition.to,
+            event = ?transition.event,
+            actor = transition.actor.as_deref(),
+            sequence = transition.sequence,
+        );
+        // Dropping the previous span closes it
+        *self.span.lock().unwrap_or_else(PoisonError::into_inner) = span;
+    }
 }
 
 /// Logs all transitions that occur in the system
@@ -13,7 +321,7 @@ pub struct TransitionLogger;
 
 impl StateObserver for TransitionLogger {
     fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent) {
-        println!("LOGGER: Transition occurred: {from:?} --({event:?})--> {to:?}");
+        emit!(info, "LOGGER: Transition occurred: {from:?} --({event:?})--> {to:?}");
     }
 }
 
@@ -25,15 +333,23 @@ impl StateObserver for NotificationService {
     fn on_state_change(&self, from: &BookState, to: &BookState, event: &BookEvent) {
         match (from, to, event) {
             (BookState::Reserved(_), BookState::CheckedOut(_), BookEvent::CheckOut(_)) => {
-                println!("NOTIFICATION: Book has been checked out!");
+                emit!(info, "NOTIFICATION: Book has been checked out!");
             }
             (BookState::CheckedOut(_), BookState::Available, BookEvent::Return) => {
-                println!("NOTIFICATION: Book has been returned!");
+                emit!(info, "NOTIFICATION: Book has been returned!");
             }
             (BookState::UnderRepair, BookState::Available, BookEvent::CompleteRepair) => {
-                println!("NOTIFICATION: Book has been repaired!");
+                emit!(info, "NOTIFICATION: Book has been repaired!");
             }
             _ => {}
         }
     }
+
+    fn on_hold_fulfilled(&self, patron: &str) {
+        emit!(info, "NOTIFICATION: Hold fulfilled, book is now reserved for {patron}!");
+    }
 }
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::{
+    Event, Id, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Record},
+};
+
+use super::*;
+use crate::system::{LibraryError, LibrarySystem};
+
+/// Collects the `state` field of a span
+#[derive(Debug, Default)]
+struct StateField(String);
+
+impl Visit for StateField {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "state" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+/// Subscriber recording when `book_state` spans are opened and closed
+#[derive(Debug, Default)]
+struct SpanRecorder {
+    /// Identifier handed to the next span
+    next_id: AtomicU64,
+    /// State of every open `book_state` span, keyed by span ID
+    open: Mutex<Vec<(u64, String)>>,
+    /// "open <state>" and "close <state>" lines in order
+    log: Mutex<Vec<String>>,
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).saturating_add(1);
+        if span.metadata().name() == "book_state" {
+            let mut state = StateField::default();
+            span.record(&mut state);
+            self.log
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(format!("open {}", state.0));
+            self.open.lock().unwrap_or_else(PoisonError::into_inner).push((id, state.0));
+        }
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+
+    fn try_close(&self, id: Id) -> bool {
+        let mut open = self.open.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(position) = open.iter().position(|(open_id, _)| *open_id == id.into_u64()) {
+            let (_, state) = open.remove(position);
+            self.log.lock().unwrap_or_else(PoisonError::into_inner).push(format!("close {state}"));
+        }
+        true
+    }
+}
+
+#[test]
+fn test_state_span_observer() -> Result<(), LibraryError> {
+    let recorder = Arc::new(SpanRecorder::default());
+    tracing::subscriber::with_default(Arc::clone(&recorder), || {
+        let mut system = LibrarySystem::new(BookState::Available, "book-1");
+        system.add_state(BookState::Lost);
+        system.add_transition(0, BookEvent::ReportLost, 1);
+        system.add_transition(1, BookEvent::Return, 0);
+        system.register_observer(Box::new(StateSpanObserver::new(
+            system.system_id(),
+            system.current_state(),
+        )));
+
+        system.process_event(BookEvent::ReportLost)?;
+        system.process_event(BookEvent::Return)?;
+        Ok::<_, LibraryError>(())
+    })?;
+
+    // Dropping the system closed the span of the state it was left in
+    let log = recorder.log.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    assert_eq!(
+        log,
+        vec![
+            "open Available",
+            "open Lost",
+            "close Available",
+            "open Available",
+            "close Lost",
+            "close Available"
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::Deref,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{book_state::BookState, system::LibraryError};
+
+/// Identifier of a patron, as registered in a [`PatronRegistry`]
+///
+/// The same identifier is used in book states and events. It is written as a
+/// plain string, and its `Debug` output is the string's, so states read
+/// `Reserved("Alice")` in logs and diagrams.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct PatronId(String);
+
+impl PatronId {
+    /// Create an identifier from a patron's name
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// Get the identifier as a string slice
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+// Manual implementation of Debug for PatronId
+impl fmt::Debug for PatronId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for PatronId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Deref for PatronId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for PatronId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for PatronId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for PatronId {
+    fn from(name: &str) -> Self {
+        Self(name.to_string())
+    }
+}
+
+impl From<String> for PatronId {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl From<PatronId> for String {
+    fn from(id: PatronId) -> Self {
+        id.0
+    }
+}
+
+impl PartialEq<str> for PatronId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for PatronId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Maximum number of books a patron may hold at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BorrowingLimits {
+    /// Maximum number of books checked out at the same time
+    pub max_checkouts: usize,
+    /// Maximum number of books reserved at the same time
+    pub max_reservations: usize,
+}
+
+impl Default for BorrowingLimits {
+    fn default() -> Self {
+        Self { max_checkouts: 5, max_reservations: 5 }
+    }
+}
+
+/// A registered patron and the books they currently hold
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Patron {
+    /// The patron's name, as used in book states and events
+    pub name: PatronId,
+    /// System IDs of the books the patron has checked out
+    pub checkouts: BTreeSet<String>,
+    /// System IDs of the books the patron has reserved or waiting on the hold shelf
+    pub reservations: BTreeSet<String>,
+}
+
+/// Registry of patrons shared by all books of a library
+///
+/// Attach it to systems with
+/// [`LibrarySystem::set_patron_registry`](crate::system::LibrarySystem::set_patron_registry)
+/// to reject reservations and checkouts by unknown patrons or patrons at
+/// their limit, and to keep their active loans up to date.
+#[derive(Debug, Clone, Default)]
+pub struct PatronRegistry {
+    /// Registered patrons keyed by name
+    patrons: BTreeMap<PatronId, Patron>,
+    /// Limits applied to every patron
+    limits: BorrowingLimits,
+}
+
+impl PatronRegistry {
+    /// Create an empty registry with the given limits
+    #[must_use]
+    pub fn new(limits: BorrowingLimits) -> Self {
+        Self { patrons: BTreeMap::new(), limits }
+    }
+
+    /// Register a patron, returning `false` if they were already registered
+    pub fn register(&mut self, name: &str) -> bool {
+        if self.patrons.contains_key(name) {
+            return false;
+        }
+        self.patrons.insert(name.into(), Patron { name: name.into(), ..Patron::default() });
+        true
+    }
+
+    /// Remove a patron from the registry
+    pub fn unregister(&mut self, name: &str) -> Option<Patron> {
+        self.patrons.remove(name)
+    }
+
+    /// Get a registered patron
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Patron> {
+        self.patrons.get(name)
+    }
+
+    /// Iterate over all registered patrons in name order
+    pub fn iter(&self) -> impl Iterator<Item = &Patron> {
+        self.patrons.values()
+    }
+
+    /// Get the limits applied to every patron
+    #[must_use]
+    pub fn limits(&self) -> BorrowingLimits {
+        self.limits
+    }
+
+    /// Change the limits applied to every patron
+    ///
+    /// Patrons already above the new limits keep their books but can't take
+    /// out more.
+    pub fn set_limits(&mut self, limits: BorrowingLimits) {
+        self.limits = limits;
+    }
+
+    /// Check whether a book may enter the given state
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::UnknownPatron` if the state names an
+    /// unregistered patron, or a `LibraryError::BorrowingLimitReached` if the
+    /// patron already holds the maximum number of books
+    pub fn check_transition(&self, system_id: &str, to: &BookState) -> Result<(), LibraryError> {
+        let (name, held, limit) = match to {
+            BookState::CheckedOut(name) => {
+                let patron = self.patron(name)?;
+                (name, &patron.checkouts, self.limits.max_checkouts)
+            }
+            BookState::Reserved(name) | BookState::OnHoldShelf(name) => {
+                let patron = self.patron(name)?;
+                (name, &patron.reservations, self.limits.max_reservations)
+            }
+            _ => return Ok(()),
+        };
+
+        if !held.contains(system_id) && held.len() >= limit {
+            return Err(LibraryError::BorrowingLimitReached { patron: name.clone(), limit });
+        }
+        Ok(())
+    }
+
+    /// Update the patrons' active reservations and checkouts after a transition
+    pub fn record_transition(&mut self, system_id: &str, from: &BookState, to: &BookState) {
+        match from {
+            BookState::CheckedOut(name) => {
+                if let Some(patron) = self.patrons.get_mut(name) {
+                    patron.checkouts.remove(system_id);
+                }
+            }
+            BookState::Reserved(name) | BookState::OnHoldShelf(name) => {
+                if let Some(patron) = self.patrons.get_mut(name) {
+                    patron.reservations.remove(system_id);
+                }
+            }
+            _ => {}
+        }
+
+        match to {
+            BookState::CheckedOut(name) => {
+                if let Some(patron) = self.patrons.get_mut(name) {
+                    patron.checkouts.insert(system_id.to_string());
+                }
+            }
+            BookState::Reserved(name) | BookState::OnHoldShelf(name) => {
+                if let Some(patron) = self.patrons.get_mut(name) {
+                    patron.reservations.insert(system_id.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Look up a patron, failing if they are not registered
+    fn patron(&self, name: &str) -> Result<&Patron, LibraryError> {
+        self.patrons.get(name).ok_or_else(|| LibraryError::UnknownPatron(name.to_string()))
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,56 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{
+    book_state::BookState,
+    events::BookEvent,
+    manager::LibraryManager,
+    patrons::{BorrowingLimits, PatronRegistry},
+    system::{LibraryError, LibrarySystem},
+};
+
+/// Create a book that Alice and Bob can check out
+fn checkout_system(system_id: &str) -> LibrarySystem {
+    let mut system = LibrarySystem::new(BookState::Available, system_id);
+    for patron in ["Alice", "Bob"] {
+        let checked_out_idx = system.add_state(BookState::CheckedOut(patron.into()));
+        system.add_transition(0, BookEvent::CheckOut(patron.into()), checked_out_idx);
+        system.add_transition(checked_out_idx, BookEvent::Return, 0);
+    }
+    system
+}
+
+#[test]
+fn test_checkout_rejected_at_borrowing_limit() {
+    let mut registry =
+        PatronRegistry::new(BorrowingLimits { max_checkouts: 1, max_reservations: 1 });
+    registry.register("Alice");
+    let registry = Arc::new(Mutex::new(registry));
+
+    let mut manager = LibraryManager::new();
+    manager.set_patron_registry(Arc::clone(&registry));
+    manager.add_system(checkout_system("book-1"));
+    manager.add_system(checkout_system("book-2"));
+
+    assert!(manager.process_event("book-1", BookEvent::CheckOut("Alice".into())).is_ok());
+    assert!(matches!(
+        manager.process_event("book-2", BookEvent::CheckOut("Alice".into())),
+        Err(LibraryError::BorrowingLimitReached { patron, limit: 1 }) if patron == "Alice"
+    ));
+    assert!(matches!(
+        manager.process_event("book-2", BookEvent::CheckOut("Bob".into())),
+        Err(LibraryError::UnknownPatron(patron)) if patron == "Bob"
+    ));
+    assert_eq!(
+        manager.get("book-2").map(LibrarySystem::current_state),
+        Some(&BookState::Available)
+    );
+
+    // Returning the first book frees up the slot
+    assert!(manager.process_event("book-1", BookEvent::Return).is_ok());
+    assert!(manager.process_event("book-2", BookEvent::CheckOut("Alice".into())).is_ok());
+
+    let registry = registry.lock().unwrap_or_else(PoisonError::into_inner);
+    let checkouts: Vec<_> =
+        registry.get("Alice").map(|p| p.checkouts.iter().cloned().collect()).unwrap_or_default();
+    assert_eq!(checkouts, vec!["book-2".to_string()]);
+}
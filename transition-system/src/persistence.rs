@@ -1,9 +1,27 @@
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    book_state::BookState,
+    branches::BranchLocation,
+    events::BookEvent,
+    fines::FinePolicy,
+    holds::HoldQueue,
+    logging::emit,
+    recurrence::RecurringEvent,
+    system::{LibraryError, StateTransition, TimingConstraints},
+};
 
 /// A serializable representation of a timestamp
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct TimeStamp {
     /// Seconds since Unix epoch
     pub seconds: u64,
@@ -26,36 +44,104 @@ impl TimeStamp {
         Self { seconds: duration.as_secs(), nanos: duration.subsec_nanos() }
     }
 
-    /// Convert to an instant
-    #[allow(dead_code)]
+    /// Create a timestamp from a wall-clock time, clamping times before the Unix epoch
+    #[must_use]
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Self { seconds: duration.as_secs(), nanos: duration.subsec_nanos() }
+    }
+
+    /// Convert to a wall-clock time
+    #[must_use]
+    pub fn to_system_time(&self) -> SystemTime {
+        let since_epoch = Duration::new(self.seconds, self.nanos);
+        UNIX_EPOCH.checked_add(since_epoch).unwrap_or(UNIX_EPOCH)
+    }
+
+    /// Convert to an instant of the monotonic clock
+    ///
+    /// See [`system_time_to_instant`] for the limits of the conversion.
     #[must_use]
     pub fn to_instant(&self) -> Instant {
-        // This is an approximation since we can't create Instant directly
-        Instant::now()
+        system_time_to_instant(self.to_system_time(), Instant::now(), SystemTime::now())
     }
 }
 
+/// Convert an instant to wall-clock time, relative to a pair of "now" readings
+#[must_use]
+pub fn instant_to_system_time(instant: Instant, now: Instant, wall_now: SystemTime) -> SystemTime {
+    let converted = if instant <= now {
+        wall_now.checked_sub(now.duration_since(instant))
+    } else {
+        wall_now.checked_add(instant.duration_since(now))
+    };
+    converted.unwrap_or(wall_now)
+}
+
+/// Convert a wall-clock time to an instant, relative to a pair of "now" readings
+///
+/// Instants can't represent times before the monotonic clock started (usually
+/// when the machine booted); such times are clamped to `now`.
+#[must_use]
+pub fn system_time_to_instant(time: SystemTime, now: Instant, wall_now: SystemTime) -> Instant {
+    let converted = match wall_now.duration_since(time) {
+        Ok(ago) => now.checked_sub(ago),
+        Err(ahead) => now.checked_add(ahead.duration()),
+    };
+    converted.unwrap_or(now)
+}
+
 /// A serializable wrapper around Instant
+///
+/// The wall-clock time of the instant is captured when the wrapper is
+/// created and is what gets serialized, so a saved timestamp stays the same
+/// across any number of save and load cycles. On load the instant is
+/// reconstructed from how long ago that wall-clock time was.
 #[derive(Debug, Clone)]
-pub struct SerializableInstant(Instant);
+pub struct SerializableInstant {
+    /// The instant on the monotonic clock
+    instant: Instant,
+    /// Wall-clock time corresponding to the instant
+    wall_time: SystemTime,
+}
 
 impl SerializableInstant {
     /// Create a new instance with the current time
     #[must_use]
     pub fn now() -> Self {
-        Self(Instant::now())
+        Self { instant: Instant::now(), wall_time: SystemTime::now() }
+    }
+
+    /// Create a new instance from an existing instant
+    #[must_use]
+    pub fn from_instant(instant: Instant) -> Self {
+        let wall_time = instant_to_system_time(instant, Instant::now(), SystemTime::now());
+        Self { instant, wall_time }
+    }
+
+    /// Create a new instance from a wall-clock time
+    #[must_use]
+    pub fn from_system_time(wall_time: SystemTime) -> Self {
+        let instant = system_time_to_instant(wall_time, Instant::now(), SystemTime::now());
+        Self { instant, wall_time }
     }
 
     /// Get the elapsed time since this instant was created
     #[must_use]
     pub fn elapsed(&self) -> Duration {
-        self.0.elapsed()
+        self.instant.elapsed()
     }
 
     /// Get the underlying Instant
     #[must_use]
     pub fn inner(&self) -> &Instant {
-        &self.0
+        &self.instant
+    }
+
+    /// Get the wall-clock time of the instant
+    #[must_use]
+    pub fn wall_time(&self) -> SystemTime {
+        self.wall_time
     }
 }
 
@@ -64,9 +150,7 @@ impl Serialize for SerializableInstant {
     where
         S: Serializer,
     {
-        // We just serialize a timestamp when it was created
-        let timestamp = TimeStamp::now();
-        timestamp.serialize(serializer)
+        TimeStamp::from_system_time(self.wall_time).serialize(serializer)
     }
 }
 
@@ -75,8 +159,724 @@ impl<'de> Deserialize<'de> for SerializableInstant {
     where
         D: Deserializer<'de>,
     {
-        // We deserialize the timestamp but discard it and create a new Instant
-        let _timestamp = TimeStamp::deserialize(deserializer)?;
-        Ok(Self::now())
+        let timestamp = TimeStamp::deserialize(deserializer)?;
+        Ok(Self::from_system_time(timestamp.to_system_time()))
+    }
+}
+
+/// Serializable representation of the system state
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SerializableSystemState {
+    /// Collection of all book states
+    pub states: Vec<BookState>,
+    /// Mapping of state transitions
+    pub transitions: Vec<((usize, BookEvent), usize)>,
+    /// Index of the current state
+    pub current_state_idx: usize,
+    /// Record of state transition history
+    pub history: Vec<StateTransition>,
+    /// Maximum number of history entries to keep
+    pub max_history_size: usize,
+    /// State timing constraints
+    pub timing_constraints: Vec<(usize, TimingConstraints)>,
+    /// Unique identifier for this system
+    pub system_id: String,
+    /// Overdue fine rules, if fines are enabled
+    #[serde(default)]
+    pub fine_policy: Option<FinePolicy>,
+    /// Patrons waiting for the book
+    #[serde(default)]
+    pub holds: HoldQueue,
+    /// Branch holding the book and any transfer in progress
+    #[serde(default)]
+    pub location: BranchLocation,
+    /// Indices of states that are expected to have no outgoing transitions
+    #[serde(default)]
+    pub final_states: BTreeSet<usize>,
+    /// Categories the states are grouped into, keyed by state index
+    #[serde(default)]
+    pub state_categories: BTreeMap<usize, String>,
+    /// Number of transitions applied since the system was created
+    #[serde(default)]
+    pub sequence: u64,
+    /// Version of the stored copy the state was loaded from or last saved
+    /// as, 0 if it was never stored
+    #[serde(default)]
+    pub version: u64,
+    /// Wall-clock time the current state was entered
+    #[serde(default)]
+    pub state_entered_at: Option<TimeStamp>,
+    /// Wall-clock time the current loan is due, if fines are enabled
+    #[serde(default)]
+    pub due_date: Option<TimeStamp>,
+    /// Recurring events and the wall-clock time each is next due
+    #[serde(default)]
+    pub recurring_events: Vec<(RecurringEvent, TimeStamp)>,
+}
+
+/// Storage backend for system state snapshots
+///
+/// Implement this to keep systems in a database or another service instead of
+/// JSON files, and use it with
+/// [`LibrarySystem::save_to`](crate::system::LibrarySystem::save_to) and
+/// [`LibrarySystem::load_from`](crate::system::LibrarySystem::load_from).
+pub trait StateStore: Send + Sync {
+    /// Save a system's state, replacing any state stored under the same system ID
+    ///
+    /// Stores shared by several instances may only replace a stored copy
+    /// whose version is `state.version`, storing the state as the next
+    /// version.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the state can't be
+    /// stored, or a `LibraryError::ConflictingUpdate` if the stored copy
+    /// changed since the state was loaded
+    fn save(&self, state: &SerializableSystemState) -> Result<(), LibraryError>;
+
+    /// Load the state of the system with the given ID
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if no state is stored for the
+    /// system or it can't be read
+    fn load(&self, system_id: &str) -> Result<SerializableSystemState, LibraryError>;
+
+    /// List the IDs of all stored systems, sorted
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the store can't be listed
+    fn list_systems(&self) -> Result<Vec<String>, LibraryError>;
+
+    /// Delete the stored state of a system
+    ///
+    /// Deleting a system that isn't stored is not an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the state can't be deleted
+    fn delete(&self, system_id: &str) -> Result<(), LibraryError>;
+
+    /// Check whether a state is stored for the system
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the store can't be listed
+    fn exists(&self, system_id: &str) -> Result<bool, LibraryError> {
+        Ok(self.list_systems()?.iter().any(|id| id == system_id))
+    }
+
+    /// Load the states of all stored systems, sorted by system ID
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered while listing or loading
+    fn load_all(&self) -> Result<Vec<SerializableSystemState>, LibraryError> {
+        self.list_systems()?.iter().map(|system_id| self.load(system_id)).collect()
+    }
+}
+
+/// When a system persists itself to its auto-save store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AutoSavePolicy {
+    /// Only save when explicitly asked to
+    #[default]
+    Never,
+    /// Save after every transition
+    EveryTransition,
+    /// Save once `n` transitions have been made since the last save
+    EveryN(u64),
+}
+
+impl AutoSavePolicy {
+    /// Check whether a save is due after the given number of unsaved transitions
+    #[must_use]
+    pub fn is_due(self, unsaved: u64) -> bool {
+        match self {
+            Self::Never => false,
+            Self::EveryTransition => unsaved > 0,
+            Self::EveryN(n) => unsaved > 0 && unsaved >= n,
+        }
+    }
+}
+
+/// Encoding used for persisted system state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PersistenceFormat {
+    /// Pretty-printed JSON, easy to read and edit by hand
+    #[default]
+    Json,
+    /// Compact binary encoding using bincode (`bincode` feature)
+    Bincode,
+    /// Compact, self-describing binary encoding (`msgpack` feature)
+    MessagePack,
+}
+
+impl PersistenceFormat {
+    /// Get the file extension used for this format
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Bincode => "bin",
+            Self::MessagePack => "msgpack",
+        }
+    }
+
+    /// Encode a system state in this format
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if encoding fails or support
+    /// for the format was not compiled in
+    pub fn serialize(self, state: &SerializableSystemState) -> Result<Vec<u8>, LibraryError> {
+        let encoded = match self {
+            Self::Json => serde_json::to_vec_pretty(state).map_err(|e| e.to_string()),
+            #[cfg(feature = "bincode")]
+            Self::Bincode => bincode::serialize(state).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::to_vec_named(state).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => Err(format!("Support for {self:?} is not enabled")),
+        };
+        encoded.map_err(LibraryError::PersistenceError)
+    }
+
+    /// Decode a system state encoded in this format
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if decoding fails or support for
+    /// the format was not compiled in
+    pub fn deserialize(self, bytes: &[u8]) -> Result<SerializableSystemState, LibraryError> {
+        let decoded = match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "bincode")]
+            Self::Bincode => bincode::deserialize(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = bytes;
+                Err(format!("Support for {self:?} is not enabled"))
+            }
+        };
+        decoded.map_err(|e| LibraryError::LoadError(format!("Failed to parse {self:?}: {e}")))
+    }
+}
+
+/// Prefix of the checksum line heading every encoded state file
+const CHECKSUM_PREFIX: &[u8] = b"sha256:";
+
+/// Length of the checksum line: prefix, 64 hex digits and a newline
+const CHECKSUM_LINE_LEN: usize = CHECKSUM_PREFIX.len() + 64 + 1;
+
+/// Prepend a `sha256:<hex>` line with the SHA-256 hash of the payload
+fn add_checksum(payload: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(CHECKSUM_LINE_LEN.saturating_add(payload.len()));
+    contents.extend_from_slice(CHECKSUM_PREFIX);
+    contents.extend_from_slice(sha256_hex(payload).as_bytes());
+    contents.push(b'\n');
+    contents.extend_from_slice(payload);
+    contents
+}
+
+/// Verify and strip the checksum line added by [`add_checksum`]
+///
+/// Contents without a checksum line are returned as is, so files saved
+/// before checksums were introduced can still be loaded.
+fn verify_checksum(contents: &[u8]) -> Result<&[u8], LibraryError> {
+    if !contents.starts_with(CHECKSUM_PREFIX) {
+        return Ok(contents);
+    }
+    let (expected, payload) = contents
+        .get(CHECKSUM_PREFIX.len()..)
+        .and_then(|rest| rest.split_at_checked(64))
+        .and_then(|(expected, rest)| Some((expected, rest.strip_prefix(b"\n")?)))
+        .ok_or_else(|| LibraryError::CorruptState("Checksum line is malformed".to_string()))?;
+
+    let actual = sha256_hex(payload);
+    if actual.as_bytes() != expected {
+        return Err(LibraryError::CorruptState(format!(
+            "Checksum mismatch: expected {}, found {actual}",
+            String::from_utf8_lossy(expected)
+        )));
+    }
+    Ok(payload)
+}
+
+/// Hash a payload with SHA-256 as lowercase hex
+fn sha256_hex(payload: &[u8]) -> String {
+    use std::fmt::Write as _;
+
+    Sha256::digest(payload).iter().fold(String::with_capacity(64), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// First bytes of a gzip stream
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// First bytes of a Zstandard frame
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression applied around the encoded system state
+///
+/// Compressed payloads are recognised by their magic bytes on load, so a
+/// store can read files written with any compression, or none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Store the encoded state as is
+    #[default]
+    None,
+    /// gzip compression (`gzip` feature)
+    Gzip,
+    /// Zstandard compression (`zstd` feature)
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression of a payload from its magic bytes
+    #[must_use]
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if bytes.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Compress an encoded payload
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if compression fails or
+    /// support for it was not compiled in
+    pub fn compress(self, bytes: Vec<u8>) -> Result<Vec<u8>, LibraryError> {
+        let compressed: std::io::Result<Vec<u8>> = match self {
+            Self::None => Ok(bytes),
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&bytes).and_then(|()| encoder.finish())
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::encode_all(bytes.as_slice(), 0),
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(LibraryError::PersistenceError(format!(
+                    "Support for {self:?} compression is not enabled"
+                )));
+            }
+        };
+        compressed
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to compress state: {e}")))
+    }
+
+    /// Decompress a payload, detecting its compression from its magic bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::CorruptState` if the payload can't be
+    /// decompressed, or a `LibraryError::LoadError` if support for the
+    /// detected compression was not compiled in
+    pub fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, LibraryError> {
+        let compression = Self::detect(&bytes);
+        let decompressed: std::io::Result<Vec<u8>> = match compression {
+            Self::None => Ok(bytes),
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut decompressed = Vec::new();
+                flate2::read::GzDecoder::new(bytes.as_slice())
+                    .read_to_end(&mut decompressed)
+                    .map(|_| decompressed)
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::decode_all(bytes.as_slice()),
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(LibraryError::LoadError(format!(
+                    "Support for {compression:?} compression is not enabled"
+                )));
+            }
+        };
+        decompressed
+            .map_err(|e| LibraryError::CorruptState(format!("Failed to decompress state: {e}")))
+    }
+}
+
+/// How system IDs are turned into file names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileNaming {
+    /// Percent-encode every character except ASCII letters, digits, `.`, `_`
+    /// and `-`, so IDs can't escape the directory and distinct IDs never
+    /// share a file
+    #[default]
+    Sanitized,
+    /// Use the system ID as is; only for IDs known to be safe in paths
+    Verbatim,
+}
+
+impl FileNaming {
+    /// Get the file name stem for a system ID
+    #[must_use]
+    pub fn file_stem(self, system_id: &str) -> String {
+        match self {
+            Self::Verbatim => system_id.to_string(),
+            Self::Sanitized => system_id
+                .bytes()
+                .map(|byte| {
+                    if byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'_' | b'-') {
+                        char::from(byte).to_string()
+                    } else {
+                        format!("%{byte:02X}")
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Recover the system ID from a file name stem, if it's a valid stem
+    #[must_use]
+    pub fn system_id(self, stem: &str) -> Option<String> {
+        match self {
+            Self::Verbatim => Some(stem.to_string()),
+            Self::Sanitized => {
+                let mut bytes = Vec::with_capacity(stem.len());
+                let mut rest = stem.as_bytes();
+                while let Some((&byte, tail)) = rest.split_first() {
+                    if byte == b'%' {
+                        let (hex, tail) = tail.split_at_checked(2)?;
+                        let hex = std::str::from_utf8(hex).ok()?;
+                        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                        rest = tail;
+                    } else {
+                        bytes.push(byte);
+                        rest = tail;
+                    }
+                }
+                String::from_utf8(bytes).ok()
+            }
+        }
+    }
+}
+
+/// Where a [`FileStore`] keeps its files and how it names them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PersistenceConfig {
+    /// Directory the files are kept in; empty for the working directory
+    pub dir: PathBuf,
+    /// File extension, or `None` for the format's default extension
+    pub extension: Option<String>,
+    /// How system IDs are turned into file names
+    pub naming: FileNaming,
+}
+
+impl PersistenceConfig {
+    /// Create a config keeping files in the given directory
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), ..Self::default() }
     }
 }
+
+/// Stores each system as a file named `<system_id>.<extension>`
+///
+/// System IDs are sanitized before use in file names and the directory and
+/// extension can be changed, see [`PersistenceConfig`].
+///
+/// Files are uncompressed pretty-printed JSON unless another
+/// [`PersistenceFormat`] or [`Compression`] is chosen. The encoded state is
+/// preceded by a `sha256:<hex>` line, and files whose contents don't match it
+/// fail to load with `LibraryError::CorruptState`.
+///
+/// Saves are atomic (written to a temporary file, then renamed) and both
+/// saves and loads hold an advisory lock on a `.lock` sidecar file, so
+/// concurrent processes can't clobber each other or read a half-written file.
+#[derive(Debug, Clone, Default)]
+pub struct FileStore {
+    /// Directory, extension and naming of the files
+    config: PersistenceConfig,
+    /// Encoding of the files
+    format: PersistenceFormat,
+    /// Compression applied when saving
+    compression: Compression,
+    /// Number of previous versions kept as `<file>.1`, `<file>.2`, ...
+    backups: usize,
+}
+
+impl FileStore {
+    /// Create a store keeping its files in the given directory
+    #[must_use]
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self::from_config(PersistenceConfig::new(directory))
+    }
+
+    /// Create a store using the given directory, extension and file naming
+    #[must_use]
+    pub fn from_config(config: PersistenceConfig) -> Self {
+        Self {
+            config,
+            format: PersistenceFormat::default(),
+            compression: Compression::default(),
+            backups: 0,
+        }
+    }
+
+    /// Use the given encoding for the files
+    #[must_use]
+    pub fn with_format(mut self, format: PersistenceFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Compress files when saving
+    ///
+    /// Loading detects the compression of each file, so switching it doesn't
+    /// make previously saved files unreadable.
+    #[must_use]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Keep the given number of previous versions of each file
+    ///
+    /// On every save the current file becomes `<file>.1`, the previous `.1`
+    /// becomes `.2`, and so on; versions beyond `backups` are deleted.
+    #[must_use]
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    /// Get the encoding of the files
+    #[must_use]
+    pub fn format(&self) -> PersistenceFormat {
+        self.format
+    }
+
+    /// Get the path of the `n`th previous version of a system's state file
+    #[must_use]
+    pub fn backup_path(&self, system_id: &str, n: usize) -> PathBuf {
+        self.sidecar_path(system_id, &n.to_string())
+    }
+
+    /// Load the `n`th previous version of a system's state, 1 being the most recent
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the backup doesn't exist or
+    /// can't be read
+    pub fn load_backup(
+        &self,
+        system_id: &str,
+        n: usize,
+    ) -> Result<SerializableSystemState, LibraryError> {
+        self.decode(self.read_path(system_id, &self.backup_path(system_id, n))?)
+    }
+
+    /// Shift the backups of a system up by one and copy the current file to `.1`
+    ///
+    /// The current file is linked rather than moved, so there is always a
+    /// complete state file even if the process dies mid-save.
+    fn rotate_backups(&self, system_id: &str) -> std::io::Result<()> {
+        let current = self.path(system_id);
+        if self.backups == 0 || !current.exists() {
+            return Ok(());
+        }
+
+        let oldest = self.backup_path(system_id, self.backups);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for n in (1..self.backups).rev() {
+            let backup = self.backup_path(system_id, n);
+            if backup.exists() {
+                fs::rename(&backup, self.backup_path(system_id, n.saturating_add(1)))?;
+            }
+        }
+
+        let newest = self.backup_path(system_id, 1);
+        fs::hard_link(&current, &newest).or_else(|_| fs::copy(&current, &newest).map(|_| ()))
+    }
+
+    /// Get the path of the file holding the given system's state
+    #[must_use]
+    pub fn path(&self, system_id: &str) -> PathBuf {
+        let stem = self.config.naming.file_stem(system_id);
+        self.config.dir.join(format!("{stem}.{}", self.extension()))
+    }
+
+    /// Get the path of a file next to the system's state file, e.g. its lock
+    fn sidecar_path(&self, system_id: &str, suffix: &str) -> PathBuf {
+        self.path(system_id).with_extension(format!("{}.{suffix}", self.extension()))
+    }
+
+    /// Get the extension of the state files
+    fn extension(&self) -> &str {
+        self.config.extension.as_deref().unwrap_or(self.format.extension())
+    }
+
+    /// Take an advisory lock on the system's `.lock` sidecar file
+    ///
+    /// The state file itself is replaced on every save, so locking it would
+    /// not exclude other processes. The lock is released when the returned
+    /// file is dropped.
+    fn lock(&self, system_id: &str, exclusive: bool) -> std::io::Result<File> {
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sidecar_path(system_id, "lock"))?;
+        if exclusive {
+            lock_file.lock()?;
+        } else {
+            lock_file.lock_shared()?;
+        }
+        Ok(lock_file)
+    }
+}
+
+impl StateStore for FileStore {
+    fn save(&self, state: &SerializableSystemState) -> Result<(), LibraryError> {
+        self.write_file(&state.system_id, &self.encode(state)?)
+    }
+
+    fn load(&self, system_id: &str) -> Result<SerializableSystemState, LibraryError> {
+        self.decode(self.read_file(system_id)?)
+    }
+
+    fn list_systems(&self) -> Result<Vec<String>, LibraryError> {
+        let directory =
+            if self.config.dir.as_os_str().is_empty() { Path::new(".") } else { &self.config.dir };
+        let entries = fs::read_dir(directory)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to list directory: {e}")))?;
+
+        let suffix = format!(".{}", self.extension());
+        let mut system_ids = Vec::new();
+        for entry in entries {
+            let entry = entry
+                .map_err(|e| LibraryError::LoadError(format!("Failed to list directory: {e}")))?;
+            let file_name = entry.file_name();
+            let Some(stem) = file_name.to_str().and_then(|name| name.strip_suffix(&suffix)) else {
+                continue;
+            };
+            // Skip names this store would never produce, e.g. other programs' files
+            if let Some(system_id) = self.config.naming.system_id(stem)
+                && self.path(&system_id).file_name() == Some(file_name.as_os_str())
+            {
+                system_ids.push(system_id);
+            }
+        }
+        system_ids.sort();
+        Ok(system_ids)
+    }
+
+    fn delete(&self, system_id: &str) -> Result<(), LibraryError> {
+        emit!(info, "PERSISTENCE: Deleting state of {system_id}");
+        let delete_error = |e: std::io::Error| {
+            LibraryError::PersistenceError(format!("Failed to delete file: {e}"))
+        };
+
+        let lock = self.lock(system_id, true).map_err(delete_error)?;
+        let backups = (1..=self.backups).map(|n| self.backup_path(system_id, n));
+        for path in std::iter::once(self.path(system_id)).chain(backups) {
+            if path.exists() {
+                fs::remove_file(&path).map_err(delete_error)?;
+            }
+        }
+        drop(lock);
+        fs::remove_file(self.sidecar_path(system_id, "lock")).ok();
+        Ok(())
+    }
+
+    fn exists(&self, system_id: &str) -> Result<bool, LibraryError> {
+        Ok(self.path(system_id).exists())
+    }
+}
+
+impl FileStore {
+    /// Serialize, checksum and compress a system state with the store's settings
+    pub(crate) fn encode(&self, state: &SerializableSystemState) -> Result<Vec<u8>, LibraryError> {
+        self.compression.compress(add_checksum(&self.format.serialize(state)?))
+    }
+
+    /// Decompress, verify and deserialize the contents of a state file
+    pub(crate) fn decode(
+        &self,
+        contents: Vec<u8>,
+    ) -> Result<SerializableSystemState, LibraryError> {
+        self.format.deserialize(verify_checksum(&Compression::decompress(contents)?)?)
+    }
+
+    /// Atomically replace a system's state file while holding its lock
+    pub(crate) fn write_file(&self, system_id: &str, contents: &[u8]) -> Result<(), LibraryError> {
+        let filename = self.path(system_id);
+        emit!(info, "PERSISTENCE: Saving state to file: {}", filename.display());
+
+        let _lock = self
+            .lock(system_id, true)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to lock file: {e}")))?;
+
+        // Write a temporary file and rename it over the target, so a crash
+        // mid-write leaves the previous state intact
+        let temp_filename = self.sidecar_path(system_id, "tmp");
+        let mut file = File::create(&temp_filename)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to create file: {e}")))?;
+
+        file.write_all(contents)
+            .and_then(|()| file.sync_all())
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to write to file: {e}")))?;
+
+        self.rotate_backups(system_id).map_err(|e| {
+            LibraryError::PersistenceError(format!("Failed to rotate backups: {e}"))
+        })?;
+
+        fs::rename(&temp_filename, &filename)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to replace file: {e}")))
+    }
+
+    /// Read a system's state file while holding its lock
+    pub(crate) fn read_file(&self, system_id: &str) -> Result<Vec<u8>, LibraryError> {
+        self.read_path(system_id, &self.path(system_id))
+    }
+
+    /// Read one of a system's files while holding its lock
+    pub(crate) fn read_path(
+        &self,
+        system_id: &str,
+        filename: &Path,
+    ) -> Result<Vec<u8>, LibraryError> {
+        emit!(info, "PERSISTENCE: Loading state from file: {}", filename.display());
+
+        if !filename.exists() {
+            return Err(LibraryError::LoadError(format!(
+                "File does not exist: {}",
+                filename.display()
+            )));
+        }
+
+        let _lock = self
+            .lock(system_id, false)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to lock file: {e}")))?;
+
+        // Read the file
+        let mut file = File::open(filename)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to open file: {e}")))?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to read file: {e}")))?;
+        Ok(contents)
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
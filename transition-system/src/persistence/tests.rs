@@ -0,0 +1,426 @@
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use super::*;
+use crate::system::LibrarySystem;
+
+/// Store keeping snapshots in memory
+#[derive(Debug, Default)]
+struct MemoryStore {
+    /// Snapshots keyed by system ID
+    states: Mutex<HashMap<String, SerializableSystemState>>,
+}
+
+impl StateStore for MemoryStore {
+    fn save(&self, state: &SerializableSystemState) -> Result<(), LibraryError> {
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(state.system_id.clone(), state.clone());
+        Ok(())
+    }
+
+    fn load(&self, system_id: &str) -> Result<SerializableSystemState, LibraryError> {
+        self.states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(system_id)
+            .cloned()
+            .ok_or_else(|| LibraryError::LoadError(format!("No state for {system_id}")))
+    }
+
+    fn list_systems(&self) -> Result<Vec<String>, LibraryError> {
+        let mut system_ids: Vec<_> =
+            self.states.lock().unwrap_or_else(PoisonError::into_inner).keys().cloned().collect();
+        system_ids.sort();
+        Ok(system_ids)
+    }
+
+    fn delete(&self, system_id: &str) -> Result<(), LibraryError> {
+        self.states.lock().unwrap_or_else(PoisonError::into_inner).remove(system_id);
+        Ok(())
+    }
+}
+
+/// Memory store rejecting stale saves, like `PostgresStore`
+#[derive(Debug, Default)]
+struct VersionedStore(MemoryStore);
+
+impl StateStore for VersionedStore {
+    fn save(&self, state: &SerializableSystemState) -> Result<(), LibraryError> {
+        let stored = self.0.load(&state.system_id).map_or(0, |stored| stored.version);
+        if stored != state.version {
+            return Err(LibraryError::ConflictingUpdate(state.system_id.clone()));
+        }
+        self.0.save(&SerializableSystemState { version: stored.saturating_add(1), ..state.clone() })
+    }
+
+    fn load(&self, system_id: &str) -> Result<SerializableSystemState, LibraryError> {
+        self.0.load(system_id)
+    }
+
+    fn list_systems(&self) -> Result<Vec<String>, LibraryError> {
+        self.0.list_systems()
+    }
+
+    fn delete(&self, system_id: &str) -> Result<(), LibraryError> {
+        self.0.delete(system_id)
+    }
+}
+
+/// Create a reserved test system
+fn setup_test_system() -> Result<LibrarySystem, LibraryError> {
+    let mut system = crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+        },
+    }?;
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+    Ok(system)
+}
+
+#[test]
+fn test_stale_saves_conflict() -> Result<(), LibraryError> {
+    let store = VersionedStore::default();
+    let system = setup_test_system()?;
+    system.save_to(&store)?;
+    system.save_to(&store)?;
+    assert_eq!(system.stored_version(), 2);
+
+    let mut loaded = LibrarySystem::load_from(&store, "test-book")?;
+    assert_eq!(loaded.stored_version(), 2);
+    loaded.process_event(BookEvent::CancelReservation)?;
+    loaded.save_to(&store)?;
+
+    // The first copy is now stale and stays at the version it had
+    assert!(matches!(system.save_to(&store), Err(LibraryError::ConflictingUpdate(_))));
+    assert_eq!(system.stored_version(), 2);
+    assert_eq!(
+        LibrarySystem::load_from(&store, "test-book")?.current_state(),
+        &BookState::Available
+    );
+    Ok(())
+}
+
+#[test]
+fn test_custom_store_round_trip() -> Result<(), LibraryError> {
+    let store = MemoryStore::default();
+    setup_test_system()?.save_to(&store)?;
+
+    let mut loaded = LibrarySystem::load_from(&store, "test-book")?;
+    assert_eq!(*loaded.current_state(), BookState::Reserved("Test User".into()));
+    assert_eq!(loaded.get_history().len(), 1);
+    loaded.process_event(BookEvent::CancelReservation)?;
+
+    assert!(matches!(LibrarySystem::load_from(&store, "missing"), Err(LibraryError::LoadError(_))));
+    Ok(())
+}
+
+#[test]
+fn test_file_store_directory() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("file-store-test-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory);
+    assert_eq!(store.path("test-book"), directory.join("test-book.json"));
+
+    let result = setup_test_system()
+        .and_then(|system| system.save_to(&store))
+        .and_then(|()| LibrarySystem::load_from(&store, "test-book"));
+    fs::remove_dir_all(&directory)?;
+
+    assert_eq!(*result?.current_state(), BookState::Reserved("Test User".into()));
+    Ok(())
+}
+
+#[test]
+fn test_file_store_atomic_locked_save() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("file-store-lock-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory);
+
+    let result = (|| {
+        setup_test_system()?.save_to(&store)?;
+        assert!(store.path("test-book").exists());
+        assert!(!store.path("test-book").with_extension("json.tmp").exists());
+
+        // While a save holds the lock, nobody else can take it
+        let lock = store.lock("test-book", true)?;
+        let other = File::open(store.path("test-book").with_extension("json.lock"))?;
+        assert!(other.try_lock_shared().is_err());
+        drop(lock);
+        assert!(other.try_lock_shared().is_ok());
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_binary_formats_round_trip() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("file-store-format-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let formats = [
+        #[cfg(feature = "bincode")]
+        PersistenceFormat::Bincode,
+        #[cfg(feature = "msgpack")]
+        PersistenceFormat::MessagePack,
+    ];
+
+    let result = (|| {
+        let system = setup_test_system()?;
+        for format in formats {
+            let store = FileStore::new(&directory).with_format(format);
+            system.save_to(&store)?;
+            assert_eq!(
+                store.path("test-book"),
+                directory.join(format!("test-book.{}", format.extension()))
+            );
+
+            let loaded = LibrarySystem::load_from(&store, "test-book")?;
+            assert_eq!(*loaded.current_state(), BookState::Reserved("Test User".into()));
+            assert_eq!(loaded.get_history().len(), 1);
+
+            // Binary files are not mistaken for JSON
+            let json = FileStore::new(&directory);
+            fs::copy(store.path("test-book"), json.path("test-book"))?;
+            assert!(matches!(
+                LibrarySystem::load_from(&json, "test-book"),
+                Err(LibraryError::LoadError(_))
+            ));
+        }
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_compression_detected_on_load() -> Result<(), LibraryError> {
+    let directory =
+        std::env::temp_dir().join(format!("file-store-compress-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let compressions = [
+        Compression::None,
+        #[cfg(feature = "gzip")]
+        Compression::Gzip,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd,
+    ];
+
+    let result = (|| {
+        let system = setup_test_system()?;
+        for compression in compressions {
+            let store = FileStore::new(&directory).with_compression(compression);
+            system.save_to(&store)?;
+            assert_eq!(Compression::detect(&fs::read(store.path("test-book"))?), compression);
+
+            // A store saving with other settings still reads the file
+            let loaded = LibrarySystem::load_from(&FileStore::new(&directory), "test-book")?;
+            assert_eq!(*loaded.current_state(), BookState::Reserved("Test User".into()));
+        }
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_corrupt_compressed_file() -> Result<(), LibraryError> {
+    let directory =
+        std::env::temp_dir().join(format!("file-store-corrupt-gzip-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory).with_compression(Compression::Gzip);
+
+    let result = (|| {
+        setup_test_system()?.save_to(&store)?;
+        let contents = fs::read(store.path("test-book"))?;
+        fs::write(store.path("test-book"), contents.get(..contents.len() / 2).unwrap_or_default())?;
+        assert!(matches!(store.load("test-book"), Err(LibraryError::CorruptState(_))));
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_timestamps_survive_restart() -> Result<(), LibraryError> {
+    let mut state = setup_test_system()?.to_serializable_state();
+    let saved = serde_json::to_string(&state)?;
+
+    // Timestamps are stable across load and save cycles
+    let reloaded: SerializableSystemState = serde_json::from_str(&saved)?;
+    assert_eq!(serde_json::to_string(&reloaded)?, saved);
+
+    // The time already spent in the current state counts towards its timeout
+    state.timing_constraints = vec![(
+        1,
+        crate::system::TimingConstraints {
+            max_duration: Duration::from_mins(3),
+            timeout_event: BookEvent::CancelReservation,
+        },
+    )];
+    state.state_entered_at =
+        SystemTime::now().checked_sub(Duration::from_mins(2)).map(TimeStamp::from_system_time);
+    let restored = LibrarySystem::from_serializable_state(state);
+    let remaining = restored.time_until_timeout().unwrap_or_default();
+    assert!(remaining > Duration::from_secs(59) && remaining <= Duration::from_mins(1));
+    Ok(())
+}
+
+#[test]
+fn test_autosave_policy() -> Result<(), LibraryError> {
+    let store = Arc::new(MemoryStore::default());
+    let mut system = setup_test_system()?;
+    system.set_autosave(AutoSavePolicy::EveryN(2), store.clone());
+    assert_eq!(system.autosave_policy(), AutoSavePolicy::EveryN(2));
+
+    // Not due after one transition
+    system.process_event(BookEvent::CancelReservation)?;
+    assert!(store.load("test-book").is_err());
+
+    system.process_event(BookEvent::Reserve("Test User".into()))?;
+    assert_eq!(store.load("test-book")?.sequence, 3);
+
+    system.set_autosave(AutoSavePolicy::EveryTransition, store.clone());
+    system.process_event(BookEvent::CancelReservation)?;
+    assert_eq!(store.load("test-book")?.sequence, 4);
+
+    // A rejected event changes nothing, so nothing is saved
+    assert!(system.process_event(BookEvent::CancelReservation).is_err());
+    assert!(!AutoSavePolicy::Never.is_due(10));
+    Ok(())
+}
+
+#[test]
+fn test_backup_rotation() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("file-store-backups-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory).with_backups(2);
+    assert_eq!(store.backup_path("test-book", 1), directory.join("test-book.json.1"));
+
+    let result = (|| {
+        let mut system = setup_test_system()?;
+        system.save_to(&store)?;
+        for _ in 0..3 {
+            system.process_event(BookEvent::CancelReservation)?;
+            system.process_event(BookEvent::Reserve("Test User".into()))?;
+            system.save_to(&store)?;
+        }
+
+        // Latest save has sequence 7, the two before it 5 and 3
+        assert_eq!(store.load("test-book")?.sequence, 7);
+        assert_eq!(store.load_backup("test-book", 1)?.sequence, 5);
+        assert_eq!(store.load_backup("test-book", 2)?.sequence, 3);
+        assert!(!store.backup_path("test-book", 3).exists());
+
+        // A corrupted latest file doesn't prevent falling back to a backup
+        fs::write(store.path("test-book"), "corrupted")?;
+        assert!(store.load("test-book").is_err());
+        let restored = LibrarySystem::from_serializable_state(store.load_backup("test-book", 1)?);
+        assert_eq!(*restored.current_state(), BookState::Reserved("Test User".into()));
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_persistence_config_naming() {
+    assert_eq!(FileNaming::Sanitized.file_stem("book-1234_v2.1"), "book-1234_v2.1");
+    assert_eq!(FileNaming::Sanitized.file_stem("../etc/passwd"), "..%2Fetc%2Fpasswd");
+    assert_eq!(FileNaming::Sanitized.file_stem("a b%"), "a%20b%25");
+    assert_eq!(FileNaming::Verbatim.file_stem("a b"), "a b");
+
+    let store = FileStore::from_config(PersistenceConfig {
+        dir: PathBuf::from("/var/lib/library"),
+        extension: Some("state".to_string()),
+        naming: FileNaming::Sanitized,
+    });
+    assert_eq!(
+        store.path("branch/book-1"),
+        PathBuf::from("/var/lib/library/branch%2Fbook-1.state")
+    );
+    assert_eq!(
+        store.backup_path("branch/book-1", 1),
+        PathBuf::from("/var/lib/library/branch%2Fbook-1.state.1")
+    );
+}
+
+#[test]
+fn test_checksum_detects_corruption() -> Result<(), LibraryError> {
+    let directory =
+        std::env::temp_dir().join(format!("file-store-checksum-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory);
+
+    let result = (|| {
+        setup_test_system()?.save_to(&store)?;
+        let contents = fs::read_to_string(store.path("test-book"))?;
+        assert!(contents.starts_with("sha256:"));
+
+        // Hand edits and truncation are reported as corruption
+        fs::write(store.path("test-book"), contents.replace("Test User", "Other User"))?;
+        assert!(matches!(store.load("test-book"), Err(LibraryError::CorruptState(_))));
+        fs::write(store.path("test-book"), contents.get(..contents.len() / 2).unwrap_or_default())?;
+        let error = store.load("test-book").err();
+        assert!(matches!(error, Some(LibraryError::CorruptState(_))));
+        assert!(error.and_then(|e| e.recovery_hint()).is_some());
+
+        // Files saved without a checksum still load
+        let (_, legacy) = contents.split_once('\n').unwrap_or_default();
+        fs::write(store.path("test-book"), legacy)?;
+        assert_eq!(store.load("test-book")?.system_id, "test-book");
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
+
+#[test]
+fn test_file_store_listing() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("file-store-listing-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let store = FileStore::new(&directory).with_backups(1);
+
+    let result = (|| {
+        let system = setup_test_system()?;
+        for system_id in ["book-2", "branch/book-1", "book-2"] {
+            let mut state = system.to_serializable_state();
+            state.system_id = system_id.to_string();
+            store.save(&state)?;
+        }
+        // Unrelated files are ignored
+        fs::write(directory.join("notes.txt"), "")?;
+        fs::write(directory.join("bad%zz.json"), "")?;
+
+        assert_eq!(store.list_systems()?, ["book-2", "branch/book-1"]);
+        assert!(store.exists("branch/book-1")?);
+        assert_eq!(store.load_all()?.len(), 2);
+
+        store.delete("book-2")?;
+        store.delete("book-2")?;
+        assert!(!store.exists("book-2")?);
+        assert!(!store.backup_path("book-2", 1).exists());
+        assert_eq!(store.list_systems()?, ["branch/book-1"]);
+
+        let mut manager = crate::manager::LibraryManager::new();
+        assert_eq!(manager.load_all_from(&store)?, 1);
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
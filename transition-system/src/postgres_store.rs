@@ -0,0 +1,165 @@
+use std::{
+    fmt,
+    sync::{Mutex, PoisonError},
+};
+
+use postgres::{Client, NoTls};
+
+use crate::{
+    logging::emit,
+    persistence::{SerializableSystemState, StateStore},
+    system::LibraryError,
+};
+
+/// Table holding one row per system
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS library_systems (
+    system_id TEXT PRIMARY KEY,
+    version BIGINT NOT NULL,
+    state TEXT NOT NULL
+)";
+
+/// Stores systems in `PostgreSQL` with optimistic concurrency control
+///
+/// Every row carries a version that is bumped on each save. Loaded states
+/// carry the version of their row, and a save only overwrites a row that
+/// still has the version of the saved state, so two instances can't silently
+/// overwrite each other's changes. A rejected save returns
+/// `LibraryError::ConflictingUpdate`; reload the system and apply the change
+/// again.
+pub struct PostgresStore {
+    /// Connection to the database
+    client: Mutex<Client>,
+}
+
+// Manual implementation of Debug for PostgresStore
+impl fmt::Debug for PostgresStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresStore").finish_non_exhaustive()
+    }
+}
+
+impl PostgresStore {
+    /// Connect to the database and create the `library_systems` table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the connection fails or
+    /// the table can't be created
+    pub fn connect(params: &str) -> Result<Self, LibraryError> {
+        let client = Client::connect(params, NoTls).map_err(|e| {
+            LibraryError::PersistenceError(format!("Failed to connect to database: {e}"))
+        })?;
+        Self::with_client(client)
+    }
+
+    /// Use an existing connection and create the `library_systems` table if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the table can't be created
+    pub fn with_client(mut client: Client) -> Result<Self, LibraryError> {
+        client
+            .batch_execute(CREATE_TABLE)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to create table: {e}")))?;
+        Ok(Self { client: Mutex::new(client) })
+    }
+}
+
+/// Convert a state version to the `BIGINT` stored in the `version` column
+fn to_column(version: u64) -> Result<i64, LibraryError> {
+    i64::try_from(version)
+        .map_err(|e| LibraryError::PersistenceError(format!("Invalid version {version}: {e}")))
+}
+
+impl StateStore for PostgresStore {
+    fn save(&self, state: &SerializableSystemState) -> Result<(), LibraryError> {
+        let serialized = serde_json::to_string(state)
+            .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+        let system_id = &state.system_id;
+        emit!(info, "PERSISTENCE: Saving state to database: {system_id}");
+
+        let version = to_column(state.version)?;
+        let next = to_column(state.version.saturating_add(1))?;
+        let mut client = self.client.lock().unwrap_or_else(PoisonError::into_inner);
+        // A state that was never stored may only create the row
+        let updated = if state.version == 0 {
+            client.execute(
+                "INSERT INTO library_systems (system_id, version, state) VALUES ($1, $2, $3) \
+                 ON CONFLICT (system_id) DO NOTHING",
+                &[system_id, &next, &serialized],
+            )
+        } else {
+            client.execute(
+                "UPDATE library_systems SET state = $1, version = $2 \
+                 WHERE system_id = $3 AND version = $4",
+                &[&serialized, &next, system_id, &version],
+            )
+        };
+
+        match updated {
+            Ok(0) => Err(LibraryError::ConflictingUpdate(system_id.clone())),
+            Ok(_) => Ok(()),
+            Err(e) => Err(LibraryError::PersistenceError(format!("Failed to save state: {e}"))),
+        }
+    }
+
+    fn load(&self, system_id: &str) -> Result<SerializableSystemState, LibraryError> {
+        emit!(info, "PERSISTENCE: Loading state from database: {system_id}");
+
+        let row = self
+            .client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .query_opt(
+                "SELECT version, state FROM library_systems WHERE system_id = $1",
+                &[&system_id],
+            )
+            .map_err(|e| LibraryError::LoadError(format!("Failed to query state: {e}")))?
+            .ok_or_else(|| LibraryError::LoadError(format!("No state stored for {system_id}")))?;
+
+        let version: i64 = row
+            .try_get("version")
+            .map_err(|e| LibraryError::LoadError(format!("Failed to read version: {e}")))?;
+        let serialized: String = row
+            .try_get("state")
+            .map_err(|e| LibraryError::LoadError(format!("Failed to read state: {e}")))?;
+        let state = serde_json::from_str(&serialized)
+            .map_err(|e| LibraryError::LoadError(format!("Failed to parse JSON: {e}")))?;
+        let version = u64::try_from(version)
+            .map_err(|e| LibraryError::LoadError(format!("Invalid version {version}: {e}")))?;
+        Ok(SerializableSystemState { version, ..state })
+    }
+
+    fn list_systems(&self) -> Result<Vec<String>, LibraryError> {
+        self.client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .query("SELECT system_id FROM library_systems ORDER BY system_id", &[])
+            .and_then(|rows| rows.iter().map(|row| row.try_get("system_id")).collect())
+            .map_err(|e| LibraryError::LoadError(format!("Failed to list systems: {e}")))
+    }
+
+    fn delete(&self, system_id: &str) -> Result<(), LibraryError> {
+        emit!(info, "PERSISTENCE: Deleting state of {system_id} from database");
+
+        self.client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .execute("DELETE FROM library_systems WHERE system_id = $1", &[&system_id])
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to delete state: {e}")))?;
+        Ok(())
+    }
+
+    fn exists(&self, system_id: &str) -> Result<bool, LibraryError> {
+        self.client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .query_opt("SELECT 1 FROM library_systems WHERE system_id = $1", &[&system_id])
+            .map(|row| row.is_some())
+            .map_err(|e| LibraryError::LoadError(format!("Failed to query state: {e}")))
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,42 @@
+use super::*;
+use crate::{book_state::BookState, system::LibrarySystem};
+
+/// Connect to the database named by `DATABASE_URL`
+fn connect() -> Result<PostgresStore, LibraryError> {
+    let params = std::env::var("DATABASE_URL")
+        .map_err(|e| LibraryError::PersistenceError(format!("DATABASE_URL not set: {e}")))?;
+    PostgresStore::connect(&params)
+}
+
+#[test]
+#[ignore = "requires a PostgreSQL server in DATABASE_URL"]
+fn test_concurrent_saves_conflict() -> Result<(), LibraryError> {
+    let system_id = format!("postgres-test-{}", std::process::id());
+    let first = connect()?;
+    let second = connect()?;
+
+    let created = LibrarySystem::new(BookState::Available, &system_id);
+    created.save_to(&first)?;
+    assert_eq!(created.stored_version(), 1);
+    let loaded = LibrarySystem::load_from(&second, &system_id)?;
+    assert_eq!(loaded.stored_version(), 1);
+
+    // The first instance saves again, so the second one's copy is stale
+    created.save_to(&first)?;
+    assert_eq!(created.stored_version(), 2);
+    assert!(matches!(loaded.save_to(&second), Err(LibraryError::ConflictingUpdate(_))));
+    assert_eq!(loaded.stored_version(), 1);
+
+    // After reloading, the second instance can save, whichever store it uses
+    let reloaded = LibrarySystem::load_from(&second, &system_id)?;
+    reloaded.save_to(&first)?;
+    assert_eq!(reloaded.stored_version(), 3);
+    // A system that was never stored can't overwrite the row
+    let fresh = LibrarySystem::new(BookState::Available, &system_id);
+    assert!(matches!(fresh.save_to(&second), Err(LibraryError::ConflictingUpdate(_))));
+
+    assert!(first.list_systems()?.contains(&system_id));
+    first.delete(&system_id)?;
+    assert!(!second.exists(&system_id)?);
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{events::BookEvent, system::LibraryError};
+
+/// Units accepted in `every` rules, with their length in seconds
+const UNITS: [(char, u64); 5] = [('w', 604_800), ('d', 86_400), ('h', 3_600), ('m', 60), ('s', 1)];
+
+/// How often a recurring event fires
+///
+/// Rules are written like cron nicknames, `@hourly`, `@daily` or `@weekly`,
+/// or as `every` followed by a number and unit, e.g. `every 2w` or
+/// `every 12h`; units are `w`, `d`, `h`, `m` and `s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RecurrenceRule {
+    /// Time between two occurrences, never zero
+    interval: Duration,
+}
+
+impl RecurrenceRule {
+    /// Fire every hour
+    pub const HOURLY: Self = Self { interval: Duration::from_hours(1) };
+    /// Fire every day
+    pub const DAILY: Self = Self { interval: Duration::from_hours(24) };
+    /// Fire every week
+    pub const WEEKLY: Self = Self { interval: Duration::from_hours(7 * 24) };
+
+    /// Fire at a fixed interval, rounded down to whole seconds
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::InvalidDefinition` if the interval is shorter
+    /// than a second
+    pub fn every(interval: Duration) -> Result<Self, LibraryError> {
+        if interval.as_secs() == 0 {
+            return Err(LibraryError::InvalidDefinition(format!(
+                "Recurrence interval {interval:?} is shorter than a second"
+            )));
+        }
+        Ok(Self { interval: Duration::from_secs(interval.as_secs()) })
+    }
+
+    /// Get the time between two occurrences
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::HOURLY => f.write_str("@hourly"),
+            Self::DAILY => f.write_str("@daily"),
+            Self::WEEKLY => f.write_str("@weekly"),
+            Self { interval } => {
+                let secs = interval.as_secs();
+                let (unit, length) = UNITS
+                    .into_iter()
+                    .find(|(_, length)| secs.is_multiple_of(*length))
+                    .unwrap_or(('s', 1));
+                write!(f, "every {}{unit}", secs.checked_div(length).unwrap_or(secs))
+            }
+        }
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = LibraryError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || LibraryError::InvalidDefinition(format!("Invalid recurrence rule {rule:?}"));
+        match rule.trim() {
+            "@hourly" => return Ok(Self::HOURLY),
+            "@daily" => return Ok(Self::DAILY),
+            "@weekly" => return Ok(Self::WEEKLY),
+            _ => {}
+        }
+
+        let every = rule.trim().strip_prefix("every").ok_or_else(invalid)?.trim();
+        let unit = every.chars().last().ok_or_else(invalid)?;
+        let (_, length) = UNITS.into_iter().find(|(name, _)| *name == unit).ok_or_else(invalid)?;
+        let count: u64 = every.trim_end_matches(unit).trim().parse().map_err(|_| invalid())?;
+        Self::every(Duration::from_secs(count.checked_mul(length).ok_or_else(invalid)?))
+    }
+}
+
+impl TryFrom<String> for RecurrenceRule {
+    type Error = LibraryError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<RecurrenceRule> for String {
+    fn from(rule: RecurrenceRule) -> Self {
+        rule.to_string()
+    }
+}
+
+/// An event processed over and over, following a recurrence rule
+///
+/// Register it with
+/// [`LibrarySystem::add_recurring_event`](crate::system::LibrarySystem::add_recurring_event);
+/// occurrences are fired by
+/// [`LibrarySystem::fire_recurring_if_due`](crate::system::LibrarySystem::fire_recurring_if_due)
+/// or a [`TimeoutScheduler`](crate::scheduler::TimeoutScheduler).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RecurringEvent {
+    /// Name identifying the recurring event, e.g. "weekly condition check"
+    pub name: String,
+    /// How often the event fires
+    pub rule: RecurrenceRule,
+    /// Event processed on every occurrence
+    pub event: BookEvent,
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
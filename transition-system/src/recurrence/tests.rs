@@ -0,0 +1,30 @@
+use super::*;
+
+#[test]
+fn test_parse_recurrence_rules() -> Result<(), LibraryError> {
+    assert_eq!("@weekly".parse::<RecurrenceRule>()?, RecurrenceRule::WEEKLY);
+    assert_eq!("every 1d".parse::<RecurrenceRule>()?, RecurrenceRule::DAILY);
+    assert_eq!("every 12h".parse::<RecurrenceRule>()?.interval(), Duration::from_hours(12));
+    assert_eq!(" every 2 w ".parse::<RecurrenceRule>()?.to_string(), "every 2w");
+    assert_eq!(RecurrenceRule::every(Duration::from_secs(90))?.to_string(), "every 90s");
+    assert_eq!(RecurrenceRule::DAILY.to_string(), "@daily");
+
+    for invalid in ["", "daily", "every", "every 0d", "every 3y", "every -1h", "@monthly"] {
+        assert!(invalid.parse::<RecurrenceRule>().is_err(), "{invalid:?} should be rejected");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_recurring_event_serialization() -> Result<(), serde_json::Error> {
+    let recurring = RecurringEvent {
+        name: "condition check".to_string(),
+        rule: RecurrenceRule::WEEKLY,
+        event: BookEvent::SendToRepair { condition: None },
+    };
+    let json = serde_json::to_string(&recurring)?;
+    assert!(json.contains(r#""rule":"@weekly""#));
+    assert_eq!(serde_json::from_str::<RecurringEvent>(&json)?, recurring);
+    assert!(serde_json::from_str::<RecurrenceRule>(r#""every 0s""#).is_err());
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{logging::emit, system::LibrarySystem};
+
+/// Background worker that fires timeout and recurring events without waiting
+/// for the next `process_event` call
+///
+/// The scheduler wakes up whenever the current state's deadline passes or a
+/// recurring event is due (or at least every `poll_interval`, so newly added
+/// constraints are picked up) and injects the event. Observers registered on
+/// the system are notified as usual. The worker is stopped when the scheduler
+/// is dropped.
+#[derive(Debug)]
+pub struct TimeoutScheduler {
+    /// Flag telling the worker thread to exit
+    stop: Arc<AtomicBool>,
+    /// Handle of the worker thread
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TimeoutScheduler {
+    /// Start watching the timing constraints of a shared system
+    #[must_use]
+    pub fn spawn(system: Arc<Mutex<LibrarySystem>>, poll_interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_stop = Arc::clone(&stop);
+
+        let handle = thread::spawn(move || {
+            while !worker_stop.load(Ordering::Acquire) {
+                let wait = match system.lock() {
+                    Ok(mut system) => {
+                        let timeout = match system.fire_timeout_if_due() {
+                            Ok(_) => system.time_until_timeout(),
+                            Err(e) => {
+                                // Don't spin on a timeout event that can never be applied
+                                emit!(warn, "SCHEDULER: Failed to fire timeout event: {e}");
+                                None
+                            }
+                        };
+                        // Failed occurrences are rescheduled, so they can't spin
+                        if let Err(e) = system.fire_recurring_if_due() {
+                            emit!(warn, "SCHEDULER: Failed to fire recurring event: {e}");
+                        }
+                        [timeout, system.time_until_recurring()]
+                            .into_iter()
+                            .flatten()
+                            .fold(poll_interval, Duration::min)
+                    }
+                    // A poisoned system can't be trusted any more
+                    Err(_) => break,
+                };
+                thread::park_timeout(wait);
+            }
+        });
+
+        Self { stop, handle: Some(handle) }
+    }
+
+    /// Stop the worker thread and wait for it to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signal the worker to exit and join it
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            if handle.join().is_err() {
+                emit!(error, "SCHEDULER: Worker thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for TimeoutScheduler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
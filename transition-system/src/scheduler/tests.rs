@@ -0,0 +1,34 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    book_state::BookState, events::BookEvent, scheduler::TimeoutScheduler, system::LibrarySystem,
+};
+
+#[test]
+fn test_scheduler_fires_expired_reservation() {
+    let mut system = LibrarySystem::new(BookState::Available, "test-book");
+    let reserved_idx = system.add_state(BookState::Reserved("Test User".into()));
+    system.add_transition(0, BookEvent::Reserve("Test User".into()), reserved_idx);
+    system.add_transition(reserved_idx, BookEvent::CancelReservation, 0);
+    system.add_timing_constraint(
+        reserved_idx,
+        Duration::from_millis(20),
+        BookEvent::CancelReservation,
+    );
+    assert!(system.process_event(BookEvent::Reserve("Test User".into())).is_ok());
+
+    let system = Arc::new(Mutex::new(system));
+    let scheduler = TimeoutScheduler::spawn(Arc::clone(&system), Duration::from_millis(5));
+
+    // Nothing else touches the system; the scheduler alone must expire the reservation
+    thread::sleep(Duration::from_millis(200));
+    scheduler.stop();
+
+    let system = system.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    assert_eq!(*system.current_state(), BookState::Available);
+    assert_eq!(system.get_history().len(), 2);
+}
@@ -0,0 +1,108 @@
+use serde_json::{Map, Value};
+
+/// What became of an enum variant that older versions wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Migration {
+    /// The variant was renamed; its data is kept
+    Renamed(&'static str),
+    /// The variant was a unit variant before it gained optional details
+    GainedDetails,
+    /// The variant was removed
+    ///
+    /// Events are read as a custom event of the same name, with their data as
+    /// payload. States can't be removed this way, as there is no custom state.
+    Removed,
+}
+
+/// `BookEvent` variants changed since state files were first written, by old name
+///
+/// Add an entry whenever a variant is renamed or removed, so files written by
+/// older versions still load.
+pub const EVENT_MIGRATIONS: &[(&str, Migration)] =
+    &[("SendToRepair", Migration::GainedDetails), ("Transfer", Migration::GainedDetails)];
+
+/// `BookState` variants changed since state files were first written, by old name
+///
+/// Add an entry whenever a variant is renamed, so files written by older
+/// versions still load.
+pub const STATE_MIGRATIONS: &[(&str, Migration)] = &[];
+
+/// Rewrite a serialized event from an older version into the current form
+///
+/// `value` is an event in its externally tagged form, e.g. `"Transfer"` or
+/// `{ "Reserve": "Alice" }`; anything else is returned unchanged. Custom
+/// events written by name only get an empty payload.
+#[must_use]
+pub fn upgrade_event(value: Value, migrations: &[(&str, Migration)]) -> Value {
+    let Some((mut name, mut data)) = split_variant(value.clone()) else {
+        return value;
+    };
+    if apply_migrations(&mut name, &mut data, migrations) {
+        data = Some(Value::Array(vec![Value::String(name), data.unwrap_or_default()]));
+        name = String::from("Custom");
+    }
+    if let ("Custom", Some(Value::String(custom))) = (name.as_str(), &data) {
+        data = Some(Value::Array(vec![Value::String(custom.clone()), Value::Null]));
+    }
+    join_variant(name, data)
+}
+
+/// Rewrite a serialized state from an older version into the current form
+///
+/// Works like [`upgrade_event`]; [`Migration::Removed`] entries are ignored.
+#[must_use]
+pub fn upgrade_state(value: Value, migrations: &[(&str, Migration)]) -> Value {
+    let Some((mut name, mut data)) = split_variant(value.clone()) else {
+        return value;
+    };
+    if apply_migrations(&mut name, &mut data, migrations) {
+        return value;
+    }
+    join_variant(name, data)
+}
+
+/// Follow the migrations of a variant, returning `true` if it was removed
+fn apply_migrations(
+    name: &mut String,
+    data: &mut Option<Value>,
+    migrations: &[(&str, Migration)],
+) -> bool {
+    // Renames may chain, but never more often than there are entries
+    for _ in 0..migrations.len() {
+        let Some((_, migration)) = migrations.iter().find(|(old, _)| *old == name.as_str()) else {
+            break;
+        };
+        match migration {
+            Migration::Renamed(new) => *name = (*new).to_string(),
+            Migration::GainedDetails => {
+                data.get_or_insert_with(|| Value::Object(Map::new()));
+                break;
+            }
+            Migration::Removed => return true,
+        }
+    }
+    false
+}
+
+/// Split an externally tagged variant into its name and data
+fn split_variant(value: Value) -> Option<(String, Option<Value>)> {
+    match value {
+        Value::String(name) => Some((name, None)),
+        Value::Object(map) if map.len() == 1 => {
+            map.into_iter().next().map(|(name, data)| (name, Some(data)))
+        }
+        _ => None,
+    }
+}
+
+/// Put a variant split by [`split_variant`] back together
+fn join_variant(name: String, data: Option<Value>) -> Value {
+    match data {
+        Some(data) => Value::Object(Map::from_iter([(name, data)])),
+        None => Value::String(name),
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,68 @@
+use serde_json::json;
+
+use super::*;
+use crate::{book_state::BookState, events::BookEvent, system::LibrarySystem};
+
+/// Migrations of an imagined older version, before renames and removals
+const OLD_EVENTS: &[(&str, Migration)] = &[
+    ("Checkout", Migration::Renamed("Borrow")),
+    ("Borrow", Migration::Renamed("CheckOut")),
+    ("Renew", Migration::Removed),
+    ("Transfer", Migration::GainedDetails),
+];
+
+#[test]
+fn test_upgrade_renamed_event() {
+    assert_eq!(
+        upgrade_event(json!({ "Checkout": "Alice" }), OLD_EVENTS),
+        json!({ "CheckOut": "Alice" })
+    );
+    assert_eq!(upgrade_event(json!("Return"), OLD_EVENTS), json!("Return"));
+}
+
+#[test]
+fn test_upgrade_removed_event_becomes_custom() -> Result<(), serde_json::Error> {
+    let upgraded = upgrade_event(json!({ "Renew": "Alice" }), OLD_EVENTS);
+    assert_eq!(upgraded, json!({ "Custom": ["Renew", "Alice"] }));
+    assert_eq!(
+        serde_json::from_value::<BookEvent>(upgraded)?,
+        BookEvent::custom_with("Renew", json!("Alice"))
+    );
+    assert_eq!(upgrade_event(json!("Renew"), OLD_EVENTS), json!({ "Custom": ["Renew", null] }));
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_event_that_gained_details() {
+    assert_eq!(upgrade_event(json!("Transfer"), OLD_EVENTS), json!({ "Transfer": {} }));
+    assert_eq!(
+        upgrade_event(json!({ "Transfer": { "destination": "Main" } }), OLD_EVENTS),
+        json!({ "Transfer": { "destination": "Main" } })
+    );
+}
+
+#[test]
+fn test_upgrade_renamed_state() {
+    let migrations = &[("Missing", Migration::Renamed("Lost")), ("Gone", Migration::Removed)];
+    assert_eq!(upgrade_state(json!("Missing"), migrations), json!("Lost"));
+    assert_eq!(upgrade_state(json!("Gone"), migrations), json!("Gone"));
+    assert_eq!(upgrade_state(json!(42), migrations), json!(42));
+}
+
+#[test]
+fn test_load_state_written_before_event_details() -> Result<(), serde_json::Error> {
+    let mut state = serde_json::to_value(
+        LibrarySystem::new(BookState::Available, "book-1").to_serializable_state(),
+    )?;
+    if let Some(fields) = state.as_object_mut() {
+        fields.insert("states".to_string(), json!(["Available", "InTransit"]));
+        fields.insert(
+            "transitions".to_string(),
+            json!([[[0, "Transfer"], 1], [[1, "SendToRepair"], 0]]),
+        );
+    }
+
+    let system = LibrarySystem::from_serializable_state(serde_json::from_value(state)?);
+    assert!(system.valid_events().contains(&BookEvent::Transfer { destination: None }));
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use super::*;
+use crate::{book_state::BookState, events::BookEvent, persistence::FileStore};
+
+/// Build the test system
+fn build_system() -> Result<LibrarySystem, LibraryError> {
+    crate::state_machine! {
+        id: "snapshot-test",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Test User")--> Reserved("Test User"),
+            Reserved("Test User") --CancelReservation--> Available,
+        },
+    }
+}
+
+/// Create a snapshot store keeping everything in `directory`
+fn store(directory: &PathBuf) -> SnapshotStore {
+    SnapshotStore::new(Box::new(FileStore::new(directory)), EventLog::new(directory))
+        .snapshot_interval(3)
+}
+
+/// Reserve the book or cancel the reservation, depending on the current state
+fn toggle(system: &mut LibrarySystem) -> Result<(), LibraryError> {
+    let event = if *system.current_state() == BookState::Available {
+        BookEvent::Reserve("Test User".into())
+    } else {
+        BookEvent::CancelReservation
+    };
+    system.process_event(event).map(|_| ())
+}
+
+#[test]
+fn test_snapshot_and_incremental_log() -> Result<(), LibraryError> {
+    let directory = std::env::temp_dir().join(format!("snapshot-test-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    let log = EventLog::new(&directory);
+
+    let result = (|| {
+        store(&directory).save(&build_system()?)?;
+        let store = store(&directory);
+        let mut system_from_store = store.load("snapshot-test")?;
+        assert_eq!(system_from_store.sequence(), 0);
+
+        // Two transitions only go to the log
+        toggle(&mut system_from_store)?;
+        toggle(&mut system_from_store)?;
+        store.save(&system_from_store)?;
+        assert_eq!(log.records("snapshot-test")?.len(), 2);
+
+        let reloaded = self::store(&directory).load("snapshot-test")?;
+        assert_eq!(reloaded.sequence(), 2);
+        assert_eq!(reloaded.get_history().len(), 2);
+
+        // The third transition reaches the interval and triggers a snapshot
+        toggle(&mut system_from_store)?;
+        store.save(&system_from_store)?;
+        assert!(log.records("snapshot-test")?.is_empty());
+
+        // Transitions already in the snapshot are skipped, e.g. after a crash
+        // between writing the snapshot and removing the log
+        let stale = system_from_store.get_history().first().cloned();
+        if let Some(transition) = stale {
+            log.append("snapshot-test", &LogRecord::Transition(transition))?;
+        }
+        let reloaded = self::store(&directory).load("snapshot-test")?;
+        assert_eq!(reloaded.sequence(), 3);
+        assert_eq!(reloaded.current_state(), system_from_store.current_state());
+        Ok(())
+    })();
+
+    fs::remove_dir_all(&directory)?;
+    result
+}
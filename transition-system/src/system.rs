@@ -1,42 +1,158 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
-    fs::File,
-    io::{Read, Write},
     path::Path,
-    time::{Duration, Instant},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver},
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
     book_state::BookState,
-    events::BookEvent,
-    observers::{NotificationService, StateObserver, TransitionLogger},
-    persistence::SerializableInstant,
+    branches::{BranchLocation, BranchRegistry, PendingTransfer},
+    clock::{Clock, SystemClock},
+    dead_letter::{DeadLetter, DeadLetterSink},
+    definition::MachineDefinition,
+    events::{BookEvent, EventEnvelope},
+    fines::{FinePolicy, FineTracker},
+    holds::HoldQueue,
+    interceptors::{TransitionInterceptor, VetoReason},
+    logging::emit,
+    observers::{
+        ChannelObserver, FilteredObserver, NotificationService, ObserverError, ObserverErrorPolicy,
+        ObserverId, StateObserver, Subscription, TransitionLogger, TransitionMessage,
+    },
+    patrons::{PatronId, PatronRegistry},
+    persistence::{
+        AutoSavePolicy, FileStore, PersistenceFormat, SerializableInstant, SerializableSystemState,
+        StateStore, TimeStamp, instant_to_system_time, system_time_to_instant,
+    },
+    recurrence::{RecurrenceRule, RecurringEvent},
+    validation::{self, ValidationIssue},
 };
 
+#[cfg(feature = "archive")]
+use crate::archive;
+#[cfg(feature = "csv")]
+use crate::history_csv;
+
 /// Custom error type for library system operations
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
 pub enum LibraryError {
     /// The requested transition is not valid for the current state
-    InvalidTransition { from_state: BookState, event: BookEvent },
+    #[error("Cannot process event {event:?} from current state {from_state:?}")]
+    InvalidTransition {
+        /// State the event was processed in
+        from_state: BookState,
+        /// Event that has no transition from `from_state`
+        event: BookEvent,
+        /// Events that would have been accepted from `from_state`
+        valid_events: Vec<BookEvent>,
+    },
     /// Error occurred while saving state
+    #[error("Persistence error: {0}")]
     PersistenceError(String),
     /// Error occurred while loading state
+    #[error("Load error: {0}")]
     LoadError(String),
+    /// No managed system has the given ID
+    #[error("Unknown system: {0}")]
+    UnknownSystem(String),
+    /// The patron is not registered in the patron registry
+    #[error("Unknown patron: {0}")]
+    UnknownPatron(String),
+    /// The patron already holds the maximum number of books allowed
+    #[error("Patron {patron} has reached the borrowing limit of {limit}")]
+    BorrowingLimitReached {
+        /// Patron who tried to take out another book
+        patron: PatronId,
+        /// Limit the patron has reached
+        limit: usize,
+    },
+    /// The branch is not registered in the branch registry
+    #[error("Unknown branch: {0}")]
+    UnknownBranch(String),
+    /// The requested transfer between branches is not allowed
+    #[error("Invalid transfer: {0}")]
+    InvalidTransfer(String),
+    /// The state machine definition is inconsistent
+    #[error("Invalid definition: {0}")]
+    InvalidDefinition(String),
+    /// The stored state doesn't match its checksum, e.g. it was truncated or edited
+    #[error("Corrupt state: {0}")]
+    CorruptState(String),
+    /// The stored state changed since it was loaded, so the save was rejected
+    #[error("Conflicting update: {0} was modified by someone else")]
+    ConflictingUpdate(String),
+    /// An interceptor blocked the transition
+    #[error("Transition on {event:?} from {from_state:?} vetoed: {reason}")]
+    Vetoed {
+        /// State the event was processed in
+        from_state: BookState,
+        /// Event whose transition was blocked
+        event: BookEvent,
+        /// Why the transition was blocked
+        reason: VetoReason,
+    },
+    /// An observer failed and the transition was rolled back
+    #[error("Observer failed: {0}")]
+    ObserverFailed(ObserverError),
+    /// An I/O operation failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Serializing or deserializing state failed
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
 }
 
-impl std::error::Error for LibraryError {}
-
-impl fmt::Display for LibraryError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl LibraryError {
+    /// Suggest how the caller can recover from the error, if there's a known way
+    #[must_use]
+    pub fn recovery_hint(&self) -> Option<String> {
         match self {
-            Self::InvalidTransition { from_state, event } => {
-                write!(f, "Cannot process event {event:?} from current state {from_state:?}")
+            Self::InvalidTransition { valid_events, .. } if valid_events.is_empty() => {
+                Some("No events are accepted from this state".to_string())
+            }
+            Self::InvalidTransition { valid_events, .. } => {
+                Some(format!("Process one of the valid events instead: {valid_events:?}"))
+            }
+            Self::UnknownSystem(_) => {
+                Some("Add the system with `LibraryManager::add_system` first".to_string())
+            }
+            Self::UnknownPatron(_) => {
+                Some("Register the patron with `PatronRegistry::register` first".to_string())
+            }
+            Self::BorrowingLimitReached { patron, .. } => {
+                Some(format!("{patron} must return or cancel another book first"))
+            }
+            Self::UnknownBranch(_) => {
+                Some("Register the branch with `BranchRegistry::register` first".to_string())
+            }
+            Self::InvalidTransfer(_) => {
+                Some("Choose a destination other than the current branch".to_string())
+            }
+            Self::ConflictingUpdate(_) => {
+                Some("Reload the system and apply the change again".to_string())
+            }
+            Self::CorruptState(_) => {
+                Some("Restore a previous version with `FileStore::load_backup`".to_string())
+            }
+            Self::Vetoed { reason, .. } => {
+                Some(format!("Wait until the policy allows it: {reason}"))
             }
-            Self::PersistenceError(msg) => write!(f, "Persistence error: {msg}"),
-            Self::LoadError(msg) => write!(f, "Load error: {msg}"),
+            Self::ObserverFailed(_) => {
+                Some("Process the event again once the observer can handle it".to_string())
+            }
+            Self::PersistenceError(_)
+            | Self::LoadError(_)
+            | Self::InvalidDefinition(_)
+            | Self::Io(_)
+            | Self::Serialization(_) => None,
         }
     }
 }
@@ -52,6 +168,21 @@ pub struct StateTransition {
     pub event: BookEvent,
     /// When the transition occurred
     pub timestamp: SerializableInstant,
+    /// Additional details recorded with the transition (e.g. `fine_cents`)
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Who triggered the event, if known
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// Note attached to the event, if any
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Wall-clock time the event occurred, if recorded
+    #[serde(default)]
+    pub occurred_at: Option<SystemTime>,
+    /// Position of the transition among all transitions of the system, from 1
+    #[serde(default)]
+    pub sequence: u64,
 }
 
 /// Timing constraints for state transitions
@@ -63,23 +194,39 @@ pub struct TimingConstraints {
     pub timeout_event: BookEvent,
 }
 
-/// Serializable representation of the system state
-#[derive(Debug, Deserialize, Serialize)]
-struct SerializableSystemState {
-    /// Collection of all book states
-    states: Vec<BookState>,
-    /// Mapping of state transitions
-    transitions: Vec<((usize, BookEvent), usize)>,
-    /// Index of the current state
+/// Store a system saves itself to automatically, and how often
+struct AutoSave {
+    /// When to save
+    policy: AutoSavePolicy,
+    /// Where to save
+    store: Arc<dyn StateStore>,
+    /// Sequence number of the last saved transition
+    saved_sequence: u64,
+}
+
+// Manual implementation of Debug for AutoSave
+impl fmt::Debug for AutoSave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoSave")
+            .field("policy", &self.policy)
+            .field("saved_sequence", &self.saved_sequence)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a transition changes in the system, kept to undo it
+#[derive(Debug)]
+struct Checkpoint {
+    /// Index of the state before the transition
     current_state_idx: usize,
-    /// Record of state transition history
-    history: Vec<StateTransition>,
-    /// Maximum number of history entries to keep
-    max_history_size: usize,
-    /// State timing constraints
-    timing_constraints: Vec<(usize, TimingConstraints)>,
-    /// Unique identifier for this system
-    system_id: String,
+    /// Sequence number before the transition
+    sequence: u64,
+    /// When the state before the transition was entered
+    state_entry_time: Instant,
+    /// Loan and fines before the transition
+    fines: Option<FineTracker>,
+    /// Location before the transition
+    location: BranchLocation,
 }
 
 /// Library book state machine
@@ -99,9 +246,42 @@ pub struct LibrarySystem {
     /// State timing constraints
     timing_constraints: HashMap<usize, TimingConstraints>,
     /// Registered state change observers
-    observers: Vec<Box<dyn StateObserver>>,
+    observers: Vec<(ObserverId, Box<dyn StateObserver>)>,
+    /// Identifier handed to the next registered observer
+    next_observer_id: u64,
+    /// What to do when an observer fails to handle a transition
+    observer_error_policy: ObserverErrorPolicy,
+    /// Policies consulted before every transition
+    interceptors: Vec<Box<dyn TransitionInterceptor>>,
+    /// Where rejected events are recorded, if anywhere
+    dead_letters: Option<Box<dyn DeadLetterSink>>,
     /// Unique identifier for this system
     system_id: String,
+    /// Source of the current time for timing constraints
+    clock: Arc<dyn Clock>,
+    /// Due date tracking for overdue fines, if fines are enabled
+    fines: Option<FineTracker>,
+    /// Patrons waiting for the book
+    holds: HoldQueue,
+    /// Shared patron registry enforcing borrowing limits, if attached
+    patrons: Option<Arc<Mutex<PatronRegistry>>>,
+    /// Branch holding the book and any transfer in progress
+    location: BranchLocation,
+    /// Registry used to validate transfer destinations, if attached
+    branches: Option<Arc<BranchRegistry>>,
+    /// Indices of states that are expected to have no outgoing transitions
+    final_states: BTreeSet<usize>,
+    /// Categories the states are grouped into, keyed by state index
+    state_categories: BTreeMap<usize, String>,
+    /// Number of transitions applied since the system was created
+    sequence: u64,
+    /// Version of the stored copy the system was loaded from or last saved
+    /// as, recorded by [`Self::save_to`] through a shared reference
+    stored_version: AtomicU64,
+    /// Automatic persistence, if configured
+    autosave: Option<AutoSave>,
+    /// Recurring events and when each is next due
+    recurring: Vec<(RecurringEvent, Instant)>,
 }
 
 // Manual implementation of Debug for LibrarySystem
@@ -116,7 +296,23 @@ impl fmt::Debug for LibrarySystem {
             .field("state_entry_time", &self.state_entry_time)
             .field("timing_constraints", &self.timing_constraints)
             .field("observers_count", &self.observers.len())
+            .field("next_observer_id", &self.next_observer_id)
+            .field("observer_error_policy", &self.observer_error_policy)
+            .field("interceptors_count", &self.interceptors.len())
+            .field("dead_letters", &self.dead_letters.is_some())
             .field("system_id", &self.system_id)
+            .field("clock", &self.clock)
+            .field("fines", &self.fines)
+            .field("holds", &self.holds)
+            .field("patrons", &self.patrons)
+            .field("location", &self.location)
+            .field("branches", &self.branches)
+            .field("final_states", &self.final_states)
+            .field("state_categories", &self.state_categories)
+            .field("sequence", &self.sequence)
+            .field("stored_version", &self.stored_version)
+            .field("autosave", &self.autosave)
+            .field("recurring", &self.recurring)
             .finish()
     }
 }
@@ -125,19 +321,50 @@ impl LibrarySystem {
     /// Create a new library system with the specified initial state
     #[must_use]
     pub fn new(initial_state: BookState, system_id: &str) -> Self {
+        Self::with_clock(initial_state, system_id, Arc::new(SystemClock))
+    }
+
+    /// Create a new library system that reads the time from the given clock
+    #[must_use]
+    pub fn with_clock(initial_state: BookState, system_id: &str, clock: Arc<dyn Clock>) -> Self {
         Self {
             states: vec![initial_state],
             transitions: HashMap::new(),
             current_state_idx: 0,
             history: Vec::new(),
             max_history_size: 100,
-            state_entry_time: Instant::now(),
+            state_entry_time: clock.now(),
             timing_constraints: HashMap::new(),
             observers: Vec::new(),
+            next_observer_id: 0,
+            observer_error_policy: ObserverErrorPolicy::default(),
+            interceptors: Vec::new(),
+            dead_letters: None,
             system_id: system_id.to_string(),
+            clock,
+            fines: None,
+            holds: HoldQueue::new(),
+            patrons: None,
+            location: BranchLocation::default(),
+            branches: None,
+            final_states: BTreeSet::new(),
+            state_categories: BTreeMap::new(),
+            sequence: 0,
+            stored_version: AtomicU64::new(0),
+            autosave: None,
+            recurring: Vec::new(),
         }
     }
 
+    /// Replace the clock used for timing constraints
+    ///
+    /// The time spent in the current state is restarted from the new clock's
+    /// current time.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.state_entry_time = clock.now();
+        self.clock = clock;
+    }
+
     /// Add a state to the system, or return its index if it already exists
     #[allow(clippy::arithmetic_side_effects)]
     pub fn add_state(&mut self, state: BookState) -> usize {
@@ -150,13 +377,151 @@ impl LibrarySystem {
     }
 
     /// Define a valid transition from one state to another when an event occurs
+    ///
+    /// Details the event carries are dropped, see [`BookEvent::transition_key`].
     pub fn add_transition(&mut self, from_state_idx: usize, event: BookEvent, to_state_idx: usize) {
-        self.transitions.insert((from_state_idx, event), to_state_idx);
+        self.transitions.insert((from_state_idx, event.transition_key()), to_state_idx);
+    }
+
+    /// Define a transition taken when a custom event with the given name occurs
+    ///
+    /// Custom events let downstream code add domain-specific events, see
+    /// [`BookEvent::Custom`]. Their payload doesn't affect the transition.
+    pub fn add_custom_transition(
+        &mut self,
+        from_state_idx: usize,
+        event: &str,
+        to_state_idx: usize,
+    ) {
+        self.add_transition(from_state_idx, BookEvent::custom(event), to_state_idx);
+    }
+
+    /// Declare a state as final, so it isn't reported as a dead end by [`Self::validate`]
+    pub fn mark_final_state(&mut self, state_idx: usize) {
+        self.final_states.insert(state_idx);
+    }
+
+    /// Check whether a state has been declared final
+    #[must_use]
+    pub fn is_final_state(&self, state_idx: usize) -> bool {
+        self.final_states.contains(&state_idx)
+    }
+
+    /// Tag a state with a category such as "circulation" or "maintenance"
+    ///
+    /// Visualizations group the states of a category together. A state has at
+    /// most one category; tagging it again replaces the previous one.
+    pub fn set_state_category(&mut self, state_idx: usize, category: &str) {
+        self.state_categories.insert(state_idx, category.to_string());
+    }
+
+    /// Get the category a state has been tagged with, if any
+    #[must_use]
+    pub fn state_category(&self, state_idx: usize) -> Option<&str> {
+        self.state_categories.get(&state_idx).map(String::as_str)
+    }
+
+    /// Check the definition for structural problems
+    ///
+    /// Reports states unreachable from the initial state, dead-end states that
+    /// aren't declared final, transitions pointing at missing states and
+    /// timeouts whose event has no transition. An empty list means the
+    /// definition is sound.
+    #[must_use]
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        validation::validate(self)
     }
 
     /// Register an observer to be notified of state changes
-    pub fn register_observer(&mut self, observer: Box<dyn StateObserver>) {
-        self.observers.push(observer);
+    ///
+    /// Keep the returned handle to remove the observer again with
+    /// [`Self::unregister_observer`].
+    pub fn register_observer(&mut self, observer: Box<dyn StateObserver>) -> ObserverId {
+        let id = ObserverId(self.next_observer_id);
+        self.next_observer_id = self.next_observer_id.saturating_add(1);
+        self.observers.push((id, observer));
+        id
+    }
+
+    /// Register an observer notified only of the transitions matching a subscription
+    ///
+    /// See [`FilteredObserver`] for wrapping the observer yourself, e.g. to
+    /// register it through the builder.
+    pub fn register_observer_for(
+        &mut self,
+        subscription: Subscription,
+        observer: impl StateObserver + 'static,
+    ) -> ObserverId {
+        self.register_observer(Box::new(FilteredObserver::new(observer, subscription)))
+    }
+
+    /// Stream the system's transitions into a channel
+    ///
+    /// Registers a [`ChannelObserver`] for the returned receiver. To be able to
+    /// unregister it later, create the channel and register the observer
+    /// yourself.
+    pub fn subscribe(&mut self) -> Receiver<TransitionMessage> {
+        let (sender, receiver) = mpsc::channel();
+        self.register_observer(Box::new(ChannelObserver::new(sender)));
+        receiver
+    }
+
+    /// Remove a registered observer, handing it back
+    ///
+    /// Returns `None` if no observer with this handle is registered, e.g.
+    /// because it has already been removed.
+    pub fn unregister_observer(&mut self, id: ObserverId) -> Option<Box<dyn StateObserver>> {
+        let position = self.observers.iter().position(|(observer_id, _)| *observer_id == id)?;
+        Some(self.observers.remove(position).1)
+    }
+
+    /// Remove all registered observers
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Notify the registered observers of every transition in the history
+    ///
+    /// Observers registered after loading a system never hear about the
+    /// transitions made before it was saved; replaying them lets projections
+    /// and metrics rebuild their state after a restart. Only the retained
+    /// history is replayed, oldest first, and the system itself is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::ObserverFailed` and stops replaying if an
+    /// observer fails under [`ObserverErrorPolicy::Rollback`]
+    pub fn replay_history_to_observers(&self) -> Result<(), LibraryError> {
+        emit!(info, "OBSERVER: Replaying {} transitions of {}", self.history.len(), self.system_id);
+        self.history.iter().try_for_each(|transition| self.notify_observers(transition))
+    }
+
+    /// Add a policy consulted before every transition, in the order added
+    pub fn add_interceptor(&mut self, interceptor: Box<dyn TransitionInterceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Record every event rejected with `LibraryError::InvalidTransition` in the given sink
+    ///
+    /// Replaces any previously configured sink.
+    pub fn set_dead_letter_sink(&mut self, sink: Box<dyn DeadLetterSink>) {
+        self.dead_letters = Some(sink);
+    }
+
+    /// Stop recording rejected events
+    pub fn clear_dead_letter_sink(&mut self) {
+        self.dead_letters = None;
+    }
+
+    /// Choose what happens when an observer fails to handle a transition
+    pub fn set_observer_error_policy(&mut self, policy: ObserverErrorPolicy) {
+        self.observer_error_policy = policy;
+    }
+
+    /// Get what happens when an observer fails to handle a transition
+    #[must_use]
+    pub fn observer_error_policy(&self) -> ObserverErrorPolicy {
+        self.observer_error_policy
     }
 
     /// Add a timing constraint to a state
@@ -170,10 +535,340 @@ impl LibrarySystem {
             .insert(state_idx, TimingConstraints { max_duration, timeout_event });
     }
 
+    /// Enable overdue fines using the given policy
+    ///
+    /// If the book is currently checked out, its loan starts now.
+    pub fn set_fine_policy(&mut self, policy: FinePolicy) {
+        let mut tracker = FineTracker::new(policy);
+        if matches!(self.current_state(), BookState::CheckedOut(_)) {
+            tracker.start_loan(self.clock.now());
+        }
+        self.fines = Some(tracker);
+    }
+
+    /// Get the overdue fine policy, if fines are enabled
+    #[must_use]
+    pub fn fine_policy(&self) -> Option<&FinePolicy> {
+        self.fines.as_ref().map(FineTracker::policy)
+    }
+
+    /// Get when the current loan is due, if the book is checked out and fines are enabled
+    #[must_use]
+    pub fn due_date(&self) -> Option<Instant> {
+        self.fines.as_ref().and_then(FineTracker::due_date)
+    }
+
+    /// Get the fine accrued so far by the current loan, in cents
+    #[must_use]
+    pub fn current_fine(&self) -> u64 {
+        self.fines.as_ref().map_or(0, |fines| fines.current_fine(self.clock.now()))
+    }
+
+    /// Check whether the book is checked out past its due date or checkout time limit
+    #[must_use]
+    pub fn is_overdue(&self) -> bool {
+        if !matches!(self.current_state(), BookState::CheckedOut(_)) {
+            return false;
+        }
+        let past_due_date = self.due_date().is_some_and(|due| self.clock.now() > due);
+        past_due_date || self.time_until_timeout() == Some(Duration::ZERO)
+    }
+
+    /// Attach a patron registry that checks borrowing limits on every transition
+    ///
+    /// The registry is typically shared by all books of a library so limits
+    /// apply across books.
+    pub fn set_patron_registry(&mut self, registry: Arc<Mutex<PatronRegistry>>) {
+        self.patrons = Some(registry);
+    }
+
+    /// Persist the system to the given store automatically as events are processed
+    ///
+    /// The current state counts as saved, so the first save happens once the
+    /// policy is met by new transitions.
+    pub fn set_autosave(&mut self, policy: AutoSavePolicy, store: Arc<dyn StateStore>) {
+        self.autosave = Some(AutoSave { policy, store, saved_sequence: self.sequence });
+    }
+
+    /// Get the auto-save policy, [`AutoSavePolicy::Never`] if none is configured
+    #[must_use]
+    pub fn autosave_policy(&self) -> AutoSavePolicy {
+        self.autosave.as_ref().map_or(AutoSavePolicy::Never, |autosave| autosave.policy)
+    }
+
+    /// Save to the auto-save store if the policy says a save is due
+    fn autosave_if_due(&mut self) -> Result<(), LibraryError> {
+        let Some(autosave) = &self.autosave else {
+            return Ok(());
+        };
+        if !autosave.policy.is_due(self.sequence.saturating_sub(autosave.saved_sequence)) {
+            return Ok(());
+        }
+
+        self.save_to(autosave.store.as_ref())?;
+        if let Some(autosave) = &mut self.autosave {
+            autosave.saved_sequence = self.sequence;
+        }
+        Ok(())
+    }
+
+    /// Attach a branch registry used to validate transfer destinations
+    pub fn set_branch_registry(&mut self, registry: Arc<BranchRegistry>) {
+        self.branches = Some(registry);
+    }
+
+    /// Get the branch holding the book and any transfer in progress
+    #[must_use]
+    pub fn location(&self) -> &BranchLocation {
+        &self.location
+    }
+
+    /// Record which branch currently holds the book
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::UnknownBranch` if a branch registry is attached
+    /// and doesn't contain the branch
+    pub fn set_current_branch(&mut self, branch: &str) -> Result<(), LibraryError> {
+        if let Some(registry) = &self.branches {
+            registry.ensure_registered(branch)?;
+        }
+        self.location.current_branch = Some(branch.to_string());
+        Ok(())
+    }
+
+    /// Start transferring the book to another branch
+    ///
+    /// This processes a `Transfer` event carrying the destination, which
+    /// becomes the current branch once `TransferComplete` is processed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::UnknownBranch` if a branch registry is attached
+    /// and doesn't contain the destination, a `LibraryError::InvalidTransfer`
+    /// if the book is already at the destination, or any error returned by
+    /// [`Self::process_event`]
+    pub fn transfer_to(&mut self, destination: &str) -> Result<&BookState, LibraryError> {
+        self.process_event(BookEvent::Transfer { destination: Some(destination.to_string()) })
+    }
+
+    /// Check the destination of a `Transfer` event, if it names one
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::UnknownBranch` if a branch registry is attached
+    /// and doesn't contain the destination, or a `LibraryError::InvalidTransfer`
+    /// if the book is already at the destination
+    fn validate_transfer(&self, event: &BookEvent) -> Result<(), LibraryError> {
+        let BookEvent::Transfer { destination: Some(destination) } = event else {
+            return Ok(());
+        };
+        if let Some(registry) = &self.branches {
+            registry.ensure_registered(destination)?;
+        }
+        self.location.validate_transfer(destination)
+    }
+
+    /// Remember the destination of a `Transfer` event as the pending transfer
+    fn start_transfer(&mut self, event: &BookEvent) {
+        if let BookEvent::Transfer { destination: Some(destination) } = event {
+            self.location.transfer = Some(PendingTransfer {
+                source: self.location.current_branch.clone(),
+                destination: destination.clone(),
+            });
+        }
+    }
+
+    /// Re-apply a recorded transition without notifying observers or registries
+    ///
+    /// Used to rebuild a system from an event log or snapshot, so the
+    /// transition has to be part of the definition.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the transition doesn't start in
+    /// the current state, or the definition doesn't have it or leads somewhere
+    /// else
+    pub(crate) fn replay_transition(
+        &mut self,
+        transition: StateTransition,
+    ) -> Result<(), LibraryError> {
+        self.check_replay_start(&transition)?;
+        let key = (self.current_state_idx, transition.event.clone().transition_key());
+        let to_idx = match self.transitions.get(&key) {
+            Some(&to_idx) if self.states.get(to_idx) == Some(&transition.to) => to_idx,
+            Some(_) => return Err(Self::replay_conflict(&transition)),
+            None => {
+                return Err(LibraryError::LoadError(format!(
+                    "Logged transition {:?} --{:?}--> {:?} is not in the definition",
+                    transition.from, transition.event, transition.to
+                )));
+            }
+        };
+        self.record_replayed(transition, to_idx);
+        Ok(())
+    }
+
+    /// Re-apply a transition of another system, adding it to the definition
+    /// if it's missing
+    ///
+    /// Used to mirror a system whose definition may have grown since it was
+    /// copied, like the web view does.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the transition doesn't start in
+    /// the current state or the definition leads somewhere else
+    #[cfg(feature = "web")]
+    pub(crate) fn mirror_transition(
+        &mut self,
+        transition: StateTransition,
+    ) -> Result<(), LibraryError> {
+        self.check_replay_start(&transition)?;
+        let to_idx = self.add_state(transition.to.clone());
+        let key = (self.current_state_idx, transition.event.clone().transition_key());
+        if *self.transitions.entry(key).or_insert(to_idx) != to_idx {
+            return Err(Self::replay_conflict(&transition));
+        }
+        self.record_replayed(transition, to_idx);
+        Ok(())
+    }
+
+    /// Check that a replayed transition starts in the current state
+    fn check_replay_start(&self, transition: &StateTransition) -> Result<(), LibraryError> {
+        if *self.current_state() == transition.from {
+            return Ok(());
+        }
+        Err(LibraryError::LoadError(format!(
+            "Logged transition starts in {:?} but the system is in {:?}",
+            transition.from,
+            self.current_state()
+        )))
+    }
+
+    /// Describe a replayed transition the definition leads somewhere else for
+    fn replay_conflict(transition: &StateTransition) -> LibraryError {
+        LibraryError::LoadError(format!(
+            "Logged transition {:?} --{:?}--> {:?} conflicts with the definition",
+            transition.from, transition.event, transition.to
+        ))
+    }
+
+    /// Move along a replayed transition and record it in history
+    fn record_replayed(&mut self, transition: StateTransition, to_idx: usize) {
+        if matches!(transition.event, BookEvent::Transfer { .. })
+            && let Some(destination) = transition.metadata.get("transfer_to")
+        {
+            self.location.transfer = Some(PendingTransfer {
+                source: transition.metadata.get("transfer_from").cloned(),
+                destination: destination.clone(),
+            });
+        }
+        self.location.on_transition(&transition.to, &transition.event);
+
+        let now = self.clock.now();
+        if let Some(fines) = &mut self.fines {
+            fines.on_transition(&transition.to, &transition.event, now);
+        }
+        self.current_state_idx = to_idx;
+        self.state_entry_time = now;
+        self.sequence = transition.sequence.max(self.sequence.saturating_add(1));
+        self.history.push(transition);
+        if self.history.len() > self.max_history_size {
+            self.history.remove(0);
+        }
+    }
+
+    /// Move to a state directly, e.g. the start state of a compacted event log
+    pub(crate) fn restore_current_state(&mut self, state: BookState) {
+        self.current_state_idx = self.add_state(state);
+        self.state_entry_time = self.clock.now();
+    }
+
+    /// Put a patron on the waitlist for this book and return their 1-based position
+    ///
+    /// When the book next becomes available through a `Return` or
+    /// `CancelReservation`, it is automatically reserved for the first patron
+    /// in the queue.
+    pub fn place_hold(&mut self, patron: &str) -> usize {
+        self.holds.place_hold(patron)
+    }
+
+    /// Remove a patron from the waitlist, returning whether they were waiting
+    pub fn cancel_hold(&mut self, patron: &str) -> bool {
+        self.holds.cancel_hold(patron)
+    }
+
+    /// Get the waitlist of patrons holding this book
+    #[must_use]
+    pub fn holds(&self) -> &HoldQueue {
+        &self.holds
+    }
+
+    /// Check whether the book is currently reserved or checked out by someone else
+    fn is_held_by_other(&self, patron: &str) -> bool {
+        match self.current_state() {
+            BookState::Reserved(holder)
+            | BookState::OnHoldShelf(holder)
+            | BookState::CheckedOut(holder) => holder != patron,
+            _ => false,
+        }
+    }
+
+    /// Reserve the book for the next waiting patron if it has become available
+    ///
+    /// Holds are only fulfilled through a `Reserve` transition the machine
+    /// declares from the current state; patrons the machine or the patron
+    /// registry won't reserve the book for are skipped, leaving the definition
+    /// untouched. On any other failure, e.g. an observer rolling the
+    /// reservation back, the patron keeps their place at the front of the queue.
+    fn fulfill_next_hold(&mut self) {
+        if *self.current_state() != BookState::Available {
+            return;
+        }
+        while let Some(patron) = self.holds.peek().map(PatronId::from) {
+            let event = BookEvent::Reserve(patron.clone());
+            if !self
+                .transitions
+                .contains_key(&(self.current_state_idx, event.clone().transition_key()))
+            {
+                emit!(
+                    warn,
+                    "HOLDS: Skipping hold for {patron}: no reservation transition for them"
+                );
+                self.holds.next_patron();
+                continue;
+            }
+
+            emit!(info, "HOLDS: Fulfilling hold for {patron}");
+            match self.apply_event(EventEnvelope::new(event).note("Hold fulfilled")) {
+                Ok(_) => {
+                    self.holds.next_patron();
+                    for (_, observer) in &self.observers {
+                        observer.on_hold_fulfilled(&patron);
+                    }
+                    break;
+                }
+                // The patron can't borrow the book, so try the next one
+                Err(
+                    e @ (LibraryError::BorrowingLimitReached { .. }
+                    | LibraryError::UnknownPatron(_)),
+                ) => {
+                    emit!(warn, "HOLDS: Skipping hold for {patron}: {e}");
+                    self.holds.next_patron();
+                }
+                Err(e) => {
+                    emit!(warn, "HOLDS: Keeping hold for {patron}: {e}");
+                    break;
+                }
+            }
+        }
+    }
+
     /// Check if the current state has timed out
     fn check_timeout(&mut self) -> Option<BookEvent> {
         if let Some(constraint) = self.timing_constraints.get(&self.current_state_idx) {
-            let time_in_state = Instant::now().duration_since(self.state_entry_time);
+            let time_in_state = self.clock.now().duration_since(self.state_entry_time);
             if time_in_state > constraint.max_duration {
                 return Some(constraint.timeout_event.clone());
             }
@@ -181,6 +876,19 @@ impl LibrarySystem {
         None
     }
 
+    /// Get the events that have a transition from the current state
+    #[must_use]
+    pub fn valid_events(&self) -> Vec<BookEvent> {
+        let mut events: Vec<_> = self
+            .transitions
+            .keys()
+            .filter(|(state_idx, _)| *state_idx == self.current_state_idx)
+            .map(|(_, event)| event.clone())
+            .collect();
+        events.sort_by_key(|event| format!("{event:?}"));
+        events
+    }
+
     /// Get the current state of the system
     ///
     /// # Panics
@@ -193,57 +901,347 @@ impl LibrarySystem {
         self.states.get(self.current_state_idx).expect("Invalid current state index")
     }
 
+    /// Get how long until the current state's timing constraint expires
+    ///
+    /// Returns `None` if the current state has no timing constraint, and
+    /// `Some(Duration::ZERO)` if the constraint has already expired.
+    #[must_use]
+    pub fn time_until_timeout(&self) -> Option<Duration> {
+        self.timing_constraints
+            .get(&self.current_state_idx)
+            .map(|constraint| constraint.max_duration.saturating_sub(self.time_in_current_state()))
+    }
+
+    /// Get how long the system has been in its current state
+    #[must_use]
+    pub fn time_in_current_state(&self) -> Duration {
+        self.clock.now().duration_since(self.state_entry_time)
+    }
+
+    /// Fire the current state's timeout event if its deadline has passed
+    ///
+    /// Returns `Ok(None)` if no timeout is due, otherwise the state reached by
+    /// processing the timeout event. Observers are notified as for any other
+    /// transition.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::InvalidTransition` if the timeout event has no
+    /// transition defined from the current state, or any error from an
+    /// auto-save (see [`Self::process_event`])
+    pub fn fire_timeout_if_due(&mut self) -> Result<Option<&BookState>, LibraryError> {
+        let Some(timeout_event) = self.check_timeout() else {
+            return Ok(None);
+        };
+        emit!(info, "State timed out! Processing timeout event: {timeout_event:?}");
+        self.apply_event(EventEnvelope::new(timeout_event).note("Timed out"))?;
+        self.autosave_if_due()?;
+        Ok(Some(self.current_state()))
+    }
+
+    /// Process an event over and over, following a recurrence rule
+    ///
+    /// The first occurrence is due one interval from now. A recurring event
+    /// with the same name is replaced. Occurrences are fired by
+    /// [`Self::fire_recurring_if_due`], e.g. from a
+    /// [`TimeoutScheduler`](crate::scheduler::TimeoutScheduler), and survive
+    /// saving and loading the system.
+    pub fn add_recurring_event(&mut self, name: &str, rule: RecurrenceRule, event: BookEvent) {
+        let now = self.clock.now();
+        let next_due = now.checked_add(rule.interval()).unwrap_or(now);
+        self.remove_recurring_event(name);
+        self.recurring.push((RecurringEvent { name: name.to_string(), rule, event }, next_due));
+    }
+
+    /// Stop a recurring event, handing it back
+    pub fn remove_recurring_event(&mut self, name: &str) -> Option<RecurringEvent> {
+        let position = self.recurring.iter().position(|(recurring, _)| recurring.name == name)?;
+        Some(self.recurring.remove(position).0)
+    }
+
+    /// Get the registered recurring events
+    pub fn recurring_events(&self) -> impl Iterator<Item = &RecurringEvent> {
+        self.recurring.iter().map(|(recurring, _)| recurring)
+    }
+
+    /// Get how long until the next recurring event is due, zero if one is overdue
+    #[must_use]
+    pub fn time_until_recurring(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.recurring.iter().map(|(_, next_due)| next_due.saturating_duration_since(now)).min()
+    }
+
+    /// Process the recurring events whose time has come
+    ///
+    /// A due event is processed once, however many occurrences were missed,
+    /// and scheduled for its next occurrence. Events without a transition
+    /// from the current state, like a condition check while the book is
+    /// checked out, are skipped until then. Returns the number of events
+    /// processed.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from processing a due event, as for
+    /// [`Self::process_event`]; its next occurrence is scheduled anyway
+    pub fn fire_recurring_if_due(&mut self) -> Result<usize, LibraryError> {
+        let now = self.clock.now();
+        let mut fired = 0_usize;
+        for idx in 0..self.recurring.len() {
+            let Some((recurring, next_due)) = self.recurring.get_mut(idx) else {
+                continue;
+            };
+            if *next_due > now {
+                continue;
+            }
+
+            // Skip the occurrences that were missed, e.g. while the system was saved
+            let interval = recurring.rule.interval().as_secs();
+            let missed = now.duration_since(*next_due).as_secs().checked_div(interval);
+            let periods = missed.unwrap_or_default().saturating_add(1);
+            *next_due = next_due
+                .checked_add(Duration::from_secs(interval.saturating_mul(periods)))
+                .unwrap_or(now);
+
+            let (name, event) = (recurring.name.clone(), recurring.event.clone());
+            let key = (self.current_state_idx, event.clone().transition_key());
+            if !self.transitions.contains_key(&key) {
+                emit!(info, "RECURRING: Skipping {name}, no transition on {event:?} from here");
+                continue;
+            }
+            emit!(info, "RECURRING: Processing {name}: {event:?}");
+            self.apply_event(EventEnvelope::new(event).note(&format!("Recurring: {name}")))?;
+            fired = fired.saturating_add(1);
+        }
+        if fired > 0 {
+            self.autosave_if_due()?;
+        }
+        Ok(fired)
+    }
+
     /// Process an event, potentially changing the system state
     ///
+    /// If the current state has timed out, its timeout event is applied first
+    /// and the event is then processed from the resulting state.
+    ///
+    /// A `Reserve` for a book that is reserved or checked out by another patron
+    /// places a hold instead of failing; the current state is returned unchanged.
+    ///
+    /// If an auto-save policy is configured (see [`Self::set_autosave`]), the
+    /// system is saved once the policy is met.
+    ///
     /// # Errors
     ///
     /// Returns a `LibraryError::InvalidTransition` if the event cannot be processed
-    /// from the current state because no valid transition is defined
+    /// from the current state because no valid transition is defined. A failed
+    /// auto-save returns the store's error even though the transition has been
+    /// applied; the save is retried after the next transition.
     pub fn process_event(&mut self, event: BookEvent) -> Result<&BookState, LibraryError> {
+        self.process_event_with_meta(EventEnvelope::new(event))
+    }
+
+    /// Process an event along with who triggered it, why, and when
+    ///
+    /// Behaves like [`Self::process_event`]; the envelope's actor, note and
+    /// time are stored in the resulting [`StateTransition`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::InvalidTransition` if the event cannot be processed
+    /// from the current state because no valid transition is defined
+    pub fn process_event_with_meta(
+        &mut self,
+        envelope: EventEnvelope,
+    ) -> Result<&BookState, LibraryError> {
+        let _span = tracing::info_span!(
+            "process_event",
+            system_id = %self.system_id,
+            event = ?envelope.event,
+            actor = ?envelope.actor,
+        )
+        .entered();
+
         // Check for timeouts first
-        if let Some(timeout_event) = self.check_timeout() {
-            println!("State timed out! Processing timeout event: {timeout_event:?}");
-            return self.process_event(timeout_event);
+        self.fire_timeout_if_due()?;
+
+        self.apply_event(envelope)?;
+        self.autosave_if_due()?;
+        Ok(self.current_state())
+    }
+
+    /// Build the error for an event with no transition, recording it as a dead letter
+    fn reject_event(&self, from_state: BookState, envelope: EventEnvelope) -> LibraryError {
+        let EventEnvelope { event, actor, note, occurred_at } = envelope;
+        tracing::debug!(from_state = ?from_state, event = ?event, "Rejected invalid transition");
+        if let Some(sink) = &self.dead_letters {
+            sink.record(&DeadLetter {
+                system_id: self.system_id.clone(),
+                state: from_state.clone(),
+                event: event.clone(),
+                timestamp: TimeStamp::from_system_time(occurred_at),
+                actor,
+                note,
+            });
         }
+        LibraryError::InvalidTransition { from_state, event, valid_events: self.valid_events() }
+    }
+
+    /// Apply an event to the current state without checking for timeouts
+    fn apply_event(&mut self, envelope: EventEnvelope) -> Result<&BookState, LibraryError> {
+        let EventEnvelope { event, actor, note, occurred_at } = envelope;
 
         // Look up the transition
         let from_state = self.current_state().clone();
 
-        match self.transitions.get(&(self.current_state_idx, event.clone())) {
-            Some(&next_state_idx) => {
-                // Apply the transition
-                self.current_state_idx = next_state_idx;
+        let Some(&next_state_idx) =
+            self.transitions.get(&(self.current_state_idx, event.clone().transition_key()))
+        else {
+            // Someone else has the book, so join the waitlist instead
+            if let BookEvent::Reserve(patron) = &event
+                && self.is_held_by_other(patron)
+            {
+                let position = self.holds.place_hold(patron);
+                emit!(info, "HOLDS: {patron} placed a hold at position {position}");
+                return Ok(self.current_state());
+            }
 
-                // Record the transition in history
-                let transition = StateTransition {
-                    from: from_state.clone(),
-                    to: self.current_state().clone(),
-                    event: event.clone(),
-                    timestamp: SerializableInstant::now(),
-                };
+            // No valid transition for this event from current state
+            let envelope = EventEnvelope { event, actor, note, occurred_at };
+            return Err(self.reject_event(from_state, envelope));
+        };
 
-                self.history.push(transition);
+        // Check where a transfer is heading before changing anything
+        self.validate_transfer(&event)?;
 
-                // Maintain history size limit
-                if self.history.len() > self.max_history_size {
-                    self.history.remove(0); // Remove oldest entry
+        // Enforce patron borrowing limits before changing anything
+        if let Some(registry) = &self.patrons
+            && let Some(to_state) = self.states.get(next_state_idx)
+        {
+            registry
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .check_transition(&self.system_id, to_state)?;
+        }
+
+        // Let the policies block the transition
+        if let Some(to_state) = self.states.get(next_state_idx) {
+            for interceptor in &self.interceptors {
+                if let Err(reason) = interceptor.before_transition(&from_state, to_state, &event) {
+                    emit!(info, "POLICY: Vetoed {event:?} from {from_state:?}: {reason}");
+                    return Err(LibraryError::Vetoed { from_state, event, reason });
                 }
+            }
+        }
+
+        // Remember what the transition changes, in case an observer rejects it
+        let checkpoint = Checkpoint {
+            current_state_idx: self.current_state_idx,
+            sequence: self.sequence,
+            state_entry_time: self.state_entry_time,
+            fines: self.fines.clone(),
+            location: self.location.clone(),
+        };
 
-                // Reset state entry time for timing constraints
-                self.state_entry_time = Instant::now();
+        // Apply the transition
+        self.current_state_idx = next_state_idx;
+        self.sequence = self.sequence.saturating_add(1);
+        let now = self.clock.now();
 
-                // Notify observers
-                for observer in &self.observers {
-                    observer.on_state_change(&from_state, self.current_state(), &event);
-                }
+        // Update the loan and assess any fine
+        let mut metadata = BTreeMap::new();
+        let to_state = self.current_state().clone();
+        if let Some(fines) = &mut self.fines
+            && let Some(fine) = fines.on_transition(&to_state, &event, now)
+        {
+            metadata.insert("fine_cents".to_string(), fine.to_string());
+        }
 
-                Ok(self.current_state())
+        // Start the transfer and record where the book is heading
+        self.start_transfer(&event);
+        if matches!(event, BookEvent::Transfer { .. })
+            && let Some(transfer) = &self.location.transfer
+        {
+            if let Some(source) = &transfer.source {
+                metadata.insert("transfer_from".to_string(), source.clone());
             }
-            None => {
-                // No valid transition for this event from current state
-                Err(LibraryError::InvalidTransition { from_state, event })
+            metadata.insert("transfer_to".to_string(), transfer.destination.clone());
+        }
+        self.location.on_transition(&to_state, &event);
+
+        // Describe the transition for the observers and history
+        let transition = StateTransition {
+            from: from_state.clone(),
+            to: to_state,
+            event: event.clone(),
+            timestamp: SerializableInstant::from_instant(now),
+            metadata,
+            actor,
+            note,
+            occurred_at: Some(occurred_at),
+            sequence: self.sequence,
+        };
+
+        // Reset state entry time for timing constraints
+        self.state_entry_time = now;
+
+        // Notify observers
+        if let Err(e) = self.notify_observers(&transition) {
+            let Checkpoint { current_state_idx, sequence, state_entry_time, fines, location } =
+                checkpoint;
+            self.current_state_idx = current_state_idx;
+            self.sequence = sequence;
+            self.state_entry_time = state_entry_time;
+            self.fines = fines;
+            self.location = location;
+            return Err(e);
+        }
+
+        // Record the transition in history, once no observer rejected it
+        self.history.push(transition);
+        if self.history.len() > self.max_history_size {
+            self.history.remove(0);
+        }
+
+        // Keep the patrons' active loans up to date
+        if let Some(registry) = &self.patrons {
+            registry.lock().unwrap_or_else(PoisonError::into_inner).record_transition(
+                &self.system_id,
+                &from_state,
+                self.current_state(),
+            );
+        }
+
+        // Hand the book over to the next patron in line
+        if matches!(event, BookEvent::Return | BookEvent::CancelReservation) {
+            self.fulfill_next_hold();
+        }
+
+        Ok(self.current_state())
+    }
+
+    /// Notify every observer of a transition, applying the observer error policy
+    ///
+    /// When the transition is rolled back, the observers that accepted it are
+    /// told it was undone.
+    fn notify_observers(&self, transition: &StateTransition) -> Result<(), LibraryError> {
+        for (notified, (_, observer)) in self.observers.iter().enumerate() {
+            let Err(e) = observer.try_on_transition(transition) else {
+                continue;
+            };
+            match self.observer_error_policy {
+                ObserverErrorPolicy::Ignore => {}
+                ObserverErrorPolicy::Log => {
+                    emit!(warn, "OBSERVER: Failed to handle transition: {e}");
+                }
+                ObserverErrorPolicy::Rollback => {
+                    emit!(warn, "OBSERVER: Failed to handle transition, rolling back: {e}");
+                    for (_, observer) in self.observers.iter().take(notified) {
+                        observer.on_rollback(transition);
+                    }
+                    return Err(LibraryError::ObserverFailed(e));
+                }
             }
         }
+        Ok(())
     }
 
     /// Get the complete transition history
@@ -252,13 +1250,65 @@ impl LibrarySystem {
         &self.history
     }
 
+    /// Write the transition history as CSV, see [`history_csv::export_csv`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the CSV can't be written
+    #[cfg(feature = "csv")]
+    pub fn export_history_csv(&self, writer: impl std::io::Write) -> Result<(), LibraryError> {
+        history_csv::export_csv(&self.history, writer)
+    }
+
+    /// Read a transition history exported with [`Self::export_history_csv`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if a row can't be read or parsed
+    #[cfg(feature = "csv")]
+    pub fn import_history_csv(
+        reader: impl std::io::Read,
+    ) -> Result<Vec<StateTransition>, LibraryError> {
+        history_csv::import_csv(reader)
+    }
+
+    /// Get the number of transitions applied since the system was created
+    #[must_use]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Get the version of the stored copy the system was loaded from or last
+    /// saved as, 0 if it was never stored
+    ///
+    /// Stores rejecting stale saves, like the `PostgresStore` of the
+    /// `postgres` feature, compare it with the version of their copy.
+    #[must_use]
+    pub fn stored_version(&self) -> u64 {
+        self.stored_version.load(Ordering::Relaxed)
+    }
+
+    /// Record that the state was stored, bumping the stored version past it
+    pub(crate) fn record_saved(&self, state: &SerializableSystemState) {
+        self.stored_version.store(state.version.saturating_add(1), Ordering::Relaxed);
+    }
+
+    /// Change how many history entries are kept, dropping the oldest extra ones
+    pub fn set_max_history_size(&mut self, max_history_size: usize) {
+        self.max_history_size = max_history_size;
+        let excess = self.history.len().saturating_sub(max_history_size);
+        self.history.drain(..excess);
+    }
+
     /// Print the transition history to stdout
     #[allow(clippy::arithmetic_side_effects)]
     pub fn print_history(&self) {
         println!("Transition History:");
         for (i, transition) in self.history.iter().enumerate() {
+            let actor =
+                transition.actor.as_ref().map_or_else(String::new, |actor| format!(" by {actor}"));
             println!(
-                "{}. {:?} --({:?})--> {:?}",
+                "{}. {:?} --({:?})--> {:?}{actor}",
                 i + 1,
                 transition.from,
                 transition.event,
@@ -267,16 +1317,16 @@ impl LibrarySystem {
         }
     }
 
-    /// Save the system state to a JSON file
+    /// Capture everything needed to restore the system later
     ///
-    /// # Errors
-    ///
-    /// Returns a `LibraryError::PersistenceError` if:
-    /// - The state cannot be serialized to JSON
-    /// - The file cannot be created
-    /// - The data cannot be written to the file
-    pub fn save_state_to_file(&self) -> Result<(), LibraryError> {
-        let serializable_state = SerializableSystemState {
+    /// Observers, the clock and attached registries are not part of the
+    /// snapshot and must be re-attached after restoring.
+    #[must_use]
+    pub fn to_serializable_state(&self) -> SerializableSystemState {
+        let (now, wall_now) = (self.clock.now(), SystemTime::now());
+        let to_timestamp =
+            |instant| TimeStamp::from_system_time(instant_to_system_time(instant, now, wall_now));
+        SerializableSystemState {
             states: self.states.clone(),
             transitions: self
                 .transitions
@@ -292,26 +1342,141 @@ impl LibrarySystem {
                 .map(|(state_idx, constraint)| (*state_idx, constraint.clone()))
                 .collect(),
             system_id: self.system_id.clone(),
+            fine_policy: self.fine_policy().cloned(),
+            holds: self.holds.clone(),
+            location: self.location.clone(),
+            final_states: self.final_states.clone(),
+            state_categories: self.state_categories.clone(),
+            sequence: self.sequence,
+            version: self.stored_version(),
+            state_entered_at: Some(to_timestamp(self.state_entry_time)),
+            due_date: self.due_date().map(to_timestamp),
+            recurring_events: self
+                .recurring
+                .iter()
+                .map(|(recurring, next_due)| (recurring.clone(), to_timestamp(*next_due)))
+                .collect(),
+        }
+    }
+
+    /// Restore a system from a snapshot, using the system clock and no observers
+    ///
+    /// The time already spent in the current state and the due date of a loan
+    /// in progress are restored from their saved wall-clock times, so timing
+    /// constraints and fines carry on across a restart.
+    #[must_use]
+    pub fn from_serializable_state(serializable_state: SerializableSystemState) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let (now, wall_now) = (clock.now(), SystemTime::now());
+        let to_instant = |timestamp: TimeStamp| {
+            system_time_to_instant(timestamp.to_system_time(), now, wall_now)
+        };
+        let mut system = Self {
+            states: serializable_state.states,
+            transitions: serializable_state.transitions.into_iter().collect(),
+            current_state_idx: serializable_state.current_state_idx,
+            history: serializable_state.history,
+            max_history_size: serializable_state.max_history_size,
+            state_entry_time: serializable_state.state_entered_at.map_or(now, to_instant),
+            timing_constraints: serializable_state.timing_constraints.into_iter().collect(),
+            observers: Vec::new(), // Observers need to be re-attached
+            next_observer_id: 0,
+            observer_error_policy: ObserverErrorPolicy::default(),
+            interceptors: Vec::new(),
+            dead_letters: None,
+            system_id: serializable_state.system_id,
+            clock,
+            fines: None,
+            holds: serializable_state.holds,
+            patrons: None,
+            location: serializable_state.location,
+            branches: None,
+            final_states: serializable_state.final_states,
+            state_categories: serializable_state.state_categories,
+            sequence: serializable_state.sequence,
+            stored_version: AtomicU64::new(serializable_state.version),
+            autosave: None,
+            recurring: serializable_state
+                .recurring_events
+                .into_iter()
+                .map(|(recurring, next_due)| (recurring, to_instant(next_due)))
+                .collect(),
         };
 
-        let serialized = serde_json::to_string_pretty(&serializable_state)
-            .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+        // Snapshots without a due date restart a loan in progress on load
+        if let Some(policy) = serializable_state.fine_policy {
+            system.set_fine_policy(policy);
+            if let (Some(fines), Some(due_date)) = (&mut system.fines, serializable_state.due_date)
+                && fines.due_date().is_some()
+            {
+                fines.resume_loan(to_instant(due_date));
+            }
+        }
 
-        let system_id = &self.system_id;
-        let filename = format!("{system_id}.json");
-        println!("PERSISTENCE: Saving state to file: {filename}");
+        system
+    }
 
-        let mut file = File::create(&filename)
-            .map_err(|e| LibraryError::PersistenceError(format!("Failed to create file: {e}")))?;
+    /// Save the system state to the given store
+    ///
+    /// The stored version is bumped on success, so saving again doesn't
+    /// conflict with this save.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by the store, usually a
+    /// `LibraryError::PersistenceError`, or a `LibraryError::ConflictingUpdate`
+    /// if the stored copy changed since this one was loaded
+    pub fn save_to(&self, store: &dyn StateStore) -> Result<(), LibraryError> {
+        let state = self.to_serializable_state();
+        store.save(&state)?;
+        self.record_saved(&state);
+        Ok(())
+    }
 
-        file.write_all(serialized.as_bytes())
-            .map_err(|e| LibraryError::PersistenceError(format!("Failed to write to file: {e}")))?;
+    /// Load a system from the given store
+    ///
+    /// The loaded system has no observers registered.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by the store, usually a
+    /// `LibraryError::LoadError`
+    pub fn load_from(store: &dyn StateStore, system_id: &str) -> Result<Self, LibraryError> {
+        store.load(system_id).map(Self::from_serializable_state)
+    }
 
-        Ok(())
+    /// Save the system state to a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if:
+    /// - The state cannot be serialized to JSON
+    /// - The file cannot be created
+    /// - The data cannot be written to the file
+    pub fn save_state_to_file(&self) -> Result<(), LibraryError> {
+        self.save_state_to_file_as(PersistenceFormat::Json)
+    }
+
+    /// Save the system state to a file in the given format
+    ///
+    /// The file is named `<system_id>.<extension>`, see
+    /// [`PersistenceFormat::extension`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the state can't be
+    /// encoded or written
+    pub fn save_state_to_file_as(&self, format: PersistenceFormat) -> Result<(), LibraryError> {
+        self.save_to(&FileStore::default().with_format(format))
     }
 
     /// Load the system state from a JSON file
     ///
+    /// The standard [`TransitionLogger`] and [`NotificationService`] observers
+    /// are registered on the loaded system.
+    /// They don't hear about earlier transitions; register your own observers
+    /// and call [`Self::replay_history_to_observers`] to rebuild their state.
+    ///
     /// # Errors
     ///
     /// Returns a `LibraryError::LoadError` if:
@@ -320,43 +1485,83 @@ impl LibrarySystem {
     /// - The file cannot be read
     /// - The JSON parsing fails
     pub fn load_state_from_file(system_id: &str) -> Result<Self, LibraryError> {
-        let filename = format!("{system_id}.json");
-        println!("PERSISTENCE: Loading state from file: {filename}");
-
-        if !Path::new(&filename).exists() {
-            return Err(LibraryError::LoadError(format!("File does not exist: {filename}")));
-        }
+        Self::load_state_from_file_as(system_id, PersistenceFormat::Json)
+    }
 
-        // Read the file
-        let mut file = File::open(&filename)
-            .map_err(|e| LibraryError::LoadError(format!("Failed to open file: {e}")))?;
+    /// Load the system state from a file in the given format
+    ///
+    /// Like [`Self::load_state_from_file`], the standard observers are
+    /// registered on the loaded system.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the file can't be read or decoded
+    pub fn load_state_from_file_as(
+        system_id: &str,
+        format: PersistenceFormat,
+    ) -> Result<Self, LibraryError> {
+        let system = Self::load_from(&FileStore::default().with_format(format), system_id)?;
+        Ok(system.with_standard_observers())
+    }
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .map_err(|e| LibraryError::LoadError(format!("Failed to read file: {e}")))?;
+    /// Load the `n`th previous version of the system's JSON file, 1 being the most recent
+    ///
+    /// Backups are only written by a [`FileStore`] configured with
+    /// [`FileStore::with_backups`]. Like [`Self::load_state_from_file`], the
+    /// standard observers are registered on the loaded system.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the backup doesn't exist or
+    /// can't be read
+    pub fn load_backup(system_id: &str, n: usize) -> Result<Self, LibraryError> {
+        let state = FileStore::default().load_backup(system_id, n)?;
+        Ok(Self::from_serializable_state(state).with_standard_observers())
+    }
 
-        // Deserialize the JSON
-        let serializable_state: SerializableSystemState = serde_json::from_str(&contents)
-            .map_err(|e| LibraryError::LoadError(format!("Failed to parse JSON: {e}")))?;
+    /// Re-register the observers a system loaded from a file starts with
+    fn with_standard_observers(mut self) -> Self {
+        self.register_observer(Box::new(TransitionLogger));
+        self.register_observer(Box::new(NotificationService));
+        self
+    }
 
-        // Convert back to our runtime representation
-        let mut system = Self {
-            states: serializable_state.states,
-            transitions: serializable_state.transitions.into_iter().collect(),
-            current_state_idx: serializable_state.current_state_idx,
-            history: serializable_state.history,
-            max_history_size: serializable_state.max_history_size,
-            state_entry_time: Instant::now(), // Reset the entry time
-            timing_constraints: serializable_state.timing_constraints.into_iter().collect(),
-            observers: Vec::new(), // Observers need to be re-attached
-            system_id: serializable_state.system_id,
-        };
+    /// Write a tar archive with the system's state, definition, history and
+    /// graph, see [`archive::export_archive`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the archive can't be written
+    #[cfg(feature = "archive")]
+    pub fn export_archive(&self, path: impl AsRef<Path>) -> Result<(), LibraryError> {
+        archive::export_archive(self, path)
+    }
 
-        // Re-register standard observers
-        system.register_observer(Box::new(TransitionLogger));
-        system.register_observer(Box::new(NotificationService));
+    /// Restore a system from an archive written by [`Self::export_archive`]
+    ///
+    /// The loaded system has no observers registered.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the archive can't be read or has
+    /// no valid system state
+    #[cfg(feature = "archive")]
+    pub fn import_archive(path: impl AsRef<Path>) -> Result<Self, LibraryError> {
+        archive::import_archive(path)
+    }
 
-        Ok(system)
+    /// Build a system from a TOML or YAML machine definition file
+    ///
+    /// The format is picked from the file extension (`.toml`, `.yaml`, `.yml`,
+    /// `.dot` or `.gv`); see [`MachineDefinition`] for the expected layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::LoadError` if the file can't be read or
+    /// parsed, or a `LibraryError::InvalidDefinition` if the definition is
+    /// inconsistent
+    pub fn from_definition_file(path: impl AsRef<Path>) -> Result<Self, LibraryError> {
+        MachineDefinition::from_file(path)?.build()
     }
 
     /// Get all states in the system
@@ -383,6 +1588,12 @@ impl LibrarySystem {
         &self.timing_constraints
     }
 
+    /// Get the unique identifier of this system
+    #[must_use]
+    pub fn system_id(&self) -> &str {
+        &self.system_id
+    }
+
     /// Find the index of a state in the system
     #[must_use]
     pub fn get_state_idx(&self, state: &BookState) -> Option<usize> {
@@ -0,0 +1,240 @@
+use std::fmt::{self, Write};
+
+use crate::generic::{State, TransitionError, TransitionSystem};
+
+/// Builds the system under test, starting in the given state
+type SystemFactory<S, E> = Box<dyn Fn(S) -> Result<TransitionSystem<S, E>, TransitionError>>;
+
+/// Drives a generic [`TransitionSystem`] through event sequences in tests
+///
+/// Each scenario starts from a fresh system built by the factory, so
+/// scenarios don't affect each other. Assertions return a
+/// [`ScenarioFailure`] describing the scenario and the transitions it went
+/// through, rather than panicking, so tests can use `?`.
+///
+/// ```
+/// use transition_system::{generic::TransitionSystem, testing::MachineTester, transitions};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Light {
+///     Red,
+///     Yellow,
+///     Green,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Signal {
+///     Timer,
+/// }
+///
+/// let tester = MachineTester::new(|initial| {
+///     let mut system = TransitionSystem::new(initial);
+///     transitions!(system: Light, Signal {
+///         Red + Timer => Green,
+///         Green + Timer => Yellow,
+///         Yellow + Timer => Red,
+///     })?;
+///     Ok(system)
+/// });
+///
+/// tester.given(Light::Red).when([Signal::Timer, Signal::Timer]).expect(&Light::Yellow)?;
+/// # Ok::<(), transition_system::testing::ScenarioFailure>(())
+/// ```
+pub struct MachineTester<S: State, E> {
+    /// Builds a fresh system for every scenario
+    factory: SystemFactory<S, E>,
+}
+
+// Manual implementation of Debug for MachineTester
+impl<S: State, E> fmt::Debug for MachineTester<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MachineTester").finish_non_exhaustive()
+    }
+}
+
+impl<S, E> MachineTester<S, E>
+where
+    S: State,
+    E: Clone + fmt::Debug,
+{
+    /// Create a tester building systems with the given factory
+    ///
+    /// The factory gets the scenario's initial state, so a
+    /// [`StateMachine::transition_system`](crate::generic::StateMachine::transition_system)
+    /// can be passed directly.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn(S) -> Result<TransitionSystem<S, E>, TransitionError> + 'static,
+    {
+        Self { factory: Box::new(factory) }
+    }
+
+    /// Start a scenario from the given state
+    #[must_use]
+    pub fn given(&self, state: S) -> Scenario<S, E> {
+        Scenario {
+            given: state.clone(),
+            events: Vec::new(),
+            system: (self.factory)(state),
+            rejected: None,
+        }
+    }
+}
+
+/// An event a [`Scenario`] couldn't apply
+#[derive(Debug)]
+struct Rejection<S, E> {
+    /// Position of the rejected event in the scenario, from 0
+    position: usize,
+    /// The rejected event
+    event: E,
+    /// The state the system was in
+    state: S,
+    /// Why the event was rejected
+    error: TransitionError,
+}
+
+/// Events applied to a system from a known state, created by [`MachineTester::given`]
+///
+/// Events are applied in order until one is rejected; the rest are recorded
+/// but not applied.
+#[derive(Debug)]
+pub struct Scenario<S: State, E> {
+    /// The state the scenario started from
+    given: S,
+    /// Every event of the scenario, applied or not
+    events: Vec<E>,
+    /// The system under test, or the error building it
+    system: Result<TransitionSystem<S, E>, TransitionError>,
+    /// The first rejected event, if any
+    rejected: Option<Rejection<S, E>>,
+}
+
+impl<S, E> Scenario<S, E>
+where
+    S: State,
+    E: Clone + fmt::Debug,
+{
+    /// Apply events in order, stopping at the first rejected one
+    #[must_use]
+    pub fn when(mut self, events: impl IntoIterator<Item = E>) -> Self {
+        for event in events {
+            self.events.push(event.clone());
+            if self.rejected.is_some() {
+                continue;
+            }
+            if let Ok(system) = &mut self.system {
+                let state = system.current_state().clone();
+                if let Err(error) = system.apply_event(event.clone()) {
+                    let position = self.events.len().saturating_sub(1);
+                    self.rejected = Some(Rejection { position, event, state, error });
+                }
+            }
+        }
+        self
+    }
+
+    /// Get the system under test, if it could be built
+    #[must_use]
+    pub fn system(&self) -> Option<&TransitionSystem<S, E>> {
+        self.system.as_ref().ok()
+    }
+
+    /// Check that every event was applied and the system ended in `state`
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScenarioFailure`] if the system couldn't be built, an event
+    /// was rejected, or the system ended in another state
+    pub fn expect(&self, state: &S) -> Result<(), ScenarioFailure> {
+        let expected = format!("state {state:?}");
+        let system = self.built(&expected)?;
+        if let Some(rejection) = &self.rejected {
+            return Err(self.failure(expected, format!("{:?} rejected", rejection.event)));
+        }
+        if system.current_state() != state {
+            return Err(self.failure(expected, format!("state {:?}", system.current_state())));
+        }
+        Ok(())
+    }
+
+    /// Check that the last event was rejected, with the system still in `state`
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ScenarioFailure`] if the system couldn't be built, an
+    /// earlier event was rejected, the last one was applied, or the system is
+    /// in another state
+    pub fn expect_rejected(&self, state: &S) -> Result<(), ScenarioFailure> {
+        let expected = format!("last event rejected in state {state:?}");
+        let system = self.built(&expected)?;
+        let last = self.events.len().saturating_sub(1);
+        match &self.rejected {
+            None => Err(self.failure(expected, format!("state {:?}", system.current_state()))),
+            Some(rejection) if rejection.position != last => {
+                Err(self.failure(expected, format!("{:?} rejected", rejection.event)))
+            }
+            Some(rejection) if rejection.state != *state => Err(self.failure(
+                expected,
+                format!("{:?} rejected in state {:?}", rejection.event, rejection.state),
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Get the system, or a failure if it couldn't be built
+    fn built(&self, expected: &str) -> Result<&TransitionSystem<S, E>, ScenarioFailure> {
+        self.system.as_ref().map_err(|error| {
+            self.failure(expected.to_string(), format!("system not built: {error}"))
+        })
+    }
+
+    /// Describe how the scenario failed, with its trace
+    fn failure(&self, expected: String, actual: String) -> ScenarioFailure {
+        let mut trace = format!("  given {:?}\n  when {:?}\n  history:\n", self.given, self.events);
+        let history = self.system.as_ref().map(TransitionSystem::get_history);
+        let history = history.map(Vec::as_slice).unwrap_or_default();
+        if history.is_empty() {
+            trace.push_str("    (no transitions)\n");
+        }
+        for record in history {
+            let _ = writeln!(
+                trace,
+                "    {}. {:?} --{:?}--> {:?}",
+                record.sequence, record.from, record.event, record.to
+            );
+        }
+        if let Some(Rejection { event, state, error, .. }) = &self.rejected {
+            let _ = writeln!(trace, "  rejected: {event:?} in {state:?}: {error}");
+        }
+        ScenarioFailure { expected, actual, trace }
+    }
+}
+
+/// A failed [`Scenario`] assertion
+///
+/// Displays as a diff of the expected and actual outcome followed by the
+/// scenario's trace:
+///
+/// ```text
+/// expected: state Yellow
+///   actual: state Green
+///   given Red
+///   when [Timer]
+///   history:
+///     1. Red --Timer--> Green
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("expected: {expected}\n  actual: {actual}\n{trace}")]
+pub struct ScenarioFailure {
+    /// The expected outcome
+    pub expected: String,
+    /// What happened instead
+    pub actual: String,
+    /// The scenario's initial state, events, applied transitions and rejection
+    pub trace: String,
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
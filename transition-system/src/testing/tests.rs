@@ -0,0 +1,116 @@
+use super::*;
+use crate::generic::TransitionBuilder;
+
+/// States of a traffic light
+#[derive(Debug, Clone, PartialEq)]
+enum Light {
+    /// Stop
+    Red,
+    /// Prepare to stop
+    Yellow,
+    /// Go
+    Green,
+}
+
+/// Events driving the traffic light
+#[derive(Debug, Clone, PartialEq)]
+enum Signal {
+    /// The light's timer ran out
+    Timer,
+    /// An emergency vehicle is approaching
+    Emergency,
+}
+
+/// Build a tester for a traffic light cycling on `Timer`
+fn tester() -> MachineTester<Light, Signal> {
+    MachineTester::new(|initial| {
+        let mut system = TransitionSystem::new(initial);
+        crate::transitions!(system: Light, Signal {
+            Red + Timer => Green,
+            Green + Timer => Yellow,
+            Yellow + Timer => Red,
+            Green + Emergency => Red,
+        })?;
+        Ok(system)
+    })
+}
+
+#[test]
+fn test_expect() -> Result<(), ScenarioFailure> {
+    let tester = tester();
+    tester.given(Light::Red).when([Signal::Timer, Signal::Timer]).expect(&Light::Yellow)?;
+    tester.given(Light::Green).when([Signal::Emergency]).expect(&Light::Red)?;
+    // Scenarios start from fresh systems
+    tester.given(Light::Red).when([]).expect(&Light::Red)?;
+
+    let scenario = tester.given(Light::Red).when([Signal::Timer]).when([Signal::Timer]);
+    scenario.expect(&Light::Yellow)?;
+    assert_eq!(scenario.system().map(|system| system.get_history().len()), Some(2));
+    Ok(())
+}
+
+#[test]
+fn test_expect_reports_diff_and_trace() {
+    let failure = tester().given(Light::Red).when([Signal::Timer]).expect(&Light::Yellow);
+    assert_eq!(
+        failure.map_err(|failure| failure.to_string()),
+        Err("expected: state Yellow\n  actual: state Green\n  given Red\n  when [Timer]\n  \
+             history:\n    1. Red --Timer--> Green\n"
+            .to_string())
+    );
+}
+
+#[test]
+fn test_expect_reports_rejected_event() {
+    let scenario = tester().given(Light::Red).when([Signal::Emergency, Signal::Timer]);
+    let failure = scenario.expect(&Light::Green).err();
+    assert_eq!(failure.as_ref().map(|failure| failure.actual.as_str()), Some("Emergency rejected"));
+    assert_eq!(
+        failure.map(|failure| failure.trace).as_deref(),
+        Some(
+            "  given Red\n  when [Emergency, Timer]\n  history:\n    (no transitions)\n  \
+             rejected: Emergency in Red: No valid transition for this event from the current \
+             state\n"
+        )
+    );
+    // The events after the rejected one are not applied
+    assert_eq!(scenario.system().map(TransitionSystem::current_state), Some(&Light::Red));
+}
+
+#[test]
+fn test_expect_rejected() -> Result<(), ScenarioFailure> {
+    let tester = tester();
+    tester
+        .given(Light::Red)
+        .when([Signal::Timer, Signal::Timer, Signal::Emergency])
+        .expect_rejected(&Light::Yellow)?;
+
+    let applied = tester.given(Light::Red).when([Signal::Timer]).expect_rejected(&Light::Red);
+    assert_eq!(applied.map_err(|failure| failure.actual), Err("state Green".to_string()));
+    let early = tester
+        .given(Light::Red)
+        .when([Signal::Emergency, Signal::Timer])
+        .expect_rejected(&Light::Red);
+    assert_eq!(early.map_err(|failure| failure.actual), Err("Emergency rejected".to_string()));
+    let elsewhere =
+        tester.given(Light::Red).when([Signal::Emergency]).expect_rejected(&Light::Green);
+    assert_eq!(
+        elsewhere.map_err(|failure| failure.actual),
+        Err("Emergency rejected in state Red".to_string())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_factory_errors_are_reported() {
+    let tester = MachineTester::new(|initial: Light| {
+        let mut system = TransitionSystem::new(initial);
+        system.register_transition(TransitionBuilder::<Light, Signal>::new().build()?);
+        Ok(system)
+    });
+    let failure = tester.given(Light::Red).when([Signal::Timer]).expect(&Light::Green);
+    assert_eq!(
+        failure.map_err(|failure| failure.actual),
+        Err("system not built: Transition is missing its target state".to_string())
+    );
+}
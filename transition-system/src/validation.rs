@@ -0,0 +1,99 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+};
+
+use crate::{events::BookEvent, system::LibrarySystem};
+
+/// A structural problem found in a state machine definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The state can't be reached from the initial state
+    UnreachableState { state_idx: usize },
+    /// The state has no outgoing transitions and isn't declared final
+    DeadEndState { state_idx: usize },
+    /// A transition refers to a state index that doesn't exist
+    InvalidStateIndex { from_state_idx: usize, event: BookEvent, to_state_idx: usize },
+    /// A timeout fires an event that has no transition from its state
+    TimeoutWithoutTransition { state_idx: usize, event: BookEvent },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnreachableState { state_idx } => {
+                write!(f, "State {state_idx} is unreachable from the initial state")
+            }
+            Self::DeadEndState { state_idx } => {
+                write!(f, "State {state_idx} has no outgoing transitions and is not final")
+            }
+            Self::InvalidStateIndex { from_state_idx, event, to_state_idx } => write!(
+                f,
+                "Transition {from_state_idx} --{event:?}--> {to_state_idx} refers to a missing state"
+            ),
+            Self::TimeoutWithoutTransition { state_idx, event } => {
+                write!(f, "Timeout event {event:?} has no transition from state {state_idx}")
+            }
+        }
+    }
+}
+
+/// Check a system's definition for structural problems
+///
+/// The first state of the system is treated as its initial state.
+pub(crate) fn validate(system: &LibrarySystem) -> Vec<ValidationIssue> {
+    let state_count = system.get_states().len();
+    let mut issues = Vec::new();
+
+    let mut transitions: Vec<_> = system.get_all_transitions().iter().collect();
+    transitions.sort_by_key(|((from, event), to)| (*from, *to, format!("{event:?}")));
+
+    for ((from, event), to) in &transitions {
+        if *from >= state_count || **to >= state_count {
+            issues.push(ValidationIssue::InvalidStateIndex {
+                from_state_idx: *from,
+                event: event.clone(),
+                to_state_idx: **to,
+            });
+        }
+    }
+
+    // Breadth-first search from the initial state
+    let mut reachable = BTreeSet::from([0]);
+    let mut queue = VecDeque::from([0]);
+    while let Some(state_idx) = queue.pop_front() {
+        for ((from, _), to) in &transitions {
+            if *from == state_idx && **to < state_count && reachable.insert(**to) {
+                queue.push_back(**to);
+            }
+        }
+    }
+
+    for state_idx in 0..state_count {
+        if !reachable.contains(&state_idx) {
+            issues.push(ValidationIssue::UnreachableState { state_idx });
+        }
+        let has_outgoing = transitions.iter().any(|((from, _), _)| *from == state_idx);
+        if !has_outgoing && !system.is_final_state(state_idx) {
+            issues.push(ValidationIssue::DeadEndState { state_idx });
+        }
+    }
+
+    let mut timeouts: Vec<_> = system.get_timing_constraints().iter().collect();
+    timeouts.sort_by_key(|(state_idx, _)| **state_idx);
+    for (state_idx, constraint) in timeouts {
+        let key = (*state_idx, constraint.timeout_event.clone().transition_key());
+        if !system.get_all_transitions().contains_key(&key) {
+            issues.push(ValidationIssue::TimeoutWithoutTransition {
+                state_idx: *state_idx,
+                event: constraint.timeout_event.clone(),
+            });
+        }
+    }
+
+    issues
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
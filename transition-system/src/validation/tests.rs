@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use super::*;
+use crate::book_state::BookState;
+
+/// Create a system with a reservation cycle and a transition to `Lost`
+fn setup_test_system() -> LibrarySystem {
+    let mut system = LibrarySystem::new(BookState::Available, "test-book");
+    let reserved = system.add_state(BookState::Reserved("Test User".into()));
+    let lost = system.add_state(BookState::Lost);
+    system.add_transition(0, BookEvent::Reserve("Test User".into()), reserved);
+    system.add_transition(reserved, BookEvent::CancelReservation, 0);
+    system.add_transition(0, BookEvent::ReportLost, lost);
+    system
+}
+
+#[test]
+fn test_dead_end_and_final_states() {
+    let mut system = setup_test_system();
+    assert_eq!(system.validate(), vec![ValidationIssue::DeadEndState { state_idx: 2 }]);
+
+    system.mark_final_state(2);
+    assert!(system.validate().is_empty());
+}
+
+#[test]
+fn test_unreachable_state() {
+    let mut system = setup_test_system();
+    system.mark_final_state(2);
+    let repair = system.add_state(BookState::UnderRepair);
+    system.add_transition(repair, BookEvent::CompleteRepair, 0);
+
+    assert_eq!(system.validate(), vec![ValidationIssue::UnreachableState { state_idx: repair }]);
+}
+
+#[test]
+fn test_invalid_index_and_timeout_without_transition() {
+    let mut system = setup_test_system();
+    system.mark_final_state(2);
+    system.add_transition(1, BookEvent::CheckOut("Test User".into()), 7);
+    system.add_timing_constraint(2, Duration::from_mins(1), BookEvent::Found);
+
+    assert_eq!(
+        system.validate(),
+        vec![
+            ValidationIssue::InvalidStateIndex {
+                from_state_idx: 1,
+                event: BookEvent::CheckOut("Test User".into()),
+                to_state_idx: 7,
+            },
+            ValidationIssue::TimeoutWithoutTransition { state_idx: 2, event: BookEvent::Found },
+        ]
+    );
+}
@@ -1,46 +1,312 @@
 use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Write as _},
+    fs::{self, File},
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
     book_state::BookState,
     events::BookEvent,
-    system::{LibrarySystem, StateTransition},
+    persistence::SerializableInstant,
+    system::{LibraryError, LibrarySystem, StateTransition},
 };
 
+/// A state machine that [`StateVisualization`] can draw
+///
+/// Implemented by [`LibrarySystem`] and the generic
+/// [`TransitionSystem`](crate::generic::TransitionSystem); implement it for
+/// any other machine (a wrapper, a read-only projection, a test double) to get
+/// every diagram, timeline and statistic for free. Only the states,
+/// transitions, current state and history are required; the other methods
+/// refine the drawings and default to "not supported". States and events are
+/// labelled with their `Debug` output unless [`Self::state_label`] says
+/// otherwise.
+pub trait Visualizable {
+    /// Type of the states
+    type State: Clone + fmt::Debug + PartialEq;
+
+    /// Type of the events
+    type Event: fmt::Debug + PartialEq;
+
+    /// Identifier used to name generated graphs
+    fn system_id(&self) -> &str;
+
+    /// All states, in index order
+    fn states(&self) -> Cow<'_, [Self::State]>;
+
+    /// All transitions as (source index, event, target index), in any order
+    fn transitions(&self) -> Vec<(usize, &Self::Event, usize)>;
+
+    /// The state the machine is in
+    fn current_state(&self) -> &Self::State;
+
+    /// Transitions taken so far, oldest first
+    fn history(&self) -> Vec<HistoryStep<'_, Self::State, Self::Event>>;
+
+    /// Index of the current state in [`Self::states`]
+    fn current_state_idx(&self) -> usize {
+        self.state_idx(self.current_state()).unwrap_or_default()
+    }
+
+    /// Timeout of a state as (maximum duration, event fired), if it has one
+    fn timeout(&self, _state_idx: usize) -> Option<(Duration, &Self::Event)> {
+        None
+    }
+
+    /// Whether a state is expected to have no outgoing transitions
+    fn is_final_state(&self, _state_idx: usize) -> bool {
+        false
+    }
+
+    /// Category a state is grouped under in DOT graphs, if any
+    fn state_category(&self, _state_idx: usize) -> Option<&str> {
+        None
+    }
+
+    /// How long the machine has been in its current state
+    fn time_in_current_state(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Find the index of a state
+    fn state_idx(&self, state: &Self::State) -> Option<usize> {
+        self.states().iter().position(|s| s == state)
+    }
+
+    /// Get a plain label for a state, as used in diagrams
+    fn state_label(&self, state: &Self::State) -> String {
+        format!("{state:?}")
+    }
+}
+
+/// A transition taken by a [`Visualizable`] machine, as drawn from its history
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryStep<'a, S, E> {
+    /// The state before the transition
+    pub from: &'a S,
+    /// The state after the transition
+    pub to: &'a S,
+    /// The event that triggered the transition
+    pub event: &'a E,
+    /// When the transition occurred
+    pub timestamp: &'a SerializableInstant,
+}
+
+impl<'a> From<&'a StateTransition> for HistoryStep<'a, BookState, BookEvent> {
+    fn from(transition: &'a StateTransition) -> Self {
+        Self {
+            from: &transition.from,
+            to: &transition.to,
+            event: &transition.event,
+            timestamp: &transition.timestamp,
+        }
+    }
+}
+
+impl Visualizable for LibrarySystem {
+    type State = BookState;
+    type Event = BookEvent;
+
+    fn system_id(&self) -> &str {
+        self.system_id()
+    }
+
+    fn states(&self) -> Cow<'_, [BookState]> {
+        Cow::Borrowed(self.get_states())
+    }
+
+    fn transitions(&self) -> Vec<(usize, &BookEvent, usize)> {
+        self.get_all_transitions().iter().map(|((from, event), to)| (*from, event, *to)).collect()
+    }
+
+    fn current_state(&self) -> &BookState {
+        self.current_state()
+    }
+
+    fn history(&self) -> Vec<HistoryStep<'_, BookState, BookEvent>> {
+        self.get_history().iter().map(HistoryStep::from).collect()
+    }
+
+    fn current_state_idx(&self) -> usize {
+        self.get_current_state_idx()
+    }
+
+    fn timeout(&self, state_idx: usize) -> Option<(Duration, &BookEvent)> {
+        self.get_timing_constraints()
+            .get(&state_idx)
+            .map(|constraint| (constraint.max_duration, &constraint.timeout_event))
+    }
+
+    fn is_final_state(&self, state_idx: usize) -> bool {
+        self.is_final_state(state_idx)
+    }
+
+    fn state_category(&self, state_idx: usize) -> Option<&str> {
+        self.state_category(state_idx)
+    }
+
+    fn time_in_current_state(&self) -> Duration {
+        self.time_in_current_state()
+    }
+
+    fn state_idx(&self, state: &BookState) -> Option<usize> {
+        self.get_state_idx(state)
+    }
+
+    fn state_label(&self, state: &BookState) -> String {
+        StateVisualization::state_label(state)
+    }
+}
+
+/// A stretch of time the system spent in one state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineSegment<S = BookState> {
+    /// State the system was in
+    pub state: S,
+    /// Wall-clock time the state was entered
+    pub start: SystemTime,
+    /// How long the system stayed in the state
+    pub duration: Duration,
+}
+
+/// Visit count and dwell time aggregates of one state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateStats<S = BookState> {
+    /// The state the aggregates are for
+    pub state: S,
+    /// How many times the state was entered according to the history
+    pub visits: usize,
+    /// Total time spent in the state
+    pub total_time: Duration,
+    /// Mean time per visit
+    pub mean_time: Duration,
+    /// Median time per visit
+    pub median_time: Duration,
+    /// Longest single visit
+    pub max_time: Duration,
+}
+
+/// Structural counts and per-state statistics of a system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats<S = BookState> {
+    /// Number of states
+    pub state_count: usize,
+    /// Number of defined transitions
+    pub transition_count: usize,
+    /// Number of history entries
+    pub history_len: usize,
+    /// The current state
+    pub current_state: S,
+    /// Statistics of every visited state, in order of first visit
+    pub states: Vec<StateStats<S>>,
+}
+
+/// Colors, shapes and character set used by the generators
+///
+/// The default theme is the classic colorful look with emoji and box drawing
+/// characters; [`Self::plain`] suits corporate documents and terminals that
+/// only handle ASCII. Fields are public, so a theme can be adjusted with
+/// struct update syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisualizationTheme {
+    /// Fill color of states in DOT output
+    pub state_color: String,
+    /// Fill color of the current state in DOT and Mermaid output
+    pub current_color: String,
+    /// Color of the transitions taken so far in DOT and Mermaid output
+    pub highlight_color: String,
+    /// Color of the other transitions in DOT output
+    pub edge_color: String,
+    /// DOT shape of non-final states (final states are always `doublecircle`)
+    pub node_shape: String,
+    /// Restrict text output to ASCII: no emoji, box drawing or block characters
+    pub ascii_only: bool,
+    /// Add a legend explaining the node and edge styles to DOT output
+    pub show_legend: bool,
+}
+
+impl Default for VisualizationTheme {
+    fn default() -> Self {
+        Self {
+            state_color: String::from("lightblue"),
+            current_color: String::from("palegreen"),
+            highlight_color: String::from("red"),
+            edge_color: String::from("black"),
+            node_shape: String::from("circle"),
+            ascii_only: false,
+            show_legend: true,
+        }
+    }
+}
+
+impl VisualizationTheme {
+    /// Grayscale colors and ASCII-only text
+    #[must_use]
+    pub fn plain() -> Self {
+        Self {
+            state_color: String::from("white"),
+            current_color: String::from("lightgray"),
+            highlight_color: String::from("black"),
+            edge_color: String::from("gray"),
+            node_shape: String::from("box"),
+            ascii_only: true,
+            show_legend: true,
+        }
+    }
+
+    /// Replace the non-ASCII decorations of text output if the theme asks for it
+    fn decorate(&self, text: String) -> String {
+        if !self.ascii_only {
+            return text;
+        }
+        text.chars()
+            .map(|c| match c {
+                '┌' | '┐' | '└' | '┘' | '╔' | '╗' | '╚' | '╝' | '┼' => '+',
+                '─' => '-',
+                '═' => '=',
+                '│' | '║' => '|',
+                '◀' => '<',
+                '█' => '#',
+                '✔' => '*',
+                c => c,
+            })
+            .collect()
+    }
+}
+
 /// Visualization tools for state machines
 #[derive(Debug)]
 pub struct StateVisualization;
 
 impl StateVisualization {
     /// Generate a textual representation of the state machine
-    pub fn print_state_machine(system: &LibrarySystem) {
+    pub fn print_state_machine(system: &impl Visualizable) {
         println!("=== State Machine Structure ===");
         println!("Current state: {:?}", system.current_state());
 
-        // Get all transitions from the system
-        let transitions = system.get_all_transitions();
-
         // Group transitions by source state for better readability
-        let mut transitions_by_source: HashMap<usize, Vec<(BookEvent, usize)>> = HashMap::new();
+        let mut transitions_by_source: HashMap<usize, Vec<_>> = HashMap::new();
 
-        for ((from, event), to) in transitions {
-            transitions_by_source.entry(*from).or_default().push((event.clone(), *to));
+        for (from, event, to) in system.transitions() {
+            transitions_by_source.entry(from).or_default().push((event, to));
         }
 
         // Print all states and their transitions
-        for (state_idx, state) in system.get_states().iter().enumerate() {
+        let states = system.states();
+        for (state_idx, state) in states.iter().enumerate() {
             println!("\nState {state_idx}: {state:?}");
 
             if let Some(transitions) = transitions_by_source.get(&state_idx) {
                 for (event, to_state_idx) in transitions {
-                    println!(
-                        "  --({event:?})--> State {to_state_idx}: {:?}",
-                        system.get_states().get(*to_state_idx).unwrap_or(&BookState::Available)
-                    );
+                    match states.get(*to_state_idx) {
+                        Some(to) => println!("  --({event:?})--> State {to_state_idx}: {to:?}"),
+                        None => println!("  --({event:?})--> State {to_state_idx}"),
+                    }
                 }
             } else {
                 println!("  (No outgoing transitions)");
@@ -48,86 +314,688 @@ impl StateVisualization {
         }
 
         println!("\n=== Timing Constraints ===");
-        for (state_idx, constraint) in system.get_timing_constraints() {
-            println!(
-                "State {}: {:?} - Timeout after {:?} seconds, triggers {:?}",
-                state_idx,
-                system.get_states().get(*state_idx).unwrap_or(&BookState::Available),
-                constraint.max_duration.as_secs(),
-                constraint.timeout_event
-            );
+        for (state_idx, state) in states.iter().enumerate() {
+            if let Some((max_duration, timeout_event)) = system.timeout(state_idx) {
+                println!(
+                    "State {state_idx}: {state:?} - Timeout after {:?} seconds, triggers {timeout_event:?}",
+                    max_duration.as_secs(),
+                );
+            }
         }
     }
 
     /// Generate a DOT graph representation of the state machine
+    ///
+    /// The graph is named after the system and final states are drawn as
+    /// double circles, so [`parse_dot`](crate::dot_import::parse_dot) can read
+    /// the definition back.
     #[must_use]
-    pub fn generate_dot(system: &LibrarySystem, highlight_path: bool) -> String {
-        let mut dot = String::from("digraph state_machine {\n");
-        dot.push_str("  rankdir=LR;\n");
-        dot.push_str("  node [shape=circle, style=filled, fillcolor=lightblue];\n");
+    pub fn generate_dot(system: &impl Visualizable, highlight_path: bool) -> String {
+        Self::generate_dot_with_theme(system, highlight_path, &VisualizationTheme::default())
+    }
 
-        // Add states
-        for (idx, state) in system.get_states().iter().enumerate() {
-            // Format the state label, properly escaping quotes
-            let state_label = match state {
-                BookState::Available => "Available".to_string(),
-                BookState::Reserved(person) => format!("Reserved({person})"),
-                BookState::CheckedOut(person) => format!("CheckedOut({person})"),
-                BookState::InTransit => "InTransit".to_string(),
-                BookState::UnderRepair => "UnderRepair".to_string(),
-                BookState::Lost => "Lost".to_string(),
+    /// Generate a DOT graph using the colors and shapes of a theme
+    #[must_use]
+    pub fn generate_dot_with_theme(
+        system: &impl Visualizable,
+        highlight_path: bool,
+        theme: &VisualizationTheme,
+    ) -> String {
+        let highlighted =
+            if highlight_path { Self::taken_transitions(system) } else { HashSet::new() };
+        Self::dot_graph(system, system.current_state_idx(), &highlighted, None, theme)
+    }
+
+    /// Generate one DOT graph per step of the history, for replaying it
+    ///
+    /// The first frame shows the state before the first recorded transition;
+    /// every following frame shows the state after one transition, with that
+    /// transition highlighted and the step captioned above the graph. Edges
+    /// are sorted so Graphviz lays every frame out the same way.
+    #[must_use]
+    pub fn generate_dot_frames(system: &impl Visualizable) -> Vec<String> {
+        Self::generate_dot_frames_with_theme(system, &VisualizationTheme::default())
+    }
+
+    /// Generate the history playback frames using the colors and shapes of a theme
+    #[must_use]
+    pub fn generate_dot_frames_with_theme(
+        system: &impl Visualizable,
+        theme: &VisualizationTheme,
+    ) -> Vec<String> {
+        let history = system.history();
+        let Some(first) = history.first() else {
+            return vec![Self::generate_dot_with_theme(system, false, theme)];
+        };
+        let steps = history.len();
+        let state_idx = |state| system.state_idx(state).unwrap_or_default();
+
+        let start = Self::dot_graph(
+            system,
+            state_idx(first.from),
+            &HashSet::new(),
+            Some(&format!("Step 0/{steps}: {}", system.state_label(first.from))),
+            theme,
+        );
+        let steps = history.iter().enumerate().map(|(idx, transition)| {
+            let (from, to) = (state_idx(transition.from), state_idx(transition.to));
+            let caption = format!(
+                "Step {}/{steps}: {:?} -> {}",
+                idx.saturating_add(1),
+                transition.event,
+                system.state_label(transition.to)
+            );
+            Self::dot_graph(system, to, &HashSet::from([(from, to)]), Some(&caption), theme)
+        });
+        std::iter::once(start).chain(steps).collect()
+    }
+
+    /// Write history playback frames to numbered `frame-NNN.dot` files
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory can't be created or a file can't be written
+    pub fn save_dot_frames(
+        frames: &[String],
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>, std::io::Error> {
+        fs::create_dir_all(dir.as_ref())?;
+        frames
+            .iter()
+            .enumerate()
+            .map(|(idx, frame)| {
+                let path = dir.as_ref().join(format!("frame-{idx:03}.dot"));
+                fs::write(&path, frame)?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Assemble history playback frames into an animated GIF
+    ///
+    /// Renders every frame with Graphviz (`dot`) and joins them with
+    /// `ImageMagick` (`convert`), so both need to be installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the frames can't be
+    /// written or either tool is missing or fails
+    pub fn render_gif(
+        frames: &[String],
+        path: impl AsRef<Path>,
+        frame_delay: Duration,
+    ) -> Result<(), LibraryError> {
+        let dir = std::env::temp_dir().join(format!("dot-frames-{}", std::process::id()));
+        let result = (|| {
+            let run = |command: &mut Command| {
+                let status = command.status().map_err(|e| {
+                    LibraryError::PersistenceError(format!(
+                        "Failed to run {}: {e}",
+                        command.get_program().display()
+                    ))
+                })?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(LibraryError::PersistenceError(format!(
+                        "{} failed with {status}",
+                        command.get_program().display()
+                    )))
+                }
             };
 
-            // Current state is highlighted
-            if idx == system.get_current_state_idx() {
-                dot.push_str(&format!(
-                    "  s{idx} [label=\"{state_label}\", fillcolor=palegreen, peripheries=2];\n",
-                ));
-            } else {
-                dot.push_str(&format!("  s{idx} [label=\"{state_label}\"];\n"));
+            let mut images = Vec::new();
+            for dot_path in Self::save_dot_frames(frames, &dir)? {
+                let image = dot_path.with_extension("png");
+                run(Command::new("dot").arg("-Tpng").arg(&dot_path).arg("-o").arg(&image))?;
+                images.push(image);
             }
+            // ImageMagick counts the delay in hundredths of a second
+            let delay = (frame_delay.as_millis() / 10).to_string();
+            run(Command::new("convert")
+                .args(["-delay", &delay, "-loop", "0"])
+                .args(&images)
+                .arg(path.as_ref()))
+        })();
+        fs::remove_dir_all(&dir).ok();
+        result
+    }
+
+    /// Build a DOT graph with the given current state and highlighted edges
+    fn dot_graph(
+        system: &impl Visualizable,
+        current_idx: usize,
+        highlighted: &HashSet<(usize, usize)>,
+        caption: Option<&str>,
+        theme: &VisualizationTheme,
+    ) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", system.system_id().replace('"', "\\\""));
+        dot.push_str("  rankdir=LR;\n");
+        if let Some(caption) = caption {
+            let _ = writeln!(dot, "  labelloc=t;\n  label=\"{}\";", caption.replace('"', "\\\""));
         }
+        let _ = writeln!(
+            dot,
+            "  node [shape={}, style=filled, fillcolor=\"{}\"];",
+            theme.node_shape, theme.state_color
+        );
 
-        // Add transitions
-        let transitions = system.get_all_transitions();
+        // Add states
+        for (idx, state) in system.states().iter().enumerate() {
+            let state_label = system.state_label(state).replace('"', "\\\"");
 
-        // If highlighting, determine which transitions to highlight
-        let mut highlighted_transitions = HashSet::new();
-        if highlight_path && !system.get_history().is_empty() {
-            // Get transitions from history
-            #[allow(clippy::arithmetic_side_effects)]
-            for i in 0..system.get_history().len() - 1 {
-                if let Some(current) = system.get_history().get(i) {
-                    // Find the state indices
-                    let from_idx = system.get_state_idx(&current.from);
-                    let to_idx = system.get_state_idx(&current.to);
+            // Final states are drawn as double circles
+            let shape = if system.is_final_state(idx) { ", shape=doublecircle" } else { "" };
 
-                    if let (Some(from), Some(to)) = (from_idx, to_idx) {
-                        highlighted_transitions.insert((from, to));
-                    }
-                }
+            // Current state is highlighted
+            if idx == current_idx {
+                let _ = writeln!(
+                    dot,
+                    "  s{idx} [label=\"{state_label}\"{shape}, fillcolor=\"{}\", peripheries=2];",
+                    theme.current_color
+                );
+            } else {
+                let _ = writeln!(dot, "  s{idx} [label=\"{state_label}\"{shape}];");
             }
         }
 
-        // Add all transitions to the graph
-        for ((from, event), to) in transitions {
-            let style = if highlight_path && highlighted_transitions.contains(&(*from, *to)) {
-                "color=red, penwidth=2.0"
+        // Add all transitions to the graph, in a stable order
+        for (from, event, to) in Self::sorted_transitions(system) {
+            let mut style = if highlighted.contains(&(from, to)) {
+                format!("color=\"{}\", penwidth=2.0", theme.highlight_color)
             } else {
-                "color=black"
+                format!("color=\"{}\"", theme.edge_color)
             };
 
+            // Transitions fired by a timing constraint are dashed and show the timeout
+            if let Some((max_duration, timeout_event)) = system.timeout(from)
+                && timeout_event == event
+            {
+                let _ = write!(
+                    style,
+                    ", style=dashed, taillabel=\"after {}\"",
+                    Self::timeout_label(max_duration)
+                );
+            }
+
             // Format the event label, escaping quotes
-            #[allow(clippy::single_char_pattern)]
-            let event_label = format!("{event:?}").replace("\"", "\\\"");
+            let event_label = format!("{event:?}").replace('"', "\\\"");
 
-            dot.push_str(&format!("  s{from} -> s{to} [label=\"{event_label}\", {style}];\n"));
+            let _ = writeln!(dot, "  s{from} -> s{to} [label=\"{event_label}\", {style}];");
+        }
+
+        Self::dot_clusters(&mut dot, system);
+
+        if theme.show_legend {
+            Self::dot_legend(&mut dot, theme);
         }
 
         dot.push_str("}\n");
         dot
     }
 
+    /// Get the transitions of a system in a stable order, by source, target and event
+    fn sorted_transitions<V: Visualizable>(system: &V) -> Vec<(usize, &V::Event, usize)> {
+        let mut transitions = system.transitions();
+        transitions.sort_by_key(|(from, event, to)| (*from, *to, format!("{event:?}")));
+        transitions
+    }
+
+    /// Append one cluster per state category, listing the states tagged with it
+    ///
+    /// The states are declared before, so the clusters only refer to them and
+    /// the first state of the graph stays the initial state.
+    fn dot_clusters(dot: &mut String, system: &impl Visualizable) {
+        let mut clusters: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for idx in 0..system.states().len() {
+            if let Some(category) = system.state_category(idx) {
+                clusters.entry(category).or_default().push(idx);
+            }
+        }
+        for (category, states) in clusters {
+            let category = category.replace('"', "\\\"");
+            let _ = writeln!(dot, "  subgraph \"cluster_{category}\" {{");
+            let _ = writeln!(dot, "    label=\"{category}\";");
+            for idx in states {
+                let _ = writeln!(dot, "    s{idx};");
+            }
+            dot.push_str("  }\n");
+        }
+    }
+
+    /// Append a cluster explaining the node and edge styles of a DOT graph
+    fn dot_legend(dot: &mut String, theme: &VisualizationTheme) {
+        let _ = writeln!(
+            dot,
+            "  subgraph cluster_legend {{
+    label=\"Legend\";
+    fontsize=10;
+    legend_state [label=\"State\"];
+    legend_current [label=\"Current\", fillcolor=\"{current}\", peripheries=2];
+    legend_final [label=\"Final\", shape=doublecircle];
+    legend_state -> legend_current [label=\"Event\", color=\"{edge}\"];
+    legend_current -> legend_final [label=\"Taken\", color=\"{highlight}\", penwidth=2.0];
+    legend_final -> legend_state [label=\"Timeout\", color=\"{edge}\", style=dashed, taillabel=\"after limit\"];
+  }}",
+            current = theme.current_color,
+            edge = theme.edge_color,
+            highlight = theme.highlight_color
+        );
+    }
+
+    /// Format a timeout exactly, with every non-zero unit, e.g. `3d 12h` or `90s`
+    fn timeout_label(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        let parts: Vec<_> = [
+            (secs / 86_400, "d"),
+            (secs / 3_600 % 24, "h"),
+            (secs / 60 % 60, "m"),
+            (secs % 60, "s"),
+        ]
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect();
+        if parts.is_empty() { String::from("0s") } else { parts.join(" ") }
+    }
+
+    /// Print a box-and-arrow text diagram of the state machine
+    pub fn print_ascii(system: &impl Visualizable) {
+        println!("{}", Self::ascii_diagram(system));
+    }
+
+    /// Lay out the box-and-arrow text diagram using the character set of a theme
+    #[must_use]
+    pub fn ascii_diagram_with_theme(
+        system: &impl Visualizable,
+        theme: &VisualizationTheme,
+    ) -> String {
+        theme.decorate(Self::ascii_diagram(system))
+    }
+
+    /// Lay out the state machine as a box-and-arrow text diagram
+    ///
+    /// States are drawn as boxes stacked top to bottom, the current one with a
+    /// double border. Every transition gets its own lane to the right of the
+    /// boxes, running from a row of the source box to an arrowhead at a row of
+    /// the target box, and is labelled with its event at the source end.
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn ascii_diagram(system: &impl Visualizable) -> String {
+        let states = system.states();
+        let transitions: Vec<_> = Self::sorted_transitions(system)
+            .into_iter()
+            .filter(|(from, _, to)| *from < states.len() && *to < states.len())
+            .collect();
+
+        // Give every edge endpoint its own row inside its box
+        let mut endpoints = vec![0_usize; states.len()];
+        let mut edges = Vec::with_capacity(transitions.len());
+        for (from, event, to) in &transitions {
+            let mut next_row = |state_idx: usize| {
+                endpoints.get_mut(state_idx).map_or(0, |count| {
+                    *count += 1;
+                    *count - 1
+                })
+            };
+            let source_row = next_row(*from);
+            let target_row = next_row(*to);
+            edges.push((*from, source_row, *to, target_row, format!("{event:?}")));
+        }
+
+        // Top row of every box
+        let mut box_tops = Vec::with_capacity(states.len());
+        let mut height = 0;
+        for count in &endpoints {
+            box_tops.push(height);
+            height += (*count).max(1) + 3;
+        }
+
+        let labels: Vec<_> = states.iter().map(|state| system.state_label(state)).collect();
+        let box_width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0) + 4;
+        let lanes_end = box_width + 2 + 2 * edges.len();
+        let label_width = edges.iter().map(|edge| edge.4.chars().count()).max().unwrap_or(0);
+        let mut grid = vec![vec![' '; lanes_end + 1 + label_width]; height];
+
+        for (idx, label) in labels.iter().enumerate() {
+            let top = box_tops.get(idx).copied().unwrap_or(0);
+            let bottom = top + endpoints.get(idx).copied().unwrap_or(0).max(1) + 1;
+            let current = idx == system.current_state_idx();
+            let (h, v, corners) = if current {
+                ('═', '║', ['╔', '╗', '╚', '╝'])
+            } else {
+                ('─', '│', ['┌', '┐', '└', '┘'])
+            };
+            for x in 1..box_width - 1 {
+                Self::put(&mut grid, x, top, h);
+                Self::put(&mut grid, x, bottom, h);
+            }
+            for y in top + 1..bottom {
+                Self::put(&mut grid, 0, y, v);
+                Self::put(&mut grid, box_width - 1, y, v);
+            }
+            let [top_left, top_right, bottom_left, bottom_right] = corners;
+            Self::put(&mut grid, 0, top, top_left);
+            Self::put(&mut grid, box_width - 1, top, top_right);
+            Self::put(&mut grid, 0, bottom, bottom_left);
+            Self::put(&mut grid, box_width - 1, bottom, bottom_right);
+            for (offset, c) in label.chars().enumerate() {
+                Self::put(&mut grid, 2 + offset, top + 1, c);
+            }
+        }
+
+        for (lane, (from, source_row, to, target_row, event)) in edges.iter().enumerate() {
+            let lane_x = box_width + 2 + 2 * lane;
+            let source_y = box_tops.get(*from).copied().unwrap_or(0) + 1 + source_row;
+            let target_y = box_tops.get(*to).copied().unwrap_or(0) + 1 + target_row;
+            let (source_corner, target_corner) =
+                if target_y > source_y { ('┐', '┘') } else { ('┘', '┐') };
+
+            for x in box_width..lane_x {
+                Self::put(&mut grid, x, source_y, '─');
+                Self::put(&mut grid, x, target_y, '─');
+            }
+            Self::put(&mut grid, box_width, target_y, '◀');
+            Self::put(&mut grid, lane_x, source_y, source_corner);
+            Self::put(&mut grid, lane_x, target_y, target_corner);
+            for y in source_y.min(target_y) + 1..source_y.max(target_y) {
+                Self::put(&mut grid, lane_x, y, '│');
+            }
+            for (offset, c) in event.chars().enumerate() {
+                Self::put(&mut grid, lanes_end + 1 + offset, source_y, c);
+            }
+        }
+
+        grid.iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Draw a character into a text grid, turning crossing lines into `┼`
+    fn put(grid: &mut [Vec<char>], x: usize, y: usize, c: char) {
+        let Some(cell) = grid.get_mut(y).and_then(|row| row.get_mut(x)) else {
+            return;
+        };
+        *cell = match (*cell, c) {
+            ('│', '─') | ('─', '│') => '┼',
+            _ => c,
+        };
+    }
+
+    /// Generate a Mermaid `stateDiagram-v2` representation of the state machine
+    ///
+    /// The result can be pasted into a ```` ```mermaid ```` block and renders
+    /// directly in GitHub and GitLab markdown. The current state is
+    /// highlighted, and with `highlight_path` so are the transitions taken so
+    /// far according to the history.
+    #[must_use]
+    pub fn generate_mermaid(system: &impl Visualizable, highlight_path: bool) -> String {
+        Self::generate_mermaid_with_theme(system, highlight_path, &VisualizationTheme::default())
+    }
+
+    /// Generate a Mermaid state diagram using the colors and character set of a theme
+    #[must_use]
+    pub fn generate_mermaid_with_theme(
+        system: &impl Visualizable,
+        highlight_path: bool,
+        theme: &VisualizationTheme,
+    ) -> String {
+        let mut mermaid = String::from("stateDiagram-v2\n");
+        mermaid.push_str("    direction LR\n");
+        if !system.states().is_empty() {
+            mermaid.push_str("    [*] --> s0\n");
+        }
+
+        for (idx, state) in system.states().iter().enumerate() {
+            let _ = writeln!(
+                mermaid,
+                "    s{idx}: {}",
+                Self::mermaid_escape(&system.state_label(state))
+            );
+        }
+
+        let highlighted_transitions =
+            if highlight_path { Self::taken_transitions(system) } else { HashSet::new() };
+        for (from, event, to) in Self::sorted_transitions(system) {
+            let marker = if highlighted_transitions.contains(&(from, to)) {
+                theme.decorate(String::from(" ✔"))
+            } else {
+                String::new()
+            };
+            let _ = writeln!(
+                mermaid,
+                "    s{from} --> s{to}: {}{marker}",
+                Self::mermaid_escape(&format!("{event:?}"))
+            );
+        }
+
+        let _ =
+            writeln!(mermaid, "    classDef current fill:{},stroke-width:3px", theme.current_color);
+        let _ = writeln!(mermaid, "    class s{} current", system.current_state_idx());
+        if highlight_path {
+            let mut visited: Vec<_> = highlighted_transitions
+                .iter()
+                .flat_map(|(from, to)| [*from, *to])
+                .filter(|idx| *idx != system.current_state_idx())
+                .collect();
+            visited.sort_unstable();
+            visited.dedup();
+            if !visited.is_empty() {
+                let _ = writeln!(
+                    mermaid,
+                    "    classDef visited stroke:{},stroke-width:2px",
+                    theme.highlight_color
+                );
+                let ids: Vec<_> = visited.iter().map(|idx| format!("s{idx}")).collect();
+                let _ = writeln!(mermaid, "    class {} visited", ids.join(","));
+            }
+        }
+
+        mermaid
+    }
+
+    /// Get a plain label for a book state, as used in diagrams
+    fn state_label(state: &BookState) -> String {
+        match state {
+            BookState::Available => "Available".to_string(),
+            BookState::Reserved(person) => format!("Reserved({person})"),
+            BookState::CheckedOut(person) => format!("CheckedOut({person})"),
+            BookState::InTransit => "InTransit".to_string(),
+            BookState::UnderRepair => "UnderRepair".to_string(),
+            BookState::Lost => "Lost".to_string(),
+            BookState::Damaged(reason) => format!("Damaged({reason})"),
+            BookState::OnHoldShelf(person) => format!("OnHoldShelf({person})"),
+            BookState::Withdrawn => "Withdrawn".to_string(),
+        }
+    }
+
+    /// Escape characters Mermaid would interpret in a label
+    fn mermaid_escape(label: &str) -> String {
+        label
+            .chars()
+            .map(|c| match c {
+                '"' => "#quot;".to_string(),
+                ':' => "#58;".to_string(),
+                ';' => "#59;".to_string(),
+                c => c.to_string(),
+            })
+            .collect()
+    }
+
+    /// Collect the (from, to) state index pairs of every transition in the history
+    fn taken_transitions(system: &impl Visualizable) -> HashSet<(usize, usize)> {
+        system
+            .history()
+            .iter()
+            .filter_map(|transition| {
+                Some((system.state_idx(transition.from)?, system.state_idx(transition.to)?))
+            })
+            .collect()
+    }
+
+    /// Generate a W3C SCXML document describing the state machine
+    ///
+    /// The first state is the initial one. State IDs and event names are
+    /// derived from their labels, with a patron becoming a sub-token (e.g.
+    /// `Reserved.Alice` and `Reserve.Alice`). Timing constraints become a
+    /// delayed `<send>` on entry that is cancelled on exit, and final states
+    /// without outgoing transitions become `<final>` elements. The naming
+    /// follows the library's states and events, so only machines over
+    /// [`BookState`] and [`BookEvent`] can be exported.
+    #[must_use]
+    pub fn generate_scxml(
+        system: &impl Visualizable<State = BookState, Event = BookEvent>,
+    ) -> String {
+        let mut ids: Vec<String> = Vec::new();
+        for (idx, state) in system.states().iter().enumerate() {
+            let id = match state {
+                BookState::Reserved(person) => format!("Reserved.{}", Self::scxml_token(person)),
+                BookState::CheckedOut(person) => {
+                    format!("CheckedOut.{}", Self::scxml_token(person))
+                }
+                BookState::Damaged(reason) => format!("Damaged.{}", Self::scxml_token(reason)),
+                BookState::OnHoldShelf(person) => {
+                    format!("OnHoldShelf.{}", Self::scxml_token(person))
+                }
+                _ => Self::state_label(state),
+            };
+            // Patron names differing only in special characters could clash
+            let id = if ids.contains(&id) { format!("{id}_{idx}") } else { id };
+            ids.push(id);
+        }
+        let id = |idx: &usize| ids.get(*idx).map_or("", String::as_str);
+
+        let mut scxml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = write!(
+            scxml,
+            "<scxml xmlns=\"http://www.w3.org/2005/07/scxml\" version=\"1.0\" name=\"{}\"",
+            Self::xml_escape(system.system_id())
+        );
+        if !ids.is_empty() {
+            let _ = write!(scxml, " initial=\"{}\"", id(&0));
+        }
+        scxml.push_str(">\n");
+
+        let transitions = Self::sorted_transitions(system);
+
+        for idx in 0..ids.len() {
+            let outgoing: Vec<_> = transitions.iter().filter(|(from, _, _)| *from == idx).collect();
+            if outgoing.is_empty() && system.is_final_state(idx) {
+                let _ = writeln!(scxml, "  <final id=\"{}\"/>", id(&idx));
+                continue;
+            }
+
+            let _ = writeln!(scxml, "  <state id=\"{}\">", id(&idx));
+            if let Some((max_duration, timeout_event)) = system.timeout(idx) {
+                let timer = format!("timeout-{}", id(&idx));
+                let _ = writeln!(
+                    scxml,
+                    "    <onentry>\n      <send id=\"{timer}\" event=\"{}\" delay=\"{}s\"/>\n    </onentry>",
+                    Self::scxml_event(timeout_event),
+                    max_duration.as_secs()
+                );
+                let _ = writeln!(
+                    scxml,
+                    "    <onexit>\n      <cancel sendid=\"{timer}\"/>\n    </onexit>"
+                );
+            }
+            for (_, event, to) in outgoing {
+                let _ = writeln!(
+                    scxml,
+                    "    <transition event=\"{}\" target=\"{}\"/>",
+                    Self::scxml_event(event),
+                    id(to)
+                );
+            }
+            scxml.push_str("  </state>\n");
+        }
+
+        scxml.push_str("</scxml>\n");
+        scxml
+    }
+
+    /// Get the SCXML event name of an event
+    fn scxml_event(event: &BookEvent) -> String {
+        match event {
+            BookEvent::Reserve(person) => format!("Reserve.{}", Self::scxml_token(person)),
+            BookEvent::CheckOut(person) => format!("CheckOut.{}", Self::scxml_token(person)),
+            BookEvent::Custom(name, _) => format!("Custom.{}", Self::scxml_token(name)),
+            BookEvent::ReportDamage(reason) => {
+                format!("ReportDamage.{}", Self::scxml_token(reason))
+            }
+            BookEvent::HoldForPickup(person) => {
+                format!("HoldForPickup.{}", Self::scxml_token(person))
+            }
+            _ => format!("{event:?}"),
+        }
+    }
+
+    /// Turn free text into a token valid in both XML IDs and SCXML event names
+    fn scxml_token(text: &str) -> String {
+        let token: String = text
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        if token.is_empty() { String::from("_") } else { token }
+    }
+
+    /// Escape characters XML would interpret in an attribute value
+    fn xml_escape(text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                '&' => "&amp;".to_string(),
+                '<' => "&lt;".to_string(),
+                '>' => "&gt;".to_string(),
+                '"' => "&quot;".to_string(),
+                '\'' => "&apos;".to_string(),
+                c => c.to_string(),
+            })
+            .collect()
+    }
+
+    /// Lay out the DOT graph of the state machine and render it as SVG
+    ///
+    /// Uses a built-in layout engine, so Graphviz doesn't need to be installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the graph can't be laid out
+    #[cfg(feature = "svg")]
+    pub fn generate_svg(
+        system: &impl Visualizable,
+        highlight_path: bool,
+    ) -> Result<String, LibraryError> {
+        // The built-in layout engine can't place clusters such as the legend
+        let theme = VisualizationTheme { show_legend: false, ..VisualizationTheme::default() };
+        let dot = Self::generate_dot_with_theme(system, highlight_path, &theme);
+        let graph = layout::gv::DotParser::new(&dot)
+            .process()
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to lay out graph: {e}")))?;
+
+        let mut builder = layout::gv::GraphBuilder::new();
+        builder.visit_graph(&graph);
+        let mut svg = layout::backends::svg::SVGWriter::new();
+        builder.get().do_it(false, false, false, &mut svg);
+        Ok(svg.finalize())
+    }
+
+    /// Render the state machine as an SVG image file, highlighting the path taken
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::PersistenceError` if the graph can't be laid
+    /// out or the file can't be written
+    #[cfg(feature = "svg")]
+    pub fn render_svg(
+        system: &impl Visualizable,
+        path: impl AsRef<Path>,
+    ) -> Result<(), LibraryError> {
+        let svg = Self::generate_svg(system, true)?;
+        std::fs::write(path, svg)
+            .map_err(|e| LibraryError::PersistenceError(format!("Failed to write SVG: {e}")))
+    }
+
     /// Save the DOT representation to a file
     ///
     /// # Errors
@@ -141,8 +1009,16 @@ impl StateVisualization {
     }
 
     /// Generate a visualization of the state machine history
-    #[allow(clippy::arithmetic_side_effects)]
     pub fn visualize_history(transitions: &[StateTransition]) {
+        Self::visualize_history_with_theme(transitions, &VisualizationTheme::default());
+    }
+
+    /// Print the transition history, with emoji unless the theme is ASCII-only
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn visualize_history_with_theme(
+        transitions: &[StateTransition],
+        theme: &VisualizationTheme,
+    ) {
         println!("=== State Transition History ===");
 
         if transitions.is_empty() {
@@ -151,18 +1027,23 @@ impl StateVisualization {
         }
 
         for (i, transition) in transitions.iter().enumerate() {
+            let actor =
+                transition.actor.as_ref().map_or_else(String::new, |actor| format!(" by {actor}"));
             println!(
-                "{}: {} --({:?})--> {}",
+                "{}: {} --({:?})--> {}{actor}",
                 i + 1,
-                Self::format_state(&transition.from),
+                Self::format_state(&transition.from, theme),
                 transition.event,
-                Self::format_state(&transition.to)
+                Self::format_state(&transition.to, theme)
             );
         }
     }
 
-    /// Format a state for display
-    fn format_state(state: &BookState) -> String {
+    /// Format a state for display, with an emoji unless the theme is ASCII-only
+    fn format_state(state: &BookState, theme: &VisualizationTheme) -> String {
+        if theme.ascii_only {
+            return Self::state_label(state);
+        }
         match state {
             BookState::Available => "📚 Available".to_string(),
             BookState::Reserved(person) => format!("🔖 Reserved({person})"),
@@ -170,51 +1051,276 @@ impl StateVisualization {
             BookState::InTransit => "🚚 InTransit".to_string(),
             BookState::UnderRepair => "🔧 UnderRepair".to_string(),
             BookState::Lost => "❓ Lost".to_string(),
+            BookState::Damaged(reason) => format!("🩹 Damaged({reason})"),
+            BookState::OnHoldShelf(person) => format!("📌 OnHoldShelf({person})"),
+            BookState::Withdrawn => "🚫 Withdrawn".to_string(),
         }
     }
 
     /// Generate a markdown table of the history
     #[must_use]
-    #[allow(clippy::arithmetic_side_effects)]
     pub fn history_table(transitions: &[StateTransition]) -> String {
+        Self::history_table_with_theme(transitions, &VisualizationTheme::default())
+    }
+
+    /// Generate a markdown table of the history, with emoji unless the theme is ASCII-only
+    #[must_use]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn history_table_with_theme(
+        transitions: &[StateTransition],
+        theme: &VisualizationTheme,
+    ) -> String {
         if transitions.is_empty() {
             return "No transitions recorded yet.".to_string();
         }
 
-        let mut table = String::from("| # | From | Event | To |\n");
-        table.push_str("|---|------|-------|----|\n");
+        let mut table = String::from("| # | From | Event | To | Actor | Note |\n");
+        table.push_str("|---|------|-------|----|-------|------|\n");
 
         for (i, transition) in transitions.iter().enumerate() {
-            table.push_str(&format!(
-                "| {} | {} | {:?} | {} |\n",
+            let _ = writeln!(
+                table,
+                "| {} | {} | {:?} | {} | {} | {} |",
                 i + 1,
-                Self::format_state(&transition.from),
+                Self::format_state(&transition.from, theme),
                 transition.event,
-                Self::format_state(&transition.to)
-            ));
+                Self::format_state(&transition.to, theme),
+                transition.actor.as_deref().unwrap_or("-"),
+                transition.note.as_deref().unwrap_or("-")
+            );
         }
 
         table
     }
 
-    /// Print a summary of available state machine statistics
-    #[allow(clippy::arithmetic_side_effects)]
-    pub fn print_stats(system: &LibrarySystem) {
-        println!("=== State Machine Statistics ===");
-        println!("Total states: {}", system.get_states().len());
-        println!("Total transitions defined: {}", system.get_all_transitions().len());
-        println!("Current state: {:?}", system.current_state());
-        println!("History entries: {}", system.get_history().len());
+    /// Split the history into the stretches of time spent in each state
+    ///
+    /// Each transition in the history starts a segment that lasts until the
+    /// next one; the last segment is the current state up to now. Time spent
+    /// before the first recorded transition is unknown and left out.
+    #[must_use]
+    pub fn timeline<V: Visualizable>(system: &V) -> Vec<TimelineSegment<V::State>> {
+        let history = system.history();
+        history
+            .iter()
+            .enumerate()
+            .map(|(idx, transition)| {
+                let duration = history.get(idx.saturating_add(1)).map_or_else(
+                    || system.time_in_current_state(),
+                    |next| {
+                        next.timestamp
+                            .inner()
+                            .saturating_duration_since(*transition.timestamp.inner())
+                    },
+                );
+                TimelineSegment {
+                    state: transition.to.clone(),
+                    start: transition.timestamp.wall_time(),
+                    duration,
+                }
+            })
+            .collect()
+    }
+
+    /// Print the time spent in each state as a text timeline
+    pub fn print_timeline(system: &impl Visualizable) {
+        println!("=== State Timeline ===");
+        println!("{}", Self::timeline_text(system));
+    }
+
+    /// Render the time spent in each state as text bars on a shared time axis
+    #[must_use]
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn timeline_text(system: &impl Visualizable) -> String {
+        /// Number of characters the whole timeline spans
+        const WIDTH: usize = 40;
+
+        let segments = Self::timeline(system);
+        let Some(first) = segments.first() else {
+            return "No transitions recorded yet.".to_string();
+        };
+        let origin = first.start;
+        let offset = |time: SystemTime| time.duration_since(origin).unwrap_or_default();
+        let total = segments
+            .iter()
+            .map(|segment| offset(segment.start) + segment.duration)
+            .max()
+            .unwrap_or_default()
+            .as_secs_f64()
+            .max(f64::EPSILON);
+        let column =
+            |time: Duration| ((time.as_secs_f64() / total) * WIDTH as f64).round() as usize;
+
+        let labels: Vec<_> =
+            segments.iter().map(|segment| system.state_label(&segment.state)).collect();
+        let label_width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0);
 
-        // Count how many times each state was visited
-        let mut state_visits = HashMap::new();
-        for transition in system.get_history() {
-            *state_visits.entry(transition.to.clone()).or_insert(0) += 1;
+        segments.iter().zip(&labels).fold(String::new(), |mut text, (segment, label)| {
+            let start = column(offset(segment.start)).min(WIDTH - 1);
+            let end = column(offset(segment.start) + segment.duration).clamp(start + 1, WIDTH);
+            let _ = writeln!(
+                text,
+                "{label:<label_width$} |{}{}{}| {}",
+                " ".repeat(start),
+                "█".repeat(end - start),
+                " ".repeat(WIDTH - end),
+                Self::format_duration(segment.duration)
+            );
+            text
+        })
+    }
+
+    /// Render the text timeline using the character set of a theme
+    #[must_use]
+    pub fn timeline_text_with_theme(
+        system: &impl Visualizable,
+        theme: &VisualizationTheme,
+    ) -> String {
+        theme.decorate(Self::timeline_text(system))
+    }
+
+    /// Generate a Mermaid `gantt` chart of the time spent in each state
+    ///
+    /// Every state gets its own section, so repeated visits line up in a row.
+    #[must_use]
+    pub fn generate_gantt(system: &impl Visualizable) -> String {
+        let millis =
+            |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+
+        let mut gantt = String::from("gantt\n");
+        let _ = writeln!(
+            gantt,
+            "    title Time spent per state by {}",
+            Self::mermaid_escape(system.system_id())
+        );
+        gantt.push_str("    dateFormat x\n");
+        gantt.push_str("    axisFormat %Y-%m-%d %H:%M\n");
+
+        let segments = Self::timeline(system);
+        let mut sections = Vec::new();
+        for segment in &segments {
+            if !sections.contains(&&segment.state) {
+                sections.push(&segment.state);
+            }
+        }
+        for state in sections {
+            let label = Self::mermaid_escape(&system.state_label(state));
+            let _ = writeln!(gantt, "    section {label}");
+            for segment in segments.iter().filter(|segment| segment.state == *state) {
+                let end = segment.start.checked_add(segment.duration).unwrap_or(segment.start);
+                let _ = writeln!(
+                    gantt,
+                    "    {} : {}, {}",
+                    Self::format_duration(segment.duration),
+                    millis(segment.start),
+                    millis(end)
+                );
+            }
         }
 
-        println!("\nState visit counts:");
-        for (state, count) in state_visits {
-            println!("  {state:?}: {count} times");
+        gantt
+    }
+
+    /// Format a duration with its two largest units, e.g. `3d 4h` or `12m 5s`
+    fn format_duration(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        let units = [
+            (secs / 86_400, "d"),
+            (secs / 3_600 % 24, "h"),
+            (secs / 60 % 60, "m"),
+            (secs % 60, "s"),
+        ];
+        let parts: Vec<_> = units
+            .iter()
+            .skip_while(|(value, _)| *value == 0)
+            .take(2)
+            .filter(|(value, _)| *value > 0)
+            .map(|(value, unit)| format!("{value}{unit}"))
+            .collect();
+        if parts.is_empty() { format!("{}ms", duration.as_millis()) } else { parts.join(" ") }
+    }
+
+    /// Collect structural counts and per-state visit and dwell time statistics
+    ///
+    /// Dwell times come from the history timestamps (see [`Self::timeline`]),
+    /// so the current visit counts up to now and time spent before the first
+    /// recorded transition is left out.
+    #[must_use]
+    pub fn stats<V: Visualizable>(system: &V) -> Stats<V::State> {
+        let mut visits: Vec<(V::State, Vec<Duration>)> = Vec::new();
+        for segment in Self::timeline(system) {
+            match visits.iter_mut().find(|(state, _)| *state == segment.state) {
+                Some((_, durations)) => durations.push(segment.duration),
+                None => visits.push((segment.state, vec![segment.duration])),
+            }
+        }
+
+        Stats {
+            state_count: system.states().len(),
+            transition_count: system.transitions().len(),
+            history_len: system.history().len(),
+            current_state: system.current_state().clone(),
+            states: visits
+                .into_iter()
+                .map(|(state, durations)| Self::state_stats(state, durations))
+                .collect(),
+        }
+    }
+
+    /// Aggregate the durations of every visit to a state
+    fn state_stats<S>(state: S, mut durations: Vec<Duration>) -> StateStats<S> {
+        durations.sort_unstable();
+        let total_time = durations.iter().sum();
+        let count = u32::try_from(durations.len()).unwrap_or(u32::MAX);
+
+        let middle = durations.len() / 2;
+        let median_time = match (durations.get(middle.wrapping_sub(1)), durations.get(middle)) {
+            (Some(lower), Some(upper)) if durations.len().is_multiple_of(2) => {
+                lower.saturating_add(*upper).checked_div(2).unwrap_or_default()
+            }
+            (_, Some(median)) => *median,
+            _ => Duration::ZERO,
+        };
+
+        StateStats {
+            state,
+            visits: durations.len(),
+            total_time,
+            mean_time: total_time.checked_div(count).unwrap_or_default(),
+            median_time,
+            max_time: durations.last().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Print a summary of available state machine statistics
+    pub fn print_stats(system: &impl Visualizable) {
+        let stats = Self::stats(system);
+        println!("=== State Machine Statistics ===");
+        println!("Total states: {}", stats.state_count);
+        println!("Total transitions defined: {}", stats.transition_count);
+        println!("Current state: {:?}", stats.current_state);
+        println!("History entries: {}", stats.history_len);
+
+        println!("\nState visit counts and time in state:");
+        for state in &stats.states {
+            println!(
+                "  {:?}: {} times, total {}, mean {}, median {}, max {}",
+                state.state,
+                state.visits,
+                Self::format_duration(state.total_time),
+                Self::format_duration(state.mean_time),
+                Self::format_duration(state.median_time),
+                Self::format_duration(state.max_time)
+            );
         }
     }
 }
+
+// Include tests module
+#[cfg(test)]
+mod tests;
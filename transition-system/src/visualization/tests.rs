@@ -0,0 +1,380 @@
+use std::time::Duration;
+
+use super::*;
+use crate::{patrons::PatronId, system::LibraryError};
+
+/// Create a system that has been reserved and cancelled once
+fn setup_test_system() -> Result<LibrarySystem, LibraryError> {
+    let mut system = crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --Reserve("Alice")--> Reserved("Alice"),
+            Reserved("Alice") --CancelReservation--> Available,
+            Available --ReportLost--> Lost,
+        },
+    }?;
+    system.process_event(BookEvent::Reserve("Alice".into()))?;
+    Ok(system)
+}
+
+#[test]
+fn test_generate_mermaid() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let mermaid = StateVisualization::generate_mermaid(&system, true);
+
+    assert!(mermaid.starts_with("stateDiagram-v2\n"));
+    assert!(mermaid.contains("    [*] --> s0\n"));
+    assert!(mermaid.contains("    s1: Reserved(Alice)\n"));
+    assert!(mermaid.contains("    s0 --> s1: Reserve(#quot;Alice#quot;) ✔\n"));
+    assert!(mermaid.contains("    s1 --> s0: CancelReservation\n"));
+    assert!(mermaid.contains("    class s1 current\n"));
+    assert!(mermaid.contains("    class s0 visited\n"));
+
+    // Without the path only the current state is highlighted
+    let plain = StateVisualization::generate_mermaid(&system, false);
+    assert!(!plain.contains('✔'));
+    assert!(!plain.contains("visited"));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn test_render_svg() -> Result<(), LibraryError> {
+    let mut system = setup_test_system()?;
+    system.set_state_category(0, "circulation");
+    let path = std::env::temp_dir().join(format!("render-svg-test-{}.svg", std::process::id()));
+    let result = StateVisualization::render_svg(&system, &path)
+        .and_then(|()| std::fs::read_to_string(&path).map_err(LibraryError::from));
+    std::fs::remove_file(&path).ok();
+
+    let svg = result?;
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("Reserved(Alice)"));
+    Ok(())
+}
+
+#[test]
+fn test_ascii_diagram() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let diagram = StateVisualization::ascii_diagram(&system);
+    println!("{diagram}");
+
+    assert!(diagram.contains("│ Available"));
+    assert!(diagram.contains("║ Reserved(Alice)"));
+    assert!(diagram.contains("Reserve(\"Alice\")"));
+    assert_eq!(diagram.matches('◀').count(), system.get_all_transitions().len());
+    Ok(())
+}
+
+#[test]
+fn test_generate_scxml() -> Result<(), LibraryError> {
+    let alice = || PatronId::from("Alice");
+    let mut system = crate::LibrarySystemBuilder::new("book <1>", BookState::Available)
+        .transition(BookState::Available, BookEvent::Reserve(alice()), BookState::Reserved(alice()))
+        .transition(
+            BookState::Reserved(alice()),
+            BookEvent::CancelReservation,
+            BookState::Available,
+        )
+        .transition(BookState::Available, BookEvent::ReportLost, BookState::Lost)
+        .timeout(
+            BookState::Reserved(alice()),
+            std::time::Duration::from_hours(72),
+            BookEvent::CancelReservation,
+        )
+        .final_state(BookState::Lost)
+        .build()?;
+    system.process_event(BookEvent::Reserve("Alice".into()))?;
+    let scxml = StateVisualization::generate_scxml(&system);
+    println!("{scxml}");
+
+    assert!(scxml.contains(r#"name="book &lt;1&gt;" initial="Available""#));
+    assert!(scxml.contains(r#"<transition event="Reserve.Alice" target="Reserved.Alice"/>"#));
+    assert!(scxml.contains(
+        r#"<send id="timeout-Reserved.Alice" event="CancelReservation" delay="259200s"/>"#
+    ));
+    assert!(scxml.contains(r#"<cancel sendid="timeout-Reserved.Alice"/>"#));
+    assert!(scxml.contains(r#"<final id="Lost"/>"#));
+    assert!(scxml.trim_end().ends_with("</scxml>"));
+    Ok(())
+}
+
+/// Create a system that spent 2 days reserved and has been checked out for 10 days
+fn setup_timeline_system() -> Result<LibrarySystem, LibraryError> {
+    let clock = crate::clock::MockClock::new();
+    let alice = || PatronId::from("Alice");
+    let mut system = crate::LibrarySystemBuilder::new("test-book", BookState::Available)
+        .transition(BookState::Available, BookEvent::Reserve(alice()), BookState::Reserved(alice()))
+        .transition(
+            BookState::Reserved(alice()),
+            BookEvent::CheckOut(alice()),
+            BookState::CheckedOut(alice()),
+        )
+        .clock(std::sync::Arc::new(clock.clone()))
+        .build()?;
+    system.process_event(BookEvent::Reserve(alice()))?;
+    clock.advance(Duration::from_hours(2 * 24));
+    system.process_event(BookEvent::CheckOut(alice()))?;
+    clock.advance(Duration::from_hours(10 * 24 + 3));
+    Ok(system)
+}
+
+#[test]
+fn test_timeline() -> Result<(), LibraryError> {
+    let system = setup_timeline_system()?;
+    let timeline = StateVisualization::timeline(&system);
+
+    assert_eq!(timeline.len(), 2);
+    assert_eq!(timeline.first().map(|s| s.duration), Some(Duration::from_hours(2 * 24)));
+    assert_eq!(timeline.last().map(|s| s.duration), Some(Duration::from_hours(10 * 24 + 3)));
+
+    let text = StateVisualization::timeline_text(&system);
+    println!("{text}");
+    let lines: Vec<_> = text.lines().collect();
+    // Two days out of twelve, then the rest of the 40 columns
+    assert_eq!(
+        lines.first(),
+        Some(&format!("Reserved(Alice)   |{}{}| 2d", "█".repeat(7), " ".repeat(33)).as_str())
+    );
+    assert_eq!(
+        lines.last(),
+        Some(&format!("CheckedOut(Alice) |{}{}| 10d 3h", " ".repeat(7), "█".repeat(33)).as_str())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_generate_gantt() -> Result<(), LibraryError> {
+    let system = setup_timeline_system()?;
+    let gantt = StateVisualization::generate_gantt(&system);
+    println!("{gantt}");
+
+    assert!(gantt.starts_with("gantt\n"));
+    assert!(gantt.contains("    dateFormat x\n"));
+    assert!(gantt.contains("    section Reserved(Alice)\n    2d : "));
+    assert!(gantt.contains("    section CheckedOut(Alice)\n    10d 3h : "));
+
+    // Consecutive segments share their boundary
+    let times: Vec<u128> = gantt
+        .lines()
+        .filter_map(|line| line.split_once(" : "))
+        .flat_map(|(_, times)| times.split(", ").filter_map(|time| time.parse().ok()))
+        .collect();
+    assert_eq!(times.len(), 4);
+    assert_eq!(times.get(1), times.get(2));
+    Ok(())
+}
+
+#[test]
+fn test_stats_dwell_times() -> Result<(), LibraryError> {
+    let clock = crate::clock::MockClock::new();
+    let mut system = crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --SendToRepair--> UnderRepair,
+            UnderRepair --CompleteRepair--> Available,
+        },
+    }?;
+    system.set_clock(std::sync::Arc::new(clock.clone()));
+
+    // Four repairs of 1, 5, 2 and 4 hours with a day on the shelf in between
+    for hours in [1, 5, 2, 4] {
+        system.process_event(BookEvent::SendToRepair { condition: None })?;
+        clock.advance(Duration::from_hours(hours));
+        system.process_event(BookEvent::CompleteRepair)?;
+        clock.advance(Duration::from_hours(24));
+    }
+
+    let stats = StateVisualization::stats(&system);
+    assert_eq!(stats.history_len, 8);
+    assert_eq!(stats.current_state, BookState::Available);
+
+    let repair = stats.states.first().ok_or(LibraryError::LoadError("no stats".into()))?;
+    assert_eq!(repair.state, BookState::UnderRepair);
+    assert_eq!(repair.visits, 4);
+    assert_eq!(repair.total_time, Duration::from_hours(12));
+    assert_eq!(repair.mean_time, Duration::from_hours(3));
+    // An even number of visits averages the two middle ones
+    assert_eq!(repair.median_time, Duration::from_hours(3));
+    assert_eq!(repair.max_time, Duration::from_hours(5));
+
+    // The current visit counts up to now
+    let shelf = stats.states.get(1).ok_or(LibraryError::LoadError("no stats".into()))?;
+    assert_eq!(shelf.visits, 4);
+    assert_eq!(shelf.total_time, Duration::from_hours(4 * 24));
+
+    StateVisualization::print_stats(&system);
+    Ok(())
+}
+
+#[test]
+fn test_plain_theme() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let theme = VisualizationTheme::plain();
+
+    let dot = StateVisualization::generate_dot_with_theme(&system, true, &theme);
+    assert!(dot.contains(r#"node [shape=box, style=filled, fillcolor="white"];"#));
+    assert!(dot.contains(r#"fillcolor="lightgray", peripheries=2"#));
+    assert!(dot.contains(r#"color="black", penwidth=2.0"#));
+    assert!(!dot.contains("lightblue"));
+
+    let mermaid = StateVisualization::generate_mermaid_with_theme(&system, true, &theme);
+    assert!(mermaid.contains("    s0 --> s1: Reserve(#quot;Alice#quot;) *\n"));
+    assert!(mermaid.contains("classDef current fill:lightgray"));
+
+    // Every text output is pure ASCII
+    let table = StateVisualization::history_table_with_theme(system.get_history(), &theme);
+    assert!(table.contains("| 1 | Available | Reserve(\"Alice\") | Reserved(Alice) |"));
+    for text in [table, mermaid, StateVisualization::ascii_diagram_with_theme(&system, &theme)] {
+        assert!(text.is_ascii(), "{text}");
+    }
+
+    // The default theme keeps the classic look
+    assert!(StateVisualization::history_table(system.get_history()).contains("📚 Available"));
+    assert_eq!(
+        StateVisualization::generate_dot(&system, false),
+        StateVisualization::generate_dot_with_theme(&system, false, &VisualizationTheme::default())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_generate_dot_frames() -> Result<(), LibraryError> {
+    let mut system = setup_test_system()?;
+    system.process_event(BookEvent::CancelReservation)?;
+    let frames = StateVisualization::generate_dot_frames(&system);
+
+    // The starting state, then one frame per transition
+    assert_eq!(frames.len(), 3);
+    let [start, reserved, cancelled] = frames.as_slice() else {
+        return Err(LibraryError::LoadError("wrong frame count".into()));
+    };
+    assert!(start.contains(r#"label="Step 0/2: Available";"#));
+    assert!(start.contains(r#"s0 [label="Available", fillcolor="palegreen""#));
+    // Only graph lines count, the legend shows every style
+    let highlighted = |frame: &str| {
+        frame.lines().filter(|line| line.starts_with("  s") && line.contains("penwidth")).count()
+    };
+    assert_eq!(highlighted(start), 0);
+
+    assert!(reserved.contains(r#"label="Step 1/2: Reserve(\"Alice\") -> Reserved(Alice)";"#));
+    assert!(reserved.contains(r#"s1 [label="Reserved(Alice)", fillcolor="palegreen""#));
+    assert!(reserved.contains(r#"s0 -> s1 [label="Reserve(\"Alice\")", color="red""#));
+    assert_eq!(highlighted(reserved), 1);
+
+    assert!(cancelled.contains(r#"s0 [label="Available", fillcolor="palegreen""#));
+    assert!(cancelled.contains(r#"s1 -> s0 [label="CancelReservation", color="red""#));
+
+    let dir = std::env::temp_dir().join(format!("dot-frames-test-{}", std::process::id()));
+    let result = (|| {
+        let paths = StateVisualization::save_dot_frames(&frames, &dir)?;
+        assert_eq!(paths.last(), Some(&dir.join("frame-002.dot")));
+        assert_eq!(std::fs::read_to_string(dir.join("frame-001.dot"))?, *reserved);
+        Ok(())
+    })();
+    std::fs::remove_dir_all(&dir).ok();
+    result
+}
+
+#[test]
+fn test_dot_timeouts_and_legend() -> Result<(), LibraryError> {
+    let mut system = setup_test_system()?;
+    system.add_timing_constraint(
+        1,
+        Duration::from_secs(3 * 86_400 + 90),
+        BookEvent::CancelReservation,
+    );
+    let dot = StateVisualization::generate_dot(&system, false);
+
+    assert!(dot.contains(
+        r#"s1 -> s0 [label="CancelReservation", color="black", style=dashed, taillabel="after 3d 1m 30s"];"#
+    ));
+    assert!(dot.contains(r#"s0 -> s1 [label="Reserve(\"Alice\")", color="black"];"#));
+    assert!(dot.contains("  subgraph cluster_legend {\n    label=\"Legend\";"));
+    assert!(
+        dot.contains(r#"legend_current [label="Current", fillcolor="palegreen", peripheries=2];"#)
+    );
+
+    let without_legend = VisualizationTheme { show_legend: false, ..VisualizationTheme::default() };
+    assert!(
+        !StateVisualization::generate_dot_with_theme(&system, false, &without_legend)
+            .contains("legend")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dot_state_clusters() -> Result<(), LibraryError> {
+    let mut system = setup_test_system()?;
+    system.set_state_category(0, "circulation");
+    system.set_state_category(1, "circulation");
+    system.set_state_category(2, "missing");
+    let dot = StateVisualization::generate_dot(&system, false);
+
+    assert!(dot.contains(
+        "  subgraph \"cluster_circulation\" {\n    label=\"circulation\";\n    s0;\n    s1;\n  }\n"
+    ));
+    assert!(
+        dot.contains("  subgraph \"cluster_missing\" {\n    label=\"missing\";\n    s2;\n  }\n")
+    );
+    // States are still declared in order, so the initial state comes first
+    assert!(dot.find("  s0 [").lt(&dot.find("  s1 [")));
+    Ok(())
+}
+
+/// Minimal machine implementing only the required methods of `Visualizable`
+struct Toggle {
+    /// The two states of the machine
+    states: [BookState; 2],
+    /// Event moving between them
+    event: BookEvent,
+}
+
+impl Visualizable for Toggle {
+    type State = BookState;
+    type Event = BookEvent;
+
+    fn system_id(&self) -> &'static str {
+        "toggle"
+    }
+
+    fn states(&self) -> Cow<'_, [BookState]> {
+        Cow::Borrowed(&self.states)
+    }
+
+    fn transitions(&self) -> Vec<(usize, &BookEvent, usize)> {
+        vec![(0, &self.event, 1), (1, &self.event, 0)]
+    }
+
+    fn current_state(&self) -> &BookState {
+        let [_, current] = &self.states;
+        current
+    }
+
+    fn history(&self) -> Vec<HistoryStep<'_, BookState, BookEvent>> {
+        Vec::new()
+    }
+}
+
+#[test]
+fn test_custom_visualizable() {
+    let toggle = Toggle {
+        states: [BookState::Available, BookState::InTransit],
+        event: BookEvent::Transfer { destination: None },
+    };
+
+    let dot = StateVisualization::generate_dot(&toggle, true);
+    assert!(dot.starts_with("digraph \"toggle\" {"));
+    assert!(dot.contains(r#"s1 [label="InTransit", fillcolor="palegreen", peripheries=2];"#));
+    assert!(dot.contains(r#"s0 -> s1 [label="Transfer", color="black"];"#));
+    assert!(
+        StateVisualization::generate_mermaid(&toggle, false).contains("    s1 --> s0: Transfer\n")
+    );
+    assert!(StateVisualization::ascii_diagram(&toggle).contains("InTransit"));
+
+    let stats = StateVisualization::stats(&toggle);
+    assert_eq!(stats.transition_count, 2);
+    assert_eq!(stats.current_state, BookState::InTransit);
+}
@@ -0,0 +1,270 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde_json::json;
+use tungstenite::Message;
+
+use crate::{
+    book_state::BookState,
+    events::BookEvent,
+    logging::emit,
+    observers::StateObserver,
+    system::{LibraryError, LibrarySystem, StateTransition},
+    visualization::StateVisualization,
+};
+
+/// Page rendering the pushed diagram and history in the browser
+const INDEX_HTML: &str = include_str!("web/index.html");
+
+/// How long a connection may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the server shows, shared between its connections and its observer
+#[derive(Debug)]
+struct LiveView {
+    /// Copy of the served system, kept in sync by replaying its transitions
+    mirror: Mutex<LibrarySystem>,
+    /// Channels of the connected WebSocket clients
+    clients: Mutex<Vec<Sender<String>>>,
+}
+
+impl LiveView {
+    /// Describe the current state, diagram and history as JSON
+    fn payload(&self) -> String {
+        let mirror = self.mirror.lock().unwrap_or_else(PoisonError::into_inner);
+        let history: Vec<_> = mirror
+            .get_history()
+            .iter()
+            .map(|t| {
+                json!({
+                    "sequence": t.sequence,
+                    "from": format!("{:?}", t.from),
+                    "event": format!("{:?}", t.event),
+                    "to": format!("{:?}", t.to),
+                })
+            })
+            .collect();
+        json!({
+            "system_id": mirror.system_id(),
+            "current_state": format!("{:?}", mirror.current_state()),
+            "mermaid": StateVisualization::generate_mermaid(&*mirror, true),
+            "history": history,
+        })
+        .to_string()
+    }
+
+    /// Register a new WebSocket client
+    fn subscribe(&self) -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        self.clients.lock().unwrap_or_else(PoisonError::into_inner).push(sender);
+        receiver
+    }
+
+    /// Push the current view to every client, forgetting disconnected ones
+    fn broadcast(&self) {
+        let payload = self.payload();
+        self.clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|client| client.send(payload.clone()).is_ok());
+    }
+}
+
+/// Observer keeping a [`VisualizationServer`] in sync with the served system
+#[derive(Debug)]
+struct LiveViewObserver(Arc<LiveView>);
+
+impl StateObserver for LiveViewObserver {
+    fn on_state_change(&self, _from: &BookState, _to: &BookState, _event: &BookEvent) {}
+
+    fn on_transition(&self, transition: &StateTransition) {
+        let replayed = self
+            .0
+            .mirror
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .mirror_transition(transition.clone());
+        match replayed {
+            Ok(()) => self.0.broadcast(),
+            Err(e) => emit!(warn, "WEB: Failed to update the live view: {e}"),
+        }
+    }
+}
+
+/// HTTP server showing a system's state diagram and history in the browser
+///
+/// `GET /` serves a page rendering the Mermaid diagram (current state and
+/// path taken highlighted) and the transition history, `GET /state` the same
+/// data as JSON, and `/ws` a WebSocket pushing it again after every
+/// transition. Register [`Self::observer`] on the served system so the page
+/// updates in real time. The server is stopped when it is dropped.
+#[derive(Debug)]
+pub struct VisualizationServer {
+    /// Address the server is listening on
+    addr: SocketAddr,
+    /// What the server shows
+    view: Arc<LiveView>,
+    /// Flag telling the accept thread to exit
+    stop: Arc<AtomicBool>,
+    /// Handle of the accept thread
+    handle: Option<JoinHandle<()>>,
+}
+
+impl VisualizationServer {
+    /// Start serving a snapshot of the given system
+    ///
+    /// Bind to port 0 to let the OS pick a free port, then ask
+    /// [`Self::local_addr`] for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LibraryError::Io` if the address can't be bound
+    pub fn bind(addr: impl ToSocketAddrs, system: &LibrarySystem) -> Result<Self, LibraryError> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let view = Arc::new(LiveView {
+            mirror: Mutex::new(LibrarySystem::from_serializable_state(
+                system.to_serializable_state(),
+            )),
+            clients: Mutex::new(Vec::new()),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let worker_view = Arc::clone(&view);
+        let worker_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if worker_stop.load(Ordering::Acquire) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let view = Arc::clone(&worker_view);
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &view) {
+                        emit!(warn, "WEB: Connection failed: {e}");
+                    }
+                });
+            }
+        });
+
+        emit!(info, "WEB: Serving {} at http://{addr}/", system.system_id());
+        Ok(Self { addr, view, stop, handle: Some(handle) })
+    }
+
+    /// Get the address the server is listening on
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Create an observer to register on the served system
+    ///
+    /// Every transition it is notified of is pushed to the connected browsers.
+    #[must_use]
+    pub fn observer(&self) -> Box<dyn StateObserver> {
+        Box::new(LiveViewObserver(Arc::clone(&self.view)))
+    }
+
+    /// Stop accepting connections and disconnect all clients
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Signal the accept thread to exit and join it
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        self.view.clients.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        if let Some(handle) = self.handle.take() {
+            // Wake the accept thread up so it sees the flag
+            TcpStream::connect(self.addr).ok();
+            if handle.join().is_err() {
+                emit!(error, "WEB: Accept thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for VisualizationServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Serve one HTTP request or WebSocket session
+fn handle_connection(mut stream: TcpStream, view: &LiveView) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let path = request_path(&stream)?;
+    if path == "/ws" {
+        let receiver = view.subscribe();
+        let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+        socket.send(Message::text(view.payload())).map_err(io::Error::other)?;
+        // Ends once the client disconnects or the server shuts down
+        for payload in receiver {
+            socket.send(Message::text(payload)).map_err(io::Error::other)?;
+        }
+        return Ok(());
+    }
+
+    skip_request_headers(&stream)?;
+    let (status, content_type, body) = match path.as_str() {
+        "/" => ("200 OK", "text/html; charset=utf-8", INDEX_HTML.to_string()),
+        "/state" => ("200 OK", "application/json", view.payload()),
+        _ => ("404 Not Found", "text/plain", String::from("Not found")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Read the path of the request line without consuming it
+///
+/// The WebSocket handshake needs to read the request itself.
+fn request_path(stream: &TcpStream) -> io::Result<String> {
+    let mut buf = [0; 1024];
+    for _ in 0..100 {
+        let len = stream.peek(&mut buf)?;
+        let received = buf.get(..len).unwrap_or_default();
+        if let Some(end) = received.windows(2).position(|w| w == b"\r\n") {
+            let line = String::from_utf8_lossy(received.get(..end).unwrap_or_default());
+            let target = line.split_whitespace().nth(1).unwrap_or("/");
+            return Ok(target.split('?').next().unwrap_or(target).to_string());
+        }
+        if len == 0 || len == buf.len() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "Malformed HTTP request"))
+}
+
+/// Consume the request line and headers so closing the socket doesn't reset it
+fn skip_request_headers(stream: &TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+// Include tests module
+#[cfg(test)]
+mod tests;
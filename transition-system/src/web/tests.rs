@@ -0,0 +1,66 @@
+use std::io::Read;
+
+use super::*;
+
+/// Create a small system to serve
+fn setup_test_system() -> Result<LibrarySystem, LibraryError> {
+    crate::state_machine! {
+        id: "test-book",
+        initial: Available,
+        transitions: {
+            Available --CheckOut("Alice")--> CheckedOut("Alice"),
+            CheckedOut("Alice") --Return--> Available,
+        },
+    }
+}
+
+/// Send a plain HTTP GET request and return the whole response
+fn get(addr: SocketAddr, path: &str) -> Result<String, LibraryError> {
+    let mut stream = TcpStream::connect(addr)?;
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[test]
+fn test_serves_page_and_state() -> Result<(), LibraryError> {
+    let system = setup_test_system()?;
+    let server = VisualizationServer::bind("127.0.0.1:0", &system)?;
+
+    let page = get(server.local_addr(), "/")?;
+    assert!(page.starts_with("HTTP/1.1 200 OK"));
+    assert!(page.contains("new WebSocket"));
+
+    let state = get(server.local_addr(), "/state")?;
+    assert!(state.contains("application/json"));
+    assert!(state.contains(r#""current_state":"Available""#));
+    assert!(state.contains("stateDiagram-v2"));
+
+    assert!(get(server.local_addr(), "/missing")?.starts_with("HTTP/1.1 404 Not Found"));
+    server.stop();
+    Ok(())
+}
+
+#[test]
+fn test_pushes_transitions_over_websocket() -> Result<(), LibraryError> {
+    let mut system = setup_test_system()?;
+    let server = VisualizationServer::bind("127.0.0.1:0", &system)?;
+    system.register_observer(server.observer());
+
+    let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", server.local_addr()))
+        .map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+    let mut next = || -> Result<serde_json::Value, LibraryError> {
+        let message = socket.read().map_err(|e| LibraryError::PersistenceError(e.to_string()))?;
+        let text = message.into_text().map_err(|e| LibraryError::LoadError(e.to_string()))?;
+        Ok(serde_json::from_str(&text)?)
+    };
+
+    assert_eq!(next()?.pointer("/current_state"), Some(&json!("Available")));
+
+    system.process_event(BookEvent::CheckOut("Alice".into()))?;
+    let update = next()?;
+    assert_eq!(update.pointer("/current_state"), Some(&json!(r#"CheckedOut("Alice")"#)));
+    assert_eq!(update.pointer("/history/0/event"), Some(&json!(r#"CheckOut("Alice")"#)));
+    Ok(())
+}